@@ -0,0 +1,49 @@
+#!/bin/sh
+# Smoke test for dice-server using curl.
+# Run from bindings/rust: ./scripts/test-server.sh
+
+set -e
+
+ADDR="127.0.0.1:${PORT:-3917}"
+
+cargo build --quiet --features server --bin dice-server
+DICE_SEED=12345 ./target/debug/dice-server "$ADDR" &
+SERVER_PID=$!
+trap 'kill $SERVER_PID 2>/dev/null' EXIT
+
+# Wait for the listener
+for _ in 1 2 3 4 5 6 7 8 9 10; do
+    curl -s "http://$ADDR/roll/6" >/dev/null 2>&1 && break
+    sleep 0.5
+done
+
+check() {
+    name="$1"
+    expected="$2"
+    actual="$3"
+    if echo "$actual" | grep -q "$expected"; then
+        echo "PASS: $name"
+    else
+        echo "FAIL: $name"
+        echo "  expected to contain: $expected"
+        echo "  got: $actual"
+        exit 1
+    fi
+}
+
+BODY=$(curl -s -X POST -H 'Content-Type: application/json' \
+    -d '{"notation":"3d6+2"}' "http://$ADDR/roll")
+check "POST /roll" '"notation":"3d6+2"' "$BODY"
+check "POST /roll individual" '"individual":\[[0-9]*,[0-9]*,[0-9]*\]' "$BODY"
+
+BODY=$(curl -s "http://$ADDR/roll/20")
+check "GET /roll/20" '"notation":"1d20"' "$BODY"
+
+STATUS=$(curl -s -o /dev/null -w '%{http_code}' -X POST \
+    -H 'Content-Type: application/json' -d '{"notation":"bogus"}' "http://$ADDR/roll")
+check "invalid notation status" '400' "$STATUS"
+
+BODY=$(curl -s "http://$ADDR/roll/0")
+check "invalid sides error" '"InvalidSides":0' "$BODY"
+
+echo "All server tests passed"
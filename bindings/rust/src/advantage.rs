@@ -0,0 +1,621 @@
+//! Advantage pools
+//!
+//! Advantage rolls extra dice and keeps the best of them, disadvantage
+//! keeps the worst: D&D 5e advantage is two d20s keeping the highest,
+//! Cypher System disadvantage two keeping the lowest.
+//!
+//! The same idea scales up to whole pools: rolling 4d6 three times and
+//! keeping the best sum is [`DiceContext::roll_pool_best_of`].
+//!
+//! Some systems express advantage as a bonus die instead of an extra one:
+//! [`DiceContext::roll_universal_modifier`] adds an advantage roll to the
+//! base roll and subtracts a disadvantage roll, so a d6 buff and a d4
+//! debuff make `1d20+1d6-1d4`.
+
+use crate::context::DiceContext;
+use crate::eval::RollResult;
+use crate::expression::DiceExpression;
+use crate::{Dice, DiceError, DiceResult};
+
+/// Which dice of an advantage pool count
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum KeepMode {
+    Highest,
+    Lowest,
+}
+
+/// Pool size, dice kept and mode for stacked advantage
+///
+/// `sources` is advantage sources minus disadvantage sources; each net
+/// source adds a die to a keep-one pool. Three advantages against one
+/// disadvantage is `(3, 1, Highest)`, no net advantage a single die.
+pub fn net_advantage(sources: i32) -> (u32, u32, KeepMode) {
+    let mode = if sources < 0 {
+        KeepMode::Lowest
+    } else {
+        KeepMode::Highest
+    };
+    (sources.unsigned_abs().saturating_add(1), 1, mode)
+}
+
+/// Dice rolled for a net advantage and the one kept
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AdvantageResult {
+    pub dice_values: Vec<i32>,
+    /// Highest die with advantage, lowest with disadvantage
+    pub kept_value: i32,
+    /// Net advantage asked for, before any limit on the pool
+    pub advantage_count: i32,
+}
+
+/// Sums of repeated pools and the one chosen
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BestOfResult {
+    /// Sum of each pool, in the order rolled
+    pub pool_results: Vec<i32>,
+    pub chosen_result: i32,
+    /// Index into `pool_results`; the first on a tie
+    pub chosen_index: usize,
+}
+
+/// Kept dice of independently rolled groups and the group chosen
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GroupBestResult {
+    /// Kept dice of each group, in the order rolled
+    pub all_groups: Vec<Vec<i32>>,
+    pub chosen_group: Vec<i32>,
+    /// Index into `all_groups`; the first on a tie
+    pub chosen_index: usize,
+    /// Sum of `chosen_group`
+    pub total: i32,
+}
+
+impl GroupBestResult {
+    /// Sum of each group, in the order rolled
+    pub fn group_totals(&self) -> Vec<i32> {
+        self.all_groups.iter().map(|group| group.iter().sum()).collect()
+    }
+}
+
+/// A base roll with an advantage roll added and a disadvantage roll
+/// subtracted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ModifiedResult {
+    pub base_roll: i32,
+    /// 0 when no advantage was rolled
+    pub advantage_roll: i32,
+    /// 0 when no disadvantage was rolled
+    pub disadvantage_roll: i32,
+    /// `base_roll + advantage_roll - disadvantage_roll`
+    pub total: i32,
+}
+
+impl DiceContext {
+    /// Roll one die, or a pool keeping one, for a net advantage count
+    ///
+    /// Positive `advantage` keeps the highest die, negative the lowest,
+    /// and 0 rolls a single die. As in 5e the pool stops at two dice
+    /// however many sources there are, unless
+    /// [`RollConfig::stack_advantage`](crate::RollConfig::stack_advantage) is
+    /// set, in which case each net source adds a die as in
+    /// [`net_advantage`].
+    ///
+    /// # Arguments
+    ///
+    /// * `sides` - Sides per die
+    /// * `advantage` - Advantage sources minus disadvantage sources
+    pub fn roll_with_advantage_count(
+        &mut self,
+        sides: u32,
+        advantage: i32,
+    ) -> DiceResult<AdvantageResult> {
+        let (mut pool_size, _, keep_mode) = net_advantage(advantage);
+        if !self.config().stack_advantage {
+            pool_size = pool_size.min(2);
+        }
+        let dice_values = self.roll_pool(pool_size, sides)?;
+        let kept_value = match keep_mode {
+            KeepMode::Highest => dice_values.iter().max(),
+            KeepMode::Lowest => dice_values.iter().min(),
+        };
+        Ok(AdvantageResult {
+            kept_value: *kept_value.ok_or(DiceError::InvalidCount(0))?,
+            dice_values,
+            advantage_count: advantage,
+        })
+    }
+
+    /// Roll `best_of` pools of `pool_size` dice and keep the highest sum
+    ///
+    /// A `best_of` of 0 fails with [`DiceError::InvalidCount`], and more
+    /// pools than
+    /// [`RollConfig::max_simulation_iterations`](crate::RollConfig::max_simulation_iterations)
+    /// with [`DiceError::RollCountLimit`].
+    ///
+    /// # Arguments
+    ///
+    /// * `pool_size` - Dice in each pool
+    /// * `sides` - Sides per die
+    /// * `best_of` - Number of pools rolled
+    pub fn roll_pool_best_of(
+        &mut self,
+        pool_size: u32,
+        sides: u32,
+        best_of: u32,
+    ) -> DiceResult<BestOfResult> {
+        self.roll_pools_keeping(pool_size, sides, best_of, KeepMode::Highest)
+    }
+
+    /// Roll `worst_of` pools of `pool_size` dice and keep the lowest sum
+    ///
+    /// # Arguments
+    ///
+    /// * `pool_size` - Dice in each pool
+    /// * `sides` - Sides per die
+    /// * `worst_of` - Number of pools rolled
+    pub fn roll_pool_worst_of(
+        &mut self,
+        pool_size: u32,
+        sides: u32,
+        worst_of: u32,
+    ) -> DiceResult<BestOfResult> {
+        self.roll_pools_keeping(pool_size, sides, worst_of, KeepMode::Lowest)
+    }
+
+    /// Roll `groups` pools, each keeping its `keep` highest dice, and
+    /// choose the group with the highest kept sum
+    ///
+    /// "Roll three sets of 4d6k3, take the best" is
+    /// `roll_sum_best_of_independent_groups(3, 4, 6, 3)`. A `groups` of 0
+    /// fails with [`DiceError::InvalidCount`], and more groups than
+    /// [`RollConfig::max_simulation_iterations`](crate::RollConfig::max_simulation_iterations)
+    /// with [`DiceError::RollCountLimit`].
+    ///
+    /// # Arguments
+    ///
+    /// * `groups` - Number of groups rolled
+    /// * `count` - Dice in each group
+    /// * `sides` - Sides per die
+    /// * `keep` - Highest dice kept in each group; at most `count`
+    pub fn roll_sum_best_of_independent_groups(
+        &mut self,
+        groups: u32,
+        count: u32,
+        sides: u32,
+        keep: u32,
+    ) -> DiceResult<GroupBestResult> {
+        let limit = self.config().max_simulation_iterations;
+        if groups == 0 {
+            return Err(DiceError::InvalidCount(0));
+        }
+        if groups > limit {
+            return Err(DiceError::RollCountLimit {
+                requested: groups,
+                limit,
+            });
+        }
+        let all_groups = (0..groups)
+            .map(|_| Ok(self.roll_advantage_pool(count, keep, sides, KeepMode::Highest)?.kept()))
+            .collect::<DiceResult<Vec<_>>>()?;
+        let mut best: Option<(usize, i32)> = None;
+        for (index, group) in all_groups.iter().enumerate() {
+            let sum = group
+                .iter()
+                .try_fold(0i32, |sum, &value| sum.checked_add(value))
+                .ok_or(DiceError::Overflow)?;
+            if best.is_none_or(|(_, highest)| sum > highest) {
+                best = Some((index, sum));
+            }
+        }
+        // groups is at least 1
+        let (chosen_index, total) = best.unwrap_or_default();
+        Ok(GroupBestResult {
+            chosen_group: all_groups[chosen_index].clone(),
+            all_groups,
+            chosen_index,
+            total,
+        })
+    }
+
+    fn roll_pools_keeping(
+        &mut self,
+        pool_size: u32,
+        sides: u32,
+        pools: u32,
+        keep_mode: KeepMode,
+    ) -> DiceResult<BestOfResult> {
+        let config = *self.config();
+        if pools == 0 {
+            return Err(DiceError::InvalidCount(0));
+        }
+        if pools > config.max_simulation_iterations {
+            return Err(DiceError::RollCountLimit {
+                requested: pools,
+                limit: config.max_simulation_iterations,
+            });
+        }
+        let pool = DiceExpression::Dice(pool_size, sides);
+        let pool_results = (0..pools)
+            .map(|_| Ok(pool.roll_with_config(self.rng(), &config)?.total))
+            .collect::<DiceResult<Vec<_>>>()?;
+        let mut chosen_index = 0;
+        for (index, &result) in pool_results.iter().enumerate() {
+            let better = match keep_mode {
+                KeepMode::Highest => result > pool_results[chosen_index],
+                KeepMode::Lowest => result < pool_results[chosen_index],
+            };
+            if better {
+                chosen_index = index;
+            }
+        }
+        Ok(BestOfResult {
+            chosen_result: pool_results[chosen_index],
+            chosen_index,
+            pool_results,
+        })
+    }
+
+    /// Roll a pool and keep its highest or lowest dice
+    ///
+    /// # Arguments
+    ///
+    /// * `pool_size` - Number of dice rolled
+    /// * `keep` - Number of dice kept; at most `pool_size`
+    /// * `sides` - Sides per die
+    /// * `keep_mode` - Whether the highest or lowest dice are kept
+    pub fn roll_advantage_pool(
+        &mut self,
+        pool_size: u32,
+        keep: u32,
+        sides: u32,
+        keep_mode: KeepMode,
+    ) -> DiceResult<RollResult> {
+        if keep == 0 {
+            return Err(DiceError::InvalidCount(0));
+        }
+        if pool_size < keep {
+            return Err(DiceError::InvalidCount(pool_size as i32));
+        }
+        let pool = Box::new(DiceExpression::Dice(pool_size, sides));
+        let expr = match keep_mode {
+            KeepMode::Highest => DiceExpression::KeepHighest(pool, keep),
+            KeepMode::Lowest => DiceExpression::KeepLowest(pool, keep),
+        };
+        let config = *self.config();
+        expr.roll_with_config(self.rng(), &config)
+    }
+
+    /// Roll a base roll plus an advantage roll minus a disadvantage roll
+    ///
+    /// # Arguments
+    ///
+    /// * `base_notation` - Dice notation for the roll itself, e.g. "1d20"
+    /// * `advantage_notation` - Dice notation added to it; empty for none
+    /// * `disadvantage_notation` - Dice notation subtracted from it; empty
+    ///   for none
+    pub fn roll_universal_modifier(
+        &mut self,
+        base_notation: &str,
+        advantage_notation: &str,
+        disadvantage_notation: &str,
+    ) -> DiceResult<ModifiedResult> {
+        let base_roll = self.roll_notation(base_notation)?.total;
+        let mut optional = |notation: &str| -> DiceResult<i32> {
+            if notation.trim().is_empty() {
+                Ok(0)
+            } else {
+                Ok(self.roll_notation(notation)?.total)
+            }
+        };
+        let advantage_roll = optional(advantage_notation)?;
+        let disadvantage_roll = optional(disadvantage_notation)?;
+        let total = base_roll
+            .checked_add(advantage_roll)
+            .and_then(|total| total.checked_sub(disadvantage_roll))
+            .ok_or(DiceError::Overflow)?;
+        Ok(ModifiedResult {
+            base_roll,
+            advantage_roll,
+            disadvantage_roll,
+            total,
+        })
+    }
+}
+
+impl Dice {
+    /// Roll a base roll plus an advantage roll minus a disadvantage roll
+    ///
+    /// # Arguments
+    ///
+    /// * `base_notation` - Dice notation for the roll itself, e.g. "1d20"
+    /// * `advantage_notation` - Dice notation added to it; empty for none
+    /// * `disadvantage_notation` - Dice notation subtracted from it; empty
+    ///   for none
+    pub fn roll_universal_modifier(
+        base_notation: &str,
+        advantage_notation: &str,
+        disadvantage_notation: &str,
+    ) -> DiceResult<ModifiedResult> {
+        DiceContext::new().roll_universal_modifier(
+            base_notation,
+            advantage_notation,
+            disadvantage_notation,
+        )
+    }
+
+    /// Roll one die, or a pool keeping one, for a net advantage count
+    ///
+    /// # Arguments
+    ///
+    /// * `sides` - Sides per die
+    /// * `advantage` - Advantage sources minus disadvantage sources
+    pub fn roll_with_advantage_count(sides: u32, advantage: i32) -> DiceResult<AdvantageResult> {
+        DiceContext::new().roll_with_advantage_count(sides, advantage)
+    }
+
+    /// Roll a pool and keep its highest or lowest dice
+    ///
+    /// # Arguments
+    ///
+    /// * `pool_size` - Number of dice rolled
+    /// * `keep` - Number of dice kept; at most `pool_size`
+    /// * `sides` - Sides per die
+    /// * `keep_mode` - Whether the highest or lowest dice are kept
+    pub fn roll_advantage_pool(
+        pool_size: u32,
+        keep: u32,
+        sides: u32,
+        keep_mode: KeepMode,
+    ) -> DiceResult<RollResult> {
+        DiceContext::new().roll_advantage_pool(pool_size, keep, sides, keep_mode)
+    }
+
+    /// Roll `best_of` pools of `pool_size` dice and keep the highest sum
+    ///
+    /// # Arguments
+    ///
+    /// * `pool_size` - Dice in each pool
+    /// * `sides` - Sides per die
+    /// * `best_of` - Number of pools rolled
+    pub fn roll_pool_best_of(pool_size: u32, sides: u32, best_of: u32) -> DiceResult<BestOfResult> {
+        DiceContext::new().roll_pool_best_of(pool_size, sides, best_of)
+    }
+
+    /// Roll `worst_of` pools of `pool_size` dice and keep the lowest sum
+    ///
+    /// # Arguments
+    ///
+    /// * `pool_size` - Dice in each pool
+    /// * `sides` - Sides per die
+    /// * `worst_of` - Number of pools rolled
+    pub fn roll_pool_worst_of(
+        pool_size: u32,
+        sides: u32,
+        worst_of: u32,
+    ) -> DiceResult<BestOfResult> {
+        DiceContext::new().roll_pool_worst_of(pool_size, sides, worst_of)
+    }
+
+    /// Roll `groups` pools, each keeping its `keep` highest dice, and
+    /// choose the group with the highest kept sum
+    ///
+    /// # Arguments
+    ///
+    /// * `groups` - Number of groups rolled
+    /// * `count` - Dice in each group
+    /// * `sides` - Sides per die
+    /// * `keep` - Highest dice kept in each group; at most `count`
+    pub fn roll_sum_best_of_independent_groups(
+        groups: u32,
+        count: u32,
+        sides: u32,
+        keep: u32,
+    ) -> DiceResult<GroupBestResult> {
+        DiceContext::new().roll_sum_best_of_independent_groups(groups, count, sides, keep)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_advantage_pool() {
+        let mut ctx = DiceContext::with_seed(132);
+        for _ in 0..200 {
+            let result = ctx.roll_advantage_pool(2, 1, 20, KeepMode::Highest).unwrap();
+            let values = result.individual();
+            assert_eq!(values.len(), 2);
+            assert_eq!(result.total, *values.iter().max().unwrap());
+
+            let result = ctx.roll_advantage_pool(4, 2, 6, KeepMode::Lowest).unwrap();
+            let mut values = result.individual();
+            values.sort_unstable();
+            assert_eq!(result.total, values[0] + values[1]);
+            assert_eq!(result.kept().len(), 2);
+        }
+    }
+
+    #[test]
+    fn test_advantage_pool_errors() {
+        let mut ctx = DiceContext::with_seed(132);
+        assert_eq!(
+            ctx.roll_advantage_pool(1, 2, 20, KeepMode::Highest),
+            Err(DiceError::InvalidCount(1))
+        );
+        assert_eq!(
+            ctx.roll_advantage_pool(2, 0, 20, KeepMode::Highest),
+            Err(DiceError::InvalidCount(0))
+        );
+        assert_eq!(
+            Dice::roll_advantage_pool(2, 1, 0, KeepMode::Lowest),
+            Err(DiceError::InvalidSides(0))
+        );
+    }
+
+    #[test]
+    fn test_advantage_count() {
+        let mut ctx = DiceContext::with_seed(174);
+        for _ in 0..100 {
+            let straight = ctx.roll_with_advantage_count(20, 0).unwrap();
+            assert_eq!(straight.dice_values, vec![straight.kept_value]);
+
+            let advantage = ctx.roll_with_advantage_count(20, 1).unwrap();
+            assert_eq!(advantage.dice_values.len(), 2);
+            assert_eq!(advantage.kept_value, *advantage.dice_values.iter().max().unwrap());
+
+            let disadvantage = ctx.roll_with_advantage_count(20, -1).unwrap();
+            assert_eq!(disadvantage.kept_value, *disadvantage.dice_values.iter().min().unwrap());
+        }
+        // Extra sources don't add dice by default
+        let triple = ctx.roll_with_advantage_count(20, 3).unwrap();
+        assert_eq!((triple.dice_values.len(), triple.advantage_count), (2, 3));
+        assert_eq!(ctx.roll_with_advantage_count(20, -4).unwrap().dice_values.len(), 2);
+        assert_eq!(ctx.roll_with_advantage_count(0, 1), Err(DiceError::InvalidSides(0)));
+    }
+
+    #[test]
+    fn test_stacked_advantage_count() {
+        let config = crate::RollConfig::default().stack_advantage(true);
+        let mut ctx = DiceContext::with_seed(174).with_config(config);
+        let triple = ctx.roll_with_advantage_count(20, 3).unwrap();
+        assert_eq!(triple.dice_values.len(), 4);
+        assert_eq!(triple.kept_value, *triple.dice_values.iter().max().unwrap());
+        let worst = ctx.roll_with_advantage_count(6, -2).unwrap();
+        assert_eq!(worst.dice_values.len(), 3);
+        assert_eq!(worst.kept_value, *worst.dice_values.iter().min().unwrap());
+        assert!(ctx.roll_with_advantage_count(6, i32::MIN).is_err());
+    }
+
+    #[test]
+    fn test_pool_best_and_worst_of() {
+        let mut ctx = DiceContext::with_seed(172);
+        for _ in 0..100 {
+            let best = ctx.roll_pool_best_of(4, 6, 3).unwrap();
+            assert_eq!(best.pool_results.len(), 3);
+            assert_eq!(best.chosen_result, *best.pool_results.iter().max().unwrap());
+            assert_eq!(best.pool_results[best.chosen_index], best.chosen_result);
+            assert!((4..=24).contains(&best.chosen_result));
+
+            let worst = ctx.roll_pool_worst_of(2, 20, 4).unwrap();
+            assert_eq!(worst.chosen_result, *worst.pool_results.iter().min().unwrap());
+            assert_eq!(worst.pool_results[worst.chosen_index], worst.chosen_result);
+        }
+        // Ties keep the first pool
+        assert_eq!(ctx.roll_pool_best_of(3, 1, 5).unwrap().chosen_index, 0);
+    }
+
+    #[test]
+    fn test_best_of_one_is_a_plain_roll() {
+        let mut a = DiceContext::with_seed(172);
+        let mut b = a.clone();
+        let best = a.roll_pool_best_of(3, 6, 1).unwrap();
+        let total: i32 = b.roll_pool(3, 6).unwrap().iter().sum();
+        assert_eq!(best.pool_results, vec![total]);
+        assert_eq!((best.chosen_result, best.chosen_index), (total, 0));
+    }
+
+    #[test]
+    fn test_best_of_errors() {
+        let mut ctx = DiceContext::with_seed(172);
+        assert_eq!(ctx.roll_pool_best_of(4, 6, 0), Err(DiceError::InvalidCount(0)));
+        assert_eq!(ctx.roll_pool_worst_of(0, 6, 2), Err(DiceError::InvalidCount(0)));
+        assert_eq!(ctx.roll_pool_best_of(4, 0, 2), Err(DiceError::InvalidSides(0)));
+        assert!(matches!(
+            ctx.roll_pool_best_of(4, 6, u32::MAX),
+            Err(DiceError::RollCountLimit { .. })
+        ));
+    }
+
+    #[test]
+    fn test_net_advantage() {
+        assert_eq!(net_advantage(3 - 1), (3, 1, KeepMode::Highest));
+        assert_eq!(net_advantage(0), (1, 1, KeepMode::Highest));
+        assert_eq!(net_advantage(-1), (2, 1, KeepMode::Lowest));
+        assert_eq!(net_advantage(i32::MIN).2, KeepMode::Lowest);
+    }
+
+    #[test]
+    fn test_universal_modifier() {
+        let mut ctx = DiceContext::with_seed(196);
+        for _ in 0..200 {
+            let result = ctx.roll_universal_modifier("1d20", "1d6", "1d4").unwrap();
+            assert!((1..=20).contains(&result.base_roll));
+            assert!((1..=6).contains(&result.advantage_roll));
+            assert!((1..=4).contains(&result.disadvantage_roll));
+            assert_eq!(
+                result.total,
+                result.base_roll + result.advantage_roll - result.disadvantage_roll
+            );
+        }
+        let result = ctx.roll_universal_modifier("1d20+2", "", " ").unwrap();
+        assert_eq!((result.advantage_roll, result.disadvantage_roll), (0, 0));
+        assert_eq!(result.total, result.base_roll);
+        let result = ctx.roll_universal_modifier("10", "", "2d4").unwrap();
+        assert_eq!(result.total, 10 - result.disadvantage_roll);
+    }
+
+    #[test]
+    fn test_universal_modifier_errors() {
+        let mut ctx = DiceContext::with_seed(196);
+        assert!(ctx.roll_universal_modifier("", "1d6", "").is_err());
+        assert!(ctx.roll_universal_modifier("1d20", "1d", "").is_err());
+        assert_eq!(
+            ctx.roll_universal_modifier("2147483647", "1", ""),
+            Err(DiceError::Overflow)
+        );
+        assert!(Dice::roll_universal_modifier("1d20", "1d6", "1d4").is_ok());
+    }
+
+    #[test]
+    fn test_best_of_independent_groups() {
+        let mut ctx = DiceContext::with_seed(199);
+        for _ in 0..200 {
+            let result = ctx.roll_sum_best_of_independent_groups(3, 4, 6, 3).unwrap();
+            assert_eq!(result.all_groups.len(), 3);
+            assert!(result.all_groups.iter().all(|group| group.len() == 3));
+            let totals = result.group_totals();
+            let highest = *totals.iter().max().unwrap();
+            assert_eq!(result.total, highest);
+            // Ties go to the first group reaching the highest sum
+            assert_eq!(Some(result.chosen_index), totals.iter().position(|&t| t == highest));
+            assert_eq!(result.chosen_group, result.all_groups[result.chosen_index]);
+        }
+        let result = GroupBestResult {
+            all_groups: vec![vec![4, 2], vec![5, 1], vec![6]],
+            chosen_group: vec![4, 2],
+            chosen_index: 0,
+            total: 6,
+        };
+        assert_eq!(result.group_totals(), vec![6, 6, 6]);
+    }
+
+    #[test]
+    fn test_best_of_independent_groups_errors() {
+        let mut ctx = DiceContext::with_seed(199);
+        let single = ctx.roll_sum_best_of_independent_groups(1, 2, 8, 2).unwrap();
+        assert_eq!((single.chosen_index, single.total), (0, single.group_totals()[0]));
+        assert_eq!(
+            ctx.roll_sum_best_of_independent_groups(0, 4, 6, 3),
+            Err(DiceError::InvalidCount(0))
+        );
+        assert_eq!(
+            ctx.roll_sum_best_of_independent_groups(3, 4, 6, 0),
+            Err(DiceError::InvalidCount(0))
+        );
+        assert_eq!(
+            ctx.roll_sum_best_of_independent_groups(3, 2, 6, 3),
+            Err(DiceError::InvalidCount(2))
+        );
+        assert!(matches!(
+            ctx.roll_sum_best_of_independent_groups(u32::MAX, 4, 6, 3),
+            Err(DiceError::RollCountLimit { .. })
+        ));
+        assert!(Dice::roll_sum_best_of_independent_groups(3, 4, 6, 3).is_ok());
+    }
+}
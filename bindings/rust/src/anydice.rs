@@ -0,0 +1,238 @@
+//! AnyDice export
+//!
+//! Renders expressions in the notation of the AnyDice probability
+//! calculator so a formula can be graphed there. Keep and drop become the
+//! `[highest N of ...]` and `[lowest N of ...]` functions, exploding dice
+//! `[explode dN]` and success counts `[count {...} in ...]`. Features
+//! AnyDice can't express fail with [`DiceError::UnsupportedFeature`].
+
+use crate::expression::DiceExpression;
+use crate::{DiceError, DiceResult};
+
+impl DiceExpression {
+    /// AnyDice notation for this expression
+    ///
+    /// `3d6` stays `3d6`, `4d6k3` becomes `[highest 3 of 4d6]` and `1d6!`
+    /// becomes `[explode d6]`. AnyDice explodes two levels deep by default,
+    /// fewer than [`MAX_EXPLOSION_DEPTH`](crate::eval::MAX_EXPLOSION_DEPTH);
+    /// prefix the output with `set "explode depth" to 10` to match.
+    ///
+    /// Variables, function calls, conditionals, `min`/`max`, clamps, custom
+    /// explosion thresholds or faces and selections on anything but a plain `NdS`
+    /// pool fail with [`DiceError::UnsupportedFeature`], as does rounding
+    /// division of a value that may be negative.
+    pub fn to_anydice_notation(&self) -> DiceResult<String> {
+        let mut out = String::new();
+        write_expr(self, &mut out)?;
+        Ok(out)
+    }
+}
+
+fn unsupported(feature: &str) -> DiceError {
+    DiceError::UnsupportedFeature(format!("{} in AnyDice notation", feature))
+}
+
+/// Binding strength of a node as written for AnyDice; higher binds tighter
+fn precedence(expr: &DiceExpression) -> u8 {
+    match expr {
+        DiceExpression::Add(..) | DiceExpression::Sub(..) => 1,
+        DiceExpression::Mul(..)
+        | DiceExpression::Div(..)
+        | DiceExpression::FloorDiv(..)
+        | DiceExpression::CeilDiv(..) => 2,
+        // Signed operands are always parenthesized
+        DiceExpression::Negate(_) => 0,
+        DiceExpression::Constant(value) if *value < 0 => 0,
+        _ => 3,
+    }
+}
+
+fn write_expr(expr: &DiceExpression, out: &mut String) -> DiceResult<()> {
+    use DiceExpression::*;
+
+    match expr {
+        Constant(value) => out.push_str(&value.to_string()),
+        Dice(count, sides) => out.push_str(&format!("{}d{}", count, sides)),
+        KeepHighest(inner, n) | KeepLowest(inner, n) | DropHighest(inner, n)
+        | DropLowest(inner, n) => {
+            let &Dice(count, sides) = &**inner else {
+                return Err(unsupported("keep or drop on a modified pool"));
+            };
+            let n = (*n).min(count);
+            let (highest, kept) = match expr {
+                KeepHighest(..) => (true, n),
+                KeepLowest(..) => (false, n),
+                DropHighest(..) => (false, count - n),
+                _ => (true, count - n),
+            };
+            if kept == 0 {
+                out.push('0');
+            } else {
+                let which = if highest { "highest" } else { "lowest" };
+                out.push_str(&format!("[{} {} of {}d{}]", which, kept, count, sides));
+            }
+        }
+        Explode(inner, threshold) => match **inner {
+            Dice(1, sides) if sides == *threshold => {
+                out.push_str(&format!("[explode d{}]", sides))
+            }
+            Dice(count, sides) if sides == *threshold => {
+                out.push_str(&format!("{}d[explode d{}]", count, sides))
+            }
+            _ => return Err(unsupported("explosion thresholds below the highest face")),
+        },
+        ExplodeOn(..) => return Err(unsupported("explosions on specific faces")),
+        CountSuccesses(inner, target) => {
+            let &Dice(count, sides) = &**inner else {
+                return Err(unsupported("success counts on a modified pool"));
+            };
+            if *target > sides {
+                out.push('0');
+            } else {
+                let low = (*target).max(1);
+                out.push_str(&format!("[count {{{}..{}}} in {}d{}]", low, sides, count, sides));
+            }
+        }
+        Negate(inner) => {
+            out.push('-');
+            write_operand(inner, precedence(inner) < 3, out)?;
+        }
+        AbsoluteValue(inner) => {
+            out.push_str("[absolute ");
+            write_operand(inner, precedence(inner) < 3, out)?;
+            out.push(']');
+        }
+        Add(left, right) => write_binary(expr, left, "+", right, out)?,
+        Sub(left, right) => write_binary(expr, left, "-", right, out)?,
+        Mul(left, right) => write_binary(expr, left, "*", right, out)?,
+        // AnyDice division truncates toward zero, like `Div`
+        Div(left, right) => write_binary(expr, left, "/", right, out)?,
+        // Truncation only rounds down or up as asked for non-negative values
+        FloorDiv(inner, divisor) | CeilDiv(inner, divisor) => {
+            if *divisor <= 0 || !matches!(inner.min_result(), Ok(min) if min >= 0) {
+                return Err(unsupported("rounding division of negative values"));
+            }
+            if matches!(expr, FloorDiv(..)) {
+                write_operand(inner, precedence(inner) < 2, out)?;
+            } else {
+                out.push('(');
+                write_operand(inner, precedence(inner) < 1, out)?;
+                out.push_str(&format!("+{})", divisor - 1));
+            }
+            out.push_str(&format!("/{}", divisor));
+        }
+        Variable(_) => return Err(unsupported("variables")),
+        DynamicDice(..) => return Err(unsupported("variable dice counts")),
+        Call(..) => return Err(unsupported("function calls")),
+        Conditional { .. } => return Err(unsupported("conditionals")),
+        Min(..) | Max(..) => return Err(unsupported("min and max")),
+        FloorClamp(..) | CeilClamp(..) => return Err(unsupported("result clamps")),
+        CountSuccessesWithBotch(..) => return Err(unsupported("botches")),
+    }
+    Ok(())
+}
+
+/// Binary operators are left associative, so a right operand at the same
+/// level keeps its parentheses
+fn write_binary(
+    expr: &DiceExpression,
+    left: &DiceExpression,
+    op: &str,
+    right: &DiceExpression,
+    out: &mut String,
+) -> DiceResult<()> {
+    let level = precedence(expr);
+    write_operand(left, precedence(left) < level, out)?;
+    out.push_str(op);
+    write_operand(right, precedence(right) <= level, out)
+}
+
+fn write_operand(expr: &DiceExpression, parenthesize: bool, out: &mut String) -> DiceResult<()> {
+    if parenthesize {
+        out.push('(');
+        write_expr(expr, out)?;
+        out.push(')');
+        Ok(())
+    } else {
+        write_expr(expr, out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn anydice(notation: &str) -> DiceResult<String> {
+        DiceExpression::parse(notation).unwrap().to_anydice_notation()
+    }
+
+    #[test]
+    fn test_common_formulas() {
+        let cases = [
+            ("3d6", "3d6"),
+            ("1d20+5", "1d20+5"),
+            ("4d6k3", "[highest 3 of 4d6]"),
+            ("4d6dl1", "[highest 3 of 4d6]"),
+            ("2d20kl1+7", "[lowest 1 of 2d20]+7"),
+            ("2d20dh1", "[lowest 1 of 2d20]"),
+            ("1d6!", "[explode d6]"),
+            ("3d6!+2", "3d[explode d6]+2"),
+            ("8d6", "8d6"),
+            ("2d6+1d8+3", "2d6+1d8+3"),
+            ("(1d8+2)*2", "(1d8+2)*2"),
+            ("1d20-(1d4-1)", "1d20-(1d4-1)"),
+            ("d%", "1d100"),
+            ("8d6>5", "[count {5..6} in 8d6]"),
+            ("8d6>7", "0"),
+            ("|1d6-4|", "[absolute (1d6-4)]"),
+            ("-1d6+10", "(-1d6)+10"),
+            ("10-(-1d6)", "10-(-1d6)"),
+            ("8d6/2", "8d6/2"),
+            ("12/1d4", "12/1d4"),
+        ];
+        for (notation, expected) in cases {
+            assert_eq!(anydice(notation).unwrap(), expected, "{}", notation);
+        }
+        let half_up = DiceExpression::parse("8d6").unwrap().ceil_div(2);
+        assert_eq!(half_up.to_anydice_notation().unwrap(), "(8d6+1)/2");
+    }
+
+    #[test]
+    fn test_output_is_well_formed() {
+        for notation in ["4d6k3+1d4*2", "(2d6!-1)*|1d4-2|", "6d10>8+1d20kl1"] {
+            let output = anydice(notation).unwrap();
+            let depth = output.chars().try_fold(0i32, |depth, c| {
+                let depth = match c {
+                    '(' | '[' | '{' => depth + 1,
+                    ')' | ']' | '}' => depth - 1,
+                    _ => depth,
+                };
+                (depth >= 0).then_some(depth)
+            });
+            assert_eq!(depth, Some(0), "{}", output);
+        }
+    }
+
+    #[test]
+    fn test_unsupported_features() {
+        for notation in [
+            "Nd6",
+            "1d6+MOD",
+            "DAMAGE(6)",
+            "(1d20>=15)?2d6:1d6",
+            "min(1d6, 1d8)",
+            "1d6min2",
+            "5d10>7b1",
+            "3d6!>5",
+            "1d6!{1,6}",
+            "4d6!k3",
+            "(1d6-3)/2",
+        ] {
+            assert!(
+                matches!(anydice(notation), Err(DiceError::UnsupportedFeature(_))),
+                "{}",
+                notation
+            );
+        }
+    }
+}
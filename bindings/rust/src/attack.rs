@@ -0,0 +1,146 @@
+//! D&D 5e attack rolls
+//!
+//! A d20, rolled with advantage or disadvantage, plus the attack bonus. A
+//! natural 20 is a critical hit, or 18 and up for a Champion fighter, and
+//! a natural 1 always misses.
+
+use crate::context::DiceContext;
+use crate::{Dice, DiceError, DiceResult};
+
+/// Outcome of a 5e attack roll
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AttackResult5e {
+    /// Every d20 rolled, two with advantage or disadvantage
+    pub d20_values: Vec<i32>,
+    /// The d20 kept
+    pub d20_result: i32,
+    pub modifier: i32,
+    /// `d20_result + modifier`
+    pub total: i32,
+    /// The kept d20 reached the critical range
+    pub is_critical: bool,
+    /// The kept d20 was a natural 1
+    pub is_fumble: bool,
+}
+
+impl AttackResult5e {
+    fn from_roll(
+        d20_values: Vec<i32>,
+        d20_result: i32,
+        modifier: i32,
+        crit_range: u32,
+    ) -> DiceResult<AttackResult5e> {
+        Ok(AttackResult5e {
+            d20_values,
+            d20_result,
+            modifier,
+            total: d20_result.checked_add(modifier).ok_or(DiceError::Overflow)?,
+            is_critical: d20_result >= crit_range as i32,
+            is_fumble: d20_result == 1,
+        })
+    }
+}
+
+impl DiceContext {
+    /// Roll a 5e attack
+    ///
+    /// A `crit_range` outside 1-20 fails with [`DiceError::InvalidRange`].
+    ///
+    /// # Arguments
+    ///
+    /// * `modifier` - Attack bonus added to the d20
+    /// * `advantage` - Net advantage, as in
+    ///   [`roll_with_advantage_count`](Self::roll_with_advantage_count)
+    /// * `crit_range` - Lowest natural roll that crits; normally 20
+    pub fn roll_d20_5e_attack(
+        &mut self,
+        modifier: i32,
+        advantage: i32,
+        crit_range: u32,
+    ) -> DiceResult<AttackResult5e> {
+        if !(1..=20).contains(&crit_range) {
+            return Err(DiceError::InvalidRange {
+                min: 1,
+                max: 20,
+                actual: crit_range.min(i32::MAX as u32) as i32,
+            });
+        }
+        let roll = self.roll_with_advantage_count(20, advantage)?;
+        AttackResult5e::from_roll(roll.dice_values, roll.kept_value, modifier, crit_range)
+    }
+}
+
+impl Dice {
+    /// Roll a 5e attack
+    ///
+    /// # Arguments
+    ///
+    /// * `modifier` - Attack bonus added to the d20
+    /// * `advantage` - Net advantage; positive keeps the higher d20
+    /// * `crit_range` - Lowest natural roll that crits; normally 20
+    pub fn roll_d20_5e_attack(
+        modifier: i32,
+        advantage: i32,
+        crit_range: u32,
+    ) -> DiceResult<AttackResult5e> {
+        DiceContext::new().roll_d20_5e_attack(modifier, advantage, crit_range)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attack(d20: i32, modifier: i32, crit_range: u32) -> AttackResult5e {
+        AttackResult5e::from_roll(vec![d20], d20, modifier, crit_range).unwrap()
+    }
+
+    #[test]
+    fn test_natural_twenty_and_one() {
+        let crit = attack(20, 5, 20);
+        assert_eq!(crit.total, 25);
+        assert!(crit.is_critical && !crit.is_fumble);
+
+        let fumble = attack(1, 12, 20);
+        assert_eq!(fumble.total, 13);
+        assert!(fumble.is_fumble && !fumble.is_critical);
+
+        let hit = attack(19, 5, 20);
+        assert!(!hit.is_critical && !hit.is_fumble);
+    }
+
+    #[test]
+    fn test_champion_crit_range() {
+        assert!(attack(18, 3, 18).is_critical);
+        assert!(attack(19, 3, 19).is_critical);
+        assert!(!attack(17, 3, 18).is_critical);
+        let mut ctx = DiceContext::with_seed(175);
+        assert!(matches!(
+            ctx.roll_d20_5e_attack(0, 0, 21),
+            Err(DiceError::InvalidRange { actual: 21, .. })
+        ));
+        assert!(ctx.roll_d20_5e_attack(0, 0, 0).is_err());
+    }
+
+    #[test]
+    fn test_advantage_rolls() {
+        let mut ctx = DiceContext::with_seed(175);
+        let (mut crits, mut fumbles) = (0, 0);
+        for _ in 0..1000 {
+            let result = ctx.roll_d20_5e_attack(4, 1, 19).unwrap();
+            assert_eq!(result.d20_values.len(), 2);
+            assert_eq!(result.d20_result, *result.d20_values.iter().max().unwrap());
+            assert_eq!(result.total, result.d20_result + 4);
+            crits += u32::from(result.is_critical);
+            fumbles += u32::from(result.is_fumble);
+
+            let result = ctx.roll_d20_5e_attack(-1, -1, 20).unwrap();
+            assert_eq!(result.d20_result, *result.d20_values.iter().min().unwrap());
+        }
+        // 1 - 0.9^2 = 19% crit with advantage, 0.25% fumble
+        assert!((140..240).contains(&crits), "{}", crits);
+        assert!(fumbles < 15, "{}", fumbles);
+        assert_eq!(ctx.roll_d20_5e_attack(0, 0, 20).unwrap().d20_values.len(), 1);
+    }
+}
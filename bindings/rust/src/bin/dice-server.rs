@@ -0,0 +1,39 @@
+//! Standalone HTTP dice server
+//!
+//! Usage: `dice-server [ADDR]` (default `127.0.0.1:3000`).
+//! Set `DICE_SEED` for a reproducible roll sequence.
+
+use roll_dice::server;
+use roll_dice::DiceContext;
+
+#[tokio::main]
+async fn main() {
+    let addr = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "127.0.0.1:3000".to_string());
+
+    let ctx = match std::env::var("DICE_SEED") {
+        Ok(seed) => match seed.parse() {
+            Ok(seed) => DiceContext::with_seed(seed),
+            Err(_) => {
+                eprintln!("Invalid DICE_SEED: {}", seed);
+                std::process::exit(1);
+            }
+        },
+        Err(_) => DiceContext::new(),
+    };
+
+    let listener = match tokio::net::TcpListener::bind(&addr).await {
+        Ok(listener) => listener,
+        Err(err) => {
+            eprintln!("Failed to bind {}: {}", addr, err);
+            std::process::exit(1);
+        }
+    };
+
+    println!("dice-server listening on {}", addr);
+    if let Err(err) = axum::serve(listener, server::router(ctx)).await {
+        eprintln!("Server error: {}", err);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,319 @@
+//! Exact outcome bounds
+//!
+//! The smallest and largest totals an expression can produce, computed from
+//! the tree without rolling. Variables have no bounds until they are bound,
+//! so expressions containing them fail with [`DiceError::UnboundVariable`].
+
+use crate::eval::{
+    ceil_div, check_exploding_faces, floor_div, MAX_DICE_COUNT, MAX_EXPLOSION_DEPTH, MAX_SIDES,
+};
+use crate::expression::DiceExpression;
+use crate::{DiceError, DiceResult};
+
+impl DiceExpression {
+    /// Smallest total the expression can produce (`3` for `3d6`)
+    ///
+    /// Fails with [`DiceError::Overflow`] if the bound doesn't fit in an
+    /// `i32`, and with the error evaluation would report for invalid dice.
+    pub fn min_result(&self) -> DiceResult<i32> {
+        bounds(self).map(|(min, _)| min)
+    }
+
+    /// Largest total the expression can produce (`18` for `3d6`)
+    ///
+    /// Fails like [`min_result`](Self::min_result).
+    pub fn max_result(&self) -> DiceResult<i32> {
+        bounds(self).map(|(_, max)| max)
+    }
+}
+
+/// (min, max) of an expression
+fn bounds(expr: &DiceExpression) -> DiceResult<(i32, i32)> {
+    match expr {
+        DiceExpression::Constant(value) => Ok((*value, *value)),
+        DiceExpression::Variable(name) => Err(DiceError::UnboundVariable(name.clone())),
+        DiceExpression::Call(name, _) => Err(DiceError::UnsupportedFeature(format!(
+            "bounds of user-defined function {}",
+            name
+        ))),
+        DiceExpression::CountSuccesses(inner, target) => {
+            let pool = pool_shape(inner)?;
+            let min = if *target <= 1 { pool.min_dice } else { 0 };
+            let max = if *target <= pool.sides { pool.max_dice } else { 0 };
+            Ok((to_i32(min)?, to_i32(max)?))
+        }
+        DiceExpression::CountSuccessesWithBotch(inner, target, botch) => {
+            let pool = pool_shape(inner)?;
+            // A die's contribution grows with its face, so 1 and the top face bound it
+            let per_die = |face: u32| i32::from(face >= *target) - i32::from(face <= *botch);
+            let (low, high) = (per_die(1), per_die(pool.sides));
+            let (min_dice, max_dice) = (to_i32(pool.min_dice)?, to_i32(pool.max_dice)?);
+            let min = (low * min_dice).min(low * max_dice);
+            let max = (high * min_dice).max(high * max_dice);
+            Ok((min, max))
+        }
+        // Either branch may be taken
+        DiceExpression::Conditional {
+            then_expr, else_expr, ..
+        } => {
+            let ((tmin, tmax), (emin, emax)) = (bounds(then_expr)?, bounds(else_expr)?);
+            Ok((tmin.min(emin), tmax.max(emax)))
+        }
+        DiceExpression::Negate(inner) => {
+            let (min, max) = bounds(inner)?;
+            Ok((checked(max.checked_neg())?, checked(min.checked_neg())?))
+        }
+        DiceExpression::AbsoluteValue(inner) => match bounds(inner)? {
+            (min, max) if min >= 0 => Ok((min, max)),
+            (min, max) if max <= 0 => Ok((checked(max.checked_neg())?, checked(min.checked_neg())?)),
+            (min, max) => Ok((0, checked(min.checked_neg())?.max(max))),
+        },
+        DiceExpression::FloorClamp(inner, minimum) => {
+            let (min, max) = bounds(inner)?;
+            Ok((min.max(*minimum), max.max(*minimum)))
+        }
+        DiceExpression::CeilClamp(inner, maximum) => {
+            let (min, max) = bounds(inner)?;
+            Ok((min.min(*maximum), max.min(*maximum)))
+        }
+        DiceExpression::FloorDiv(inner, divisor) | DiceExpression::CeilDiv(inner, divisor) => {
+            let divide = match expr {
+                DiceExpression::FloorDiv(..) => floor_div,
+                _ => ceil_div,
+            };
+            let (min, max) = bounds(inner)?;
+            // Rounded division is monotonic, so the bounds map to bounds
+            let (a, b) = (divide(min, *divisor)?, divide(max, *divisor)?);
+            Ok((a.min(b), a.max(b)))
+        }
+        DiceExpression::Min(left, right) => {
+            let ((lmin, lmax), (rmin, rmax)) = (bounds(left)?, bounds(right)?);
+            Ok((lmin.min(rmin), lmax.min(rmax)))
+        }
+        DiceExpression::Max(left, right) => {
+            let ((lmin, lmax), (rmin, rmax)) = (bounds(left)?, bounds(right)?);
+            Ok((lmin.max(rmin), lmax.max(rmax)))
+        }
+        DiceExpression::Add(left, right) => {
+            let ((lmin, lmax), (rmin, rmax)) = (bounds(left)?, bounds(right)?);
+            Ok((checked(lmin.checked_add(rmin))?, checked(lmax.checked_add(rmax))?))
+        }
+        DiceExpression::Sub(left, right) => {
+            let ((lmin, lmax), (rmin, rmax)) = (bounds(left)?, bounds(right)?);
+            Ok((checked(lmin.checked_sub(rmax))?, checked(lmax.checked_sub(rmin))?))
+        }
+        DiceExpression::Mul(left, right) => {
+            let ((lmin, lmax), (rmin, rmax)) = (bounds(left)?, bounds(right)?);
+            extremes(&[lmin, lmax], &[rmin, rmax], i32::checked_mul)
+        }
+        DiceExpression::Div(left, right) => {
+            let ((lmin, lmax), (rmin, rmax)) = (bounds(left)?, bounds(right)?);
+            // Truncating division peaks at the divisor's endpoints or at ±1
+            let divisors: Vec<i32> = [rmin, rmax, -1, 1]
+                .into_iter()
+                .filter(|&d| d != 0 && (rmin..=rmax).contains(&d))
+                .collect();
+            if divisors.is_empty() {
+                return Err(DiceError::InvalidNotation("division by zero".to_string()));
+            }
+            extremes(&[lmin, lmax], &divisors, i32::checked_div)
+        }
+        _ => {
+            let pool = pool_shape(expr)?;
+            let max = pool.max_dice.checked_mul(pool.sides).ok_or(DiceError::Overflow)?;
+            Ok((to_i32(pool.min_dice)?, to_i32(max)?))
+        }
+    }
+}
+
+/// How many dice a pool node can keep, and of what size
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct PoolShape {
+    pub min_dice: u32,
+    pub max_dice: u32,
+    pub sides: u32,
+}
+
+/// Shape of a pool node
+pub(crate) fn pool_shape(expr: &DiceExpression) -> DiceResult<PoolShape> {
+    match expr {
+        DiceExpression::Dice(count, sides) => {
+            if *count == 0 || *count > MAX_DICE_COUNT {
+                return Err(DiceError::InvalidCount(*count as i32));
+            }
+            if *sides == 0 || *sides > MAX_SIDES {
+                return Err(DiceError::InvalidSides(*sides as i32));
+            }
+            Ok(PoolShape {
+                min_dice: *count,
+                max_dice: *count,
+                sides: *sides,
+            })
+        }
+        DiceExpression::DynamicDice(count, sides) => {
+            let (min, max) = bounds(count)?;
+            if max <= 0 || max > MAX_DICE_COUNT as i32 {
+                return Err(DiceError::InvalidCount(max));
+            }
+            pool_shape(&DiceExpression::Dice(max as u32, *sides))?;
+            Ok(PoolShape {
+                min_dice: min.max(1) as u32,
+                max_dice: max as u32,
+                sides: *sides,
+            })
+        }
+        DiceExpression::Explode(inner, threshold) => {
+            let pool = pool_shape(inner)?;
+            let chained = pool.max_dice * (1 + MAX_EXPLOSION_DEPTH);
+            Ok(PoolShape {
+                // A threshold of 1 explodes every die to the limit
+                min_dice: if *threshold <= 1 { chained } else { pool.min_dice },
+                max_dice: if *threshold <= pool.sides { chained } else { pool.max_dice },
+                ..pool
+            })
+        }
+        DiceExpression::ExplodeOn(inner, faces) => {
+            let pool = pool_shape(inner)?;
+            check_exploding_faces(faces, pool.sides)?;
+            let chained = pool.max_dice * (1 + MAX_EXPLOSION_DEPTH);
+            Ok(PoolShape {
+                max_dice: if faces.is_empty() { pool.max_dice } else { chained },
+                ..pool
+            })
+        }
+        DiceExpression::KeepHighest(inner, n) | DiceExpression::KeepLowest(inner, n) => {
+            let pool = pool_shape(inner)?;
+            Ok(PoolShape {
+                min_dice: pool.min_dice.min(*n),
+                max_dice: pool.max_dice.min(*n),
+                ..pool
+            })
+        }
+        DiceExpression::DropHighest(inner, n) | DiceExpression::DropLowest(inner, n) => {
+            let pool = pool_shape(inner)?;
+            Ok(PoolShape {
+                min_dice: pool.min_dice.saturating_sub(*n),
+                max_dice: pool.max_dice.saturating_sub(*n),
+                ..pool
+            })
+        }
+        _ => Err(DiceError::InvalidNotation(
+            "selection requires a dice pool".to_string(),
+        )),
+    }
+}
+
+/// Smallest and largest `op(a, b)` over the candidate values
+fn extremes(a: &[i32], b: &[i32], op: fn(i32, i32) -> Option<i32>) -> DiceResult<(i32, i32)> {
+    let mut values = Vec::with_capacity(a.len() * b.len());
+    for &x in a {
+        for &y in b {
+            values.push(checked(op(x, y))?);
+        }
+    }
+    Ok((
+        values.iter().copied().min().unwrap_or(0),
+        values.iter().copied().max().unwrap_or(0),
+    ))
+}
+
+fn checked(value: Option<i32>) -> DiceResult<i32> {
+    value.ok_or(DiceError::Overflow)
+}
+
+fn to_i32(value: u32) -> DiceResult<i32> {
+    i32::try_from(value).map_err(|_| DiceError::Overflow)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::DiceRng;
+
+    fn range(notation: &str) -> (i32, i32) {
+        let expr = DiceExpression::parse(notation).unwrap();
+        (expr.min_result().unwrap(), expr.max_result().unwrap())
+    }
+
+    #[test]
+    fn test_dice_bounds() {
+        assert_eq!(range("3d6"), (3, 18));
+        assert_eq!(range("3d6+2"), (5, 20));
+        assert_eq!(range("1d20-1d4"), (-3, 19));
+        assert_eq!(range("7"), (7, 7));
+        assert_eq!(range("-2d6+5"), (-7, 3));
+        assert_eq!(range("-1d1"), (-1, -1));
+        assert_eq!(range("|1d6-4|"), (0, 3));
+        assert_eq!(range("|1d6|"), (1, 6));
+        assert_eq!(range("|1d6-9|"), (3, 8));
+        assert_eq!(range("|1d20-10|"), (0, 10));
+        assert_eq!(range("|1d20-3|"), (0, 17));
+    }
+
+    #[test]
+    fn test_selection_bounds() {
+        assert_eq!(range("4d6k3"), (3, 18));
+        assert_eq!(range("2d20kl1"), (1, 20));
+        assert_eq!(range("4d6dl1"), (3, 18));
+        assert_eq!(range("2d6dl5"), (0, 0));
+        assert_eq!(range("8d6>4"), (0, 8));
+        assert_eq!(range("8d6>1"), (8, 8));
+        assert_eq!(range("8d6>7"), (0, 0));
+        assert_eq!(range("2d6!"), (2, 132));
+        assert_eq!(range("2d6!>7"), (2, 12));
+        assert_eq!(range("4d6!k3"), (3, 18));
+    }
+
+    #[test]
+    fn test_arithmetic_bounds() {
+        assert_eq!(range("(1d6-3)*2"), (-4, 6));
+        assert_eq!(range("(1d6-3)*(1d4-2)"), (-4, 6));
+        assert_eq!(range("10/1d4"), (2, 10));
+        assert_eq!(range("12/(1d3-2)"), (-12, 12));
+        assert_eq!(range("(1d20>=15)?(2d6+5):1d4"), (1, 17));
+        assert_eq!(range("(1d6)?-3:10"), (-3, 10));
+        assert_eq!(range("2d6/2"), (1, 6));
+        assert_eq!(range("(1d6-4)/2"), (-2, 1));
+        let expr = DiceExpression::parse("3d6").unwrap();
+        assert_eq!(expr.clone().ceil_div(2).min_result(), Ok(2));
+        assert_eq!(expr.clone().ceil_div(-2).min_result(), Ok(-9));
+        assert_eq!(expr.floor_div(-2).max_result(), Ok(-2));
+        assert_eq!(range("min(2d6, 1d8)"), (1, 8));
+        assert_eq!(range("max(2d6, 1d8)"), (2, 12));
+        assert_eq!(range("max(1d4, 10)"), (10, 10));
+        assert_eq!(range("1d6min3"), (3, 6));
+        assert_eq!(range("1d6min2max5"), (2, 5));
+        assert_eq!(range("1d6min9"), (9, 9));
+    }
+
+    #[test]
+    fn test_bounds_contain_rolls() {
+        let mut rng = DiceRng::new(31);
+        for notation in ["4d6k3+1d4", "(2d6-7)*1d3", "20/1d6-1d2", "6d4dh2>3", "3d4!>3",
+            "min(2d6,1d8)", "max(1d4-2,1d3)", "3d6min8max14"] {
+            let expr = DiceExpression::parse(notation).unwrap();
+            let (min, max) = (expr.min_result().unwrap(), expr.max_result().unwrap());
+            for _ in 0..500 {
+                let total = expr.evaluate(&mut rng).unwrap();
+                assert!((min..=max).contains(&total), "{} gave {}", notation, total);
+            }
+        }
+    }
+
+    #[test]
+    fn test_bounds_errors() {
+        let big = DiceExpression::parse("3*1000d1000000").unwrap();
+        assert_eq!(big.max_result(), Err(DiceError::Overflow));
+        let sum = DiceExpression::parse("2147483647+1d2").unwrap();
+        assert_eq!(sum.max_result(), Err(DiceError::Overflow));
+        assert!(DiceExpression::parse("1d6/0").unwrap().min_result().is_err());
+        assert_eq!(
+            DiceExpression::Dice(0, 6).min_result(),
+            Err(DiceError::InvalidCount(0))
+        );
+        assert_eq!(
+            DiceExpression::parse("Nd6+2").unwrap().max_result(),
+            Err(DiceError::UnboundVariable("N".to_string()))
+        );
+    }
+}
@@ -0,0 +1,303 @@
+//! Playing cards
+//!
+//! Some systems draw cards alongside or instead of dice: Deadlands and
+//! Savage Worlds use a poker deck for initiative and for suit modifiers. A
+//! [`Deck`] is shuffled with the same generators as the dice, so draws are
+//! reproducible from a seed.
+
+use crate::context::DiceContext;
+use crate::{Dice, DiceError, DiceResult};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use std::fmt;
+
+/// Suit of a playing card, ordered Clubs < Diamonds < Hearts < Spades
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Suit {
+    Clubs,
+    Diamonds,
+    Hearts,
+    Spades,
+}
+
+impl Suit {
+    /// Every suit, lowest first
+    pub const ALL: [Suit; 4] = [Suit::Clubs, Suit::Diamonds, Suit::Hearts, Suit::Spades];
+}
+
+/// A card from a [`Deck`]
+///
+/// Cards order by rank, then by suit, with Jokers above everything.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PlayingCard {
+    /// `rank` runs from 2 to 14: 11 is a Jack, 12 a Queen, 13 a King and
+    /// 14 an Ace
+    Suited { rank: u8, suit: Suit },
+    Joker,
+}
+
+impl PlayingCard {
+    /// Suit of the card; Jokers have none
+    pub fn suit(&self) -> Option<Suit> {
+        match self {
+            PlayingCard::Suited { suit, .. } => Some(*suit),
+            PlayingCard::Joker => None,
+        }
+    }
+
+    /// Whether the card is a Joker
+    pub fn is_joker(&self) -> bool {
+        matches!(self, PlayingCard::Joker)
+    }
+}
+
+impl fmt::Display for PlayingCard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (rank, suit) = match self {
+            PlayingCard::Joker => return write!(f, "Joker"),
+            PlayingCard::Suited { rank, suit } => (rank, suit),
+        };
+        match rank {
+            11 => write!(f, "J")?,
+            12 => write!(f, "Q")?,
+            13 => write!(f, "K")?,
+            14 => write!(f, "A")?,
+            rank => write!(f, "{}", rank)?,
+        }
+        let suit = match suit {
+            Suit::Clubs => 'C',
+            Suit::Diamonds => 'D',
+            Suit::Hearts => 'H',
+            Suit::Spades => 'S',
+        };
+        write!(f, "{}", suit)
+    }
+}
+
+/// A deck of playing cards with a discard pile
+///
+/// Drawn cards go to the discard pile. When the draw pile runs out the
+/// discards are shuffled back in, unless `auto_reshuffle` is off.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Deck {
+    draw_pile: Vec<PlayingCard>,
+    discard_pile: Vec<PlayingCard>,
+    /// Shuffle the discards back in when a draw finds the deck empty
+    pub auto_reshuffle: bool,
+}
+
+impl Deck {
+    /// Unshuffled 52-card deck
+    pub fn new() -> Deck {
+        let draw_pile = Suit::ALL
+            .iter()
+            .flat_map(|&suit| (2..=14).map(move |rank| PlayingCard::Suited { rank, suit }))
+            .collect();
+        Deck {
+            draw_pile,
+            discard_pile: Vec::new(),
+            auto_reshuffle: true,
+        }
+    }
+
+    /// Unshuffled 54-card deck with two Jokers, as Savage Worlds uses
+    pub fn with_jokers() -> Deck {
+        let mut deck = Deck::new();
+        deck.draw_pile.extend([PlayingCard::Joker; 2]);
+        deck
+    }
+
+    /// Cards left to draw
+    pub fn remaining(&self) -> usize {
+        self.draw_pile.len()
+    }
+
+    /// Cards drawn since the last shuffle
+    pub fn discarded(&self) -> &[PlayingCard] {
+        &self.discard_pile
+    }
+
+    /// Shuffle the discards back into the deck
+    pub fn shuffle<R: Rng + ?Sized>(&mut self, rng: &mut R) {
+        self.draw_pile.append(&mut self.discard_pile);
+        self.draw_pile.shuffle(rng);
+    }
+
+    /// Draw the top card, moving it to the discard pile
+    ///
+    /// An empty deck is reshuffled first if `auto_reshuffle` is on; `None`
+    /// when no card can be drawn.
+    pub fn draw<R: Rng + ?Sized>(&mut self, rng: &mut R) -> Option<PlayingCard> {
+        if self.draw_pile.is_empty() && self.auto_reshuffle {
+            self.shuffle(rng);
+        }
+        let card = self.draw_pile.pop()?;
+        self.discard_pile.push(card);
+        Some(card)
+    }
+}
+
+impl Default for Deck {
+    fn default() -> Self {
+        Deck::new()
+    }
+}
+
+/// Modifier each suit adds to a die roll
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SuitModifiers {
+    pub clubs: i32,
+    pub diamonds: i32,
+    pub hearts: i32,
+    pub spades: i32,
+}
+
+impl SuitModifiers {
+    /// Clubs +0, Diamonds +1, Hearts +2, Spades +3
+    pub fn deadlands_standard() -> SuitModifiers {
+        SuitModifiers {
+            clubs: 0,
+            diamonds: 1,
+            hearts: 2,
+            spades: 3,
+        }
+    }
+
+    /// Modifier for `card`; a Joker is wild and takes the best suit
+    pub fn modifier(&self, card: PlayingCard) -> i32 {
+        match card.suit() {
+            Some(Suit::Clubs) => self.clubs,
+            Some(Suit::Diamonds) => self.diamonds,
+            Some(Suit::Hearts) => self.hearts,
+            Some(Suit::Spades) => self.spades,
+            None => self.clubs.max(self.diamonds).max(self.hearts).max(self.spades),
+        }
+    }
+}
+
+impl DiceContext {
+    /// Roll a die and add the modifier of a card drawn from `deck`
+    ///
+    /// An empty deck that can't reshuffle fails with
+    /// [`DiceError::InvalidCount`].
+    ///
+    /// # Arguments
+    ///
+    /// * `deck` - Deck to draw from, shuffled with this context's generator
+    /// * `base_die_sides` - Sides of the die rolled
+    /// * `suit_modifiers` - Modifier each suit adds
+    ///
+    /// # Returns
+    ///
+    /// Tuple of (die plus modifier, card drawn)
+    pub fn roll_using_card_suits(
+        &mut self,
+        deck: &mut Deck,
+        base_die_sides: u32,
+        suit_modifiers: &SuitModifiers,
+    ) -> DiceResult<(i32, PlayingCard)> {
+        let roll = self.roll_pool(1, base_die_sides)?[0];
+        let card = deck.draw(self.rng()).ok_or(DiceError::InvalidCount(0))?;
+        let total = roll
+            .checked_add(suit_modifiers.modifier(card))
+            .ok_or(DiceError::Overflow)?;
+        Ok((total, card))
+    }
+}
+
+impl Dice {
+    /// Roll a die and add the modifier of a card drawn from `deck`
+    ///
+    /// # Arguments
+    ///
+    /// * `deck` - Deck to draw from
+    /// * `base_die_sides` - Sides of the die rolled
+    /// * `suit_modifiers` - Modifier each suit adds
+    ///
+    /// # Returns
+    ///
+    /// Tuple of (die plus modifier, card drawn)
+    pub fn roll_using_card_suits(
+        deck: &mut Deck,
+        base_die_sides: u32,
+        suit_modifiers: &SuitModifiers,
+    ) -> DiceResult<(i32, PlayingCard)> {
+        DiceContext::new().roll_using_card_suits(deck, base_die_sides, suit_modifiers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::DiceRng;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_deck_contents() {
+        let deck = Deck::new();
+        assert_eq!(deck.remaining(), 52);
+        let unique: HashSet<_> = deck.draw_pile.iter().collect();
+        assert_eq!(unique.len(), 52);
+        assert_eq!(Deck::with_jokers().remaining(), 54);
+    }
+
+    #[test]
+    fn test_card_order() {
+        let card = |rank, suit| PlayingCard::Suited { rank, suit };
+        assert!(card(14, Suit::Clubs) > card(13, Suit::Spades));
+        assert!(card(10, Suit::Spades) > card(10, Suit::Hearts));
+        assert!(PlayingCard::Joker > card(14, Suit::Spades));
+        assert_eq!(card(12, Suit::Hearts).to_string(), "QH");
+        assert_eq!(card(10, Suit::Clubs).to_string(), "10C");
+        assert_eq!(PlayingCard::Joker.to_string(), "Joker");
+    }
+
+    #[test]
+    fn test_draw_and_reshuffle() {
+        let mut rng = DiceRng::new(164);
+        let mut deck = Deck::new();
+        deck.shuffle(&mut rng);
+        let drawn: HashSet<_> = (0..52).map(|_| deck.draw(&mut rng).unwrap()).collect();
+        assert_eq!(drawn.len(), 52);
+        assert_eq!((deck.remaining(), deck.discarded().len()), (0, 52));
+        assert!(deck.draw(&mut rng).is_some());
+        assert_eq!((deck.remaining(), deck.discarded().len()), (51, 1));
+
+        let mut deck = Deck::new();
+        deck.auto_reshuffle = false;
+        for _ in 0..52 {
+            deck.draw(&mut rng).unwrap();
+        }
+        assert_eq!(deck.draw(&mut rng), None);
+    }
+
+    #[test]
+    fn test_roll_using_card_suits() {
+        let mut ctx = DiceContext::with_seed(4);
+        let modifiers = SuitModifiers::deadlands_standard();
+        let mut deck = Deck::with_jokers();
+        deck.shuffle(ctx.rng());
+        for _ in 0..200 {
+            let (total, card) = ctx.roll_using_card_suits(&mut deck, 6, &modifiers).unwrap();
+            let roll = total - modifiers.modifier(card);
+            assert!((1..=6).contains(&roll), "{} with {}", total, card);
+        }
+        assert_eq!(modifiers.modifier(PlayingCard::Joker), 3);
+
+        let mut empty = Deck::new();
+        empty.auto_reshuffle = false;
+        empty.draw_pile.clear();
+        assert_eq!(
+            ctx.roll_using_card_suits(&mut empty, 6, &modifiers),
+            Err(DiceError::InvalidCount(0))
+        );
+        assert_eq!(
+            ctx.roll_using_card_suits(&mut Deck::new(), 0, &modifiers),
+            Err(DiceError::InvalidSides(0))
+        );
+    }
+}
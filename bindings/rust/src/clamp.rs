@@ -0,0 +1,106 @@
+//! Clamped rolls
+//!
+//! A single die whose result is raised to a minimum or capped at a
+//! maximum, the plain-die form of the `1d6min3` and `1d6max5` modifiers.
+
+use crate::context::DiceContext;
+use crate::{Dice, DiceError, DiceResult};
+
+impl DiceContext {
+    /// Roll a die, raising a result below `minimum` to `minimum`
+    ///
+    /// # Arguments
+    ///
+    /// * `sides` - Number of sides on the die
+    /// * `minimum` - Lowest result; above `sides` every roll returns it.
+    ///   Below 1 fails with [`DiceError::InvalidSides`].
+    pub fn roll_with_minimum_result(&mut self, sides: i32, minimum: i32) -> DiceResult<i32> {
+        if minimum < 1 {
+            return Err(DiceError::InvalidSides(minimum));
+        }
+        Ok(self.roll(sides)?.max(minimum))
+    }
+
+    /// Roll a die, lowering a result above `maximum` to `maximum`
+    ///
+    /// # Arguments
+    ///
+    /// * `sides` - Number of sides on the die
+    /// * `maximum` - Highest result. Below 1 fails with
+    ///   [`DiceError::InvalidSides`].
+    pub fn roll_with_maximum_result(&mut self, sides: i32, maximum: i32) -> DiceResult<i32> {
+        if maximum < 1 {
+            return Err(DiceError::InvalidSides(maximum));
+        }
+        Ok(self.roll(sides)?.min(maximum))
+    }
+}
+
+impl Dice {
+    /// Roll a die, raising a result below `minimum` to `minimum`
+    ///
+    /// # Arguments
+    ///
+    /// * `sides` - Number of sides on the die
+    /// * `minimum` - Lowest result; above `sides` every roll returns it.
+    ///   Below 1 fails with [`DiceError::InvalidSides`].
+    pub fn roll_with_minimum_result(sides: i32, minimum: i32) -> DiceResult<i32> {
+        DiceContext::new().roll_with_minimum_result(sides, minimum)
+    }
+
+    /// Roll a die, lowering a result above `maximum` to `maximum`
+    ///
+    /// # Arguments
+    ///
+    /// * `sides` - Number of sides on the die
+    /// * `maximum` - Highest result. Below 1 fails with
+    ///   [`DiceError::InvalidSides`].
+    pub fn roll_with_maximum_result(sides: i32, maximum: i32) -> DiceResult<i32> {
+        DiceContext::new().roll_with_maximum_result(sides, maximum)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DiceExpression;
+
+    #[test]
+    fn test_minimum_result() {
+        let mut ctx = DiceContext::with_seed(157);
+        let mut seen = [false; 7];
+        for _ in 0..500 {
+            let value = ctx.roll_with_minimum_result(6, 3).unwrap();
+            assert!((3..=6).contains(&value));
+            seen[value as usize] = true;
+        }
+        assert_eq!(seen, [false, false, false, true, true, true, true]);
+        assert_eq!(ctx.roll_with_minimum_result(6, 9), Ok(9));
+        assert_eq!(ctx.roll_with_minimum_result(6, 0), Err(DiceError::InvalidSides(0)));
+        assert_eq!(ctx.roll_with_minimum_result(0, 3), Err(DiceError::InvalidSides(0)));
+    }
+
+    #[test]
+    fn test_maximum_result() {
+        let mut ctx = DiceContext::with_seed(158);
+        for _ in 0..500 {
+            assert!((1..=4).contains(&ctx.roll_with_maximum_result(6, 4).unwrap()));
+        }
+        assert_eq!(ctx.roll_with_maximum_result(6, 1), Ok(1));
+        assert_eq!(ctx.roll_with_maximum_result(6, -2), Err(DiceError::InvalidSides(-2)));
+        assert!(Dice::roll_with_maximum_result(20, 25).is_ok());
+    }
+
+    #[test]
+    fn test_matches_notation() {
+        let expr = DiceExpression::parse("1d6min3").unwrap();
+        let mut a = DiceContext::with_seed(3);
+        let mut b = DiceContext::with_seed(3);
+        for _ in 0..100 {
+            assert_eq!(a.roll_with_minimum_result(6, 3), expr.evaluate(b.rng()));
+        }
+        let both = DiceExpression::parse("1d6min2max5").unwrap();
+        assert_eq!(both.all_possible_outcomes(), Ok(vec![2, 3, 4, 5]));
+        assert!((both.pdf_exact(2).unwrap() - 2.0 / 6.0).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,100 @@
+//! Coin flips
+//!
+//! A fair coin, or a weighted one for mechanics where the odds aren't even.
+
+use crate::context::DiceContext;
+use crate::{Dice, DiceError, DiceResult};
+use rand::distributions::{Bernoulli, Distribution};
+
+/// Face a coin landed on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CoinResult {
+    Heads,
+    Tails,
+}
+
+impl DiceContext {
+    /// Flip a coin that lands heads with probability `heads_probability`
+    ///
+    /// A probability outside `(0.0, 1.0)`, including the certain 0 and 1,
+    /// fails with [`DiceError::InvalidProbability`].
+    ///
+    /// # Arguments
+    ///
+    /// * `heads_probability` - Chance of heads, strictly between 0 and 1
+    pub fn roll_coin_weighted(&mut self, heads_probability: f64) -> DiceResult<CoinResult> {
+        if !(heads_probability > 0.0 && heads_probability < 1.0) {
+            return Err(DiceError::InvalidProbability(heads_probability));
+        }
+        let coin = Bernoulli::new(heads_probability)
+            .map_err(|_| DiceError::InvalidProbability(heads_probability))?;
+        Ok(if coin.sample(self.rng()) {
+            CoinResult::Heads
+        } else {
+            CoinResult::Tails
+        })
+    }
+
+    /// Flip a fair coin
+    pub fn roll_coin(&mut self) -> DiceResult<CoinResult> {
+        self.roll_coin_weighted(0.5)
+    }
+}
+
+impl Dice {
+    /// Flip a coin that lands heads with probability `heads_probability`
+    ///
+    /// # Arguments
+    ///
+    /// * `heads_probability` - Chance of heads, strictly between 0 and 1
+    pub fn roll_coin_weighted(heads_probability: f64) -> DiceResult<CoinResult> {
+        DiceContext::new().roll_coin_weighted(heads_probability)
+    }
+
+    /// Flip a fair coin
+    pub fn roll_coin() -> DiceResult<CoinResult> {
+        DiceContext::new().roll_coin()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn heads(ctx: &mut DiceContext, p: f64, flips: u32) -> u32 {
+        (0..flips)
+            .map(|_| u32::from(ctx.roll_coin_weighted(p).unwrap() == CoinResult::Heads))
+            .sum()
+    }
+
+    #[test]
+    fn test_weighted_coin() {
+        let mut ctx = DiceContext::with_seed(179);
+        let count = heads(&mut ctx, 0.8, 10_000);
+        assert!((7_800..8_200).contains(&count), "{}", count);
+        let count = heads(&mut ctx, 0.01, 10_000);
+        assert!((50..150).contains(&count), "{}", count);
+    }
+
+    #[test]
+    fn test_fair_coin() {
+        let mut ctx = DiceContext::with_seed(179);
+        let count = (0..10_000)
+            .filter(|_| ctx.roll_coin().unwrap() == CoinResult::Heads)
+            .count();
+        assert!((4_800..5_200).contains(&count), "{}", count);
+    }
+
+    #[test]
+    fn test_invalid_probability() {
+        let mut ctx = DiceContext::with_seed(179);
+        for p in [0.0, 1.0, -0.5, 1.5, f64::INFINITY] {
+            assert_eq!(ctx.roll_coin_weighted(p), Err(DiceError::InvalidProbability(p)));
+        }
+        assert!(matches!(
+            ctx.roll_coin_weighted(f64::NAN),
+            Err(DiceError::InvalidProbability(p)) if p.is_nan()
+        ));
+    }
+}
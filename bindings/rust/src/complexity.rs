@@ -0,0 +1,55 @@
+//! Complexity metrics
+//!
+//! Size measures of the expression tree, so servers can reject oversized
+//! user notation before rolling it.
+
+use crate::expression::DiceExpression;
+
+impl DiceExpression {
+    /// Longest path from this node to a leaf, counting both; a leaf is 1
+    pub fn depth(&self) -> usize {
+        1 + self
+            .children()
+            .into_iter()
+            .map(DiceExpression::depth)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Number of nodes in the tree
+    pub fn node_count(&self) -> usize {
+        1 + self
+            .children()
+            .into_iter()
+            .map(DiceExpression::node_count)
+            .sum::<usize>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metrics(notation: &str) -> (usize, usize) {
+        let expr = DiceExpression::parse(notation).unwrap();
+        (expr.depth(), expr.node_count())
+    }
+
+    #[test]
+    fn test_leaf_metrics() {
+        assert_eq!(metrics("3d6"), (1, 1));
+        assert_eq!(metrics("7"), (1, 1));
+        assert_eq!(metrics("MOD"), (1, 1));
+    }
+
+    #[test]
+    fn test_tree_metrics() {
+        // Add(Add(KeepHighest(Dice), Dice), Constant)
+        assert_eq!(metrics("(4d6k3)+(2d8)+5"), (4, 6));
+        assert_eq!(metrics("-|1d6-3|"), (4, 5));
+        assert_eq!(metrics("F(1, 2d6, N)"), (2, 4));
+        assert_eq!(metrics("(1d20>=15)?(2d6+5):1d4"), (3, 6));
+        // Simplification removes identity nodes first
+        assert_eq!(metrics("3d6*1+0"), (1, 1));
+    }
+}
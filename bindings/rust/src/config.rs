@@ -0,0 +1,72 @@
+//! Evaluation limits
+//!
+//! `RollConfig` is the Rust counterpart of the C `dice_policy_t`. The
+//! defaults match `dice_default_policy()`, plus a cap on the repeated-roll
+//! helpers that only exist in Rust, the margin that makes one degree of
+//! success, whether advantage stacks and the Forged in the Dark thresholds.
+
+use crate::eval::{
+    MAX_DICE_COUNT, MAX_EXPLOSION_DEPTH, MAX_REROLLS, MAX_SIDES, MAX_SIMULATION_ITERATIONS,
+};
+use crate::fitd::{FITD_PARTIAL_SUCCESS, FITD_SUCCESS};
+use crate::margin::DEGREE_THRESHOLD;
+
+/// Limits applied while rolling
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RollConfig {
+    /// Maximum dice a single `NdS` term may roll
+    pub max_dice_count: u32,
+    /// Maximum sides a die may have
+    pub max_sides: u32,
+    /// Maximum extra dice a single die may explode into
+    pub max_explosion_depth: u32,
+    /// Maximum times a single die may be rerolled, whatever the caller asks for
+    pub max_rerolls: u32,
+    /// Maximum rolls a repeated-roll helper such as
+    /// [`DiceContext::roll_n_compare`](crate::DiceContext::roll_n_compare) may make
+    pub max_simulation_iterations: u32,
+    /// Margin worth one degree of success in
+    /// [`DiceContext::roll_margin_of_success`](crate::DiceContext::roll_margin_of_success)
+    pub degree_threshold: u32,
+    /// Let each net source of advantage add a die in
+    /// [`DiceContext::roll_with_advantage_count`](crate::DiceContext::roll_with_advantage_count)
+    /// instead of stopping at two, as 5e does
+    pub stack_advantage: bool,
+    /// Lowest die that's a partial success in
+    /// [`DiceContext::roll_forged_in_the_dark`](crate::DiceContext::roll_forged_in_the_dark)
+    pub fitd_partial_success: u32,
+    /// Lowest die that's a full success, and that makes a critical when two
+    /// dice reach it, in Forged in the Dark rolls
+    pub fitd_success: u32,
+}
+
+impl Default for RollConfig {
+    fn default() -> Self {
+        RollConfig {
+            max_dice_count: MAX_DICE_COUNT,
+            max_sides: MAX_SIDES,
+            max_explosion_depth: MAX_EXPLOSION_DEPTH,
+            max_rerolls: MAX_REROLLS,
+            max_simulation_iterations: MAX_SIMULATION_ITERATIONS,
+            degree_threshold: DEGREE_THRESHOLD,
+            stack_advantage: false,
+            fitd_partial_success: FITD_PARTIAL_SUCCESS,
+            fitd_success: FITD_SUCCESS,
+        }
+    }
+}
+
+impl RollConfig {
+    /// Set the margin worth one degree of success
+    pub fn degree_threshold(mut self, threshold: u32) -> Self {
+        self.degree_threshold = threshold;
+        self
+    }
+
+    /// Let each net source of advantage add a die instead of stopping at two
+    pub fn stack_advantage(mut self, enabled: bool) -> Self {
+        self.stack_advantage = enabled;
+        self
+    }
+}
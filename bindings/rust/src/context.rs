@@ -0,0 +1,312 @@
+//! Seeded dice context
+//!
+//! `DiceContext` owns a deterministic generator and rolls notation through
+//! the pure-Rust expression engine. It is the Rust counterpart of the C
+//! `dice_context_t`: each context is independent, so separate contexts can
+//! be used from separate threads without sharing state.
+//!
+//! One context is shared process-wide as the global default, which the
+//! crate's free functions ([`roll`](crate::roll),
+//! [`roll_notation`](crate::roll_notation), ...) roll with. Install a
+//! configured one at startup with [`DiceContext::set_global_default`].
+
+use crate::config::RollConfig;
+use crate::eval::RollResult;
+use crate::expression::DiceExpression;
+use crate::rng::DiceRng;
+use crate::{DiceError, DiceResult};
+use rand::Rng;
+use std::sync::{Mutex, OnceLock, PoisonError};
+
+/// Context behind the crate's free functions, created on first use
+static GLOBAL: OnceLock<Mutex<DiceContext>> = OnceLock::new();
+
+/// Independent dice rolling context with its own seeded RNG
+#[derive(Debug, Clone)]
+pub struct DiceContext {
+    seed: u64,
+    rng: DiceRng,
+    config: RollConfig,
+}
+
+impl DiceContext {
+    /// Create a context seeded from system entropy
+    pub fn new() -> Self {
+        DiceContext::with_seed(rand::random())
+    }
+
+    /// Create a context with a fixed seed for reproducible rolls
+    pub fn with_seed(seed: u64) -> Self {
+        DiceContext {
+            seed,
+            rng: DiceRng::new(seed),
+            config: RollConfig::default(),
+        }
+    }
+
+    /// Independent child context for worker `offset`
+    ///
+    /// The child is seeded with `seed ^ (offset * 0x9E3779B9)`, so forks
+    /// are reproducible from the parent's seed however far the parent has
+    /// rolled, and different offsets roll different sequences. It copies
+    /// the parent's config and shares nothing with it afterwards.
+    pub fn fork(&self, offset: u32) -> DiceContext {
+        let seed = self.seed ^ u64::from(offset).wrapping_mul(0x9E37_79B9);
+        DiceContext::with_seed(seed).with_config(self.config)
+    }
+
+    /// Install `ctx` as the global default context
+    ///
+    /// Call once at startup, before anything rolls with the global context.
+    ///
+    /// # Panics
+    ///
+    /// If a global context was already set, or already created by a call
+    /// to [`global`](Self::global) or one of the free functions.
+    pub fn set_global_default(ctx: DiceContext) {
+        if GLOBAL.set(Mutex::new(ctx)).is_err() {
+            panic!("global dice context already initialized");
+        }
+    }
+
+    /// The global default context, seeded from system entropy unless
+    /// [`set_global_default`](Self::set_global_default) ran first
+    ///
+    /// Lock it to roll; rolls from different threads take turns.
+    pub fn global() -> &'static Mutex<DiceContext> {
+        GLOBAL.get_or_init(|| Mutex::new(DiceContext::new()))
+    }
+
+    /// Run `f` with the global context locked
+    ///
+    /// A panic while another caller held the lock leaves the generator in
+    /// a valid state, so a poisoned lock is used as is.
+    pub(crate) fn with_global<T>(f: impl FnOnce(&mut DiceContext) -> T) -> T {
+        let mut ctx = DiceContext::global().lock().unwrap_or_else(PoisonError::into_inner);
+        f(&mut ctx)
+    }
+
+    /// Replace the limits applied to this context's rolls
+    pub fn with_config(mut self, config: RollConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Limits applied to this context's rolls
+    pub fn config(&self) -> &RollConfig {
+        &self.config
+    }
+
+    /// Seed this context was created with
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Generator used by this context
+    pub fn rng(&mut self) -> &mut DiceRng {
+        &mut self.rng
+    }
+
+    /// Resume from a saved generator
+    pub(crate) fn with_rng(mut self, rng: DiceRng) -> Self {
+        self.rng = rng;
+        self
+    }
+
+    /// Generator state, for saving the context mid-sequence
+    pub(crate) fn rng_state(&self) -> [u64; 4] {
+        self.rng.state()
+    }
+
+    /// Roll a single die
+    ///
+    /// # Arguments
+    ///
+    /// * `sides` - Number of sides on the die
+    pub fn roll(&mut self, sides: i32) -> DiceResult<i32> {
+        if sides <= 0 {
+            return Err(DiceError::InvalidSides(sides));
+        }
+        Ok(self.rng.gen_range(1..=sides))
+    }
+
+    /// Roll multiple dice and return individual results
+    ///
+    /// `count` and `sides` are held to this context's
+    /// [`RollConfig`] the same way notation is, and a sum too large for an
+    /// `i32` fails with [`DiceError::Overflow`].
+    ///
+    /// # Returns
+    ///
+    /// Tuple of (sum, vector of individual results)
+    pub fn roll_individual(&mut self, count: i32, sides: i32) -> DiceResult<(i32, Vec<i32>)> {
+        if count <= 0 {
+            return Err(DiceError::InvalidCount(count));
+        }
+        if sides <= 0 {
+            return Err(DiceError::InvalidSides(sides));
+        }
+        let results = self.roll_pool(count as u32, sides as u32)?;
+        let sum = results
+            .iter()
+            .try_fold(0i32, |sum, &value| sum.checked_add(value))
+            .ok_or(DiceError::Overflow)?;
+        Ok((sum, results))
+    }
+
+    /// Roll `count` dice with `sides` sides under this context's limits
+    pub(crate) fn roll_pool(&mut self, count: u32, sides: u32) -> DiceResult<Vec<i32>> {
+        let pool = DiceExpression::Dice(count, sides);
+        Ok(pool.roll_with_config(&mut self.rng, &self.config)?.individual())
+    }
+
+    /// Roll an action pool of `count` dice and read the highest
+    ///
+    /// An empty pool rolls two dice and reads the lowest, as Forged in the
+    /// Dark and Spire do. Returns every value rolled and the one that counts.
+    pub(crate) fn roll_action_pool(
+        &mut self,
+        count: u32,
+        sides: u32,
+    ) -> DiceResult<(Vec<i32>, i32)> {
+        let values = self.roll_pool(if count == 0 { 2 } else { count }, sides)?;
+        // roll_pool rejects empty pools
+        let effective = if count == 0 {
+            values.iter().copied().min().unwrap_or(1)
+        } else {
+            values.iter().copied().max().unwrap_or(1)
+        };
+        Ok((values, effective))
+    }
+
+    /// Parse and roll dice notation
+    ///
+    /// # Arguments
+    ///
+    /// * `notation` - Dice notation like "3d6", "1d20+5", "4d6k3"
+    pub fn roll_notation(&mut self, notation: &str) -> DiceResult<RollResult> {
+        DiceExpression::parse(notation)?.roll_with_config(&mut self.rng, &self.config)
+    }
+}
+
+impl Default for DiceContext {
+    fn default() -> Self {
+        DiceContext::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seeded_contexts_repeat() {
+        let mut a = DiceContext::with_seed(99);
+        let mut b = DiceContext::with_seed(99);
+        for _ in 0..20 {
+            assert_eq!(a.roll_notation("4d6k3+1"), b.roll_notation("4d6k3+1"));
+        }
+        assert_eq!(a.seed(), 99);
+    }
+
+    #[test]
+    fn test_fork() {
+        let config = RollConfig {
+            max_dice_count: 8,
+            ..RollConfig::default()
+        };
+        let mut parent = DiceContext::with_seed(198).with_config(config);
+        let (mut first, mut second) = (parent.fork(0), parent.fork(1));
+        assert_eq!(second.seed(), 198 ^ 0x9E37_79B9);
+        let rolls = |ctx: &mut DiceContext| -> Vec<i32> {
+            (0..20).map(|_| ctx.roll(1000).unwrap()).collect()
+        };
+        assert_ne!(rolls(&mut first), rolls(&mut second));
+
+        // Forks depend on the seed, not on how far the parent has rolled
+        rolls(&mut parent);
+        assert_eq!(rolls(&mut parent.fork(1)), rolls(&mut DiceContext::with_seed(second.seed())));
+        assert_eq!(parent.fork(7).config().max_dice_count, 8);
+        assert_eq!(parent.fork(u32::MAX).seed(), 198 ^ 0x9E37_79B8_61C8_8647);
+    }
+
+    #[test]
+    fn test_context_rolls() {
+        let mut ctx = DiceContext::with_seed(5);
+        assert!((1..=20).contains(&ctx.roll(20).unwrap()));
+        assert_eq!(ctx.roll(0), Err(DiceError::InvalidSides(0)));
+
+        let (sum, individual) = ctx.roll_individual(3, 6).unwrap();
+        assert_eq!(individual.len(), 3);
+        assert_eq!(sum, individual.iter().sum::<i32>());
+        assert_eq!(ctx.roll_individual(0, 6), Err(DiceError::InvalidCount(0)));
+
+        assert!(ctx.roll_notation("invalid").is_err());
+    }
+
+    #[test]
+    fn test_percentile_notation() {
+        let mut ctx = DiceContext::with_seed(123);
+        for _ in 0..1000 {
+            let result = ctx.roll_notation("d%").unwrap();
+            assert!((1..=100).contains(&result.total));
+            let triple = ctx.roll_notation("3d%").unwrap();
+            assert!((3..=300).contains(&triple.total));
+        }
+    }
+
+    #[test]
+    fn test_global_default_is_shared() {
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                std::thread::spawn(|| {
+                    for _ in 0..250 {
+                        assert!((1..=6).contains(&crate::roll(6).unwrap()));
+                        assert!((3..=18).contains(&crate::roll_multiple(3, 6).unwrap()));
+                        assert!((5..=25).contains(&crate::roll_notation("1d20+5").unwrap()));
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        let (sum, values) = crate::roll_individual(4, 8).unwrap();
+        assert_eq!((values.len(), values.iter().sum::<i32>()), (4, sum));
+        assert_eq!(crate::roll(0), Err(DiceError::InvalidSides(0)));
+        assert!(crate::roll_notation("2d").is_err());
+        assert_eq!(crate::roll_multiple(100_000, 6), Err(DiceError::InvalidCount(100_000)));
+        assert_eq!(crate::roll_individual(3, 10_000_000), Err(DiceError::InvalidSides(10_000_000)));
+    }
+
+    #[test]
+    fn test_roll_individual_overflow() {
+        let config = RollConfig {
+            max_sides: i32::MAX as u32,
+            ..RollConfig::default()
+        };
+        let mut ctx = DiceContext::with_seed(178).with_config(config);
+        assert_eq!(ctx.roll_individual(1000, i32::MAX), Err(DiceError::Overflow));
+    }
+
+    #[test]
+    fn test_global_default_set_once() {
+        DiceContext::global();
+        let late = std::panic::catch_unwind(|| {
+            DiceContext::set_global_default(DiceContext::with_seed(178));
+        });
+        assert!(late.is_err());
+    }
+
+    #[test]
+    fn test_context_config() {
+        let config = RollConfig {
+            max_dice_count: 5,
+            ..RollConfig::default()
+        };
+        let mut ctx = DiceContext::with_seed(5).with_config(config);
+        assert_eq!(ctx.config().max_dice_count, 5);
+        assert!(ctx.roll_notation("5d6").is_ok());
+        assert_eq!(ctx.roll_notation("6d6"), Err(DiceError::InvalidCount(6)));
+    }
+}
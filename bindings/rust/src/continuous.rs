@@ -0,0 +1,100 @@
+//! Continuous rolls
+//!
+//! Uniform floats for systems that interpolate between discrete results or
+//! use percentages finer than a d100.
+
+use crate::context::DiceContext;
+use crate::{Dice, DiceError, DiceResult};
+use rand::Rng;
+
+impl DiceContext {
+    /// Roll a die and return a uniform float in `(0.0, 1.0]`
+    ///
+    /// The face picks the slot `((face - 1) / sides, face / sides]` and a
+    /// uniform fraction the position within it, so the value rounds up to
+    /// the face that was rolled: `(value * sides).ceil() == face`.
+    ///
+    /// # Arguments
+    ///
+    /// * `sides` - Number of sides on the die
+    pub fn roll_f64(&mut self, sides: u32) -> DiceResult<f64> {
+        let sides_i32 = i32::try_from(sides).map_err(|_| DiceError::InvalidSides(i32::MAX))?;
+        let face = self.roll(sides_i32)?;
+        // gen() is in [0, 1), so 1 - gen() is in (0, 1]
+        let fraction = 1.0 - self.rng().gen::<f64>();
+        Ok((face as f64 - 1.0 + fraction) / sides as f64)
+    }
+
+    /// Uniform float in `[min, max]`
+    ///
+    /// # Arguments
+    ///
+    /// * `min` - Smallest value
+    /// * `max` - Largest value; at least `min`
+    pub fn roll_range_f64(&mut self, min: f64, max: f64) -> DiceResult<f64> {
+        if !min.is_finite() || !max.is_finite() || min > max {
+            return Err(DiceError::InvalidFormat(format!(
+                "invalid range [{}, {}]",
+                min, max
+            )));
+        }
+        Ok(self.rng().gen_range(min..=max))
+    }
+}
+
+impl Dice {
+    /// Roll a die and return a uniform float in `(0.0, 1.0]`
+    ///
+    /// # Arguments
+    ///
+    /// * `sides` - Number of sides on the die
+    pub fn roll_f64(sides: u32) -> DiceResult<f64> {
+        DiceContext::new().roll_f64(sides)
+    }
+
+    /// Uniform float in `[min, max]`
+    ///
+    /// # Arguments
+    ///
+    /// * `min` - Smallest value
+    /// * `max` - Largest value; at least `min`
+    pub fn roll_range_f64(min: f64, max: f64) -> DiceResult<f64> {
+        DiceContext::new().roll_range_f64(min, max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roll_f64() {
+        let mut ctx = DiceContext::with_seed(139);
+        let mut faces = DiceContext::with_seed(139);
+        let (mut sum, trials) = (0.0, 20_000);
+        for _ in 0..trials {
+            let value = ctx.roll_f64(6).unwrap();
+            assert!(value > 0.0 && value <= 1.0);
+            // The same face is rolled before each fraction is drawn
+            let face = faces.roll(6).unwrap();
+            faces.rng().gen::<f64>();
+            assert_eq!((value * 6.0).ceil() as i32, face);
+            sum += value;
+        }
+        assert!((sum / trials as f64 - 0.5).abs() < 0.01);
+        assert_eq!(ctx.roll_f64(0), Err(DiceError::InvalidSides(0)));
+    }
+
+    #[test]
+    fn test_roll_range_f64() {
+        let mut ctx = DiceContext::with_seed(139);
+        for _ in 0..1000 {
+            let value = ctx.roll_range_f64(-2.5, 7.25).unwrap();
+            assert!((-2.5..=7.25).contains(&value));
+        }
+        assert_eq!(ctx.roll_range_f64(3.0, 3.0), Ok(3.0));
+        assert!(matches!(ctx.roll_range_f64(2.0, 1.0), Err(DiceError::InvalidFormat(_))));
+        assert!(Dice::roll_range_f64(0.0, f64::NAN).is_err());
+        assert!(Dice::roll_f64(20).is_ok());
+    }
+}
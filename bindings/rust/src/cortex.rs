@@ -0,0 +1,123 @@
+//! Cortex Prime stepped dice
+//!
+//! Cortex Prime rates traits as die sizes that step up and down the
+//! sequence d4, d6, d8, d10, d12. Steps below a d4 stay at a d4; each step
+//! past a d12 adds a d6 to the d12, and the highest die counts.
+
+use crate::context::DiceContext;
+use crate::{Dice, DiceError, DiceResult};
+
+/// Die sizes a trait steps through, smallest first
+pub const CORTEX_STEPS: [u32; 5] = [4, 6, 8, 10, 12];
+
+/// Sides of the die added for each step past a d12
+const EXTRA_DIE_SIDES: u32 = 6;
+
+impl DiceContext {
+    /// Roll a trait die stepped up or down from its starting size
+    ///
+    /// A starting size that isn't in [`CORTEX_STEPS`] fails with
+    /// [`DiceError::InvalidSides`]. Extra d6s count against
+    /// `RollConfig::max_dice_count`.
+    ///
+    /// # Arguments
+    ///
+    /// * `starting_sides` - Die size before stepping, d4 to d12
+    /// * `steps_up` - Steps to move up the sequence; negative steps down
+    ///
+    /// # Returns
+    ///
+    /// Tuple of (highest die, every die rolled with the d12 or the stepped
+    /// die first)
+    pub fn roll_cortex_stepped(
+        &mut self,
+        starting_sides: u32,
+        steps_up: i32,
+    ) -> DiceResult<(i32, Vec<i32>)> {
+        let start = CORTEX_STEPS
+            .iter()
+            .position(|&sides| sides == starting_sides)
+            .ok_or(DiceError::InvalidSides(starting_sides as i32))?;
+        let step = (start as i64 + steps_up as i64).max(0);
+        let top = CORTEX_STEPS.len() as i64 - 1;
+        let mut dice = self.roll_pool(1, CORTEX_STEPS[step.min(top) as usize])?;
+        if step > top {
+            let extra = u32::try_from(step - top).map_err(|_| DiceError::InvalidCount(i32::MAX))?;
+            dice.extend(self.roll_pool(extra, EXTRA_DIE_SIDES)?);
+        }
+        let highest = dice.iter().copied().max().ok_or(DiceError::InvalidCount(0))?;
+        Ok((highest, dice))
+    }
+}
+
+impl Dice {
+    /// Roll a trait die stepped up or down from its starting size
+    ///
+    /// # Arguments
+    ///
+    /// * `starting_sides` - Die size before stepping, d4 to d12
+    /// * `steps_up` - Steps to move up the sequence; negative steps down
+    ///
+    /// # Returns
+    ///
+    /// Tuple of (highest die, every die rolled)
+    pub fn roll_cortex_stepped(starting_sides: u32, steps_up: i32) -> DiceResult<(i32, Vec<i32>)> {
+        DiceContext::new().roll_cortex_stepped(starting_sides, steps_up)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RollConfig;
+
+    /// Largest value seen over many rolls, and the number of dice rolled
+    fn largest(ctx: &mut DiceContext, starting_sides: u32, steps_up: i32) -> (i32, usize) {
+        let mut largest = (0, 0);
+        for _ in 0..500 {
+            let (highest, dice) = ctx.roll_cortex_stepped(starting_sides, steps_up).unwrap();
+            assert_eq!(Some(&highest), dice.iter().max());
+            largest = (largest.0.max(highest), dice.len());
+        }
+        largest
+    }
+
+    #[test]
+    fn test_steps_within_sequence() {
+        let mut ctx = DiceContext::with_seed(183);
+        assert_eq!(largest(&mut ctx, 8, 0), (8, 1));
+        assert_eq!(largest(&mut ctx, 4, 2), (8, 1));
+        assert_eq!(largest(&mut ctx, 6, 3), (12, 1));
+        assert_eq!(largest(&mut ctx, 10, -2), (6, 1));
+    }
+
+    #[test]
+    fn test_steps_past_ends() {
+        let mut ctx = DiceContext::with_seed(183);
+        // Stepping down stops at a d4
+        assert_eq!(largest(&mut ctx, 6, -3), (4, 1));
+        assert_eq!(largest(&mut ctx, 4, i32::MIN), (4, 1));
+        // Each step past a d12 adds a d6
+        assert_eq!(largest(&mut ctx, 12, 1), (12, 2));
+        assert_eq!(largest(&mut ctx, 8, 5), (12, 4));
+        let (_, dice) = ctx.roll_cortex_stepped(12, 2).unwrap();
+        assert!((1..=12).contains(&dice[0]));
+        assert!(dice[1..].iter().all(|die| (1..=6).contains(die)));
+    }
+
+    #[test]
+    fn test_invalid_steps() {
+        let mut ctx = DiceContext::with_seed(183);
+        assert_eq!(ctx.roll_cortex_stepped(20, 0), Err(DiceError::InvalidSides(20)));
+        assert_eq!(ctx.roll_cortex_stepped(0, 1), Err(DiceError::InvalidSides(0)));
+        let config = RollConfig {
+            max_dice_count: 3,
+            ..RollConfig::default()
+        };
+        let mut ctx = DiceContext::with_seed(183).with_config(config);
+        assert!(ctx.roll_cortex_stepped(12, 3).is_ok());
+        assert_eq!(ctx.roll_cortex_stepped(12, 4), Err(DiceError::InvalidCount(4)));
+        assert!(matches!(ctx.roll_cortex_stepped(4, i32::MAX), Err(DiceError::InvalidCount(_))));
+        assert!(Dice::roll_cortex_stepped(10, 1).is_ok());
+    }
+}
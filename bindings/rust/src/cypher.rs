@@ -0,0 +1,188 @@
+//! Cypher System task rolls
+//!
+//! A task has a difficulty from 0 to 10 and the player rolls a d20 against
+//! three times that. Each level of Effort lowers the target by 3. Edge only
+//! makes Effort cheaper to pay for from a stat pool. A natural 1 brings a
+//! GM intrusion whatever the target, and a 19 or 20 a special effect.
+
+use crate::context::DiceContext;
+use crate::{Dice, DiceError, DiceResult};
+
+/// Hardest difficulty a task can have
+pub const MAX_DIFFICULTY: u32 = 10;
+
+/// Outcome of a Cypher System task roll
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CypherResult {
+    /// The d20
+    pub roll: i32,
+    /// Target after Effort, never below 0
+    pub target: i32,
+    pub succeeded: bool,
+    /// Bonus from a high roll: +1 on a 17 up to +4 on a 20
+    pub asset_bonus: i32,
+    /// Points the Effort costs from a stat pool after Edge
+    pub effort_cost: u32,
+    /// A 19 or 20: a minor or major effect
+    pub special_effect: bool,
+    /// A natural 1: a GM intrusion
+    pub intrusion: bool,
+}
+
+impl CypherResult {
+    /// Judge a d20 already rolled against a task
+    ///
+    /// A `die_value` off the d20 or a difficulty above [`MAX_DIFFICULTY`]
+    /// fails with [`DiceError::InvalidRange`].
+    ///
+    /// # Arguments
+    ///
+    /// * `die_value` - Face the d20 shows
+    /// * `difficulty` - Task difficulty, 0 to 10
+    /// * `effort` - Levels of Effort applied
+    /// * `edge` - Edge in the stat paying for the Effort
+    pub fn from_roll(
+        die_value: i32,
+        difficulty: u32,
+        effort: u32,
+        edge: u32,
+    ) -> DiceResult<CypherResult> {
+        if !(1..=20).contains(&die_value) {
+            return Err(DiceError::InvalidRange {
+                min: 1,
+                max: 20,
+                actual: die_value,
+            });
+        }
+        if difficulty > MAX_DIFFICULTY {
+            return Err(DiceError::InvalidRange {
+                min: 0,
+                max: MAX_DIFFICULTY as i32,
+                actual: difficulty as i32,
+            });
+        }
+        // Difficulty is at most 10, so the clamped target fits an i32
+        let target = (difficulty as i64 * 3 - effort as i64 * 3).max(0) as i32;
+        // The first level of Effort costs 3 points and each later one 2
+        let effort_cost = match effort {
+            0 => 0,
+            effort => (1 + 2 * effort as u64).min(u32::MAX as u64) as u32,
+        };
+        Ok(CypherResult {
+            roll: die_value,
+            target,
+            succeeded: die_value >= target,
+            asset_bonus: (die_value - 16).max(0),
+            effort_cost: effort_cost.saturating_sub(edge),
+            special_effect: die_value >= 19,
+            intrusion: die_value == 1,
+        })
+    }
+}
+
+impl DiceContext {
+    /// Roll a d20 for a Cypher System task
+    ///
+    /// # Arguments
+    ///
+    /// * `difficulty` - Task difficulty, 0 to 10
+    /// * `effort` - Levels of Effort applied
+    /// * `edge` - Edge in the stat paying for the Effort
+    pub fn roll_cypher_system(
+        &mut self,
+        difficulty: u32,
+        effort: u32,
+        edge: u32,
+    ) -> DiceResult<CypherResult> {
+        let roll = self.roll(20)?;
+        CypherResult::from_roll(roll, difficulty, effort, edge)
+    }
+}
+
+impl Dice {
+    /// Roll a d20 for a Cypher System task
+    ///
+    /// # Arguments
+    ///
+    /// * `difficulty` - Task difficulty, 0 to 10
+    /// * `effort` - Levels of Effort applied
+    /// * `edge` - Edge in the stat paying for the Effort
+    pub fn roll_cypher_system(difficulty: u32, effort: u32, edge: u32) -> DiceResult<CypherResult> {
+        DiceContext::new().roll_cypher_system(difficulty, effort, edge)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_target_numbers() {
+        let result = CypherResult::from_roll(12, 4, 0, 0).unwrap();
+        assert_eq!((result.target, result.succeeded), (12, true));
+        let result = CypherResult::from_roll(11, 4, 0, 0).unwrap();
+        assert!(!result.succeeded);
+        // Two levels of Effort take difficulty 4 down to a target of 6
+        let result = CypherResult::from_roll(6, 4, 2, 0).unwrap();
+        assert_eq!((result.target, result.succeeded), (6, true));
+        // Effort can't push the target below 0
+        assert_eq!(CypherResult::from_roll(2, 1, 5, 0).unwrap().target, 0);
+        assert_eq!(CypherResult::from_roll(2, 0, 0, 0).unwrap().target, 0);
+        // However much Effort is applied
+        for effort in [1_000_000_000, u32::MAX / 3 + 1, u32::MAX] {
+            let result = CypherResult::from_roll(1, 3, effort, 0).unwrap();
+            assert_eq!((result.target, result.succeeded), (0, true), "{}", effort);
+        }
+    }
+
+    #[test]
+    fn test_special_rolls() {
+        // A 19 is a special effect even against an impossible target
+        let result = CypherResult::from_roll(19, 10, 0, 0).unwrap();
+        assert!(result.special_effect && !result.succeeded && !result.intrusion);
+        assert_eq!(result.asset_bonus, 3);
+        let result = CypherResult::from_roll(20, 2, 0, 0).unwrap();
+        assert!(result.special_effect && result.succeeded);
+        assert_eq!(result.asset_bonus, 4);
+
+        // A 1 is an intrusion even when it succeeds
+        let result = CypherResult::from_roll(1, 0, 0, 0).unwrap();
+        assert!(result.intrusion && result.succeeded && !result.special_effect);
+        assert_eq!(result.asset_bonus, 0);
+        let result = CypherResult::from_roll(17, 3, 0, 0).unwrap();
+        assert!(!result.special_effect && !result.intrusion);
+        assert_eq!(result.asset_bonus, 1);
+    }
+
+    #[test]
+    fn test_edge_pays_for_effort() {
+        let cost = |effort, edge| CypherResult::from_roll(10, 5, effort, edge).unwrap().effort_cost;
+        assert_eq!((cost(0, 0), cost(1, 0), cost(2, 0), cost(3, 0)), (0, 3, 5, 7));
+        assert_eq!((cost(1, 1), cost(2, 2), cost(1, 5)), (2, 3, 0));
+        // Edge doesn't change the target
+        let with_edge = CypherResult::from_roll(10, 5, 2, 3).unwrap();
+        assert_eq!(with_edge.target, CypherResult::from_roll(10, 5, 2, 0).unwrap().target);
+    }
+
+    #[test]
+    fn test_roll_cypher_system() {
+        let mut ctx = DiceContext::with_seed(186);
+        let (mut intrusions, mut specials) = (0, 0);
+        for _ in 0..400 {
+            let result = ctx.roll_cypher_system(3, 1, 0).unwrap();
+            assert!((1..=20).contains(&result.roll));
+            assert_eq!(result.succeeded, result.roll >= 6);
+            intrusions += u32::from(result.intrusion);
+            specials += u32::from(result.special_effect);
+        }
+        assert!(intrusions > 0 && specials > intrusions);
+        assert!(matches!(
+            ctx.roll_cypher_system(11, 0, 0),
+            Err(DiceError::InvalidRange { actual: 11, .. })
+        ));
+        assert!(CypherResult::from_roll(0, 1, 0, 0).is_err());
+        assert!(CypherResult::from_roll(21, 1, 0, 0).is_err());
+        assert!(Dice::roll_cypher_system(2, 0, 0).is_ok());
+    }
+}
@@ -0,0 +1,98 @@
+//! Single die sizes
+
+use crate::expression::DiceExpression;
+use crate::{DiceError, DiceResult};
+use std::fmt;
+
+/// A die with a fixed number of sides, from 1 to `i32::MAX`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Die {
+    sides: u32,
+}
+
+impl Die {
+    pub const D4: Die = Die { sides: 4 };
+    pub const D6: Die = Die { sides: 6 };
+    pub const D8: Die = Die { sides: 8 };
+    pub const D10: Die = Die { sides: 10 };
+    pub const D12: Die = Die { sides: 12 };
+    pub const D20: Die = Die { sides: 20 };
+    pub const D100: Die = Die { sides: 100 };
+
+    /// A die with `sides` sides
+    ///
+    /// No sides, or more than `i32::MAX` so faces no longer fit a roll's
+    /// value, fails with [`DiceError::InvalidSides`].
+    pub fn new(sides: u32) -> DiceResult<Die> {
+        match i32::try_from(sides) {
+            Ok(0) => Err(DiceError::InvalidSides(0)),
+            Ok(_) => Ok(Die { sides }),
+            Err(_) => Err(DiceError::InvalidSides(i32::MAX)),
+        }
+    }
+
+    pub fn sides(&self) -> u32 {
+        self.sides
+    }
+}
+
+impl TryFrom<i32> for Die {
+    type Error = DiceError;
+
+    fn try_from(sides: i32) -> DiceResult<Die> {
+        if sides <= 0 {
+            return Err(DiceError::InvalidSides(sides));
+        }
+        Die::new(sides as u32)
+    }
+}
+
+/// Sides of the die
+impl From<Die> for i32 {
+    fn from(die: Die) -> i32 {
+        // Die::new keeps sides within i32
+        die.sides as i32
+    }
+}
+
+/// A single roll of the die, `1dS`
+impl From<Die> for DiceExpression {
+    fn from(die: Die) -> DiceExpression {
+        DiceExpression::Dice(1, die.sides)
+    }
+}
+
+impl fmt::Display for Die {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "d{}", self.sides)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_die() {
+        let die = Die::new(6).unwrap();
+        assert_eq!(die.sides(), 6);
+        assert_eq!(die.to_string(), "d6");
+        assert_eq!(Die::new(0), Err(DiceError::InvalidSides(0)));
+        assert_eq!(Die::new(i32::MAX as u32).unwrap().sides(), i32::MAX as u32);
+        assert_eq!(Die::new(i32::MAX as u32 + 1), Err(DiceError::InvalidSides(i32::MAX)));
+        assert_eq!(Die::new(u32::MAX), Err(DiceError::InvalidSides(i32::MAX)));
+    }
+
+    #[test]
+    fn test_conversions() {
+        assert_eq!(Die::try_from(6), Ok(Die::D6));
+        assert_eq!(Die::try_from(0), Err(DiceError::InvalidSides(0)));
+        assert_eq!(Die::try_from(-4), Err(DiceError::InvalidSides(-4)));
+        assert_eq!(i32::from(Die::D6), 6);
+        assert_eq!(i32::from(Die::new(i32::MAX as u32).unwrap()), i32::MAX);
+        let expr = DiceExpression::from(Die::D20);
+        assert!(matches!(expr, DiceExpression::Dice(1, 20)));
+        assert_eq!(expr.to_notation(), "1d20");
+    }
+}
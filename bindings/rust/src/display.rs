@@ -0,0 +1,498 @@
+//! Human-readable expressions and results
+//!
+//! An expression displays as its canonical notation. Pairing it with the
+//! [`RollResult`] it produced gives a line suitable for chat or logs, in one
+//! of the [`DisplayStyle`]s. A [`DiceExpressionFormatter`] goes further and
+//! writes each pool's dice in place of its notation, as plain text,
+//! Markdown, HTML or ANSI-colored text.
+
+use crate::eval::{DieRoll, RollResult, MAX_EXPLOSION_DEPTH};
+use crate::expression::DiceExpression;
+use crate::notation::precedence;
+use std::fmt;
+
+impl fmt::Display for DiceExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_notation())
+    }
+}
+
+/// How much of a roll [`RollDisplay`] shows
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DisplayStyle {
+    /// `4d6k3+5 = 20`
+    #[default]
+    Plain,
+    /// `4d6k3+5 = [4, 5, 6, (2)] = 20`, dropped dice in parentheses
+    Verbose,
+    /// `4d6k3+5 = [4, 5, 6, ~~2~~] = 20`, dropped dice struck through
+    Markdown,
+}
+
+/// Expression and result formatter returned by
+/// [`DiceExpression::display_with_result`]
+#[derive(Debug, Clone, Copy)]
+pub struct RollDisplay<'a> {
+    expr: &'a DiceExpression,
+    result: &'a RollResult,
+    style: DisplayStyle,
+}
+
+impl DiceExpression {
+    /// Format the expression with a result it produced, in
+    /// [`DisplayStyle::Plain`]
+    pub fn display_with_result<'a>(&'a self, result: &'a RollResult) -> RollDisplay<'a> {
+        RollDisplay {
+            expr: self,
+            result,
+            style: DisplayStyle::Plain,
+        }
+    }
+}
+
+impl RollDisplay<'_> {
+    pub fn with_style(mut self, style: DisplayStyle) -> Self {
+        self.style = style;
+        self
+    }
+
+    fn write_die(&self, f: &mut fmt::Formatter<'_>, die: &DieRoll) -> fmt::Result {
+        match self.style {
+            _ if die.kept => write!(f, "{}", die.value),
+            DisplayStyle::Markdown => write!(f, "~~{}~~", die.value),
+            _ => write!(f, "({})", die.value),
+        }
+    }
+}
+
+impl fmt::Display for RollDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} = ", self.expr)?;
+        if self.style != DisplayStyle::Plain && !self.result.dice.is_empty() {
+            write!(f, "[")?;
+            for (i, die) in self.result.dice.iter().enumerate() {
+                if i > 0 {
+                    write!(f, ", ")?;
+                }
+                self.write_die(f, die)?;
+            }
+            write!(f, "] = ")?;
+        }
+        write!(f, "{}", self.result.total)
+    }
+}
+
+/// Markup written by a [`DiceExpressionFormatter`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum OutputFormat {
+    /// Dropped dice in parentheses: `[4, 5, 6, (2)]`
+    #[default]
+    Plain,
+    /// Dropped dice struck through: `[4, 5, 6, ~~2~~]`
+    Markdown,
+    /// `<span class="roll">` around the line and `<span class="die">` around
+    /// each die, with `dropped`, `critical` and `fumble` classes
+    Html,
+    /// Dropped dice struck through with SGR escape codes
+    Ansi,
+}
+
+/// Configurable roll formatter
+///
+/// The dice of each pool are read from the result in evaluation order, so
+/// `4d6k3+5` formats as `4d6k3+5 = [4, 5, 6, (2)]+5 = 20`. Explosions are
+/// matched up assuming the default
+/// [`MAX_EXPLOSION_DEPTH`](crate::eval::MAX_EXPLOSION_DEPTH). Expressions
+/// whose dice can't be matched to their pools, such as conditionals,
+/// function calls and variable dice counts, list every die instead:
+/// `(1d20>=15)?2d6:1d6 = [17, 3, 5] = 8`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DiceExpressionFormatter {
+    pub format: OutputFormat,
+    /// Show the dice rolled, not just the total
+    pub show_individual: bool,
+    /// Show dice that keep or drop discarded
+    pub show_dropped: bool,
+    /// Mark dice showing their highest face as criticals and 1s as fumbles;
+    /// only HTML and ANSI output have colors
+    pub color: bool,
+}
+
+impl Default for DiceExpressionFormatter {
+    fn default() -> Self {
+        DiceExpressionFormatter::new(OutputFormat::Plain)
+    }
+}
+
+impl DiceExpressionFormatter {
+    /// Formatter showing every die, without color
+    pub fn new(format: OutputFormat) -> DiceExpressionFormatter {
+        DiceExpressionFormatter {
+            format,
+            show_individual: true,
+            show_dropped: true,
+            color: false,
+        }
+    }
+
+    /// Format `expr` with a result it produced
+    pub fn format_result(&self, expr: &DiceExpression, result: &RollResult) -> String {
+        let mut out = String::new();
+        if self.format == OutputFormat::Html {
+            out.push_str("<span class=\"roll\">");
+        }
+        self.text(&expr.to_notation(), &mut out);
+        if self.show_individual && !result.dice.is_empty() {
+            self.text(" = ", &mut out);
+            let mut writer = PoolWriter {
+                formatter: self,
+                dice: &result.dice,
+                next: 0,
+                out: String::new(),
+            };
+            match writer.write(expr) {
+                Some(()) if writer.next == result.dice.len() => out.push_str(&writer.out),
+                _ => self.pool(&result.dice, &mut out),
+            }
+        }
+        self.text(&format!(" = {}", result.total), &mut out);
+        if self.format == OutputFormat::Html {
+            out.push_str("</span>");
+        }
+        out
+    }
+
+    /// Text outside the dice, escaped for HTML
+    fn text(&self, text: &str, out: &mut String) {
+        if self.format != OutputFormat::Html {
+            out.push_str(text);
+            return;
+        }
+        for c in text.chars() {
+            match c {
+                '<' => out.push_str("&lt;"),
+                '>' => out.push_str("&gt;"),
+                '&' => out.push_str("&amp;"),
+                '"' => out.push_str("&quot;"),
+                c => out.push(c),
+            }
+        }
+    }
+
+    fn pool(&self, dice: &[DieRoll], out: &mut String) {
+        out.push('[');
+        let shown = dice.iter().filter(|die| die.kept || self.show_dropped);
+        for (i, die) in shown.enumerate() {
+            if i > 0 {
+                out.push_str(", ");
+            }
+            self.die(die, out);
+        }
+        out.push(']');
+    }
+
+    fn die(&self, die: &DieRoll, out: &mut String) {
+        let critical = self.color && die.value == die.sides as i32;
+        let fumble = self.color && die.value == 1 && !critical;
+        match self.format {
+            OutputFormat::Plain if !die.kept => out.push_str(&format!("({})", die.value)),
+            OutputFormat::Markdown if !die.kept => out.push_str(&format!("~~{}~~", die.value)),
+            OutputFormat::Plain | OutputFormat::Markdown => out.push_str(&die.value.to_string()),
+            OutputFormat::Html => {
+                let mut class = String::from("die");
+                for (name, applies) in
+                    [("dropped", !die.kept), ("critical", critical), ("fumble", fumble)]
+                {
+                    if applies {
+                        class.push(' ');
+                        class.push_str(name);
+                    }
+                }
+                out.push_str(&format!("<span class=\"{}\">{}</span>", class, die.value));
+            }
+            OutputFormat::Ansi => {
+                let codes: Vec<&str> = [("9", !die.kept), ("32", critical), ("31", fumble)]
+                    .into_iter()
+                    .filter_map(|(code, applies)| applies.then_some(code))
+                    .collect();
+                if codes.is_empty() {
+                    out.push_str(&die.value.to_string());
+                } else {
+                    out.push_str(&format!("\x1b[{}m{}\x1b[0m", codes.join(";"), die.value));
+                }
+            }
+        }
+    }
+}
+
+/// Writes an expression with each pool replaced by the dice it rolled
+struct PoolWriter<'a> {
+    formatter: &'a DiceExpressionFormatter,
+    dice: &'a [DieRoll],
+    /// First die not yet matched to a pool
+    next: usize,
+    out: String,
+}
+
+impl PoolWriter<'_> {
+    /// `None` when the dice can't be matched to the expression's pools
+    fn write(&mut self, expr: &DiceExpression) -> Option<()> {
+        use DiceExpression::*;
+
+        match expr {
+            Constant(value) => self.text(&value.to_string()),
+            Variable(name) => self.text(name),
+            Dice(..) | Explode(..) | ExplodeOn(..) => {
+                let count = self.pool_len(expr)?;
+                let pool = &self.dice[self.next..self.next + count];
+                self.next += count;
+                self.formatter.pool(pool, &mut self.out);
+            }
+            KeepHighest(inner, _) | KeepLowest(inner, _) | DropHighest(inner, _)
+            | DropLowest(inner, _) => self.write(inner)?,
+            CountSuccesses(inner, target) => {
+                self.write(inner)?;
+                self.text(&format!(">{}", target));
+            }
+            CountSuccessesWithBotch(inner, target, botch) => {
+                self.write(inner)?;
+                self.text(&format!(">{}b{}", target, botch));
+            }
+            FloorClamp(inner, limit) | CeilClamp(inner, limit) => {
+                self.text(if matches!(expr, FloorClamp(..)) { "max(" } else { "min(" });
+                self.write(inner)?;
+                self.text(&format!(",{})", limit));
+            }
+            Negate(inner) => {
+                self.text("-");
+                self.operand(inner, precedence(inner) < 3)?;
+            }
+            AbsoluteValue(inner) => {
+                self.text("|");
+                self.write(inner)?;
+                self.text("|");
+            }
+            FloorDiv(inner, divisor) => {
+                self.operand(inner, precedence(inner) < 2)?;
+                self.text(&format!("/{}", divisor));
+            }
+            CeilDiv(inner, divisor) => {
+                self.text("-(-");
+                self.operand(inner, precedence(inner) < 3)?;
+                self.text(&format!("/{})", divisor));
+            }
+            Min(left, right) | Max(left, right) => {
+                self.text(if matches!(expr, Min(..)) { "min(" } else { "max(" });
+                self.write(left)?;
+                self.text(",");
+                self.write(right)?;
+                self.text(")");
+            }
+            Add(left, right) => self.binary(expr, left, "+", right)?,
+            Sub(left, right) => self.binary(expr, left, "-", right)?,
+            Mul(left, right) => self.binary(expr, left, "*", right)?,
+            Div(left, right) => self.binary(expr, left, "/", right)?,
+            // Only one branch rolled, and functions and variable counts roll
+            // dice the tree doesn't describe
+            Conditional { .. } | Call(..) | DynamicDice(..) => return None,
+        }
+        Some(())
+    }
+
+    /// Dice a plain or exploding pool rolled, starting at `next`
+    fn pool_len(&self, expr: &DiceExpression) -> Option<usize> {
+        let (inner, explodes): (_, Box<dyn Fn(i32) -> bool>) = match expr {
+            DiceExpression::Explode(inner, threshold) => {
+                (&**inner, Box::new(move |value| value >= *threshold as i32))
+            }
+            DiceExpression::ExplodeOn(inner, faces) => {
+                (&**inner, Box::new(move |value| faces.contains(&(value as u32))))
+            }
+            expr => (expr, Box::new(|_| false)),
+        };
+        let &DiceExpression::Dice(count, sides) = inner else {
+            return None;
+        };
+        let base = self.dice.get(self.next..self.next + count as usize)?;
+        let mut len = base.len();
+        for die in base {
+            let (mut value, mut depth) = (die.value, 0);
+            while explodes(value) && depth < MAX_EXPLOSION_DEPTH {
+                value = self.dice.get(self.next + len)?.value;
+                len += 1;
+                depth += 1;
+            }
+        }
+        let pool = &self.dice[self.next..self.next + len];
+        pool.iter().all(|die| die.sides == sides).then_some(len)
+    }
+
+    fn text(&mut self, text: &str) {
+        self.formatter.text(text, &mut self.out);
+    }
+
+    fn operand(&mut self, expr: &DiceExpression, parenthesize: bool) -> Option<()> {
+        if parenthesize {
+            self.text("(");
+            self.write(expr)?;
+            self.text(")");
+            Some(())
+        } else {
+            self.write(expr)
+        }
+    }
+
+    /// Binary operators are left associative, so a right operand at the
+    /// same level keeps its parentheses
+    fn binary(
+        &mut self,
+        expr: &DiceExpression,
+        left: &DiceExpression,
+        op: &str,
+        right: &DiceExpression,
+    ) -> Option<()> {
+        let level = precedence(expr);
+        self.operand(left, precedence(left) < level)?;
+        self.text(op);
+        self.operand(right, precedence(right) <= level)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn die(value: i32, kept: bool) -> DieRoll {
+        DieRoll {
+            sides: 6,
+            value,
+            kept,
+        }
+    }
+
+    #[test]
+    fn test_display_expression() {
+        let expr = DiceExpression::parse("4d6kh3 + 5").unwrap();
+        assert_eq!(expr.to_string(), "4d6k3+5");
+        assert_eq!(format!("{}", DiceExpression::parse("d%").unwrap()), "1d100");
+    }
+
+    #[test]
+    fn test_display_with_result() {
+        let expr = DiceExpression::parse("4d6k3+5").unwrap();
+        let result = RollResult {
+            total: 20,
+            dice: vec![die(4, true), die(5, true), die(6, true), die(2, false)],
+        };
+        let display = expr.display_with_result(&result);
+        assert_eq!(display.to_string(), "4d6k3+5 = 20");
+        assert_eq!(
+            display.with_style(DisplayStyle::Verbose).to_string(),
+            "4d6k3+5 = [4, 5, 6, (2)] = 20"
+        );
+        assert_eq!(
+            display.with_style(DisplayStyle::Markdown).to_string(),
+            "4d6k3+5 = [4, 5, 6, ~~2~~] = 20"
+        );
+    }
+
+    #[test]
+    fn test_display_without_dice() {
+        let expr = DiceExpression::parse("2*3").unwrap();
+        let mut rng = crate::DiceRng::new(1);
+        let result = expr.roll(&mut rng).unwrap();
+        let display = expr.display_with_result(&result).with_style(DisplayStyle::Verbose);
+        assert_eq!(display.to_string(), "2*3 = 6");
+    }
+
+    fn keep_three() -> (DiceExpression, RollResult) {
+        let expr = DiceExpression::parse("4d6k3+5").unwrap();
+        let result = RollResult {
+            total: 20,
+            dice: vec![die(4, true), die(5, true), die(6, true), die(2, false)],
+        };
+        (expr, result)
+    }
+
+    #[test]
+    fn test_formatter_formats() {
+        let (expr, result) = keep_three();
+        let format = |format| DiceExpressionFormatter::new(format).format_result(&expr, &result);
+        assert_eq!(format(OutputFormat::Plain), "4d6k3+5 = [4, 5, 6, (2)]+5 = 20");
+        assert_eq!(format(OutputFormat::Markdown), "4d6k3+5 = [4, 5, 6, ~~2~~]+5 = 20");
+        assert_eq!(
+            format(OutputFormat::Html),
+            "<span class=\"roll\">4d6k3+5 = [<span class=\"die\">4</span>, \
+             <span class=\"die\">5</span>, <span class=\"die\">6</span>, \
+             <span class=\"die dropped\">2</span>]+5 = 20</span>"
+        );
+        assert_eq!(format(OutputFormat::Ansi), "4d6k3+5 = [4, 5, 6, \x1b[9m2\x1b[0m]+5 = 20");
+    }
+
+    #[test]
+    fn test_formatter_options() {
+        let (expr, result) = keep_three();
+        let mut formatter = DiceExpressionFormatter {
+            show_dropped: false,
+            ..DiceExpressionFormatter::default()
+        };
+        assert_eq!(formatter.format_result(&expr, &result), "4d6k3+5 = [4, 5, 6]+5 = 20");
+        formatter.show_individual = false;
+        assert_eq!(formatter.format_result(&expr, &result), "4d6k3+5 = 20");
+
+        let colored = DiceExpressionFormatter {
+            color: true,
+            ..DiceExpressionFormatter::new(OutputFormat::Ansi)
+        };
+        let result = RollResult {
+            total: 7,
+            dice: vec![die(6, true), die(1, true)],
+        };
+        let expr = DiceExpression::parse("1d6+1d6").unwrap();
+        assert_eq!(
+            colored.format_result(&expr, &result),
+            "1d6+1d6 = [\x1b[32m6\x1b[0m]+[\x1b[31m1\x1b[0m] = 7"
+        );
+    }
+
+    #[test]
+    fn test_formatter_follows_tree() {
+        let formatter = DiceExpressionFormatter::default();
+        let mut rng = crate::DiceRng::new(187);
+        for notation in ["(2d6+3)*2", "20-(1d4+1d4)", "10d6!>4+1d4", "5d10>7b1", "|1d6-4|"] {
+            let expr = DiceExpression::parse(notation).unwrap();
+            let result = expr.roll(&mut rng).unwrap();
+            let output = formatter.format_result(&expr, &result);
+            let pools = output.matches('[').count();
+            assert_eq!(pools, notation.matches('d').count(), "{}", output);
+        }
+
+        let expr = DiceExpression::parse("8d6>4").unwrap();
+        let result = expr.roll(&mut rng).unwrap();
+        let html = DiceExpressionFormatter::new(OutputFormat::Html).format_result(&expr, &result);
+        assert!(html.starts_with("<span class=\"roll\">8d6&gt;4 = ["), "{}", html);
+    }
+
+    #[test]
+    fn test_formatter_falls_back_to_every_die() {
+        let expr = DiceExpression::parse("(1d20>=15)?2d6:1d6").unwrap();
+        let result = RollResult {
+            total: 8,
+            dice: vec![die(17, true), die(3, true), die(5, true)],
+        };
+        assert_eq!(
+            DiceExpressionFormatter::default().format_result(&expr, &result),
+            "(1d20>=15)?2d6:1d6 = [17, 3, 5] = 8"
+        );
+        let result = RollResult {
+            total: 3,
+            dice: vec![die(3, true)],
+        };
+        let expr = DiceExpression::parse("2d6").unwrap();
+        let output = DiceExpressionFormatter::default().format_result(&expr, &result);
+        assert_eq!(output, "2d6 = [3] = 3");
+    }
+}
@@ -0,0 +1,504 @@
+//! Outcome distributions
+//!
+//! Sums and arithmetic over plain dice are computed analytically by
+//! convolution. Anything else (keep/drop, success counting) is estimated by
+//! rolling the expression [`DISTRIBUTION_SAMPLES`] times.
+
+use crate::eval::{ceil_div, floor_div};
+use crate::expression::DiceExpression;
+use crate::rng::DiceRng;
+use crate::{DiceError, DiceResult};
+use rand::Rng;
+use std::collections::BTreeMap;
+use std::io::Write;
+
+/// Rolls used to estimate a distribution that can't be computed exactly
+pub const DISTRIBUTION_SAMPLES: u32 = 100_000;
+
+/// Buckets suggested for [`DiceExpression::approximate_distribution`]
+pub const DEFAULT_BUCKETS: u32 = 100;
+
+/// Largest number of distinct outcomes the analytic path will track
+pub const MAX_EXACT_OUTCOMES: usize = 10_000;
+
+/// Probability of each outcome, keyed by outcome
+pub type Distribution = BTreeMap<i32, f64>;
+
+impl DiceExpression {
+    /// Probability of every outcome of the expression
+    ///
+    /// Exact for expressions built from constants, plain `NdS` terms and
+    /// arithmetic; simulated otherwise.
+    pub fn distribution(&self) -> DiceResult<Distribution> {
+        let mut rng = DiceRng::new(rand::random());
+        self.distribution_with(&mut rng)
+    }
+
+    /// [`distribution`](Self::distribution) drawing any simulated rolls from `rng`
+    pub fn distribution_with<R: Rng + ?Sized>(&self, rng: &mut R) -> DiceResult<Distribution> {
+        if let Some(exact) = exact(self) {
+            return Ok(exact);
+        }
+
+        let mut counts: BTreeMap<i32, u32> = BTreeMap::new();
+        for _ in 0..DISTRIBUTION_SAMPLES {
+            *counts.entry(self.evaluate(rng)?).or_insert(0) += 1;
+        }
+        Ok(counts
+            .into_iter()
+            .map(|(outcome, count)| (outcome, count as f64 / DISTRIBUTION_SAMPLES as f64))
+            .collect())
+    }
+
+    /// Distribution grouped into `buckets` equal-width ranges of outcomes
+    ///
+    /// Splits `[min_result, max_result]` evenly and estimates each bucket's
+    /// probability from [`DISTRIBUTION_SAMPLES`] rolls. Returns
+    /// `(bucket midpoint, probability)` pairs in ascending order, including
+    /// empty buckets. Suits large pools like `100d100` whose exact
+    /// distribution has thousands of entries.
+    pub fn approximate_distribution(&self, buckets: u32) -> DiceResult<Vec<(f64, f64)>> {
+        let mut rng = DiceRng::new(rand::random());
+        self.approximate_distribution_with(&mut rng, buckets)
+    }
+
+    /// [`approximate_distribution`](Self::approximate_distribution) drawing
+    /// rolls from `rng`
+    pub fn approximate_distribution_with<R: Rng + ?Sized>(
+        &self,
+        rng: &mut R,
+        buckets: u32,
+    ) -> DiceResult<Vec<(f64, f64)>> {
+        if buckets == 0 {
+            return Err(DiceError::InvalidCount(0));
+        }
+        let (min, max) = (self.min_result()? as f64, self.max_result()? as f64);
+        let width = (max - min + 1.0) / buckets as f64;
+
+        let mut counts = vec![0u32; buckets as usize];
+        for _ in 0..DISTRIBUTION_SAMPLES {
+            let outcome = self.evaluate(rng)? as f64;
+            let bucket = ((outcome - min) / width) as usize;
+            counts[bucket.min(buckets as usize - 1)] += 1;
+        }
+        Ok(counts
+            .into_iter()
+            .enumerate()
+            .map(|(i, count)| {
+                // Outcomes are integers, so bucket i covers
+                // [min - 0.5 + i * width, min - 0.5 + (i + 1) * width)
+                let midpoint = min - 0.5 + (i as f64 + 0.5) * width;
+                (midpoint, count as f64 / DISTRIBUTION_SAMPLES as f64)
+            })
+            .collect())
+    }
+
+    /// Exact probability of rolling exactly `target`
+    ///
+    /// Only available for the expressions [`distribution`](Self::distribution)
+    /// computes analytically; keep/drop, explosions and success counting fail
+    /// with [`DiceError::UnsupportedFeature`] and need simulation instead.
+    pub fn pdf_exact(&self, target: i32) -> DiceResult<f64> {
+        let exact = exact(self).ok_or_else(|| {
+            DiceError::UnsupportedFeature(
+                "analytic PMF not available for this expression type".to_string(),
+            )
+        })?;
+        Ok(exact.get(&target).copied().unwrap_or(0.0))
+    }
+
+    /// Every distinct result the expression can produce, in ascending order
+    ///
+    /// `1d6` gives `[1, 2, 3, 4, 5, 6]` and `2d3` gives `[2, 3, 4, 5, 6]`.
+    /// Pair with [`pdf_exact`](Self::pdf_exact) for the probability of each.
+    /// Fails with [`DiceError::Overflow`] past [`MAX_EXACT_OUTCOMES`]
+    /// results, and with [`DiceError::UnsupportedFeature`] where
+    /// `pdf_exact` does.
+    pub fn all_possible_outcomes(&self) -> DiceResult<Vec<i32>> {
+        self.all_possible_outcomes_within(MAX_EXACT_OUTCOMES)
+    }
+
+    /// [`all_possible_outcomes`](Self::all_possible_outcomes), failing with
+    /// [`DiceError::Overflow`] past `limit` results
+    ///
+    /// Expressions with more than [`MAX_EXACT_OUTCOMES`] results always fail,
+    /// whatever the limit.
+    pub fn all_possible_outcomes_within(&self, limit: usize) -> DiceResult<Vec<i32>> {
+        if !has_exact(self) {
+            return Err(DiceError::UnsupportedFeature(
+                "outcomes not available for this expression type".to_string(),
+            ));
+        }
+        // Every node has an analytic form, so a missing distribution means
+        // too many outcomes (or an overflowing one)
+        let outcomes: Vec<i32> = exact(self).ok_or(DiceError::Overflow)?.into_keys().collect();
+        if outcomes.len() > limit {
+            return Err(DiceError::Overflow);
+        }
+        Ok(outcomes)
+    }
+
+    /// Print the distribution as an ASCII bar chart, one outcome per line
+    ///
+    /// The most likely outcome gets a bar `width` characters long; the bar
+    /// nearest the mean is marked with `*`.
+    ///
+    /// ```text
+    ///  2 | ##          2.78%
+    ///  ...
+    ///  7 | ##########* 16.67%
+    /// ```
+    pub fn print_distribution(&self, width: usize, mut writer: impl Write) -> DiceResult<()> {
+        let distribution = self.distribution()?;
+        write_histogram(&distribution, width, &mut writer)?;
+        Ok(())
+    }
+}
+
+/// Render a distribution as the bar chart described on
+/// [`DiceExpression::print_distribution`]
+fn write_histogram(
+    distribution: &Distribution,
+    width: usize,
+    writer: &mut impl Write,
+) -> std::io::Result<()> {
+    let peak = distribution.values().copied().fold(0.0, f64::max);
+    let mean: f64 = distribution
+        .iter()
+        .map(|(&outcome, &p)| outcome as f64 * p)
+        .sum();
+    let mean_outcome = distribution
+        .keys()
+        .copied()
+        .min_by(|a, b| {
+            let (da, db) = ((*a as f64 - mean).abs(), (*b as f64 - mean).abs());
+            da.total_cmp(&db)
+        });
+    let label_width = distribution
+        .keys()
+        .map(|outcome| outcome.to_string().len())
+        .max()
+        .unwrap_or(0);
+
+    for (&outcome, &p) in distribution {
+        let length = if peak > 0.0 {
+            (p / peak * width as f64).round() as usize
+        } else {
+            0
+        };
+        let mut bar = "#".repeat(length);
+        if Some(outcome) == mean_outcome {
+            bar.push('*');
+        }
+        writeln!(
+            writer,
+            "{:>label_width$} | {:<bar_width$} {:>5.2}%",
+            outcome,
+            bar,
+            p * 100.0,
+            bar_width = width + 1,
+        )?;
+    }
+    Ok(())
+}
+
+/// Exact distribution, or None when the expression needs simulation or
+/// would track too many outcomes
+pub(crate) fn exact(expr: &DiceExpression) -> Option<Distribution> {
+    match expr {
+        DiceExpression::Constant(value) => Some(BTreeMap::from([(*value, 1.0)])),
+        DiceExpression::Dice(count, sides) => {
+            if *count == 0 || *sides == 0 {
+                return None;
+            }
+            let support = (*count as usize).checked_mul(*sides as usize)?;
+            if support > MAX_EXACT_OUTCOMES {
+                return None;
+            }
+            let face = 1.0 / *sides as f64;
+            let die: Distribution = (1..=*sides as i32).map(|value| (value, face)).collect();
+            let mut total = die.clone();
+            for _ in 1..*count {
+                total = combine(&total, &die, i32::checked_add)?;
+            }
+            Some(total)
+        }
+        DiceExpression::Conditional {
+            condition,
+            predicate,
+            then_expr,
+            else_expr,
+        } => {
+            let taken: f64 = exact(condition)?
+                .into_iter()
+                .filter(|(outcome, _)| predicate.matches(*outcome))
+                .map(|(_, p)| p)
+                .sum();
+            let mut result = BTreeMap::new();
+            for (branch, weight) in [(then_expr, taken), (else_expr, 1.0 - taken)] {
+                for (outcome, p) in exact(branch)? {
+                    *result.entry(outcome).or_insert(0.0) += p * weight;
+                }
+            }
+            Some(result)
+        }
+        DiceExpression::Negate(inner) => exact(inner)?
+            .into_iter()
+            .map(|(outcome, p)| Some((outcome.checked_neg()?, p)))
+            .collect(),
+        DiceExpression::AbsoluteValue(inner) => {
+            let mut result = BTreeMap::new();
+            for (outcome, p) in exact(inner)? {
+                *result.entry(outcome.checked_abs()?).or_insert(0.0) += p;
+            }
+            Some(result)
+        }
+        DiceExpression::Add(left, right) => combine(&exact(left)?, &exact(right)?, i32::checked_add),
+        DiceExpression::Sub(left, right) => combine(&exact(left)?, &exact(right)?, i32::checked_sub),
+        DiceExpression::Mul(left, right) => combine(&exact(left)?, &exact(right)?, i32::checked_mul),
+        DiceExpression::Min(left, right) => {
+            combine(&exact(left)?, &exact(right)?, |a, b| Some(a.min(b)))
+        }
+        DiceExpression::Max(left, right) => {
+            combine(&exact(left)?, &exact(right)?, |a, b| Some(a.max(b)))
+        }
+        DiceExpression::FloorClamp(inner, limit) | DiceExpression::CeilClamp(inner, limit) => {
+            let clamp = match expr {
+                DiceExpression::FloorClamp(..) => i32::max,
+                _ => i32::min,
+            };
+            let mut result = BTreeMap::new();
+            for (outcome, p) in exact(inner)? {
+                *result.entry(clamp(outcome, *limit)).or_insert(0.0) += p;
+            }
+            Some(result)
+        }
+        DiceExpression::FloorDiv(inner, divisor) | DiceExpression::CeilDiv(inner, divisor) => {
+            let divide = match expr {
+                DiceExpression::FloorDiv(..) => floor_div,
+                _ => ceil_div,
+            };
+            let mut result = BTreeMap::new();
+            for (outcome, p) in exact(inner)? {
+                // A zero divisor is left for simulation to report
+                *result.entry(divide(outcome, *divisor).ok()?).or_insert(0.0) += p;
+            }
+            Some(result)
+        }
+        DiceExpression::Div(left, right) => {
+            let divisor = exact(right)?;
+            if divisor.contains_key(&0) {
+                // Let simulation surface the division error
+                return None;
+            }
+            combine(&exact(left)?, &divisor, i32::checked_div)
+        }
+        _ => None,
+    }
+}
+
+/// Whether every node of `expr` has a case in [`exact`]
+fn has_exact(expr: &DiceExpression) -> bool {
+    use DiceExpression::*;
+
+    matches!(
+        expr,
+        Constant(_)
+            | Dice(..)
+            | Conditional { .. }
+            | Negate(_)
+            | AbsoluteValue(_)
+            | Add(..)
+            | Sub(..)
+            | Mul(..)
+            | Div(..)
+            | FloorClamp(..)
+            | CeilClamp(..)
+            | FloorDiv(..)
+            | CeilDiv(..)
+            | Min(..)
+            | Max(..)
+    ) && expr.children().into_iter().all(has_exact)
+}
+
+/// Distribution of `op(a, b)` for independent `a` and `b`
+///
+/// Gives up as soon as the result tracks more than [`MAX_EXACT_OUTCOMES`]
+/// outcomes, rather than after every pair has been combined.
+fn combine(
+    a: &Distribution,
+    b: &Distribution,
+    op: fn(i32, i32) -> Option<i32>,
+) -> Option<Distribution> {
+    let mut result = BTreeMap::new();
+    for (&x, &px) in a {
+        for (&y, &py) in b {
+            *result.entry(op(x, y)?).or_insert(0.0) += px * py;
+        }
+        if result.len() > MAX_EXACT_OUTCOMES {
+            return None;
+        }
+    }
+    Some(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(notation: &str) -> DiceExpression {
+        DiceExpression::parse(notation).unwrap()
+    }
+
+    #[test]
+    fn test_exact_distribution() {
+        let dist = parse("2d6").distribution().unwrap();
+        assert_eq!(dist.len(), 11);
+        assert!((dist[&7] - 6.0 / 36.0).abs() < 1e-12);
+        assert!((dist[&2] - 1.0 / 36.0).abs() < 1e-12);
+        assert!((dist.values().sum::<f64>() - 1.0).abs() < 1e-12);
+
+        let conditional = parse("(1d4>=4)?10:1d2").distribution().unwrap();
+        assert!((conditional[&10] - 0.25).abs() < 1e-12);
+        assert!((conditional[&1] - 0.375).abs() < 1e-12);
+
+        let lower = parse("min(1d6, 1d6)").distribution().unwrap();
+        assert!((lower[&1] - 11.0 / 36.0).abs() < 1e-12);
+        assert!((lower[&6] - 1.0 / 36.0).abs() < 1e-12);
+        let higher = parse("max(1d6, 1d6)").distribution().unwrap();
+        assert!((higher[&6] - 11.0 / 36.0).abs() < 1e-12);
+
+        let shifted = parse("1d4*2+1").distribution().unwrap();
+        assert_eq!(shifted.keys().copied().collect::<Vec<_>>(), vec![3, 5, 7, 9]);
+    }
+
+    #[test]
+    fn test_all_possible_outcomes() {
+        assert_eq!(parse("1d6").all_possible_outcomes(), Ok(vec![1, 2, 3, 4, 5, 6]));
+        assert_eq!(parse("2d3").all_possible_outcomes(), Ok(vec![2, 3, 4, 5, 6]));
+        assert_eq!(parse("1d4*10-5").all_possible_outcomes(), Ok(vec![5, 15, 25, 35]));
+        assert_eq!(parse("|1d3-2|").all_possible_outcomes(), Ok(vec![0, 1]));
+        assert_eq!(parse("(1d2>=2)?7:-1").all_possible_outcomes(), Ok(vec![-1, 7]));
+        assert_eq!(parse("1d100*1000").all_possible_outcomes().unwrap().len(), 100);
+
+        assert_eq!(parse("200d100").all_possible_outcomes(), Err(DiceError::Overflow));
+        assert_eq!(parse("(20d100)*(20d100)").all_possible_outcomes(), Err(DiceError::Overflow));
+        assert_eq!(parse("3d6").all_possible_outcomes_within(16), Ok((3..=18).collect()));
+        assert_eq!(parse("3d6").all_possible_outcomes_within(15), Err(DiceError::Overflow));
+        for notation in ["4d6k3", "3d6!", "6d6>5", "Nd6", "F(1)+1d6"] {
+            assert!(matches!(
+                parse(notation).all_possible_outcomes(),
+                Err(DiceError::UnsupportedFeature(_))
+            ));
+        }
+
+        // Each outcome has its exact probability
+        let expr = parse("2d4+1");
+        let total: f64 = expr
+            .all_possible_outcomes()
+            .unwrap()
+            .into_iter()
+            .map(|outcome| expr.pdf_exact(outcome).unwrap())
+            .sum();
+        assert!((total - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_pdf_exact() {
+        let expr = parse("3d6");
+        assert!((expr.pdf_exact(10).unwrap() - 27.0 / 216.0).abs() < 1e-12);
+        assert!((expr.pdf_exact(3).unwrap() - 1.0 / 216.0).abs() < 1e-12);
+        assert_eq!(expr.pdf_exact(19), Ok(0.0));
+        assert!((parse("2d6+3").pdf_exact(10).unwrap() - 6.0 / 36.0).abs() < 1e-12);
+
+        for notation in ["4d6k3", "3d6!", "6d6>5"] {
+            assert!(matches!(
+                parse(notation).pdf_exact(10),
+                Err(DiceError::UnsupportedFeature(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_pdf_exact_matches_simulation() {
+        let samples = 1_000_000;
+        let expr = parse("3d6");
+        let mut rng = DiceRng::new(124);
+        let mut counts: BTreeMap<i32, u32> = BTreeMap::new();
+        for _ in 0..samples {
+            *counts.entry(expr.evaluate(&mut rng).unwrap()).or_insert(0) += 1;
+        }
+        for target in 3..=18 {
+            let p = expr.pdf_exact(target).unwrap();
+            let expected = p * samples as f64;
+            let sigma = (samples as f64 * p * (1.0 - p)).sqrt();
+            let observed = counts[&target] as f64;
+            assert!(
+                (observed - expected).abs() <= 3.0 * sigma,
+                "{}: observed {} expected {}",
+                target,
+                observed,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_approximate_distribution() {
+        let mut rng = DiceRng::new(136);
+        let buckets = parse("20d100").approximate_distribution_with(&mut rng, 10).unwrap();
+        assert_eq!(buckets.len(), 10);
+        assert!((buckets.iter().map(|(_, p)| p).sum::<f64>() - 1.0).abs() < 1e-9);
+        assert!((buckets[0].0 - 118.55).abs() < 1e-9);
+        assert!((buckets[9].0 - 1901.45).abs() < 1e-9);
+        // Sums of 20 dice cluster around the mean of 1010
+        let peak = buckets.iter().max_by(|a, b| a.1.total_cmp(&b.1)).unwrap();
+        assert!((peak.0 - 1010.0).abs() < 200.0);
+
+        // One bucket per face gives the face probabilities
+        let faces = parse("1d6").approximate_distribution_with(&mut rng, 6).unwrap();
+        for (i, (midpoint, p)) in faces.into_iter().enumerate() {
+            assert!((midpoint - (i + 1) as f64).abs() < 1e-9);
+            assert!((p - 1.0 / 6.0).abs() < 0.01);
+        }
+        assert_eq!(
+            parse("1d6").approximate_distribution(0),
+            Err(DiceError::InvalidCount(0))
+        );
+        assert!(parse("Nd6").approximate_distribution(DEFAULT_BUCKETS).is_err());
+    }
+
+    #[test]
+    fn test_simulated_distribution() {
+        let mut rng = DiceRng::new(8);
+        let dist = parse("4d6k3").distribution_with(&mut rng).unwrap();
+        assert_eq!(*dist.keys().next().unwrap(), 3);
+        assert_eq!(*dist.keys().last().unwrap(), 18);
+        // At least three sixes among four dice
+        assert!((dist[&18] - 21.0 / 1296.0).abs() < 0.002);
+        assert!(parse("1d6/0").distribution_with(&mut rng).is_err());
+    }
+
+    #[test]
+    fn test_print_distribution() {
+        let mut out = Vec::new();
+        parse("1d2+1d2").print_distribution(4, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "2 | ##    25.00%\n\
+             3 | ####* 50.00%\n\
+             4 | ##    25.00%\n"
+        );
+    }
+
+    #[test]
+    fn test_histogram_labels_align() {
+        let mut out = Vec::new();
+        parse("1d3*5-5").print_distribution(3, &mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], " 0 | ###  33.33%");
+        assert_eq!(lines[1], " 5 | ###* 33.33%");
+        assert_eq!(lines[2], "10 | ###  33.33%");
+    }
+}
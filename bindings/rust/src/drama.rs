@@ -0,0 +1,131 @@
+//! Drama dice
+//!
+//! A success pool where any die landing exactly on a drama face triggers a
+//! narrative event, whatever the successes say.
+
+use crate::context::DiceContext;
+use crate::{Dice, DiceError, DiceResult};
+
+/// Outcome of a drama pool
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DramaResult {
+    pub roll_values: Vec<i32>,
+    /// Dice showing at least the target
+    pub successes: u32,
+    /// At least one die showed the drama threshold
+    pub drama_triggered: bool,
+    /// Dice showing the drama threshold
+    pub drama_count: u32,
+}
+
+impl DiceContext {
+    /// Roll a success pool and look for drama faces
+    ///
+    /// # Arguments
+    ///
+    /// * `pool_size` - Number of dice
+    /// * `sides` - Sides per die
+    /// * `target` - Minimum value counted as a success
+    /// * `drama_threshold` - Face that triggers drama; must be on the die
+    pub fn roll_drama(
+        &mut self,
+        pool_size: u32,
+        sides: u32,
+        target: u32,
+        drama_threshold: u32,
+    ) -> DiceResult<DramaResult> {
+        if sides == 0 {
+            return Err(DiceError::InvalidSides(0));
+        }
+        if drama_threshold == 0 || drama_threshold > sides {
+            return Err(DiceError::InvalidSides(drama_threshold as i32));
+        }
+        let roll_values = self.roll_pool(pool_size, sides)?;
+        let count = |matches: &dyn Fn(i32) -> bool| {
+            roll_values.iter().filter(|&&value| matches(value)).count() as u32
+        };
+        let successes = count(&|value| value >= target as i32);
+        let drama_count = count(&|value| value == drama_threshold as i32);
+
+        Ok(DramaResult {
+            successes,
+            drama_triggered: drama_count > 0,
+            drama_count,
+            roll_values,
+        })
+    }
+}
+
+impl Dice {
+    /// Roll a success pool and look for drama faces
+    ///
+    /// # Arguments
+    ///
+    /// * `pool_size` - Number of dice
+    /// * `sides` - Sides per die
+    /// * `target` - Minimum value counted as a success
+    /// * `drama_threshold` - Face that triggers drama; must be on the die
+    pub fn roll_drama(
+        pool_size: u32,
+        sides: u32,
+        target: u32,
+        drama_threshold: u32,
+    ) -> DiceResult<DramaResult> {
+        DiceContext::new().roll_drama(pool_size, sides, target, drama_threshold)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_drama_counts() {
+        let mut ctx = DiceContext::with_seed(19);
+        for _ in 0..200 {
+            let result = ctx.roll_drama(6, 6, 4, 6).unwrap();
+            assert_eq!(result.roll_values.len(), 6);
+            let sixes = result.roll_values.iter().filter(|&&v| v == 6).count() as u32;
+            let hits = result.roll_values.iter().filter(|&&v| v >= 4).count() as u32;
+            assert_eq!(result.drama_count, sixes);
+            assert_eq!(result.drama_triggered, sixes > 0);
+            assert_eq!(result.successes, hits);
+        }
+    }
+
+    #[test]
+    fn test_drama_triggered() {
+        // A d1 always lands on its only face
+        let mut ctx = DiceContext::with_seed(19);
+        let result = ctx.roll_drama(3, 1, 1, 1).unwrap();
+        assert!(result.drama_triggered);
+        assert_eq!(result.drama_count, 3);
+        assert_eq!(result.successes, 3);
+    }
+
+    #[test]
+    fn test_drama_not_triggered() {
+        let mut ctx = DiceContext::with_seed(19);
+        let mut quiet = 0;
+        for _ in 0..100 {
+            let result = ctx.roll_drama(1, 20, 10, 20).unwrap();
+            if result.roll_values[0] != 20 {
+                assert!(!result.drama_triggered);
+                assert_eq!(result.drama_count, 0);
+                quiet += 1;
+            }
+        }
+        assert!(quiet > 0);
+    }
+
+    #[test]
+    fn test_drama_validation() {
+        let mut ctx = DiceContext::with_seed(19);
+        assert_eq!(ctx.roll_drama(4, 6, 4, 7), Err(DiceError::InvalidSides(7)));
+        assert_eq!(ctx.roll_drama(4, 6, 4, 0), Err(DiceError::InvalidSides(0)));
+        assert_eq!(ctx.roll_drama(0, 6, 4, 6), Err(DiceError::InvalidCount(0)));
+        assert_eq!(ctx.roll_drama(4, 0, 4, 1), Err(DiceError::InvalidSides(0)));
+        assert!(Dice::roll_drama(4, 6, 4, 6).is_ok());
+    }
+}
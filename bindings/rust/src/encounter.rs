@@ -0,0 +1,295 @@
+//! Random encounter tables
+//!
+//! Game masters keep encounter tables in files: a die to roll and the
+//! encounter for each range of results. Tables load from two-column CSV
+//! (`range,encounter`) or, with the `toml` feature, from TOML:
+//!
+//! ```toml
+//! die = "d8"
+//!
+//! [[entries]]
+//! range = "1-3"
+//! encounter = "Goblins"
+//!
+//! [[entries]]
+//! range = "4-8"
+//! encounter = "Wolves"
+//! ```
+
+use crate::eval::MAX_SIMULATION_ITERATIONS;
+use crate::expression::DiceExpression;
+use crate::history::parse_csv;
+use crate::{DiceError, DiceResult};
+use rand::Rng;
+use std::io::Read;
+
+/// Die and the encounter each range of its results leads to
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EncounterTable {
+    die: DiceExpression,
+    /// (lowest, highest, encounter), both ends inclusive
+    entries: Vec<(i32, i32, String)>,
+}
+
+#[cfg(feature = "toml")]
+#[derive(serde::Deserialize)]
+struct TomlTable {
+    die: String,
+    entries: Vec<TomlEntry>,
+}
+
+#[cfg(feature = "toml")]
+#[derive(serde::Deserialize)]
+struct TomlEntry {
+    range: String,
+    encounter: String,
+}
+
+impl EncounterTable {
+    /// Table rolling `die`, from `(range, encounter)` rows
+    ///
+    /// A range is a single result (`"4"`) or two joined by a dash
+    /// (`"1-3"`). Ranges may leave gaps but not overlap, and every range
+    /// must be a result the die can roll; anything else fails with
+    /// [`DiceError::InvalidFormat`].
+    pub fn new<'a>(
+        die: &str,
+        rows: impl IntoIterator<Item = (&'a str, &'a str)>,
+    ) -> DiceResult<EncounterTable> {
+        let die = DiceExpression::parse(die)?;
+        let (min, max) = (die.min_result()?, die.max_result()?);
+        let mut entries = Vec::new();
+        for (range, encounter) in rows {
+            let (low, high) = parse_range(range)
+                .ok_or_else(|| DiceError::InvalidFormat(format!("invalid range '{}'", range)))?;
+            if low < min || high > max {
+                return Err(DiceError::InvalidFormat(format!(
+                    "range '{}' is outside {}-{}",
+                    range, min, max
+                )));
+            }
+            if entries.iter().any(|&(l, h, _)| low <= h && l <= high) {
+                return Err(DiceError::InvalidFormat(format!("range '{}' overlaps", range)));
+            }
+            entries.push((low, high, encounter.to_string()));
+        }
+        Ok(EncounterTable { die, entries })
+    }
+
+    /// Parse a table from TOML with a `die` and `[[entries]]` of `range`
+    /// and `encounter`
+    ///
+    /// Malformed TOML fails with [`DiceError::InvalidFormat`].
+    #[cfg(feature = "toml")]
+    pub fn from_toml_str(toml: &str) -> DiceResult<EncounterTable> {
+        let table: TomlTable =
+            toml::from_str(toml).map_err(|err| DiceError::InvalidFormat(err.to_string()))?;
+        let rows = table.entries.iter().map(|entry| (&*entry.range, &*entry.encounter));
+        EncounterTable::new(&table.die, rows)
+    }
+
+    /// Read a table from two-column `range,encounter` CSV
+    ///
+    /// A `range,encounter` header row is optional. The die is a single die
+    /// covering the highest range, so a table ending at `7-8` rolls a d8.
+    pub fn from_csv(mut reader: impl Read) -> DiceResult<EncounterTable> {
+        let mut input = String::new();
+        reader.read_to_string(&mut input)?;
+
+        let mut rows = parse_csv(&input)?;
+        if rows.first().is_some_and(|row| row == &["range", "encounter"]) {
+            rows.remove(0);
+        }
+        for (line, row) in rows.iter().enumerate() {
+            if row.len() != 2 {
+                return Err(DiceError::InvalidFormat(format!(
+                    "row {}: expected 2 fields, found {}",
+                    line + 1,
+                    row.len()
+                )));
+            }
+        }
+        let sides = rows
+            .iter()
+            .filter_map(|row| parse_range(&row[0]))
+            .map(|(_, high)| high)
+            .max()
+            .ok_or_else(|| DiceError::InvalidFormat("no encounters".to_string()))?;
+        let rows = rows.iter().map(|row| (&*row[0], &*row[1]));
+        EncounterTable::new(&format!("1d{}", sides.max(1)), rows)
+    }
+
+    /// Die rolled on the table
+    pub fn die(&self) -> &DiceExpression {
+        &self.die
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Roll the die and return the encounter it lands on
+    ///
+    /// A result in a gap between ranges fails with
+    /// [`DiceError::InvalidRange`] spanning the die's results.
+    pub fn roll<R: Rng + ?Sized>(&self, rng: &mut R) -> DiceResult<&str> {
+        let roll = self.die.evaluate(rng)?;
+        self.entries
+            .iter()
+            .find(|(low, high, _)| (*low..=*high).contains(&roll))
+            .map(|(_, _, encounter)| encounter.as_str())
+            .ok_or(DiceError::InvalidRange {
+                min: self.die.min_result()?,
+                max: self.die.max_result()?,
+                actual: roll,
+            })
+    }
+
+    /// Roll `count` encounters, each independently of the others
+    ///
+    /// More than
+    /// [`MAX_SIMULATION_ITERATIONS`](crate::eval::MAX_SIMULATION_ITERATIONS)
+    /// fails with [`DiceError::RollCountLimit`].
+    pub fn roll_multiple<R: Rng + ?Sized>(
+        &self,
+        count: u32,
+        rng: &mut R,
+    ) -> DiceResult<Vec<&str>> {
+        if count > MAX_SIMULATION_ITERATIONS {
+            return Err(DiceError::RollCountLimit {
+                requested: count,
+                limit: MAX_SIMULATION_ITERATIONS,
+            });
+        }
+        (0..count).map(|_| self.roll(rng)).collect()
+    }
+}
+
+/// `"4"` or `"1-3"` as (lowest, highest)
+fn parse_range(range: &str) -> Option<(i32, i32)> {
+    let range = range.trim();
+    // Skip a leading sign so "-2--1" splits at the dash between the ends
+    let split = range.char_indices().skip(1).find(|&(_, c)| c == '-').map(|(i, _)| i);
+    let (low, high) = match split {
+        Some(i) => (range[..i].trim().parse().ok()?, range[i + 1..].trim().parse().ok()?),
+        None => {
+            let value = range.parse().ok()?;
+            (value, value)
+        }
+    };
+    (low <= high).then_some((low, high))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::DiceRng;
+
+    fn wilderness() -> EncounterTable {
+        let rows = [("1-3", "Goblins"), ("4-6", "Wolves"), ("7", "Ogre"), ("8", "Dragon")];
+        EncounterTable::new("d8", rows).unwrap()
+    }
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(parse_range("1-3"), Some((1, 3)));
+        assert_eq!(parse_range(" 4 "), Some((4, 4)));
+        assert_eq!(parse_range("10 - 12"), Some((10, 12)));
+        assert_eq!(parse_range("-2--1"), Some((-2, -1)));
+        for range in ["", "3-1", "a-b", "1-", "1-2-3"] {
+            assert_eq!(parse_range(range), None, "{}", range);
+        }
+    }
+
+    #[test]
+    fn test_roll() {
+        let table = wilderness();
+        assert_eq!(table.len(), 4);
+        let mut rng = DiceRng::new(177);
+        let rolls = table.roll_multiple(400, &mut rng).unwrap();
+        for encounter in ["Goblins", "Wolves", "Ogre", "Dragon"] {
+            let count = rolls.iter().filter(|&&rolled| rolled == encounter).count();
+            assert!(count > 20, "{} rolled {} times", encounter, count);
+        }
+        assert!(table.roll_multiple(0, &mut rng).unwrap().is_empty());
+        assert!(matches!(
+            table.roll_multiple(u32::MAX, &mut rng),
+            Err(DiceError::RollCountLimit { .. })
+        ));
+    }
+
+    #[test]
+    fn test_invalid_tables() {
+        let invalid = |rows: &[(&str, &str)]| EncounterTable::new("1d6", rows.iter().copied());
+        assert!(matches!(invalid(&[("1-4", "A"), ("4-6", "B")]), Err(DiceError::InvalidFormat(_))));
+        assert!(matches!(invalid(&[("1-7", "A")]), Err(DiceError::InvalidFormat(_))));
+        assert!(matches!(invalid(&[("one", "A")]), Err(DiceError::InvalidFormat(_))));
+        assert!(EncounterTable::new("2d", []).is_err());
+
+        // A gap is allowed, but rolling into it fails
+        let gappy = EncounterTable::new("1d2", [("1", "Bandits")]).unwrap();
+        let mut rng = DiceRng::new(177);
+        let results: Vec<_> = (0..20).map(|_| gappy.roll(&mut rng)).collect();
+        assert!(results.contains(&Ok("Bandits")));
+        assert!(results.contains(&Err(DiceError::InvalidRange {
+            min: 1,
+            max: 2,
+            actual: 2
+        })));
+    }
+
+    #[test]
+    fn test_from_csv() {
+        let csv = "range,encounter\r\n1-3,Goblins\r\n4-6,Wolves\r\n7,Ogre\r\n8,Dragon\r\n";
+        assert_eq!(EncounterTable::from_csv(csv.as_bytes()).unwrap(), wilderness());
+
+        let headless = "1-2,\"Bandits, mounted\"\n3-4,Nothing\n";
+        let table = EncounterTable::from_csv(headless.as_bytes()).unwrap();
+        assert_eq!(table.die(), &DiceExpression::Dice(1, 4));
+        assert_eq!(table.entries[0].2, "Bandits, mounted");
+
+        for csv in ["", "range,encounter\n", "1-3\n", "1-3,A,extra\n", "x,A\n"] {
+            let result = EncounterTable::from_csv(csv.as_bytes());
+            assert!(matches!(result, Err(DiceError::InvalidFormat(_))), "{:?}", csv);
+        }
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn test_from_toml_str() {
+        let toml = r#"
+            die = "d8"
+
+            [[entries]]
+            range = "1-3"
+            encounter = "Goblins"
+
+            [[entries]]
+            range = "4-6"
+            encounter = "Wolves"
+
+            [[entries]]
+            range = "7"
+            encounter = "Ogre"
+
+            [[entries]]
+            range = "8"
+            encounter = "Dragon"
+        "#;
+        assert_eq!(EncounterTable::from_toml_str(toml).unwrap(), wilderness());
+        assert!(matches!(
+            EncounterTable::from_toml_str("die = \"d8\""),
+            Err(DiceError::InvalidFormat(_))
+        ));
+        let too_wide = "die = \"d4\"\n[[entries]]\nrange = \"1-5\"\nencounter = \"A\"";
+        assert!(matches!(
+            EncounterTable::from_toml_str(too_wide),
+            Err(DiceError::InvalidFormat(_))
+        ));
+    }
+}
@@ -0,0 +1,199 @@
+//! Pre-flight cost estimates
+//!
+//! How many dice an expression will roll, worked out from the tree so
+//! servers can reject expensive notation before evaluating it.
+
+use crate::distribution::exact;
+use crate::eval::{exploding_faces, MAX_DICE_COUNT, MAX_EXPLOSION_DEPTH};
+use crate::expression::DiceExpression;
+
+/// Number of dice an expression rolls
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ExpectedRollCount {
+    pub minimum: u32,
+    /// Mean over all outcomes
+    pub expected: f64,
+    /// None if the count doesn't fit in a `u32`
+    pub maximum: Option<u32>,
+}
+
+impl ExpectedRollCount {
+    fn exactly(count: u32) -> Self {
+        ExpectedRollCount {
+            minimum: count,
+            expected: count as f64,
+            maximum: Some(count),
+        }
+    }
+
+    fn plus(self, other: ExpectedRollCount) -> Self {
+        ExpectedRollCount {
+            minimum: self.minimum.saturating_add(other.minimum),
+            expected: self.expected + other.expected,
+            maximum: self.maximum.zip(other.maximum).and_then(|(a, b)| a.checked_add(b)),
+        }
+    }
+}
+
+impl DiceExpression {
+    /// Dice this expression rolls: 4 for `4d6k3`, at least 4 for `4d6!`
+    ///
+    /// Explosions chain at most [`MAX_EXPLOSION_DEPTH`] times per die, so
+    /// the maximum is always finite; `4d6!` rolls at most 44 dice. A
+    /// variable count (`Nd6`) isn't known before evaluation and is budgeted
+    /// at [`MAX_DICE_COUNT`]. Dice rolled inside user-defined functions
+    /// aren't counted.
+    pub fn count_expected_rolls(&self) -> ExpectedRollCount {
+        use DiceExpression::*;
+
+        match self {
+            Constant(_) | Variable(_) => ExpectedRollCount::exactly(0),
+            Dice(count, _) => ExpectedRollCount::exactly(*count),
+            DynamicDice(count, _) => count.count_expected_rolls().plus(ExpectedRollCount {
+                minimum: 1,
+                expected: MAX_DICE_COUNT as f64,
+                maximum: Some(MAX_DICE_COUNT),
+            }),
+            Explode(inner, threshold) => {
+                explosions(inner, |sides| (sides + 1).saturating_sub((*threshold).max(1)))
+            }
+            ExplodeOn(inner, faces) => explosions(inner, |sides| exploding_faces(faces, sides)),
+            KeepHighest(inner, _)
+            | KeepLowest(inner, _)
+            | DropHighest(inner, _)
+            | DropLowest(inner, _)
+            | CountSuccesses(inner, _)
+            | CountSuccessesWithBotch(inner, ..)
+            | FloorClamp(inner, _)
+            | CeilClamp(inner, _)
+            | FloorDiv(inner, _)
+            | CeilDiv(inner, _)
+            | Negate(inner)
+            | AbsoluteValue(inner) => inner.count_expected_rolls(),
+            Add(left, right)
+            | Sub(left, right)
+            | Mul(left, right)
+            | Div(left, right)
+            | Min(left, right)
+            | Max(left, right) => left.count_expected_rolls().plus(right.count_expected_rolls()),
+            Conditional {
+                condition,
+                predicate,
+                then_expr,
+                else_expr,
+            } => {
+                let taken = then_expr.count_expected_rolls();
+                let skipped = else_expr.count_expected_rolls();
+                // Weight the branches by the condition's odds when they're known
+                let p = exact(condition).map_or(0.5, |distribution| {
+                    distribution
+                        .iter()
+                        .filter(|(outcome, _)| predicate.matches(**outcome))
+                        .map(|(_, p)| p)
+                        .sum()
+                });
+                condition.count_expected_rolls().plus(ExpectedRollCount {
+                    minimum: taken.minimum.min(skipped.minimum),
+                    expected: taken.expected * p + skipped.expected * (1.0 - p),
+                    maximum: taken.maximum.zip(skipped.maximum).map(|(a, b)| a.max(b)),
+                })
+            }
+            // Dice a function rolls itself aren't recorded in the result
+            Call(_, args) => args
+                .iter()
+                .map(DiceExpression::count_expected_rolls)
+                .fold(ExpectedRollCount::exactly(0), ExpectedRollCount::plus),
+        }
+    }
+}
+
+/// Rolls of an exploding pool on `inner`, where `exploding_faces` counts the
+/// faces of a die that explode
+fn explosions(inner: &DiceExpression, exploding_faces: impl Fn(u32) -> u32) -> ExpectedRollCount {
+    use DiceExpression::*;
+
+    let base = inner.count_expected_rolls();
+    let sides = match *inner {
+        Dice(_, sides) | DynamicDice(_, sides) => sides,
+        _ => return base,
+    };
+    // Chance a single die explodes
+    let exploding_faces = exploding_faces(sides);
+    let p = exploding_faces as f64 / sides as f64;
+    let chain = MAX_EXPLOSION_DEPTH + 1;
+    // Dice per original: 1 + p + p^2 + ... + p^depth
+    let per_die = (0..chain as i32).map(|k| p.powi(k)).sum::<f64>();
+    let chained = |count: u32| count.checked_mul(chain);
+    ExpectedRollCount {
+        minimum: if exploding_faces >= sides {
+            chained(base.minimum).unwrap_or(u32::MAX)
+        } else {
+            base.minimum
+        },
+        expected: base.expected * per_die,
+        maximum: if exploding_faces > 0 {
+            base.maximum.and_then(chained)
+        } else {
+            base.maximum
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::DiceRng;
+
+    fn count(notation: &str) -> ExpectedRollCount {
+        DiceExpression::parse(notation).unwrap().count_expected_rolls()
+    }
+
+    #[test]
+    fn test_fixed_counts() {
+        assert_eq!(count("4d6k3"), ExpectedRollCount::exactly(4));
+        assert_eq!(count("3d6+1d4*2-1"), ExpectedRollCount::exactly(4));
+        assert_eq!(count("8d6>4"), ExpectedRollCount::exactly(8));
+        assert_eq!(count("|-2d6|"), ExpectedRollCount::exactly(2));
+        assert_eq!(count("7"), ExpectedRollCount::exactly(0));
+    }
+
+    #[test]
+    fn test_conditional_counts() {
+        let estimate = count("(1d20>=16)?3d6:1d4");
+        assert_eq!((estimate.minimum, estimate.maximum), (2, Some(4)));
+        // 1 + 3 * 0.25 + 1 * 0.75
+        assert!((estimate.expected - 2.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_exploding_counts() {
+        let explode = count("4d6!");
+        assert_eq!(explode.minimum, 4);
+        assert_eq!(explode.maximum, Some(44));
+        // Geometric series: 4 * (1 + 1/6 + 1/36 + ...) ≈ 4.8
+        assert!((explode.expected - 4.8).abs() < 0.001);
+
+        assert_eq!(count("4d6!>7"), ExpectedRollCount::exactly(4));
+        let always = count("2d6!>1");
+        assert_eq!(always.minimum, 22);
+        assert_eq!(always.maximum, Some(22));
+        assert!((always.expected - 22.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_expected_matches_rolls() {
+        let expr = DiceExpression::parse("5d4!>3").unwrap();
+        let estimate = expr.count_expected_rolls();
+        let mut rng = DiceRng::new(17);
+        let trials = 20_000;
+        let mut total = 0usize;
+        for _ in 0..trials {
+            let dice = expr.roll(&mut rng).unwrap().dice.len();
+            assert!(dice as u32 >= estimate.minimum && Some(dice as u32) <= estimate.maximum);
+            total += dice;
+        }
+        let mean = total as f64 / trials as f64;
+        assert!((mean - estimate.expected).abs() < 0.1, "{} vs {}", mean, estimate.expected);
+    }
+}
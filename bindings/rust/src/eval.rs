@@ -0,0 +1,824 @@
+//! Expression evaluation
+//!
+//! Walks a [`DiceExpression`] and rolls every die through the supplied
+//! generator, recording each die in the returned [`RollResult`].
+
+use crate::config::RollConfig;
+use crate::expression::DiceExpression;
+use crate::roll_context::Functions;
+use crate::trace::{EvalTrace, Tracer};
+use crate::{DiceError, DiceResult};
+use rand::Rng;
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::io::Write;
+
+/// Maximum dice a single `NdS` term may roll (matches the C default policy)
+pub const MAX_DICE_COUNT: u32 = 1000;
+
+/// Maximum sides a die may have (matches the C default policy)
+pub const MAX_SIDES: u32 = 1_000_000;
+
+/// Maximum extra dice a single die may explode into (matches the C default policy)
+pub const MAX_EXPLOSION_DEPTH: u32 = 10;
+
+/// Maximum times a single die may be rerolled (matches the C reroll safety limit)
+pub const MAX_REROLLS: u32 = 100;
+
+/// Maximum rolls a single repeated-roll helper may make (Rust only; the C
+/// policy has no equivalent)
+pub const MAX_SIMULATION_ITERATIONS: u32 = 1_000_000;
+
+/// Maximum user-defined function calls active at once on a thread
+///
+/// Functions that roll notation themselves can recurse; this bounds them.
+pub const MAX_CALL_DEPTH: u32 = 32;
+
+/// Bindings used when rolling without a [`RollContext`](crate::RollContext)
+static NO_VARIABLES: BTreeMap<String, i32> = BTreeMap::new();
+static NO_FUNCTIONS: Functions = BTreeMap::new();
+
+thread_local! {
+    static CALL_DEPTH: Cell<u32> = const { Cell::new(0) };
+}
+
+/// A single die rolled while evaluating an expression
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DieRoll {
+    pub sides: u32,
+    pub value: i32,
+    /// False when a keep/drop selection discarded this die
+    pub kept: bool,
+}
+
+/// Outcome of evaluating an expression
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RollResult {
+    pub total: i32,
+    /// Every die rolled, in evaluation order
+    pub dice: Vec<DieRoll>,
+}
+
+impl RollResult {
+    /// Values of every die rolled, including dropped ones
+    pub fn individual(&self) -> Vec<i32> {
+        self.dice.iter().map(|die| die.value).collect()
+    }
+
+    /// Values of the dice that count toward the total
+    pub fn kept(&self) -> Vec<i32> {
+        self.dice
+            .iter()
+            .filter(|die| die.kept)
+            .map(|die| die.value)
+            .collect()
+    }
+}
+
+impl DiceExpression {
+    /// Roll the expression, returning the total and every die rolled
+    pub fn roll<R: Rng + ?Sized>(&self, rng: &mut R) -> DiceResult<RollResult> {
+        self.roll_with_config(rng, &RollConfig::default())
+    }
+
+    /// [`roll`](Self::roll) under explicit limits
+    pub fn roll_with_config<R: Rng + ?Sized>(
+        &self,
+        rng: &mut R,
+        config: &RollConfig,
+    ) -> DiceResult<RollResult> {
+        self.roll_bound(rng, config, &NO_VARIABLES, &NO_FUNCTIONS)
+    }
+
+    /// Roll with variables and function calls resolved from the bindings
+    pub(crate) fn roll_bound<R: Rng + ?Sized>(
+        &self,
+        rng: &mut R,
+        config: &RollConfig,
+        variables: &BTreeMap<String, i32>,
+        functions: &Functions,
+    ) -> DiceResult<RollResult> {
+        let mut evaluator = Evaluator {
+            rng,
+            config,
+            variables,
+            functions,
+            dice: Vec::new(),
+            trace: None,
+        };
+        let total = evaluator.eval(self)?;
+        Ok(RollResult {
+            total,
+            dice: evaluator.dice,
+        })
+    }
+
+    /// [`roll_with_config`](Self::roll_with_config), also tracing every node
+    pub(crate) fn roll_traced<R: Rng + ?Sized>(
+        &self,
+        rng: &mut R,
+        config: &RollConfig,
+    ) -> DiceResult<(RollResult, EvalTrace)> {
+        let mut evaluator = Evaluator {
+            rng,
+            config,
+            variables: &NO_VARIABLES,
+            functions: &NO_FUNCTIONS,
+            dice: Vec::new(),
+            trace: Some(Tracer::default()),
+        };
+        let total = evaluator.eval(self)?;
+        let trace = evaluator.trace.take().map(Tracer::finish).unwrap_or_default();
+        let result = RollResult {
+            total,
+            dice: evaluator.dice,
+        };
+        Ok((result, trace))
+    }
+
+    /// Roll the expression and return only the total
+    pub fn evaluate<R: Rng + ?Sized>(&self, rng: &mut R) -> DiceResult<i32> {
+        self.roll(rng).map(|result| result.total)
+    }
+
+    /// [`evaluate`](Self::evaluate) `count` times, stopping at the first
+    /// error
+    pub fn roll_many<R: Rng + ?Sized>(&self, count: u32, rng: &mut R) -> DiceResult<Vec<i32>> {
+        (0..count).map(|_| self.evaluate(rng)).collect()
+    }
+
+    /// [`evaluate`](Self::evaluate) `count` times, keeping the totals and
+    /// the errors apart
+    ///
+    /// Every roll is made even after one fails, so the two lists together
+    /// hold `count` entries.
+    pub fn roll_many_ok<R: Rng + ?Sized>(
+        &self,
+        count: u32,
+        rng: &mut R,
+    ) -> (Vec<i32>, Vec<DiceError>) {
+        let mut totals = Vec::new();
+        let mut errors = Vec::new();
+        for _ in 0..count {
+            match self.evaluate(rng) {
+                Ok(total) => totals.push(total),
+                Err(err) => errors.push(err),
+            }
+        }
+        (totals, errors)
+    }
+
+    /// [`evaluate`](Self::evaluate) `count` times, writing each total to
+    /// `writer` on its own line as it's rolled
+    ///
+    /// Nothing is collected, so memory use doesn't grow with `count`. The
+    /// writer isn't buffered here; wrap files and stdout in a
+    /// [`BufWriter`](std::io::BufWriter). Stops at the first roll or write
+    /// that fails, with the totals before it already written.
+    pub fn roll_many_into<R: Rng + ?Sized>(
+        &self,
+        count: u32,
+        rng: &mut R,
+        mut writer: impl Write,
+    ) -> DiceResult<()> {
+        for _ in 0..count {
+            writeln!(writer, "{}", self.evaluate(rng)?)?;
+        }
+        Ok(())
+    }
+}
+
+struct Evaluator<'r, R: Rng + ?Sized> {
+    rng: &'r mut R,
+    config: &'r RollConfig,
+    variables: &'r BTreeMap<String, i32>,
+    functions: &'r Functions,
+    dice: Vec<DieRoll>,
+    /// Only set by [`DiceExpression::roll_traced`]
+    trace: Option<Tracer>,
+}
+
+/// One active user-defined function call on this thread
+struct CallGuard;
+
+impl CallGuard {
+    fn enter() -> DiceResult<CallGuard> {
+        CALL_DEPTH.with(|depth| {
+            if depth.get() >= MAX_CALL_DEPTH {
+                return Err(DiceError::InvalidNotation(format!(
+                    "function call depth exceeds {}",
+                    MAX_CALL_DEPTH
+                )));
+            }
+            depth.set(depth.get() + 1);
+            Ok(CallGuard)
+        })
+    }
+}
+
+impl Drop for CallGuard {
+    fn drop(&mut self) {
+        CALL_DEPTH.with(|depth| depth.set(depth.get() - 1));
+    }
+}
+
+impl<R: Rng + ?Sized> Evaluator<'_, R> {
+    fn eval(&mut self, expr: &DiceExpression) -> DiceResult<i32> {
+        if self.trace.is_none() {
+            return self.eval_node(expr);
+        }
+        self.enter_trace();
+        if expr.is_pool() {
+            let pool = self.pool_node(expr)?;
+            let total = self.sum(&pool)?;
+            self.exit_trace(expr, total, Some(self.values(&pool)));
+            return Ok(total);
+        }
+        let value = self.eval_node(expr)?;
+        self.exit_trace(expr, value, None);
+        Ok(value)
+    }
+
+    fn eval_node(&mut self, expr: &DiceExpression) -> DiceResult<i32> {
+        match expr {
+            DiceExpression::Constant(value) => Ok(*value),
+            DiceExpression::Variable(name) => self
+                .variables
+                .get(name)
+                .copied()
+                .ok_or_else(|| DiceError::UnboundVariable(name.clone())),
+            DiceExpression::Call(name, args) => {
+                let function = self.functions.get(name).cloned().ok_or_else(|| {
+                    DiceError::InvalidNotation(format!("undefined function: {}", name))
+                })?;
+                let args = args
+                    .iter()
+                    .map(|arg| self.eval(arg))
+                    .collect::<DiceResult<Vec<i32>>>()?;
+                let _guard = CallGuard::enter()?;
+                function(&args, &mut self.rng)
+            }
+            DiceExpression::Conditional {
+                condition,
+                predicate,
+                then_expr,
+                else_expr,
+            } => {
+                if predicate.matches(self.eval(condition)?) {
+                    self.eval(then_expr)
+                } else {
+                    self.eval(else_expr)
+                }
+            }
+            DiceExpression::Negate(inner) => {
+                self.eval(inner)?.checked_neg().ok_or(DiceError::Overflow)
+            }
+            DiceExpression::AbsoluteValue(inner) => {
+                self.eval(inner)?.checked_abs().ok_or(DiceError::Overflow)
+            }
+            DiceExpression::Add(left, right) => {
+                let (l, r) = (self.eval(left)?, self.eval(right)?);
+                l.checked_add(r).ok_or(DiceError::Overflow)
+            }
+            DiceExpression::Sub(left, right) => {
+                let (l, r) = (self.eval(left)?, self.eval(right)?);
+                l.checked_sub(r).ok_or(DiceError::Overflow)
+            }
+            DiceExpression::Mul(left, right) => {
+                let (l, r) = (self.eval(left)?, self.eval(right)?);
+                l.checked_mul(r).ok_or(DiceError::Overflow)
+            }
+            DiceExpression::FloorClamp(inner, minimum) => Ok(self.eval(inner)?.max(*minimum)),
+            DiceExpression::CeilClamp(inner, maximum) => Ok(self.eval(inner)?.min(*maximum)),
+            DiceExpression::FloorDiv(inner, divisor) => floor_div(self.eval(inner)?, *divisor),
+            DiceExpression::CeilDiv(inner, divisor) => ceil_div(self.eval(inner)?, *divisor),
+            DiceExpression::Min(left, right) => {
+                let (l, r) = (self.eval(left)?, self.eval(right)?);
+                Ok(l.min(r))
+            }
+            DiceExpression::Max(left, right) => {
+                let (l, r) = (self.eval(left)?, self.eval(right)?);
+                Ok(l.max(r))
+            }
+            DiceExpression::Div(left, right) => {
+                let (l, r) = (self.eval(left)?, self.eval(right)?);
+                if r == 0 {
+                    return Err(DiceError::InvalidNotation("division by zero".to_string()));
+                }
+                l.checked_div(r).ok_or(DiceError::Overflow)
+            }
+            DiceExpression::CountSuccesses(inner, target) => {
+                let pool = self.pool(inner)?;
+                let successes = pool
+                    .iter()
+                    .filter(|&&index| self.dice[index].value >= *target as i32)
+                    .count();
+                Ok(successes as i32)
+            }
+            DiceExpression::CountSuccessesWithBotch(inner, target, botch) => {
+                let pool = self.pool(inner)?;
+                let net = pool.iter().fold(0i32, |net, &index| {
+                    let value = self.dice[index].value;
+                    net + i32::from(value >= *target as i32) - i32::from(value <= *botch as i32)
+                });
+                Ok(net)
+            }
+            _ => {
+                let pool = self.pool_node(expr)?;
+                self.sum(&pool)
+            }
+        }
+    }
+
+    /// Evaluate a pool node, returning indices of the dice it keeps
+    fn pool(&mut self, expr: &DiceExpression) -> DiceResult<Vec<usize>> {
+        if self.trace.is_none() {
+            return self.pool_node(expr);
+        }
+        self.enter_trace();
+        let pool = self.pool_node(expr)?;
+        // A pool whose sum overflows can still be counted, so don't fail the trace
+        let total = pool
+            .iter()
+            .fold(0i32, |sum, &index| sum.saturating_add(self.dice[index].value));
+        self.exit_trace(expr, total, Some(self.values(&pool)));
+        Ok(pool)
+    }
+
+    fn pool_node(&mut self, expr: &DiceExpression) -> DiceResult<Vec<usize>> {
+        match expr {
+            DiceExpression::Dice(count, sides) => self.roll_dice(*count, *sides),
+            DiceExpression::DynamicDice(count, sides) => {
+                let count = self.eval(count)?;
+                if count <= 0 {
+                    return Err(DiceError::InvalidCount(count));
+                }
+                self.roll_dice(count as u32, *sides)
+            }
+            DiceExpression::Explode(inner, threshold) => {
+                let pool = self.pool(inner)?;
+                Ok(self.explode(pool, |value| value >= *threshold as i32))
+            }
+            DiceExpression::ExplodeOn(inner, faces) => {
+                let pool = self.pool(inner)?;
+                for &index in &pool {
+                    check_exploding_faces(faces, self.dice[index].sides)?;
+                }
+                Ok(self.explode(pool, |value| faces.contains(&(value as u32))))
+            }
+            DiceExpression::KeepHighest(inner, n) => self.select(inner, *n as usize, true, true),
+            DiceExpression::KeepLowest(inner, n) => self.select(inner, *n as usize, false, true),
+            DiceExpression::DropHighest(inner, n) => self.select(inner, *n as usize, true, false),
+            DiceExpression::DropLowest(inner, n) => self.select(inner, *n as usize, false, false),
+            _ => Err(DiceError::InvalidNotation(
+                "selection requires a dice pool".to_string(),
+            )),
+        }
+    }
+
+    fn sum(&self, pool: &[usize]) -> DiceResult<i32> {
+        pool.iter()
+            .try_fold(0i32, |sum, &index| sum.checked_add(self.dice[index].value))
+            .ok_or(DiceError::Overflow)
+    }
+
+    fn values(&self, pool: &[usize]) -> Vec<i32> {
+        pool.iter().map(|&index| self.dice[index].value).collect()
+    }
+
+    fn enter_trace(&mut self) {
+        if let Some(tracer) = &mut self.trace {
+            tracer.enter();
+        }
+    }
+
+    fn exit_trace(&mut self, expr: &DiceExpression, output: i32, inputs: Option<Vec<i32>>) {
+        if let Some(tracer) = &mut self.trace {
+            tracer.exit(expr, output, inputs);
+        }
+    }
+
+    fn roll_dice(&mut self, count: u32, sides: u32) -> DiceResult<Vec<usize>> {
+        if count == 0 || count > self.config.max_dice_count {
+            return Err(DiceError::InvalidCount(count as i32));
+        }
+        if sides == 0 || sides > self.config.max_sides {
+            return Err(DiceError::InvalidSides(sides as i32));
+        }
+
+        let start = self.dice.len();
+        for _ in 0..count {
+            let value = self.rng.gen_range(1..=sides) as i32;
+            self.dice.push(DieRoll {
+                sides,
+                value,
+                kept: true,
+            });
+        }
+        Ok((start..self.dice.len()).collect())
+    }
+
+    /// Add a die to the pool for every die showing a face that `explodes`,
+    /// chaining up to `max_explosion_depth` times per original die
+    fn explode(&mut self, pool: Vec<usize>, explodes: impl Fn(i32) -> bool) -> Vec<usize> {
+        let mut exploded = Vec::with_capacity(pool.len());
+        for index in pool {
+            exploded.push(index);
+            let DieRoll { sides, mut value, .. } = self.dice[index];
+            let mut depth = 0;
+            while explodes(value) && depth < self.config.max_explosion_depth {
+                value = self.rng.gen_range(1..=sides) as i32;
+                self.dice.push(DieRoll {
+                    sides,
+                    value,
+                    kept: true,
+                });
+                exploded.push(self.dice.len() - 1);
+                depth += 1;
+            }
+        }
+        exploded
+    }
+
+    /// Keep or drop the `n` highest/lowest dice of a pool
+    ///
+    /// Mirrors the C filter: keeping more dice than were rolled keeps them
+    /// all, dropping at least as many as were rolled keeps none.
+    fn select(
+        &mut self,
+        inner: &DiceExpression,
+        n: usize,
+        highest: bool,
+        keep: bool,
+    ) -> DiceResult<Vec<usize>> {
+        let mut pool = self.pool(inner)?;
+        // Stable sort so ties resolve to the earliest die
+        pool.sort_by_key(|&index| self.dice[index].value);
+        if highest {
+            pool.reverse();
+        }
+
+        let n = n.min(pool.len());
+        let (selected, discarded) = if keep {
+            let (selected, discarded) = pool.split_at(n);
+            (selected.to_vec(), discarded.to_vec())
+        } else {
+            let (discarded, selected) = pool.split_at(n);
+            (selected.to_vec(), discarded.to_vec())
+        };
+
+        for index in discarded {
+            self.dice[index].kept = false;
+        }
+        let mut selected = selected;
+        selected.sort_unstable();
+        Ok(selected)
+    }
+}
+
+/// Distinct faces of a `sides`-sided die in `faces`
+pub(crate) fn exploding_faces(faces: &[u32], sides: u32) -> u32 {
+    let mut faces: Vec<u32> =
+        faces.iter().copied().filter(|face| (1..=sides).contains(face)).collect();
+    faces.sort_unstable();
+    faces.dedup();
+    faces.len() as u32
+}
+
+/// Check that `faces` are faces of a `sides`-sided die and that at least
+/// one face doesn't explode, so a chain can end
+pub(crate) fn check_exploding_faces(faces: &[u32], sides: u32) -> DiceResult<()> {
+    if let Some(&face) = faces.iter().find(|&&face| face == 0 || face > sides) {
+        return Err(DiceError::InvalidSides(face as i32));
+    }
+    if exploding_faces(faces, sides) == sides {
+        return Err(DiceError::InvalidSides(sides as i32));
+    }
+    Ok(())
+}
+
+/// `a / b` rounded toward negative infinity
+pub(crate) fn floor_div(a: i32, b: i32) -> DiceResult<i32> {
+    if b == 0 {
+        return Err(DiceError::InvalidNotation("division by zero".to_string()));
+    }
+    let quotient = a.checked_div(b).ok_or(DiceError::Overflow)?;
+    // Truncation rounded a negative quotient up
+    if a % b != 0 && (a < 0) != (b < 0) {
+        return Ok(quotient - 1);
+    }
+    Ok(quotient)
+}
+
+/// `a / b` rounded toward positive infinity
+pub(crate) fn ceil_div(a: i32, b: i32) -> DiceResult<i32> {
+    if b == 0 {
+        return Err(DiceError::InvalidNotation("division by zero".to_string()));
+    }
+    let quotient = a.checked_div(b).ok_or(DiceError::Overflow)?;
+    // Truncation rounded a positive quotient down
+    if a % b != 0 && (a < 0) == (b < 0) {
+        return Ok(quotient + 1);
+    }
+    Ok(quotient)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::DiceRng;
+
+    #[test]
+    fn test_roll_records_dice() {
+        let mut rng = DiceRng::new(12345);
+        let result = DiceExpression::parse("3d6+2").unwrap().roll(&mut rng).unwrap();
+        assert_eq!(result.dice.len(), 3);
+        assert_eq!(result.total, result.individual().iter().sum::<i32>() + 2);
+        assert!(result.individual().iter().all(|v| (1..=6).contains(v)));
+    }
+
+    #[test]
+    fn test_keep_highest() {
+        let mut rng = DiceRng::new(7);
+        let expr = DiceExpression::parse("4d6k3").unwrap();
+        for _ in 0..100 {
+            let result = expr.roll(&mut rng).unwrap();
+            let mut values = result.individual();
+            values.sort_unstable();
+            assert_eq!(result.kept().len(), 3);
+            assert_eq!(result.total, values[1..].iter().sum::<i32>());
+        }
+    }
+
+    #[test]
+    fn test_drop_all_and_keep_all() {
+        let mut rng = DiceRng::new(7);
+        let dropped = DiceExpression::parse("2d6dl5").unwrap().roll(&mut rng).unwrap();
+        assert_eq!(dropped.total, 0);
+        assert!(dropped.kept().is_empty());
+
+        let kept = DiceExpression::parse("2d6k5").unwrap().roll(&mut rng).unwrap();
+        assert_eq!(kept.kept().len(), 2);
+    }
+
+    #[test]
+    fn test_count_successes() {
+        let mut rng = DiceRng::new(3);
+        let expr = DiceExpression::parse("8d6>4").unwrap();
+        for _ in 0..100 {
+            let result = expr.roll(&mut rng).unwrap();
+            let expected = result.individual().iter().filter(|&&v| v >= 4).count();
+            assert_eq!(result.total, expected as i32);
+        }
+        let always = DiceExpression::parse("5d6>1").unwrap();
+        assert_eq!(always.evaluate(&mut rng), Ok(5));
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let mut rng = DiceRng::new(1);
+        let eval = |notation: &str, rng: &mut DiceRng| {
+            DiceExpression::parse(notation).unwrap().evaluate(rng)
+        };
+        assert_eq!(eval("2*3", &mut rng), Ok(6));
+        assert_eq!(eval("(2+3)*4", &mut rng), Ok(20));
+        assert_eq!(eval("10/3", &mut rng), Ok(3));
+        assert!(eval("10/0", &mut rng).is_err());
+        assert_eq!(eval("2147483647+1", &mut rng), Err(DiceError::Overflow));
+    }
+
+    #[test]
+    fn test_precedence_totals() {
+        let eval = |notation: &str| {
+            let mut rng = DiceRng::new(77);
+            DiceExpression::parse(notation).unwrap().evaluate(&mut rng).unwrap()
+        };
+        assert_eq!(eval("3d6+2*4"), eval("3d6+8"));
+        assert_eq!(eval("2*3d6+4"), eval("3d6*2+4"));
+        assert_eq!(eval("(3d6+2)*4"), (eval("3d6") + 2) * 4);
+        let mut rng = DiceRng::new(77);
+        let result = DiceExpression::parse("3d6-1d4*2").unwrap().roll(&mut rng).unwrap();
+        let values = result.individual();
+        assert_eq!(result.total, values[..3].iter().sum::<i32>() - values[3] * 2);
+        assert_eq!(eval("2+3*4"), 14);
+        assert_eq!(eval("(2+3)*4"), 20);
+        assert_eq!(eval("-2*3+10"), 4);
+    }
+
+    #[test]
+    fn test_chained_modifiers() {
+        let mut rng = DiceRng::new(118);
+        let expr = DiceExpression::parse("1d20+5-2+1d4-1").unwrap();
+        assert_eq!((expr.min_result(), expr.max_result()), (Ok(4), Ok(26)));
+        for _ in 0..500 {
+            let result = expr.roll(&mut rng).unwrap();
+            let values = result.individual();
+            assert_eq!(result.total, values[0] + 5 - 2 + values[1] - 1);
+            assert!((4..=26).contains(&result.total));
+        }
+    }
+
+    #[test]
+    fn test_explode() {
+        let mut rng = DiceRng::new(11);
+        let expr = DiceExpression::parse("3d6!").unwrap();
+        let mut exploded = false;
+        for _ in 0..200 {
+            let result = expr.roll(&mut rng).unwrap();
+            let values = result.individual();
+            assert!(values.len() >= 3);
+            // Every die after the first three follows a six
+            let sixes = values.iter().filter(|&&v| v == 6).count();
+            assert_eq!(values.len() - 3, sixes);
+            assert_eq!(result.total, values.iter().sum::<i32>());
+            exploded |= values.len() > 3;
+        }
+        assert!(exploded);
+    }
+
+    #[test]
+    fn test_conditional() {
+        let mut rng = DiceRng::new(128);
+        let expr = DiceExpression::parse("(1d20>=15)?(2d6+5):1d4").unwrap();
+        for _ in 0..200 {
+            let result = expr.roll(&mut rng).unwrap();
+            // Only the taken branch rolls
+            if result.dice[0].value >= 15 {
+                assert_eq!(result.dice.len(), 3);
+                assert_eq!(result.total, result.individual()[1..].iter().sum::<i32>() + 5);
+            } else {
+                assert_eq!(result.dice.len(), 2);
+                assert_eq!(result.total, result.dice[1].value);
+            }
+        }
+        let constant = |notation: &str| {
+            DiceExpression::parse(notation)
+                .unwrap()
+                .evaluate(&mut DiceRng::new(1))
+        };
+        assert_eq!(constant("(3)?1:2"), Ok(1));
+        assert_eq!(constant("(0)?1:2"), Ok(2));
+        assert_eq!(constant("(2*3==6)?10:20"), Ok(10));
+        assert_eq!(constant("(1d6>6)?1:2"), Ok(2));
+        assert!(constant("(1d6<7)?1:(1/0)").is_ok());
+        assert!(constant("(1d6<7)?1:1/0").is_err());
+    }
+
+    #[test]
+    fn test_min_max() {
+        let mut rng = DiceRng::new(153);
+        let min = DiceExpression::parse("min(2d6, 1d8)").unwrap();
+        let max = DiceExpression::parse("max(2d6, 1d8)").unwrap();
+        for _ in 0..200 {
+            // Both sides roll, left first
+            let result = min.roll(&mut rng).unwrap();
+            let dice = result.individual();
+            assert_eq!(dice.len(), 3);
+            assert_eq!(result.dice[2].sides, 8);
+            assert_eq!(result.total, (dice[0] + dice[1]).min(dice[2]));
+
+            let result = max.roll(&mut rng).unwrap();
+            let dice = result.individual();
+            assert_eq!(result.total, (dice[0] + dice[1]).max(dice[2]));
+        }
+        let constant = |notation: &str| {
+            DiceExpression::parse(notation)
+                .unwrap()
+                .evaluate(&mut DiceRng::new(1))
+        };
+        assert_eq!(constant("min(3, -2)"), Ok(-2));
+        assert_eq!(constant("max(3, -2)*2"), Ok(6));
+        assert!(constant("min(1, 1/0)").is_err());
+    }
+
+    #[test]
+    fn test_floor_and_ceil_division() {
+        let mut rng = DiceRng::new(154);
+        let half = DiceExpression::parse("2d6/2").unwrap();
+        assert!(matches!(half, DiceExpression::FloorDiv(_, 2)));
+        let rounded_up = DiceExpression::parse("2d6").unwrap().ceil_div(2);
+        for _ in 0..200 {
+            let result = half.roll(&mut rng).unwrap();
+            assert_eq!(result.total, result.individual().iter().sum::<i32>() / 2);
+            let result = rounded_up.roll(&mut rng).unwrap();
+            assert_eq!(result.total, (result.individual().iter().sum::<i32>() + 1) / 2);
+        }
+
+        let cases = [(7, 2, 3, 4), (-7, 2, -4, -3), (7, -2, -4, -3), (-6, 2, -3, -3)];
+        for (a, b, floor, ceil) in cases {
+            assert_eq!(floor_div(a, b), Ok(floor), "{}/{}", a, b);
+            assert_eq!(ceil_div(a, b), Ok(ceil), "{}/{}", a, b);
+        }
+        assert_eq!(floor_div(i32::MIN, -1), Err(DiceError::Overflow));
+
+        // A rolled divisor still truncates
+        let constant = |notation: &str| {
+            DiceExpression::parse(notation)
+                .unwrap()
+                .evaluate(&mut DiceRng::new(1))
+        };
+        assert_eq!(constant("-7/2"), Ok(-4));
+        assert_eq!(constant("-7/(1d1+1)"), Ok(-3));
+        let by_zero = DiceExpression::Constant(5).floor_div(0).evaluate(&mut rng);
+        assert_eq!(by_zero, Err(DiceError::InvalidNotation("division by zero".to_string())));
+    }
+
+    #[test]
+    fn test_explosion_depth_limit() {
+        let mut rng = DiceRng::new(1);
+        // A d1 always shows its threshold
+        let result = DiceExpression::parse("2d1!").unwrap().roll(&mut rng).unwrap();
+        assert_eq!(result.dice.len(), 2 * (1 + MAX_EXPLOSION_DEPTH as usize));
+        assert_eq!(result.total, 22);
+    }
+
+    #[test]
+    fn test_policy_limits() {
+        let mut rng = DiceRng::new(1);
+        assert_eq!(
+            DiceExpression::Dice(1001, 6).roll(&mut rng).unwrap_err(),
+            DiceError::InvalidCount(1001)
+        );
+        assert_eq!(
+            DiceExpression::Dice(1, 0).roll(&mut rng).unwrap_err(),
+            DiceError::InvalidSides(0)
+        );
+
+        let config = RollConfig {
+            max_dice_count: 10,
+            max_sides: 20,
+            max_explosion_depth: 2,
+            ..RollConfig::default()
+        };
+        let roll = |notation: &str, rng: &mut DiceRng| {
+            DiceExpression::parse(notation)
+                .unwrap()
+                .roll_with_config(rng, &config)
+        };
+        assert_eq!(roll("11d6", &mut rng), Err(DiceError::InvalidCount(11)));
+        assert_eq!(roll("1d100", &mut rng), Err(DiceError::InvalidSides(100)));
+        assert_eq!(roll("2d1!", &mut rng).unwrap().dice.len(), 6);
+    }
+
+    #[test]
+    fn test_roll_many() {
+        let expr = DiceExpression::parse("2d6").unwrap();
+        let mut rng = DiceRng::new(195);
+        let totals = expr.roll_many(500, &mut rng).unwrap();
+        assert_eq!(totals.len(), 500);
+        assert!(totals.iter().all(|total| (2..=12).contains(total)));
+        // The same seed rolls the same totals one at a time
+        let mut rng = DiceRng::new(195);
+        let single: Vec<i32> = (0..500).map(|_| expr.evaluate(&mut rng).unwrap()).collect();
+        assert_eq!(totals, single);
+        assert_eq!(expr.roll_many(0, &mut rng), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn test_roll_many_errors() {
+        // Divides by zero on every 1
+        let expr = DiceExpression::parse("6/(1d2-1)").unwrap();
+        let mut rng = DiceRng::new(195);
+        let division_by_zero = DiceError::InvalidNotation("division by zero".to_string());
+        assert_eq!(expr.roll_many(100, &mut rng), Err(division_by_zero.clone()));
+
+        let (totals, errors) = expr.roll_many_ok(200, &mut rng);
+        assert_eq!(totals.len() + errors.len(), 200);
+        assert!(totals.iter().all(|&total| total == 6));
+        assert!(errors.iter().all(|err| *err == division_by_zero));
+        assert!((60..140).contains(&errors.len()), "{}", errors.len());
+    }
+
+    #[test]
+    fn test_roll_many_into() {
+        let expr = DiceExpression::parse("1d20+3").unwrap();
+        let mut out = Vec::new();
+        expr.roll_many_into(50, &mut DiceRng::new(195), &mut out).unwrap();
+        let totals = expr.roll_many(50, &mut DiceRng::new(195)).unwrap();
+        let written: Vec<i32> =
+            String::from_utf8(out).unwrap().lines().map(|line| line.parse().unwrap()).collect();
+        assert_eq!(written, totals);
+
+        /// Counts lines without keeping them
+        struct LineCounter(usize);
+        impl Write for LineCounter {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0 += buf.iter().filter(|&&byte| byte == b'\n').count();
+                Ok(buf.len())
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+        let mut counter = LineCounter(0);
+        let die = DiceExpression::parse("1d6").unwrap();
+        die.roll_many_into(1_000_000, &mut DiceRng::new(195), &mut counter).unwrap();
+        assert_eq!(counter.0, 1_000_000);
+
+        let mut full = [0u8; 8];
+        let result = expr.roll_many_into(50, &mut DiceRng::new(195), &mut full[..]);
+        assert!(matches!(result, Err(DiceError::Io(_))));
+    }
+}
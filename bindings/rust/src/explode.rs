@@ -0,0 +1,126 @@
+//! Explosions on chosen faces
+//!
+//! Some games reroll and add on faces other than the highest: "on a 1 or
+//! 6, roll again". These build a `1d6!{1,6}` pool, so chains stop at
+//! `RollConfig::max_explosion_depth` like any other explosion.
+
+use crate::context::DiceContext;
+use crate::expression::DiceExpression;
+use crate::{Dice, DiceError, DiceResult};
+
+impl DiceContext {
+    /// Roll a die, rolling again and adding whenever it shows one of
+    /// `explode_values`
+    ///
+    /// Every value must be a face of the die, and at least one face must
+    /// not explode, or the roll fails with [`DiceError::InvalidSides`].
+    ///
+    /// # Arguments
+    ///
+    /// * `sides` - Number of sides on the die
+    /// * `explode_values` - Faces that trigger another roll; empty rolls
+    ///   the die once
+    pub fn roll_with_explode_on_specific(
+        &mut self,
+        sides: i32,
+        explode_values: &[i32],
+    ) -> DiceResult<i32> {
+        if sides <= 0 {
+            return Err(DiceError::InvalidSides(sides));
+        }
+        let faces = explode_values
+            .iter()
+            .map(|&value| u32::try_from(value).map_err(|_| DiceError::InvalidSides(value)))
+            .collect::<DiceResult<Vec<u32>>>()?;
+        let die = DiceExpression::Dice(1, sides as u32);
+        let expr = if faces.is_empty() {
+            die
+        } else {
+            DiceExpression::ExplodeOn(Box::new(die), faces)
+        };
+        let config = *self.config();
+        Ok(expr.roll_with_config(self.rng(), &config)?.total)
+    }
+}
+
+impl Dice {
+    /// Roll a die, rolling again and adding whenever it shows one of
+    /// `explode_values`
+    ///
+    /// # Arguments
+    ///
+    /// * `sides` - Number of sides on the die
+    /// * `explode_values` - Faces that trigger another roll; empty rolls
+    ///   the die once
+    pub fn roll_with_explode_on_specific(sides: i32, explode_values: &[i32]) -> DiceResult<i32> {
+        DiceContext::new().roll_with_explode_on_specific(sides, explode_values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RollConfig;
+
+    #[test]
+    fn test_explodes_past_highest_face() {
+        let mut ctx = DiceContext::with_seed(180);
+        let rolls: Vec<i32> = (0..600)
+            .map(|_| ctx.roll_with_explode_on_specific(6, &[6]).unwrap())
+            .collect();
+        assert!(rolls.iter().any(|&roll| roll > 6));
+        // A chain only stops on a face that doesn't explode
+        assert!(rolls.iter().all(|&roll| roll % 6 != 0));
+    }
+
+    #[test]
+    fn test_explodes_on_listed_faces() {
+        let mut ctx = DiceContext::with_seed(180);
+        for _ in 0..200 {
+            let roll = ctx.roll_with_explode_on_specific(6, &[1, 6]).unwrap();
+            assert!(roll >= 2, "{}", roll);
+        }
+        for _ in 0..50 {
+            assert!((1..=6).contains(&ctx.roll_with_explode_on_specific(6, &[]).unwrap()));
+        }
+        let total = DiceExpression::parse("3d6!{1,6}").unwrap().roll(ctx.rng()).unwrap();
+        assert!(total.total >= 6);
+    }
+
+    #[test]
+    fn test_depth_limit() {
+        let config = RollConfig {
+            max_explosion_depth: 2,
+            ..RollConfig::default()
+        };
+        let mut ctx = DiceContext::with_seed(3).with_config(config);
+        for _ in 0..200 {
+            // At most two extra dice
+            assert!(ctx.roll_with_explode_on_specific(2, &[2]).unwrap() <= 6);
+        }
+    }
+
+    #[test]
+    fn test_invalid_faces() {
+        let mut ctx = DiceContext::with_seed(1);
+        assert_eq!(ctx.roll_with_explode_on_specific(6, &[7]), Err(DiceError::InvalidSides(7)));
+        assert_eq!(ctx.roll_with_explode_on_specific(6, &[0]), Err(DiceError::InvalidSides(0)));
+        assert_eq!(ctx.roll_with_explode_on_specific(6, &[-1]), Err(DiceError::InvalidSides(-1)));
+        assert_eq!(
+            ctx.roll_with_explode_on_specific(2, &[1, 2, 2]),
+            Err(DiceError::InvalidSides(2))
+        );
+        assert_eq!(ctx.roll_with_explode_on_specific(0, &[]), Err(DiceError::InvalidSides(0)));
+        assert_eq!(
+            DiceExpression::parse("1d4!{1,2,3,4}").unwrap().max_result(),
+            Err(DiceError::InvalidSides(4))
+        );
+    }
+
+    #[test]
+    fn test_notation_round_trip() {
+        let expr = DiceExpression::parse("2d8!{1,8}+3").unwrap();
+        assert_eq!(expr.to_notation(), "2d8!{1,8}+3");
+        assert_eq!(DiceExpression::parse(&expr.to_notation()).unwrap(), expr);
+    }
+}
@@ -0,0 +1,252 @@
+//! Dice expression AST
+//!
+//! A pure-Rust mirror of the C library's expression tree. Expressions are
+//! built by [`DiceExpression::parse`] and evaluated against any `rand::Rng`,
+//! so results are reproducible when a seeded generator is used.
+
+use crate::parser;
+use crate::DiceResult;
+
+/// Parsed dice expression
+///
+/// With the `serde` feature an expression serializes as its canonical
+/// notation string and deserializes by parsing one.
+#[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "String", into = "String")
+)]
+pub enum DiceExpression {
+    /// Integer literal
+    Constant(i32),
+    /// Named value bound at evaluation time (`MOD`)
+    Variable(String),
+    /// `NdS` - roll N dice with S sides and sum them
+    Dice(u32, u32),
+    /// Dice whose count is only known at evaluation time (`Nd6`)
+    DynamicDice(Box<DiceExpression>, u32),
+    /// Roll another die whenever a die shows at least the threshold
+    /// (`3d6!` explodes on 6, `3d6!>5` on 5 or 6)
+    Explode(Box<DiceExpression>, u32),
+    /// Roll another die whenever a die shows one of the listed faces
+    /// (`1d6!{1,6}` explodes on 1 or 6)
+    ExplodeOn(Box<DiceExpression>, Vec<u32>),
+    /// Keep the highest N dice of a pool (`4d6k3`, `4d6kh3`)
+    KeepHighest(Box<DiceExpression>, u32),
+    /// Keep the lowest N dice of a pool (`2d20kl1`)
+    KeepLowest(Box<DiceExpression>, u32),
+    /// Drop the highest N dice of a pool (`5d6dh2`)
+    DropHighest(Box<DiceExpression>, u32),
+    /// Drop the lowest N dice of a pool (`4d6l1`, `4d6dl1`)
+    DropLowest(Box<DiceExpression>, u32),
+    /// Count the dice of a pool showing at least the target (`8d6>4`)
+    CountSuccesses(Box<DiceExpression>, u32),
+    /// Count the dice showing at least the target, minus the dice showing
+    /// at most the botch value (`5d10>7b1`)
+    CountSuccessesWithBotch(Box<DiceExpression>, u32, u32),
+    /// Raise a result below the minimum to the minimum (`1d6min3`)
+    FloorClamp(Box<DiceExpression>, i32),
+    /// Lower a result above the maximum to the maximum (`1d6max5`)
+    CeilClamp(Box<DiceExpression>, i32),
+    /// User-defined function call (`DAMAGE(6, 4)`), resolved by a
+    /// [`RollContext`](crate::RollContext)
+    Call(String, Vec<DiceExpression>),
+    /// Evaluate `then_expr` if `condition` satisfies `predicate`, otherwise
+    /// `else_expr` (`(1d20>=15)?(2d6+5):1d4`)
+    Conditional {
+        condition: Box<DiceExpression>,
+        predicate: DicePredicate,
+        then_expr: Box<DiceExpression>,
+        else_expr: Box<DiceExpression>,
+    },
+    /// Lower of two results (`min(2d6, 1d8)`)
+    Min(Box<DiceExpression>, Box<DiceExpression>),
+    /// Higher of two results (`max(2d6, 1d8)`)
+    Max(Box<DiceExpression>, Box<DiceExpression>),
+    /// Unary minus (`-2d6`)
+    Negate(Box<DiceExpression>),
+    /// Absolute value (`|2d6-7|`)
+    AbsoluteValue(Box<DiceExpression>),
+    Add(Box<DiceExpression>, Box<DiceExpression>),
+    Sub(Box<DiceExpression>, Box<DiceExpression>),
+    Mul(Box<DiceExpression>, Box<DiceExpression>),
+    /// Integer division, truncating toward zero (`10/1d4`)
+    Div(Box<DiceExpression>, Box<DiceExpression>),
+    /// Division by a constant, rounding down (`2d6/2`)
+    FloorDiv(Box<DiceExpression>, i32),
+    /// Division by a constant, rounding up
+    CeilDiv(Box<DiceExpression>, i32),
+}
+
+/// Comparison a [`DiceExpression::Conditional`] applies to its condition
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DicePredicate {
+    /// `>=`
+    AtLeast(i32),
+    /// `>`
+    GreaterThan(i32),
+    /// `<=`
+    AtMost(i32),
+    /// `<`
+    LessThan(i32),
+    /// `==`
+    Equal(i32),
+    /// `!=`; a bare condition (`(1d6)?...`) is `!=0`
+    NotEqual(i32),
+}
+
+impl DicePredicate {
+    /// Whether `value` satisfies the predicate
+    pub fn matches(&self, value: i32) -> bool {
+        match *self {
+            DicePredicate::AtLeast(n) => value >= n,
+            DicePredicate::GreaterThan(n) => value > n,
+            DicePredicate::AtMost(n) => value <= n,
+            DicePredicate::LessThan(n) => value < n,
+            DicePredicate::Equal(n) => value == n,
+            DicePredicate::NotEqual(n) => value != n,
+        }
+    }
+
+    /// Comparison operator as written in notation
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            DicePredicate::AtLeast(_) => ">=",
+            DicePredicate::GreaterThan(_) => ">",
+            DicePredicate::AtMost(_) => "<=",
+            DicePredicate::LessThan(_) => "<",
+            DicePredicate::Equal(_) => "==",
+            DicePredicate::NotEqual(_) => "!=",
+        }
+    }
+
+    /// Value compared against
+    pub fn operand(&self) -> i32 {
+        match *self {
+            DicePredicate::AtLeast(n)
+            | DicePredicate::GreaterThan(n)
+            | DicePredicate::AtMost(n)
+            | DicePredicate::LessThan(n)
+            | DicePredicate::Equal(n)
+            | DicePredicate::NotEqual(n) => n,
+        }
+    }
+}
+
+impl DiceExpression {
+    /// Parse dice notation into an expression tree
+    ///
+    /// # Arguments
+    ///
+    /// * `notation` - Dice notation like "3d6", "4d6k3+2", "(1d6+2)*3"
+    ///
+    /// The tree is [simplified](Self::simplify) before it is returned.
+    pub fn parse(notation: &str) -> DiceResult<DiceExpression> {
+        parser::parse(notation).map(|expr| expr.simplify())
+    }
+
+    /// This expression divided by `divisor`, rounding down
+    ///
+    /// Half damage rounded down is `damage.floor_div(2)`. A zero divisor
+    /// fails when the expression is evaluated.
+    pub fn floor_div(self, divisor: i32) -> DiceExpression {
+        DiceExpression::FloorDiv(Box::new(self), divisor)
+    }
+
+    /// This expression divided by `divisor`, rounding up
+    pub fn ceil_div(self, divisor: i32) -> DiceExpression {
+        DiceExpression::CeilDiv(Box::new(self), divisor)
+    }
+
+    /// Direct subexpressions, in evaluation order
+    pub(crate) fn children(&self) -> Vec<&DiceExpression> {
+        use DiceExpression::*;
+
+        match self {
+            Constant(_) | Variable(_) | Dice(..) => Vec::new(),
+            DynamicDice(inner, _)
+            | Explode(inner, _)
+            | ExplodeOn(inner, _)
+            | KeepHighest(inner, _)
+            | KeepLowest(inner, _)
+            | DropHighest(inner, _)
+            | DropLowest(inner, _)
+            | CountSuccesses(inner, _)
+            | CountSuccessesWithBotch(inner, ..)
+            | FloorClamp(inner, _)
+            | CeilClamp(inner, _)
+            | FloorDiv(inner, _)
+            | CeilDiv(inner, _)
+            | Negate(inner)
+            | AbsoluteValue(inner) => vec![inner],
+            Call(_, args) => args.iter().collect(),
+            Conditional {
+                condition,
+                then_expr,
+                else_expr,
+                ..
+            } => vec![condition, then_expr, else_expr],
+            Add(left, right)
+            | Sub(left, right)
+            | Mul(left, right)
+            | Div(left, right)
+            | Min(left, right)
+            | Max(left, right) => vec![left, right],
+        }
+    }
+
+    /// Mutable [`children`](Self::children), in the same order
+    pub(crate) fn children_mut(&mut self) -> Vec<&mut DiceExpression> {
+        use DiceExpression::*;
+
+        match self {
+            Constant(_) | Variable(_) | Dice(..) => Vec::new(),
+            DynamicDice(inner, _)
+            | Explode(inner, _)
+            | ExplodeOn(inner, _)
+            | KeepHighest(inner, _)
+            | KeepLowest(inner, _)
+            | DropHighest(inner, _)
+            | DropLowest(inner, _)
+            | CountSuccesses(inner, _)
+            | CountSuccessesWithBotch(inner, ..)
+            | FloorClamp(inner, _)
+            | CeilClamp(inner, _)
+            | FloorDiv(inner, _)
+            | CeilDiv(inner, _)
+            | Negate(inner)
+            | AbsoluteValue(inner) => vec![inner],
+            Call(_, args) => args.iter_mut().collect(),
+            Conditional {
+                condition,
+                then_expr,
+                else_expr,
+                ..
+            } => vec![condition, then_expr, else_expr],
+            Add(left, right)
+            | Sub(left, right)
+            | Mul(left, right)
+            | Div(left, right)
+            | Min(left, right)
+            | Max(left, right) => vec![left, right],
+        }
+    }
+
+    /// Whether this node produces a pool of individual dice that
+    /// keep/drop selections can operate on
+    pub fn is_pool(&self) -> bool {
+        matches!(
+            self,
+            DiceExpression::Dice(..)
+                | DiceExpression::DynamicDice(..)
+                | DiceExpression::Explode(..)
+                | DiceExpression::ExplodeOn(..)
+                | DiceExpression::KeepHighest(..)
+                | DiceExpression::KeepLowest(..)
+                | DiceExpression::DropHighest(..)
+                | DiceExpression::DropLowest(..)
+        )
+    }
+}
@@ -0,0 +1,156 @@
+//! Fate points
+//!
+//! FATE and similar games let a player spend points from a pool for a
+//! bonus on a roll. Each point spent adds +2, and the pool refreshes to its
+//! maximum between sessions.
+
+use crate::context::DiceContext;
+use crate::{Dice, DiceError, DiceResult};
+
+/// Bonus each spent point adds
+const BONUS_PER_POINT: i32 = 2;
+
+/// A character's fate points
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FatePointPool {
+    pub current: u32,
+    pub max: u32,
+}
+
+impl FatePointPool {
+    /// Full pool of `max` points
+    pub fn new(max: u32) -> FatePointPool {
+        FatePointPool { current: max, max }
+    }
+
+    /// Restore the pool to its maximum
+    pub fn refresh(&mut self) {
+        self.current = self.max;
+    }
+}
+
+/// Outcome of a roll with fate points spent on it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FateResult {
+    pub roll: i32,
+    /// +2 for each point spent
+    pub bonus: i32,
+    /// `roll + bonus`
+    pub total: i32,
+    /// Points left in the pool after the spend
+    pub remaining_fate_points: u32,
+}
+
+impl DiceContext {
+    /// Spend fate points from `pool` and roll with the bonus
+    ///
+    /// Spending more points than the pool holds fails with
+    /// [`DiceError::InvalidRange`] before any dice are rolled. The points
+    /// are only taken once the roll succeeds.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - Fate points to spend from
+    /// * `notation` - Dice notation to roll, e.g. "4d3-8"
+    /// * `points_to_spend` - Points spent; each adds +2
+    pub fn roll_fate_point_spend(
+        &mut self,
+        pool: &mut FatePointPool,
+        notation: &str,
+        points_to_spend: u32,
+    ) -> DiceResult<FateResult> {
+        let Some(remaining) = pool.current.checked_sub(points_to_spend) else {
+            return Err(DiceError::InvalidRange {
+                min: 0,
+                max: pool.current.min(i32::MAX as u32) as i32,
+                actual: points_to_spend.min(i32::MAX as u32) as i32,
+            });
+        };
+        let bonus = i32::try_from(points_to_spend)
+            .ok()
+            .and_then(|points| points.checked_mul(BONUS_PER_POINT))
+            .ok_or(DiceError::Overflow)?;
+        let roll = self.roll_notation(notation)?.total;
+        let total = roll.checked_add(bonus).ok_or(DiceError::Overflow)?;
+        pool.current = remaining;
+        Ok(FateResult {
+            roll,
+            bonus,
+            total,
+            remaining_fate_points: remaining,
+        })
+    }
+}
+
+impl Dice {
+    /// Spend fate points from `pool` and roll with the bonus
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - Fate points to spend from
+    /// * `notation` - Dice notation to roll, e.g. "4d3-8"
+    /// * `points_to_spend` - Points spent; each adds +2
+    pub fn roll_fate_point_spend(
+        pool: &mut FatePointPool,
+        notation: &str,
+        points_to_spend: u32,
+    ) -> DiceResult<FateResult> {
+        DiceContext::new().roll_fate_point_spend(pool, notation, points_to_spend)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spend() {
+        let mut ctx = DiceContext::with_seed(168);
+        let mut pool = FatePointPool::new(3);
+        let result = ctx.roll_fate_point_spend(&mut pool, "4d3-8", 2).unwrap();
+        assert_eq!(result.bonus, 4);
+        assert_eq!(result.total, result.roll + 4);
+        assert!((-4..=4).contains(&result.roll));
+        assert_eq!((result.remaining_fate_points, pool.current), (1, 1));
+
+        pool.refresh();
+        assert_eq!(pool, FatePointPool { current: 3, max: 3 });
+    }
+
+    #[test]
+    fn test_zero_and_maximum_spend() {
+        let mut ctx = DiceContext::with_seed(168);
+        let mut pool = FatePointPool::new(5);
+        let result = ctx.roll_fate_point_spend(&mut pool, "1d6", 0).unwrap();
+        assert_eq!((result.bonus, result.total), (0, result.roll));
+        assert_eq!(pool.current, 5);
+
+        let result = ctx.roll_fate_point_spend(&mut pool, "1d6", 5).unwrap();
+        assert_eq!((result.bonus, result.remaining_fate_points), (10, 0));
+        assert!(ctx.roll_fate_point_spend(&mut pool, "1d6", 0).is_ok());
+    }
+
+    #[test]
+    fn test_insufficient_points_do_not_roll() {
+        let mut ctx = DiceContext::with_seed(168);
+        let mut fresh = ctx.clone();
+        let mut pool = FatePointPool::new(2);
+        assert_eq!(
+            ctx.roll_fate_point_spend(&mut pool, "1d6", 3),
+            Err(DiceError::InvalidRange {
+                min: 0,
+                max: 2,
+                actual: 3
+            })
+        );
+        assert_eq!(pool.current, 2);
+        // The failed spend left the generator untouched
+        assert_eq!(ctx.roll_notation("1d20"), fresh.roll_notation("1d20"));
+
+        // A roll that fails keeps the points
+        assert!(ctx.roll_fate_point_spend(&mut pool, "bad", 1).is_err());
+        assert_eq!(pool.current, 2);
+    }
+}
@@ -0,0 +1,177 @@
+//! Forged in the Dark action rolls
+//!
+//! Blades in the Dark and its descendants roll a pool of d6s and read only
+//! the best die: a 6 succeeds, a 4 or 5 is a partial success and anything
+//! lower fails. Two or more 6s are a critical. A pool of zero dice rolls
+//! 2d6 and takes the worse, so it can never crit. The 4 and 6 come from
+//! [`RollConfig::fitd_partial_success`](crate::RollConfig::fitd_partial_success)
+//! and [`RollConfig::fitd_success`](crate::RollConfig::fitd_success).
+
+use crate::context::DiceContext;
+use crate::{Dice, DiceError, DiceResult};
+
+/// Lowest die that's a partial success by default
+pub const FITD_PARTIAL_SUCCESS: u32 = 4;
+
+/// Lowest die that's a full success by default
+pub const FITD_SUCCESS: u32 = 6;
+
+/// Result band of a Forged in the Dark roll
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FitDOutcome {
+    Failure,
+    PartialSuccess,
+    Success,
+    /// Two or more dice at the success threshold
+    Critical,
+}
+
+/// Outcome of a Forged in the Dark roll
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FitDResult {
+    /// Every die rolled; two for a zero-dice pool
+    pub all_values: Vec<i32>,
+    /// The die that counts: the highest, or the lowest of a zero-dice pool
+    pub effective_value: i32,
+    pub outcome: FitDOutcome,
+}
+
+impl DiceContext {
+    /// Roll a Forged in the Dark action pool
+    ///
+    /// Thresholds in the config that aren't faces of a d6, or a partial
+    /// success above a full one, fail with [`DiceError::InvalidRange`].
+    ///
+    /// # Arguments
+    ///
+    /// * `dice_pool` - Number of d6s; 0 rolls 2d6 and keeps the lowest
+    pub fn roll_forged_in_the_dark(&mut self, dice_pool: u32) -> DiceResult<FitDResult> {
+        let success = self.config().fitd_success as i32;
+        let partial = self.config().fitd_partial_success as i32;
+        if !(1..=6).contains(&success) {
+            return Err(DiceError::InvalidRange {
+                min: 1,
+                max: 6,
+                actual: success,
+            });
+        }
+        if !(1..=success).contains(&partial) {
+            return Err(DiceError::InvalidRange {
+                min: 1,
+                max: success,
+                actual: partial,
+            });
+        }
+
+        let (all_values, effective_value) = self.roll_action_pool(dice_pool, 6)?;
+        let successes = if dice_pool == 0 {
+            0
+        } else {
+            all_values.iter().filter(|&&value| value >= success).count()
+        };
+        let outcome = if successes >= 2 {
+            FitDOutcome::Critical
+        } else if effective_value >= success {
+            FitDOutcome::Success
+        } else if effective_value >= partial {
+            FitDOutcome::PartialSuccess
+        } else {
+            FitDOutcome::Failure
+        };
+        Ok(FitDResult {
+            all_values,
+            effective_value,
+            outcome,
+        })
+    }
+}
+
+impl Dice {
+    /// Roll a Forged in the Dark action pool
+    ///
+    /// # Arguments
+    ///
+    /// * `dice_pool` - Number of d6s; 0 rolls 2d6 and keeps the lowest
+    pub fn roll_forged_in_the_dark(dice_pool: u32) -> DiceResult<FitDResult> {
+        DiceContext::new().roll_forged_in_the_dark(dice_pool)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RollConfig;
+
+    #[test]
+    fn test_outcome_bands() {
+        let mut ctx = DiceContext::with_seed(185);
+        let mut seen = Vec::new();
+        for _ in 0..500 {
+            let result = ctx.roll_forged_in_the_dark(3).unwrap();
+            assert_eq!(result.all_values.len(), 3);
+            assert_eq!(Some(&result.effective_value), result.all_values.iter().max());
+            let sixes = result.all_values.iter().filter(|&&value| value == 6).count();
+            let expected = match (sixes, result.effective_value) {
+                (2.., _) => FitDOutcome::Critical,
+                (1, _) => FitDOutcome::Success,
+                (_, 4 | 5) => FitDOutcome::PartialSuccess,
+                _ => FitDOutcome::Failure,
+            };
+            assert_eq!(result.outcome, expected, "{:?}", result.all_values);
+            if !seen.contains(&result.outcome) {
+                seen.push(result.outcome);
+            }
+        }
+        assert_eq!(seen.len(), 4);
+    }
+
+    #[test]
+    fn test_zero_dice_takes_lowest() {
+        let mut ctx = DiceContext::with_seed(185);
+        let mut successes = 0;
+        for _ in 0..500 {
+            let result = ctx.roll_forged_in_the_dark(0).unwrap();
+            assert_eq!(result.all_values.len(), 2);
+            assert_eq!(Some(&result.effective_value), result.all_values.iter().min());
+            // Even two 6s aren't a critical
+            assert_ne!(result.outcome, FitDOutcome::Critical);
+            successes += u32::from(result.outcome == FitDOutcome::Success);
+        }
+        // Both dice show 6 one time in 36
+        assert!((3..35).contains(&successes), "{}", successes);
+        let one_die = ctx.roll_forged_in_the_dark(1).unwrap();
+        assert_eq!(one_die.all_values, vec![one_die.effective_value]);
+    }
+
+    #[test]
+    fn test_configured_thresholds() {
+        let config = RollConfig {
+            fitd_partial_success: 1,
+            fitd_success: 1,
+            ..RollConfig::default()
+        };
+        let mut ctx = DiceContext::with_seed(185).with_config(config);
+        assert_eq!(ctx.roll_forged_in_the_dark(1).unwrap().outcome, FitDOutcome::Success);
+        assert_eq!(ctx.roll_forged_in_the_dark(2).unwrap().outcome, FitDOutcome::Critical);
+
+        for (partial, success, max, actual) in [(4, 7, 6, 7), (5, 4, 4, 5), (0, 6, 6, 0)] {
+            let config = RollConfig {
+                fitd_partial_success: partial,
+                fitd_success: success,
+                ..RollConfig::default()
+            };
+            let mut ctx = DiceContext::with_seed(185).with_config(config);
+            assert_eq!(
+                ctx.roll_forged_in_the_dark(2),
+                Err(DiceError::InvalidRange {
+                    min: 1,
+                    max,
+                    actual
+                })
+            );
+        }
+        assert!(Dice::roll_forged_in_the_dark(4).is_ok());
+    }
+}
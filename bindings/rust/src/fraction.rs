@@ -0,0 +1,259 @@
+//! Exact expected values
+//!
+//! Expected values of sums and products of plain dice are rationals, so
+//! [`DiceExpression::expected_value_exact`] works in [`Fraction`]s instead
+//! of floating point. An absolute value folds the distribution of its
+//! operand, so `|1d20-10|` is computed by counting outcomes exactly.
+
+use crate::distribution::MAX_EXACT_OUTCOMES;
+use crate::expression::DiceExpression;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// A rational number in lowest terms with a positive denominator
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Fraction {
+    pub numerator: i64,
+    pub denominator: i64,
+}
+
+impl Fraction {
+    /// `numerator / denominator` reduced to lowest terms
+    ///
+    /// `None` for a zero denominator or a reduced value that doesn't fit.
+    pub fn new(numerator: i64, denominator: i64) -> Option<Fraction> {
+        Fraction::reduced(numerator as i128, denominator as i128)
+    }
+
+    /// Whole number as a fraction
+    pub fn from_integer(value: i64) -> Fraction {
+        Fraction {
+            numerator: value,
+            denominator: 1,
+        }
+    }
+
+    /// Nearest `f64`
+    pub fn to_f64(&self) -> f64 {
+        self.numerator as f64 / self.denominator as f64
+    }
+
+    /// `self + other`, `None` on overflow
+    pub fn checked_add(self, other: Fraction) -> Option<Fraction> {
+        let (a, b) = (self.numerator as i128, self.denominator as i128);
+        let (c, d) = (other.numerator as i128, other.denominator as i128);
+        Fraction::reduced(a * d + c * b, b * d)
+    }
+
+    /// `self * other`, `None` on overflow
+    pub fn checked_mul(self, other: Fraction) -> Option<Fraction> {
+        let (a, b) = (self.numerator as i128, self.denominator as i128);
+        let (c, d) = (other.numerator as i128, other.denominator as i128);
+        Fraction::reduced(a * c, b * d)
+    }
+
+    /// `-self`, `None` on overflow
+    pub fn checked_neg(self) -> Option<Fraction> {
+        Some(Fraction {
+            numerator: self.numerator.checked_neg()?,
+            denominator: self.denominator,
+        })
+    }
+
+    fn reduced(numerator: i128, denominator: i128) -> Option<Fraction> {
+        if denominator == 0 {
+            return None;
+        }
+        let divisor = gcd(numerator, denominator) * denominator.signum();
+        Some(Fraction {
+            numerator: i64::try_from(numerator / divisor).ok()?,
+            denominator: i64::try_from(denominator / divisor).ok()?,
+        })
+    }
+}
+
+fn gcd(a: i128, b: i128) -> i128 {
+    let (mut a, mut b) = (a.abs(), b.abs());
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+impl fmt::Display for Fraction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.denominator == 1 {
+            write!(f, "{}", self.numerator)
+        } else {
+            write!(f, "{}/{}", self.numerator, self.denominator)
+        }
+    }
+}
+
+impl DiceExpression {
+    /// Expected value as an exact fraction
+    ///
+    /// Covers constants, plain `NdS` dice, negation, `+`, `-` and `*`; the
+    /// operands of a product are independent rolls, so their expectations
+    /// multiply. An absolute value is covered when its operand only adds,
+    /// subtracts and negates dice and constants. Keep and drop, explosions,
+    /// success counts, division and every other node return `None`, as does
+    /// a value too large for an `i64` fraction.
+    pub fn expected_value_exact(&self) -> Option<Fraction> {
+        use DiceExpression::*;
+
+        match self {
+            Constant(value) => Some(Fraction::from_integer(*value as i64)),
+            Dice(count, sides) => Fraction::new(*count as i64 * (*sides as i64 + 1), 2),
+            Negate(inner) => inner.expected_value_exact()?.checked_neg(),
+            Add(left, right) => {
+                left.expected_value_exact()?.checked_add(right.expected_value_exact()?)
+            }
+            Sub(left, right) => left
+                .expected_value_exact()?
+                .checked_add(right.expected_value_exact()?.checked_neg()?),
+            Mul(left, right) => {
+                left.expected_value_exact()?.checked_mul(right.expected_value_exact()?)
+            }
+            AbsoluteValue(inner) => {
+                let (counts, total) = outcome_counts(inner)?;
+                let mut sum: i128 = 0;
+                for (outcome, count) in counts {
+                    sum = sum.checked_add((outcome as i128).abs().checked_mul(count)?)?;
+                }
+                Fraction::reduced(sum, total)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Ways each outcome can be rolled, and the total number of ways
+type Counts = (BTreeMap<i32, i128>, i128);
+
+/// Outcome counts of `expr`, `None` past [`MAX_EXACT_OUTCOMES`] outcomes
+fn outcome_counts(expr: &DiceExpression) -> Option<Counts> {
+    use DiceExpression::*;
+
+    match expr {
+        Constant(value) => Some((BTreeMap::from([(*value, 1)]), 1)),
+        Dice(count, sides) => {
+            if *count == 0 || *sides == 0 {
+                return None;
+            }
+            // Also keeps `sides` well inside an `i32`
+            let support = (*count as usize).checked_mul(*sides as usize)?;
+            if support > MAX_EXACT_OUTCOMES {
+                return None;
+            }
+            let die: BTreeMap<i32, i128> = (1..=*sides as i32).map(|face| (face, 1)).collect();
+            let mut result = (BTreeMap::from([(0, 1)]), 1);
+            for _ in 0..*count {
+                result = combine(&result, &(die.clone(), *sides as i128), i32::checked_add)?;
+            }
+            Some(result)
+        }
+        Negate(inner) => {
+            let (counts, total) = outcome_counts(inner)?;
+            let counts = counts
+                .into_iter()
+                .map(|(outcome, count)| Some((outcome.checked_neg()?, count)))
+                .collect::<Option<_>>()?;
+            Some((counts, total))
+        }
+        Add(left, right) => {
+            combine(&outcome_counts(left)?, &outcome_counts(right)?, i32::checked_add)
+        }
+        Sub(left, right) => {
+            combine(&outcome_counts(left)?, &outcome_counts(right)?, i32::checked_sub)
+        }
+        _ => None,
+    }
+}
+
+/// Counts of `op` applied to every pair of outcomes
+fn combine(left: &Counts, right: &Counts, op: fn(i32, i32) -> Option<i32>) -> Option<Counts> {
+    // No single entry can exceed the total, so checking it covers the sums
+    let total = left.1.checked_mul(right.1)?;
+    let mut counts = BTreeMap::new();
+    for (&a, &x) in &left.0 {
+        for (&b, &y) in &right.0 {
+            *counts.entry(op(a, b)?).or_insert(0i128) += x.checked_mul(y)?;
+        }
+    }
+    if counts.len() > MAX_EXACT_OUTCOMES {
+        return None;
+    }
+    Some((counts, total))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exact(notation: &str) -> Option<Fraction> {
+        DiceExpression::parse(notation).unwrap().expected_value_exact()
+    }
+
+    #[test]
+    fn test_fraction_arithmetic() {
+        assert_eq!(Fraction::new(6, -4), Fraction::new(-3, 2));
+        assert_eq!(Fraction::new(-3, 2).unwrap().denominator, 2);
+        assert_eq!(Fraction::new(0, 5), Some(Fraction::from_integer(0)));
+        assert_eq!(Fraction::new(1, 0), None);
+        let half = Fraction::new(1, 2).unwrap();
+        let third = Fraction::new(1, 3).unwrap();
+        assert_eq!(half.checked_add(third), Fraction::new(5, 6));
+        assert_eq!(half.checked_mul(third), Fraction::new(1, 6));
+        assert_eq!(half.to_string(), "1/2");
+        assert_eq!(Fraction::from_integer(-4).to_string(), "-4");
+        assert!((Fraction::new(7, 2).unwrap().to_f64() - 3.5).abs() < 1e-12);
+        assert_eq!(Fraction::from_integer(i64::MIN).checked_neg(), None);
+    }
+
+    #[test]
+    fn test_expected_value_exact() {
+        assert_eq!(exact("1d6"), Fraction::new(7, 2));
+        assert_eq!(exact("3d6"), Fraction::new(21, 2));
+        assert_eq!(exact("42"), Some(Fraction::from_integer(42)));
+        assert_eq!(exact("1d20+5"), Fraction::new(31, 2));
+        assert_eq!(exact("2d6-1d4"), Fraction::new(9, 2));
+        assert_eq!(exact("-(1d8)*3"), Fraction::new(-27, 2));
+        // Independent dice multiply their expectations
+        assert_eq!(exact("1d6*1d6"), Fraction::new(49, 4));
+        assert_eq!(exact("d%"), Fraction::new(101, 2));
+    }
+
+    #[test]
+    fn test_unsupported_returns_none() {
+        for notation in ["4d6k3", "1d6!", "8d6>4", "10/1d4", "7/2", "min(1d6, 3)", "Nd6"] {
+            assert_eq!(exact(notation), None, "{}", notation);
+        }
+        assert_eq!(exact("1000d1000000*1000d1000000*1000d1000000"), None);
+        assert_eq!(exact("|1d6-2d4k1|"), None);
+        // Too many outcomes to count, however few dice
+        assert_eq!(exact("|1d4000000000|"), None);
+        assert_eq!(exact("|1d2000000000-1|"), None);
+        assert_eq!(exact("|20d1000|"), None);
+    }
+
+    #[test]
+    fn test_absolute_difference() {
+        // |1d6-4| folds to 0, 1, 2, 3 with weights 1, 2, 2, 1
+        assert_eq!(exact("|1d6-4|"), Fraction::new(9, 6));
+        assert_eq!(exact("|1d20-10|"), Some(Fraction::from_integer(5)));
+        assert_eq!(exact("|2d6-7|"), Fraction::new(35, 18));
+        assert_eq!(exact("|1d6|"), exact("1d6"));
+        assert_eq!(exact("|-2d4+1|*2"), Fraction::new(8, 1));
+    }
+
+    #[test]
+    fn test_matches_float_estimate() {
+        let expr = DiceExpression::parse("(2d6+3)*1d4-1d10").unwrap();
+        let exact = expr.expected_value_exact().unwrap().to_f64();
+        let distribution = expr.distribution().unwrap();
+        let mean: f64 = distribution.iter().map(|(&v, &p)| v as f64 * p).sum();
+        assert!((exact - mean).abs() < 1e-9, "{} vs {}", exact, mean);
+    }
+}
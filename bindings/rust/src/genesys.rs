@@ -0,0 +1,387 @@
+//! Genesys symbol dice
+//!
+//! The Genesys system (and the Star Wars games built on it) rolls dice
+//! whose faces show symbols instead of numbers. Each face of a
+//! [`SymbolDie`] carries zero or more [`SymbolResult`]s; a pool's symbols
+//! cancel in pairs, successes against failures and advantages against
+//! threats. A Triumph also counts as a success and a Despair as a failure.
+
+use crate::context::DiceContext;
+use crate::{Dice, DiceError, DiceResult};
+use rand::Rng;
+
+/// A symbol printed on a [`SymbolDie`] face
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SymbolResult {
+    Success,
+    Failure,
+    Advantage,
+    Threat,
+    Triumph,
+    Despair,
+}
+
+/// A die whose faces each show a list of symbols; a blank face shows none
+///
+/// With the `serde` feature a die deserializes through [`SymbolDie::new`],
+/// so a die with no faces is rejected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "SymbolDieFaces")
+)]
+pub struct SymbolDie {
+    faces: Vec<Vec<SymbolResult>>,
+}
+
+/// Unchecked [`SymbolDie`] as read by serde
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct SymbolDieFaces {
+    faces: Vec<Vec<SymbolResult>>,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<SymbolDieFaces> for SymbolDie {
+    type Error = DiceError;
+
+    fn try_from(raw: SymbolDieFaces) -> DiceResult<SymbolDie> {
+        SymbolDie::new(raw.faces)
+    }
+}
+
+impl SymbolDie {
+    /// Die with the given faces; fails with [`DiceError::InvalidSides`]
+    /// when there are none
+    pub fn new(faces: Vec<Vec<SymbolResult>>) -> DiceResult<SymbolDie> {
+        if faces.is_empty() {
+            return Err(DiceError::InvalidSides(0));
+        }
+        Ok(SymbolDie { faces })
+    }
+
+    /// Green d8 Ability die
+    pub fn ability() -> SymbolDie {
+        use SymbolResult::*;
+        SymbolDie::from_faces(&[
+            &[],
+            &[Success],
+            &[Success],
+            &[Success, Success],
+            &[Advantage],
+            &[Advantage],
+            &[Success, Advantage],
+            &[Advantage, Advantage],
+        ])
+    }
+
+    /// Purple d8 Difficulty die
+    pub fn difficulty() -> SymbolDie {
+        use SymbolResult::*;
+        SymbolDie::from_faces(&[
+            &[],
+            &[Failure],
+            &[Failure, Failure],
+            &[Threat],
+            &[Threat],
+            &[Threat],
+            &[Threat, Threat],
+            &[Failure, Threat],
+        ])
+    }
+
+    /// Yellow d12 Proficiency die, the upgraded Ability die
+    pub fn proficiency() -> SymbolDie {
+        use SymbolResult::*;
+        SymbolDie::from_faces(&[
+            &[],
+            &[Success],
+            &[Success],
+            &[Success, Success],
+            &[Success, Success],
+            &[Advantage],
+            &[Success, Advantage],
+            &[Success, Advantage],
+            &[Success, Advantage],
+            &[Advantage, Advantage],
+            &[Advantage, Advantage],
+            &[Triumph],
+        ])
+    }
+
+    /// Red d12 Challenge die, the upgraded Difficulty die
+    pub fn challenge() -> SymbolDie {
+        use SymbolResult::*;
+        SymbolDie::from_faces(&[
+            &[],
+            &[Failure],
+            &[Failure],
+            &[Failure, Failure],
+            &[Failure, Failure],
+            &[Threat],
+            &[Threat],
+            &[Failure, Threat],
+            &[Failure, Threat],
+            &[Threat, Threat],
+            &[Threat, Threat],
+            &[Despair],
+        ])
+    }
+
+    fn from_faces(faces: &[&[SymbolResult]]) -> SymbolDie {
+        SymbolDie {
+            faces: faces.iter().map(|face| face.to_vec()).collect(),
+        }
+    }
+
+    /// Number of faces
+    pub fn sides(&self) -> usize {
+        self.faces.len()
+    }
+
+    /// Symbols on every face
+    pub fn faces(&self) -> &[Vec<SymbolResult>] {
+        &self.faces
+    }
+
+    /// Symbols on a randomly chosen face
+    pub fn roll<R: Rng + ?Sized>(&self, rng: &mut R) -> &[SymbolResult] {
+        &self.faces[rng.gen_range(0..self.faces.len())]
+    }
+}
+
+/// Whether a Genesys check succeeded, and which way its side effects went
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GenesysOutcome {
+    SuccessWithAdvantage,
+    Success,
+    SuccessWithThreat,
+    FailureWithAdvantage,
+    Failure,
+    FailureWithThreat,
+}
+
+/// Symbols rolled by a Genesys pool
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GenesysResult {
+    /// Successes rolled, Triumphs included
+    pub successes: i32,
+    /// Failures rolled, Despairs included
+    pub failures: i32,
+    pub advantages: i32,
+    pub threats: i32,
+    pub triumphs: u32,
+    pub despairs: u32,
+    /// Successes minus failures; the check succeeds when this is positive
+    pub net_successes: i32,
+    /// Advantages minus threats
+    pub net_advantages: i32,
+    pub outcome: GenesysOutcome,
+}
+
+impl GenesysResult {
+    /// Tally the symbols of a pool
+    pub fn from_symbols<'a>(symbols: impl IntoIterator<Item = &'a SymbolResult>) -> Self {
+        let (mut successes, mut failures, mut advantages, mut threats) = (0, 0, 0, 0);
+        let (mut triumphs, mut despairs) = (0, 0);
+        for symbol in symbols {
+            match symbol {
+                SymbolResult::Success => successes += 1,
+                SymbolResult::Failure => failures += 1,
+                SymbolResult::Advantage => advantages += 1,
+                SymbolResult::Threat => threats += 1,
+                SymbolResult::Triumph => {
+                    triumphs += 1;
+                    successes += 1;
+                }
+                SymbolResult::Despair => {
+                    despairs += 1;
+                    failures += 1;
+                }
+            }
+        }
+        let net_successes = successes - failures;
+        let net_advantages: i32 = advantages - threats;
+        let outcome = match (net_successes > 0, net_advantages.signum()) {
+            (true, 1) => GenesysOutcome::SuccessWithAdvantage,
+            (true, 0) => GenesysOutcome::Success,
+            (true, _) => GenesysOutcome::SuccessWithThreat,
+            (false, 1) => GenesysOutcome::FailureWithAdvantage,
+            (false, 0) => GenesysOutcome::Failure,
+            (false, _) => GenesysOutcome::FailureWithThreat,
+        };
+        GenesysResult {
+            successes,
+            failures,
+            advantages,
+            threats,
+            triumphs,
+            despairs,
+            net_successes,
+            net_advantages,
+            outcome,
+        }
+    }
+
+    /// Whether the check succeeded
+    pub fn is_success(&self) -> bool {
+        self.net_successes > 0
+    }
+}
+
+impl DiceContext {
+    /// Roll a Genesys pool of Ability, Difficulty, Proficiency and
+    /// Challenge dice
+    ///
+    /// An empty pool fails with [`DiceError::InvalidCount`], as does one
+    /// larger than `RollConfig::max_dice_count`.
+    ///
+    /// # Arguments
+    ///
+    /// * `ability` - Green Ability dice
+    /// * `difficulty` - Purple Difficulty dice
+    /// * `proficiency` - Yellow Proficiency dice
+    /// * `challenge` - Red Challenge dice
+    pub fn roll_genesys(
+        &mut self,
+        ability: u32,
+        difficulty: u32,
+        proficiency: u32,
+        challenge: u32,
+    ) -> DiceResult<GenesysResult> {
+        let pool = [
+            (SymbolDie::ability(), ability),
+            (SymbolDie::difficulty(), difficulty),
+            (SymbolDie::proficiency(), proficiency),
+            (SymbolDie::challenge(), challenge),
+        ];
+        let size = pool.iter().fold(0u32, |size, (_, count)| size.saturating_add(*count));
+        if size == 0 || size > self.config().max_dice_count {
+            return Err(DiceError::InvalidCount(i32::try_from(size).unwrap_or(i32::MAX)));
+        }
+        let mut symbols = Vec::new();
+        for (die, count) in &pool {
+            for _ in 0..*count {
+                symbols.extend_from_slice(die.roll(self.rng()));
+            }
+        }
+        Ok(GenesysResult::from_symbols(&symbols))
+    }
+}
+
+impl Dice {
+    /// Roll a Genesys pool of Ability, Difficulty, Proficiency and
+    /// Challenge dice
+    ///
+    /// # Arguments
+    ///
+    /// * `ability` - Green Ability dice
+    /// * `difficulty` - Purple Difficulty dice
+    /// * `proficiency` - Yellow Proficiency dice
+    /// * `challenge` - Red Challenge dice
+    pub fn roll_genesys(
+        ability: u32,
+        difficulty: u32,
+        proficiency: u32,
+        challenge: u32,
+    ) -> DiceResult<GenesysResult> {
+        DiceContext::new().roll_genesys(ability, difficulty, proficiency, challenge)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RollConfig;
+    use SymbolResult::*;
+
+    fn count(die: &SymbolDie, symbol: SymbolResult) -> usize {
+        die.faces().iter().flatten().filter(|&&s| s == symbol).count()
+    }
+
+    #[test]
+    fn test_standard_dice() {
+        let ability = SymbolDie::ability();
+        assert_eq!(ability.sides(), 8);
+        assert_eq!((count(&ability, Success), count(&ability, Advantage)), (5, 5));
+        let proficiency = SymbolDie::proficiency();
+        assert_eq!(proficiency.sides(), 12);
+        assert_eq!((count(&proficiency, Success), count(&proficiency, Advantage)), (9, 8));
+        assert_eq!(count(&proficiency, Triumph), 1);
+        let difficulty = SymbolDie::difficulty();
+        assert_eq!((count(&difficulty, Failure), count(&difficulty, Threat)), (4, 6));
+        let challenge = SymbolDie::challenge();
+        assert_eq!((count(&challenge, Failure), count(&challenge, Threat)), (8, 8));
+        assert_eq!(count(&challenge, Despair), 1);
+        assert_eq!(SymbolDie::new(Vec::new()), Err(DiceError::InvalidSides(0)));
+    }
+
+    #[test]
+    fn test_symbols_cancel() {
+        let result = GenesysResult::from_symbols(&[Success, Success, Failure, Threat]);
+        assert_eq!((result.net_successes, result.net_advantages), (1, -1));
+        assert_eq!(result.outcome, GenesysOutcome::SuccessWithThreat);
+        assert!(result.is_success());
+
+        // A Failure cancels the success a Triumph carries, though the Triumph itself stands
+        let result = GenesysResult::from_symbols(&[Triumph, Failure, Advantage]);
+        assert_eq!((result.successes, result.triumphs, result.net_successes), (1, 1, 0));
+        assert_eq!(result.outcome, GenesysOutcome::FailureWithAdvantage);
+
+        let result = GenesysResult::from_symbols(&[Despair, Success, Success]);
+        assert_eq!((result.failures, result.despairs), (1, 1));
+        assert_eq!(result.outcome, GenesysOutcome::Success);
+        assert_eq!(GenesysResult::from_symbols(&[]).outcome, GenesysOutcome::Failure);
+    }
+
+    #[test]
+    fn test_roll_genesys() {
+        let mut ctx = DiceContext::with_seed(182);
+        let mut triumphs = 0;
+        for _ in 0..300 {
+            let result = ctx.roll_genesys(2, 2, 1, 1).unwrap();
+            assert_eq!(result.net_successes, result.successes - result.failures);
+            assert_eq!(result.net_advantages, result.advantages - result.threats);
+            // Two Ability dice and a Proficiency die show at most 6 successes
+            assert!(result.successes <= 6 && result.failures <= 5);
+            assert!(result.triumphs <= 1 && result.despairs <= 1);
+            triumphs += result.triumphs;
+        }
+        // About one roll in twelve
+        assert!((10..50).contains(&triumphs), "{}", triumphs);
+
+        let only_ability = ctx.roll_genesys(3, 0, 0, 0).unwrap();
+        assert_eq!((only_ability.failures, only_ability.threats), (0, 0));
+        assert_eq!(ctx.roll_genesys(0, 0, 0, 0), Err(DiceError::InvalidCount(0)));
+    }
+
+    #[test]
+    fn test_pool_limit() {
+        let config = RollConfig {
+            max_dice_count: 4,
+            ..RollConfig::default()
+        };
+        let mut ctx = DiceContext::with_seed(182).with_config(config);
+        assert!(ctx.roll_genesys(2, 2, 0, 0).is_ok());
+        assert_eq!(ctx.roll_genesys(2, 2, 1, 0), Err(DiceError::InvalidCount(5)));
+        assert_eq!(
+            ctx.roll_genesys(u32::MAX, 1, 0, 0),
+            Err(DiceError::InvalidCount(i32::MAX))
+        );
+        assert!(Dice::roll_genesys(1, 1, 0, 0).is_ok());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_checks_faces() {
+        let die = SymbolDie::ability();
+        let json = serde_json::to_string(&die).unwrap();
+        assert_eq!(serde_json::from_str::<SymbolDie>(&json).unwrap(), die);
+        let error = serde_json::from_str::<SymbolDie>(r#"{"faces":[]}"#).unwrap_err();
+        assert!(error.to_string().contains("Invalid number of sides: 0"), "{}", error);
+    }
+}
@@ -0,0 +1,331 @@
+//! Roll history
+//!
+//! An in-memory log of rolls that can be exported to and re-imported from
+//! RFC 4180 CSV for post-session analysis or dispute resolution.
+
+use crate::eval::RollResult;
+use crate::{DiceError, DiceResult};
+use std::io::{Read, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// CSV header, in column order
+const CSV_HEADER: [&str; 6] = [
+    "sequence_number",
+    "timestamp_unix_ms",
+    "notation",
+    "result",
+    "individual_results",
+    "context_name",
+];
+
+/// A single logged roll
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RollRecord {
+    pub sequence_number: u64,
+    pub timestamp_unix_ms: u64,
+    pub notation: String,
+    pub result: i32,
+    pub individual_results: Vec<i32>,
+    pub context_name: String,
+}
+
+/// Ordered log of rolls
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RollHistory {
+    records: Vec<RollRecord>,
+}
+
+impl RollHistory {
+    pub fn new() -> Self {
+        RollHistory::default()
+    }
+
+    /// Append a roll, timestamped now
+    ///
+    /// # Arguments
+    ///
+    /// * `notation` - Notation that was rolled
+    /// * `result` - Result of the roll
+    /// * `context_name` - Who or what rolled (character, table, bot channel)
+    pub fn record(
+        &mut self,
+        notation: &str,
+        result: &RollResult,
+        context_name: &str,
+    ) -> &RollRecord {
+        let timestamp_unix_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_millis() as u64)
+            .unwrap_or(0);
+        let sequence_number = self
+            .records
+            .last()
+            .map_or(1, |last| last.sequence_number + 1);
+
+        self.records.push(RollRecord {
+            sequence_number,
+            timestamp_unix_ms,
+            notation: notation.to_string(),
+            result: result.total,
+            individual_results: result.individual(),
+            context_name: context_name.to_string(),
+        });
+        &self.records[self.records.len() - 1]
+    }
+
+    /// Logged rolls, oldest first
+    pub fn records(&self) -> &[RollRecord] {
+        &self.records
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Write the history as RFC 4180 CSV with a header row
+    ///
+    /// Individual results are joined with `;` inside a single field.
+    pub fn to_csv(&self, mut writer: impl Write) -> std::io::Result<()> {
+        write_row(
+            &mut writer,
+            CSV_HEADER.iter().map(|column| column.to_string()),
+        )?;
+        for record in &self.records {
+            let individual = join_individual(&record.individual_results);
+            write_row(
+                &mut writer,
+                [
+                    record.sequence_number.to_string(),
+                    record.timestamp_unix_ms.to_string(),
+                    record.notation.clone(),
+                    record.result.to_string(),
+                    individual,
+                    record.context_name.clone(),
+                ]
+                .into_iter(),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Read a history previously written by [`to_csv`](Self::to_csv)
+    pub fn from_csv(mut reader: impl Read) -> DiceResult<RollHistory> {
+        let mut input = String::new();
+        reader.read_to_string(&mut input)?;
+
+        let mut rows = parse_csv(&input)?.into_iter();
+        match rows.next() {
+            Some(header) if header == CSV_HEADER => {}
+            _ => return Err(DiceError::InvalidFormat("missing CSV header".to_string())),
+        }
+
+        let mut records = Vec::new();
+        for (line, row) in rows.enumerate() {
+            let line = line + 2;
+            if row.len() != CSV_HEADER.len() {
+                return Err(DiceError::InvalidFormat(format!(
+                    "row {}: expected {} fields, found {}",
+                    line,
+                    CSV_HEADER.len(),
+                    row.len()
+                )));
+            }
+            let number = |field: &str, column: &str| {
+                DiceError::InvalidFormat(format!("row {}: invalid {} '{}'", line, column, field))
+            };
+
+            let individual_results =
+                split_individual(&row[4]).ok_or_else(|| number(&row[4], CSV_HEADER[4]))?;
+
+            records.push(RollRecord {
+                sequence_number: row[0].parse().map_err(|_| number(&row[0], CSV_HEADER[0]))?,
+                timestamp_unix_ms: row[1].parse().map_err(|_| number(&row[1], CSV_HEADER[1]))?,
+                notation: row[2].clone(),
+                result: row[3].parse().map_err(|_| number(&row[3], CSV_HEADER[3]))?,
+                individual_results,
+                context_name: row[5].clone(),
+            });
+        }
+        Ok(RollHistory { records })
+    }
+}
+
+/// Encode individual die results as a `;`-separated list
+pub(crate) fn join_individual(values: &[i32]) -> String {
+    values
+        .iter()
+        .map(|value| value.to_string())
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+/// Decode a list written by [`join_individual`]
+pub(crate) fn split_individual(field: &str) -> Option<Vec<i32>> {
+    if field.is_empty() {
+        return Some(Vec::new());
+    }
+    field.split(';').map(|value| value.parse().ok()).collect()
+}
+
+/// Write one CSV row terminated by CRLF, quoting fields as needed
+fn write_row(writer: &mut impl Write, fields: impl Iterator<Item = String>) -> std::io::Result<()> {
+    for (i, field) in fields.enumerate() {
+        if i > 0 {
+            writer.write_all(b",")?;
+        }
+        if field.contains([',', '"', '\r', '\n']) {
+            write!(writer, "\"{}\"", field.replace('"', "\"\""))?;
+        } else {
+            writer.write_all(field.as_bytes())?;
+        }
+    }
+    writer.write_all(b"\r\n")
+}
+
+/// Split RFC 4180 CSV into rows of fields
+///
+/// Accepts both CRLF and bare LF line endings. Quoted fields may contain
+/// commas, line breaks and doubled quotes.
+pub(crate) fn parse_csv(input: &str) -> DiceResult<Vec<Vec<String>>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut chars = input.chars().peekable();
+    let mut in_quotes = false;
+    let mut row_has_content = false;
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => in_quotes = false,
+                _ => field.push(c),
+            }
+            continue;
+        }
+
+        match c {
+            '"' if field.is_empty() => {
+                in_quotes = true;
+                row_has_content = true;
+            }
+            ',' => {
+                row.push(std::mem::take(&mut field));
+                row_has_content = true;
+            }
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                if row_has_content || !field.is_empty() {
+                    row.push(std::mem::take(&mut field));
+                    rows.push(std::mem::take(&mut row));
+                }
+                row_has_content = false;
+            }
+            _ => {
+                field.push(c);
+                row_has_content = true;
+            }
+        }
+    }
+
+    if in_quotes {
+        return Err(DiceError::InvalidFormat(
+            "unterminated quoted field".to_string(),
+        ));
+    }
+    if row_has_content || !field.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::DiceContext;
+
+    #[test]
+    fn test_record_sequence() {
+        let mut ctx = DiceContext::with_seed(1);
+        let mut history = RollHistory::new();
+        let result = ctx.roll_notation("3d6").unwrap();
+        let record = history.record("3d6", &result, "Aragorn").clone();
+        assert_eq!(record.sequence_number, 1);
+        assert_eq!(record.result, result.total);
+        assert_eq!(record.individual_results, result.individual());
+        assert!(record.timestamp_unix_ms > 0);
+
+        history.record("3d6", &result, "Aragorn");
+        assert_eq!(history.records()[1].sequence_number, 2);
+        assert_eq!(history.len(), 2);
+    }
+
+    #[test]
+    fn test_csv_round_trip() {
+        let mut ctx = DiceContext::with_seed(2024);
+        let mut history = RollHistory::new();
+        let notations = ["3d6", "1d20+5", "4d6k3", "2d8-1", "10"];
+        let names = ["Gimli", "table, main", "the \"bard\"", "line\nbreak", ""];
+        for i in 0..1000 {
+            let notation = notations[i % notations.len()];
+            let result = ctx.roll_notation(notation).unwrap();
+            history.record(notation, &result, names[i % names.len()]);
+        }
+
+        let mut csv = Vec::new();
+        history.to_csv(&mut csv).unwrap();
+        let restored = RollHistory::from_csv(csv.as_slice()).unwrap();
+        assert_eq!(restored, history);
+    }
+
+    #[test]
+    fn test_csv_quoting() {
+        let mut history = RollHistory::new();
+        history.records.push(RollRecord {
+            sequence_number: 7,
+            timestamp_unix_ms: 1_700_000_000_000,
+            notation: "2d6".to_string(),
+            result: 9,
+            individual_results: vec![4, 5],
+            context_name: "Bob, \"the\" Brave".to_string(),
+        });
+
+        let mut csv = Vec::new();
+        history.to_csv(&mut csv).unwrap();
+        let text = String::from_utf8(csv).unwrap();
+        assert_eq!(
+            text,
+            "sequence_number,timestamp_unix_ms,notation,result,individual_results,context_name\r\n\
+             7,1700000000000,2d6,9,4;5,\"Bob, \"\"the\"\" Brave\"\r\n"
+        );
+    }
+
+    #[test]
+    fn test_from_csv_errors() {
+        assert!(RollHistory::from_csv("".as_bytes()).is_err());
+        assert!(RollHistory::from_csv("a,b\r\n".as_bytes()).is_err());
+
+        let header = CSV_HEADER.join(",");
+        let short = format!("{}\r\n1,2,3d6\r\n", header);
+        assert!(matches!(
+            RollHistory::from_csv(short.as_bytes()),
+            Err(DiceError::InvalidFormat(_))
+        ));
+        let bad_number = format!("{}\r\n1,x,3d6,4,1;2;1,\r\n", header);
+        assert!(RollHistory::from_csv(bad_number.as_bytes()).is_err());
+        let unterminated = format!("{}\r\n1,2,\"3d6,4,1,\r\n", header);
+        assert!(RollHistory::from_csv(unterminated.as_bytes()).is_err());
+
+        let empty = format!("{}\r\n", header);
+        assert!(RollHistory::from_csv(empty.as_bytes()).unwrap().is_empty());
+    }
+}
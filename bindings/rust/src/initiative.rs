@@ -0,0 +1,334 @@
+//! Initiative order
+//!
+//! Everyone rolls a die plus their initiative modifier and acts highest
+//! first. Characters tied on the total roll the die again, and again while
+//! still tied, up to [`MAX_TIEBREAK_ROUNDS`] times.
+//!
+//! Deadlands deals initiative from a [`Deck`] instead: the highest card acts
+//! first, suits break ties between ranks and Jokers beat everything.
+
+use crate::cards::{Deck, PlayingCard};
+use crate::context::DiceContext;
+use crate::{Dice, DiceError, DiceResult};
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+
+/// Tie-break rounds rolled before a tie is left in input order
+pub const MAX_TIEBREAK_ROUNDS: u32 = 10;
+
+/// Characters in the order they act
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InitiativeOrder {
+    /// `(name, total_initiative, tiebreak_roll)`, highest total first
+    ///
+    /// `tiebreak_roll` is the first tie-break roll, or 0 for a character
+    /// that wasn't tied. Later rounds only order characters whose first
+    /// tie-break rolls matched too.
+    pub order: Vec<(String, i32, i32)>,
+}
+
+/// Characters in the order they act, by the card each drew
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InitiativeCardOrder {
+    /// `(name, card)`, highest card first
+    pub order: Vec<(String, PlayingCard)>,
+}
+
+impl InitiativeCardOrder {
+    /// Characters holding Jokers, who act before everyone else
+    pub fn jokers(&self) -> Vec<&str> {
+        self.order
+            .iter()
+            .filter(|(_, card)| card.is_joker())
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+}
+
+/// Name of each character, with repeated names numbered from 1
+fn distinct_names(characters: &[(&str, i32)]) -> Vec<String> {
+    let mut name_counts: HashMap<&str, usize> = HashMap::new();
+    for &(name, _) in characters {
+        *name_counts.entry(name).or_insert(0) += 1;
+    }
+    // Names kept as they are, then every number handed out
+    let mut taken: HashSet<String> = name_counts
+        .iter()
+        .filter(|&(_, &count)| count == 1)
+        .map(|(&name, _)| name.to_string())
+        .collect();
+    let mut next: HashMap<&str, usize> = HashMap::new();
+    characters
+        .iter()
+        .map(|&(name, _)| {
+            if name_counts[name] == 1 {
+                return name.to_string();
+            }
+            let number = next.entry(name).or_insert(0);
+            loop {
+                *number += 1;
+                let numbered = format!("{} {}", name, number);
+                if taken.insert(numbered.clone()) {
+                    return numbered;
+                }
+            }
+        })
+        .collect()
+}
+
+impl DiceContext {
+    /// Roll initiative and break ties with further rolls
+    ///
+    /// Names that appear more than once are numbered in the order they
+    /// appear, so two "Goblin"s become "Goblin 1" and "Goblin 2". A number
+    /// that would repeat another character's name is skipped: next to a
+    /// "Goblin 2", two "Goblin"s become "Goblin 1" and "Goblin 3".
+    ///
+    /// # Arguments
+    ///
+    /// * `characters` - (name, initiative modifier) for each character
+    /// * `sides` - Sides of the initiative and tie-break die
+    pub fn roll_initiative_tiebreak(
+        &mut self,
+        characters: &[(&str, i32)],
+        sides: u32,
+    ) -> DiceResult<InitiativeOrder> {
+        let mut totals = Vec::with_capacity(characters.len());
+        for &(_, modifier) in characters {
+            let roll = self.roll_pool(1, sides)?[0];
+            totals.push(roll.checked_add(modifier).ok_or(DiceError::Overflow)?);
+        }
+
+        // Each round rolls once more for everyone still tied with someone
+        let mut tiebreaks: Vec<Vec<i32>> = vec![Vec::new(); characters.len()];
+        for _ in 0..MAX_TIEBREAK_ROUNDS {
+            let mut group_sizes: HashMap<(i32, &[i32]), usize> = HashMap::new();
+            for (total, rolls) in totals.iter().zip(&tiebreaks) {
+                *group_sizes.entry((*total, rolls.as_slice())).or_insert(0) += 1;
+            }
+            let tied: Vec<bool> = totals
+                .iter()
+                .zip(&tiebreaks)
+                .map(|(total, rolls)| group_sizes[&(*total, rolls.as_slice())] > 1)
+                .collect();
+            if !tied.contains(&true) {
+                break;
+            }
+            for (rolls, _) in tiebreaks.iter_mut().zip(tied).filter(|(_, tied)| *tied) {
+                rolls.push(self.roll_pool(1, sides)?[0]);
+            }
+        }
+
+        let mut ranked: Vec<usize> = (0..characters.len()).collect();
+        // Stable, so ties still unbroken keep their input order
+        ranked.sort_by(|&a, &b| (totals[b], &tiebreaks[b]).cmp(&(totals[a], &tiebreaks[a])));
+
+        let names = distinct_names(characters);
+        let order = ranked
+            .into_iter()
+            .map(|index| {
+                let tiebreak = tiebreaks[index].first().copied().unwrap_or(0);
+                (names[index].clone(), totals[index], tiebreak)
+            })
+            .collect();
+        Ok(InitiativeOrder { order })
+    }
+
+    /// Deal each character a card and order them by it
+    ///
+    /// A deck with fewer cards left than characters shuffles its discards
+    /// back in first; if that still isn't enough the deal fails with
+    /// [`DiceError::InvalidCount`].
+    ///
+    /// # Arguments
+    ///
+    /// * `deck` - Deck to deal from, shuffled with this context's generator
+    /// * `characters` - Name of each character
+    pub fn roll_initiative_card_draw(
+        &mut self,
+        deck: &mut Deck,
+        characters: &[&str],
+    ) -> DiceResult<InitiativeCardOrder> {
+        let shortage = || DiceError::InvalidCount(characters.len().min(i32::MAX as usize) as i32);
+        if deck.remaining() < characters.len() {
+            deck.shuffle(self.rng());
+            if deck.remaining() < characters.len() {
+                return Err(shortage());
+            }
+        }
+        let mut order = Vec::with_capacity(characters.len());
+        for &name in characters {
+            let card = deck.draw(self.rng()).ok_or_else(shortage)?;
+            order.push((name.to_string(), card));
+        }
+        // Stable, so two Jokers keep the order they were dealt in
+        order.sort_by_key(|&(_, card)| Reverse(card));
+        Ok(InitiativeCardOrder { order })
+    }
+}
+
+impl Dice {
+    /// Roll initiative and break ties with further rolls
+    ///
+    /// # Arguments
+    ///
+    /// * `characters` - (name, initiative modifier) for each character
+    /// * `sides` - Sides of the initiative and tie-break die
+    pub fn roll_initiative_tiebreak(
+        characters: &[(&str, i32)],
+        sides: u32,
+    ) -> DiceResult<InitiativeOrder> {
+        DiceContext::new().roll_initiative_tiebreak(characters, sides)
+    }
+
+    /// Deal each character a card and order them by it
+    ///
+    /// # Arguments
+    ///
+    /// * `deck` - Deck to deal from
+    /// * `characters` - Name of each character
+    pub fn roll_initiative_card_draw(
+        deck: &mut Deck,
+        characters: &[&str],
+    ) -> DiceResult<InitiativeCardOrder> {
+        DiceContext::new().roll_initiative_card_draw(deck, characters)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sorted_by_total() {
+        let mut ctx = DiceContext::with_seed(176);
+        let party = [("Fighter", 2), ("Rogue", 5), ("Wizard", 1), ("Cleric", 0)];
+        for _ in 0..100 {
+            let initiative = ctx.roll_initiative_tiebreak(&party, 20).unwrap();
+            assert_eq!(initiative.order.len(), 4);
+            assert!(initiative.order.windows(2).all(|w| (w[0].1, w[0].2) >= (w[1].1, w[1].2)));
+        }
+    }
+
+    #[test]
+    fn test_ties_are_broken() {
+        let mut ctx = DiceContext::with_seed(176);
+        // A d1 ties every round, so the tie stays in input order
+        let initiative = ctx.roll_initiative_tiebreak(&[("A", 3), ("B", 3)], 1).unwrap();
+        assert_eq!(initiative.order, [("A".to_string(), 4, 1), ("B".to_string(), 4, 1)]);
+
+        let mut tied_at_top = 0;
+        for _ in 0..200 {
+            let group = [("A", 0), ("B", 0), ("C", 0), ("D", 0)];
+            let initiative = ctx.roll_initiative_tiebreak(&group, 2).unwrap();
+            // Tie-break rolls only happen for characters that tied
+            for (_, total, tiebreak) in &initiative.order {
+                let tied = initiative.order.iter().filter(|(_, t, _)| t == total).count() > 1;
+                assert_eq!(*tiebreak != 0, tied);
+            }
+            tied_at_top += u32::from(initiative.order[0].2 != 0);
+        }
+        // Four d2 rolls almost always tie at the top
+        assert!(tied_at_top > 100, "{}", tied_at_top);
+    }
+
+    #[test]
+    fn test_duplicate_names() {
+        let mut ctx = DiceContext::with_seed(176);
+        let group = [("Goblin", 1), ("Hero", 4), ("Goblin", 1)];
+        let initiative = ctx.roll_initiative_tiebreak(&group, 20).unwrap();
+        let mut names: Vec<_> = initiative.order.iter().map(|(name, ..)| name.as_str()).collect();
+        names.sort_unstable();
+        assert_eq!(names, ["Goblin 1", "Goblin 2", "Hero"]);
+
+        let group = [("Goblin", 1), ("Goblin 2", 0), ("Goblin", 1), ("Goblin", 2)];
+        let initiative = ctx.roll_initiative_tiebreak(&group, 20).unwrap();
+        let mut names: Vec<_> = initiative.order.iter().map(|(name, ..)| name.as_str()).collect();
+        names.sort_unstable();
+        assert_eq!(names, ["Goblin 1", "Goblin 2", "Goblin 3", "Goblin 4"]);
+        // Numbers follow the order the characters were listed in
+        let modifier = |name: &str| initiative.order.iter().find(|(n, ..)| n == name).unwrap().1;
+        assert!(modifier("Goblin 4") >= 3);
+    }
+
+    #[test]
+    fn test_distinct_names() {
+        let names = distinct_names(&[("Orc", 0), ("Orc 1", 0), ("Orc", 0), ("Orc 1", 0)]);
+        // A repeated name is numbered too, so it doesn't hold its number back
+        assert_eq!(names, ["Orc 1", "Orc 1 1", "Orc 2", "Orc 1 2"]);
+        assert_eq!(distinct_names(&[("Hero", 0)]), ["Hero"]);
+    }
+
+    #[test]
+    fn test_edge_cases() {
+        let mut ctx = DiceContext::with_seed(176);
+        assert_eq!(ctx.roll_initiative_tiebreak(&[], 20), Ok(InitiativeOrder::default()));
+        assert_eq!(
+            ctx.roll_initiative_tiebreak(&[("A", 0)], 0),
+            Err(DiceError::InvalidSides(0))
+        );
+        assert_eq!(
+            ctx.roll_initiative_tiebreak(&[("A", i32::MAX)], 20),
+            Err(DiceError::Overflow)
+        );
+    }
+
+    #[test]
+    fn test_card_draw_order() {
+        let mut ctx = DiceContext::with_seed(188);
+        let mut deck = Deck::with_jokers();
+        deck.shuffle(ctx.rng());
+        let posse = ["Marshal", "Gambler", "Huckster", "Blessed", "Brave"];
+        let mut jokers = 0;
+        for _ in 0..40 {
+            let initiative = ctx.roll_initiative_card_draw(&mut deck, &posse).unwrap();
+            assert_eq!(initiative.order.len(), 5);
+            assert!(initiative.order.windows(2).all(|w| w[0].1 > w[1].1 || w[1].1.is_joker()));
+            let leading = initiative.order.iter().take_while(|(_, card)| card.is_joker()).count();
+            assert_eq!(initiative.jokers().len(), leading);
+            jokers += leading;
+        }
+        assert!(jokers > 0);
+    }
+
+    #[test]
+    fn test_card_draw_suits_break_ties() {
+        use crate::cards::Suit;
+
+        let mut ctx = DiceContext::with_seed(188);
+        // An unshuffled deck deals from the Spades end
+        let mut deck = Deck::new();
+        let initiative = ctx.roll_initiative_card_draw(&mut deck, &["A", "B"]).unwrap();
+        let ace = |suit| PlayingCard::Suited { rank: 14, suit };
+        assert_eq!(initiative.order[0].1, ace(Suit::Spades));
+        assert!(initiative.jokers().is_empty());
+        let card = |rank, suit| PlayingCard::Suited { rank, suit };
+        assert!(card(9, Suit::Spades) > card(9, Suit::Hearts));
+        assert!(card(9, Suit::Diamonds) > card(9, Suit::Clubs));
+    }
+
+    #[test]
+    fn test_card_draw_reshuffles() {
+        let mut ctx = DiceContext::with_seed(188);
+        let mut deck = Deck::new();
+        deck.auto_reshuffle = false;
+        let crowd: Vec<String> = (0..50).map(|i| format!("Extra {}", i)).collect();
+        let crowd: Vec<&str> = crowd.iter().map(String::as_str).collect();
+        ctx.roll_initiative_card_draw(&mut deck, &crowd).unwrap();
+        assert_eq!(deck.remaining(), 2);
+        // Only 2 cards left, so the discards go back in before dealing
+        ctx.roll_initiative_card_draw(&mut deck, &crowd[..3]).unwrap();
+        assert_eq!((deck.remaining(), deck.discarded().len()), (49, 3));
+
+        let crowd: Vec<&str> = vec!["Extra"; 53];
+        assert_eq!(
+            ctx.roll_initiative_card_draw(&mut Deck::new(), &crowd),
+            Err(DiceError::InvalidCount(53))
+        );
+        let empty = ctx.roll_initiative_card_draw(&mut deck, &[]).unwrap();
+        assert_eq!(empty, InitiativeCardOrder::default());
+        assert!(Dice::roll_initiative_card_draw(&mut Deck::new(), &["A"]).is_ok());
+    }
+}
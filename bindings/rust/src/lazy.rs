@@ -0,0 +1,115 @@
+//! Pending rolls
+//!
+//! A [`DiceRoll`] is notation checked up front but only rolled when its
+//! value is first asked for, so game logic can pass a roll along to code
+//! that may never need it.
+
+use crate::eval::RollResult;
+use crate::expression::DiceExpression;
+use crate::DiceResult;
+use rand::Rng;
+
+/// Parsed notation rolled on first use
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DiceRoll {
+    expr: DiceExpression,
+    result: Option<RollResult>,
+}
+
+impl DiceRoll {
+    /// Parse `notation` without rolling it
+    ///
+    /// # Arguments
+    ///
+    /// * `notation` - Dice notation like "3d6", "1d20+5", "4d6k3"
+    pub fn new(notation: &str) -> DiceResult<DiceRoll> {
+        Ok(DiceRoll::from(DiceExpression::parse(notation)?))
+    }
+
+    /// Expression this roll evaluates
+    pub fn expression(&self) -> &DiceExpression {
+        &self.expr
+    }
+
+    /// Total of the roll, rolling it the first time it is asked for
+    ///
+    /// Later calls return the same total without touching `rng`. A roll
+    /// that fails isn't cached, so the next call tries again.
+    pub fn value<R: Rng + ?Sized>(&mut self, rng: &mut R) -> DiceResult<i32> {
+        match &self.result {
+            Some(result) => Ok(result.total),
+            None => self.reroll(rng),
+        }
+    }
+
+    /// Roll again, replacing any cached total
+    ///
+    /// On failure the previous total is discarded.
+    pub fn reroll<R: Rng + ?Sized>(&mut self, rng: &mut R) -> DiceResult<i32> {
+        self.result = None;
+        let result = self.expr.roll(rng)?;
+        Ok(self.result.insert(result).total)
+    }
+
+    /// Whether the roll has been made
+    pub fn is_evaluated(&self) -> bool {
+        self.result.is_some()
+    }
+
+    /// Full result of the roll, if it has been made
+    pub fn result(&self) -> Option<&RollResult> {
+        self.result.as_ref()
+    }
+}
+
+impl From<DiceExpression> for DiceRoll {
+    fn from(expr: DiceExpression) -> Self {
+        DiceRoll { expr, result: None }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::DiceRng;
+    use crate::DiceError;
+
+    #[test]
+    fn test_value_is_cached() {
+        let mut rng = DiceRng::new(148);
+        let mut roll = DiceRoll::new("10d100").unwrap();
+        assert!(!roll.is_evaluated());
+        assert_eq!(roll.result(), None);
+
+        let first = roll.value(&mut rng).unwrap();
+        assert!(roll.is_evaluated());
+        assert_eq!(roll.result().unwrap().dice.len(), 10);
+        for _ in 0..10 {
+            assert_eq!(roll.value(&mut rng), Ok(first));
+        }
+
+        // reroll draws a fresh roll and caches it
+        let mut rerolls = (0..10).map(|_| roll.reroll(&mut rng).unwrap());
+        assert!(rerolls.any(|total| total != first));
+        let latest = roll.result().unwrap().total;
+        assert_eq!(roll.value(&mut rng), Ok(latest));
+    }
+
+    #[test]
+    fn test_new_validates() {
+        assert!(matches!(DiceRoll::new("4d"), Err(DiceError::ParseError { .. })));
+        assert_eq!(DiceRoll::new("3d6k2").unwrap().expression().to_notation(), "3d6k2");
+    }
+
+    #[test]
+    fn test_failed_roll_not_cached() {
+        let mut rng = DiceRng::new(148);
+        let mut roll = DiceRoll::new("1d6/0").unwrap();
+        assert_eq!(
+            roll.value(&mut rng),
+            Err(DiceError::InvalidNotation("division by zero".to_string()))
+        );
+        assert!(!roll.is_evaluated());
+    }
+}
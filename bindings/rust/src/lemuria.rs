@@ -0,0 +1,131 @@
+//! Barbarians of Lemuria checks
+//!
+//! Roll 2d6, add an ability and meet a target of 9 plus the difficulty.
+//! Doubles make the result memorable: spectacular on a success, a fumble
+//! on a failure.
+
+use crate::context::DiceContext;
+use crate::{Dice, DiceError, DiceResult};
+
+/// Target number of a check at difficulty 0
+const BASE_TARGET: i32 = 9;
+
+/// Outcome of a Barbarians of Lemuria check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BoLResult {
+    pub dice_values: [i32; 2],
+    /// Both dice plus the ability
+    pub total: i32,
+    /// 9 plus the difficulty
+    pub target: i32,
+    pub success: bool,
+    /// `total - target`
+    pub margin: i32,
+    /// Doubles on a success
+    pub spectacular: bool,
+    /// Doubles on a failure
+    pub fumble: bool,
+}
+
+impl BoLResult {
+    fn graded(dice_values: [i32; 2], ability: u32, difficulty: u32) -> DiceResult<BoLResult> {
+        let ability = i32::try_from(ability).map_err(|_| DiceError::Overflow)?;
+        let total = (dice_values[0] + dice_values[1])
+            .checked_add(ability)
+            .ok_or(DiceError::Overflow)?;
+        let target = i32::try_from(difficulty)
+            .ok()
+            .and_then(|difficulty| difficulty.checked_add(BASE_TARGET))
+            .ok_or(DiceError::Overflow)?;
+        let success = total >= target;
+        let doubles = dice_values[0] == dice_values[1];
+        Ok(BoLResult {
+            dice_values,
+            total,
+            target,
+            success,
+            margin: total - target,
+            spectacular: doubles && success,
+            fumble: doubles && !success,
+        })
+    }
+}
+
+impl DiceContext {
+    /// Roll a Barbarians of Lemuria check
+    ///
+    /// # Arguments
+    ///
+    /// * `combat_ability` - Ability added to the 2d6
+    /// * `difficulty` - Added to the base target of 9
+    pub fn roll_barbarians_of_lemuria(
+        &mut self,
+        combat_ability: u32,
+        difficulty: u32,
+    ) -> DiceResult<BoLResult> {
+        let dice = self.roll_pool(2, 6)?;
+        BoLResult::graded([dice[0], dice[1]], combat_ability, difficulty)
+    }
+}
+
+impl Dice {
+    /// Roll a Barbarians of Lemuria check
+    ///
+    /// # Arguments
+    ///
+    /// * `combat_ability` - Ability added to the 2d6
+    /// * `difficulty` - Added to the base target of 9
+    pub fn roll_barbarians_of_lemuria(
+        combat_ability: u32,
+        difficulty: u32,
+    ) -> DiceResult<BoLResult> {
+        DiceContext::new().roll_barbarians_of_lemuria(combat_ability, difficulty)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_graded_checks() {
+        let result = BoLResult::graded([3, 4], 2, 0).unwrap();
+        assert_eq!((result.total, result.target, result.margin), (9, 9, 0));
+        assert!(result.success && !result.spectacular && !result.fumble);
+        let result = BoLResult::graded([2, 5], 1, 2).unwrap();
+        assert_eq!((result.total, result.target, result.margin), (8, 11, -3));
+        assert!(!result.success);
+    }
+
+    #[test]
+    fn test_doubles() {
+        let result = BoLResult::graded([5, 5], 0, 0).unwrap();
+        assert!(result.success && result.spectacular && !result.fumble);
+        let result = BoLResult::graded([2, 2], 1, 0).unwrap();
+        assert!(!result.success && result.fumble && !result.spectacular);
+        // Doubles only count once the outcome is known
+        let result = BoLResult::graded([4, 4], 1, 0).unwrap();
+        assert!(result.spectacular);
+        assert!(BoLResult::graded([6, 6], 0, 4).unwrap().fumble);
+    }
+
+    #[test]
+    fn test_roll_barbarians_of_lemuria() {
+        let mut ctx = DiceContext::with_seed(191);
+        let mut doubles = 0;
+        for _ in 0..600 {
+            let result = ctx.roll_barbarians_of_lemuria(2, 1).unwrap();
+            let [a, b] = result.dice_values;
+            assert!((1..=6).contains(&a) && (1..=6).contains(&b));
+            assert_eq!((result.total, result.target), (a + b + 2, 10));
+            assert_eq!(result.spectacular || result.fumble, a == b);
+            doubles += u32::from(a == b);
+        }
+        // One roll in six
+        assert!((60..140).contains(&doubles), "{}", doubles);
+        assert_eq!(ctx.roll_barbarians_of_lemuria(u32::MAX, 0), Err(DiceError::Overflow));
+        assert_eq!(ctx.roll_barbarians_of_lemuria(0, i32::MAX as u32), Err(DiceError::Overflow));
+        assert!(Dice::roll_barbarians_of_lemuria(1, 0).is_ok());
+    }
+}
@@ -1,6 +1,120 @@
 //! Rust bindings for the Roll dice library
 //! 
 //! This library provides safe Rust bindings for the universal dice rolling library.
+//!
+//! Alongside the FFI wrappers it includes a pure-Rust expression engine
+//! ([`DiceExpression`], [`DiceContext`]) driven by a seedable RNG, so rolls
+//! can be reproduced exactly.
+
+pub mod advantage;
+pub mod anydice;
+pub mod attack;
+pub mod bounds;
+pub mod cards;
+pub mod clamp;
+pub mod coin;
+pub mod complexity;
+pub mod config;
+pub mod context;
+pub mod continuous;
+pub mod cortex;
+pub mod cypher;
+pub mod die;
+pub mod display;
+pub mod distribution;
+pub mod drama;
+pub mod encounter;
+pub mod estimate;
+pub mod eval;
+pub mod explode;
+pub mod expression;
+pub mod fate;
+pub mod fitd;
+pub mod fraction;
+pub mod genesys;
+pub mod history;
+pub mod initiative;
+pub mod lazy;
+pub mod lemuria;
+pub mod margin;
+pub mod nco;
+pub mod notation;
+pub mod npc;
+pub mod opposed;
+mod parser;
+pub mod pool;
+pub mod recharge;
+pub mod reroll;
+pub mod rng;
+pub mod roll_context;
+pub mod sequence;
+pub mod simplify;
+pub mod spec;
+pub mod spire;
+pub mod state;
+#[cfg(feature = "server")]
+pub mod server;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod stats;
+pub mod stress;
+pub mod success;
+pub mod table;
+#[cfg(feature = "terminal")]
+pub mod terminal;
+pub mod trace;
+pub mod troika;
+pub mod unary;
+pub mod wide;
+pub mod wrath;
+pub mod year_zero;
+
+pub use advantage::{AdvantageResult, BestOfResult, GroupBestResult, KeepMode, ModifiedResult};
+pub use attack::AttackResult5e;
+pub use cards::{Deck, PlayingCard, Suit, SuitModifiers};
+pub use coin::CoinResult;
+pub use config::RollConfig;
+pub use context::DiceContext;
+pub use cypher::CypherResult;
+pub use die::Die;
+pub use display::{DiceExpressionFormatter, DisplayStyle, OutputFormat, RollDisplay};
+pub use drama::DramaResult;
+pub use encounter::EncounterTable;
+pub use estimate::ExpectedRollCount;
+pub use eval::{DieRoll, RollResult};
+pub use expression::{DiceExpression, DicePredicate};
+pub use fate::{FatePointPool, FateResult};
+pub use fitd::{FitDOutcome, FitDResult};
+pub use fraction::Fraction;
+pub use genesys::{GenesysOutcome, GenesysResult, SymbolDie, SymbolResult};
+pub use history::{RollHistory, RollRecord};
+pub use initiative::{InitiativeCardOrder, InitiativeOrder};
+pub use lazy::DiceRoll;
+pub use lemuria::BoLResult;
+pub use margin::MarginResult;
+pub use nco::{NcoOutcome, NcoResult};
+pub use npc::{NpcGenConfig, NpcGenerator, NpcStats};
+pub use opposed::{
+    OpposedResult, OpposedTieredResult, OpposedWinner, PoolOpposedResult, SuccessLevel,
+    TieredConfig,
+};
+pub use parser::DiceParser;
+pub use pool::{
+    DicePool, LabeledRoll, LabeledRollSet, NamedDicePool, PoolModifier, SortOrder, SortedPoolResult,
+};
+pub use recharge::RechargeDie;
+pub use rng::{DiceRng, RngWrapper};
+pub use roll_context::{DiceFunction, RollContext};
+pub use spec::DiceSpec;
+pub use spire::{SpireConfig, SpireOutcome, SpireResult};
+pub use stats::{CompareResult, FairnessReport};
+pub use stress::StressResult;
+pub use success::{Comparison, SuccessCount, SuccessWithBotch, WoDOutcome};
+pub use table::{D66Table, ModifierTable, WoundTable};
+pub use trace::{EvalTrace, TraceEntry};
+pub use troika::{TroikaInitiative, TroikaToken};
+pub use wrath::WnGResult;
+pub use year_zero::RumbleResult;
 
 use libc::{c_char, c_int, c_uint};
 use std::ffi::{CStr, CString};
@@ -18,11 +132,30 @@ extern "C" {
 
 /// Error type for dice operations
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum DiceError {
     InvalidSides(i32),
     InvalidCount(i32),
     InvalidNotation(String),
+    /// Notation is malformed at a specific byte offset
+    ParseError { position: usize, message: String },
     NullPointer,
+    /// Malformed serialized data (e.g. an exported roll history)
+    InvalidFormat(String),
+    /// Reading or writing external storage failed
+    Io(String),
+    /// A result does not fit in an `i32`
+    Overflow,
+    /// Notation references a variable with no binding
+    UnboundVariable(String),
+    /// The operation isn't available for this expression
+    UnsupportedFeature(String),
+    /// A value fell outside the range it was required to be in
+    InvalidRange { min: i32, max: i32, actual: i32 },
+    /// More rolls were requested than `RollConfig::max_simulation_iterations`
+    RollCountLimit { requested: u32, limit: u32 },
+    /// A probability wasn't strictly between 0 and 1
+    InvalidProbability(f64),
 }
 
 impl fmt::Display for DiceError {
@@ -31,13 +164,36 @@ impl fmt::Display for DiceError {
             DiceError::InvalidSides(sides) => write!(f, "Invalid number of sides: {}", sides),
             DiceError::InvalidCount(count) => write!(f, "Invalid count: {}", count),
             DiceError::InvalidNotation(notation) => write!(f, "Invalid dice notation: {}", notation),
+            DiceError::ParseError { position, message } => {
+                write!(f, "Parse error at position {}: {}", position, message)
+            }
             DiceError::NullPointer => write!(f, "Null pointer error"),
+            DiceError::InvalidFormat(message) => write!(f, "Invalid format: {}", message),
+            DiceError::Io(message) => write!(f, "I/O error: {}", message),
+            DiceError::Overflow => write!(f, "Arithmetic overflow"),
+            DiceError::UnboundVariable(name) => write!(f, "Unbound variable: {}", name),
+            DiceError::UnsupportedFeature(message) => write!(f, "Unsupported feature: {}", message),
+            DiceError::InvalidRange { min, max, actual } => {
+                write!(f, "result {} is outside expected range [{}, {}]", actual, min, max)
+            }
+            DiceError::RollCountLimit { requested, limit } => {
+                write!(f, "{} rolls requested, limit is {}", requested, limit)
+            }
+            DiceError::InvalidProbability(p) => {
+                write!(f, "probability {} is outside (0, 1)", p)
+            }
         }
     }
 }
 
 impl std::error::Error for DiceError {}
 
+impl From<std::io::Error> for DiceError {
+    fn from(err: std::io::Error) -> Self {
+        DiceError::Io(err.to_string())
+    }
+}
+
 /// Result type for dice operations
 pub type DiceResult<T> = Result<T, DiceError>;
 
@@ -183,9 +339,22 @@ impl Dice {
     }
 }
 
-/// Convenience functions
+// Convenience functions. Apart from `version`, these roll with the global
+// default `DiceContext` rather than the C library.
+
+/// Seed the C library, and reseed the global [`DiceContext`] keeping its
+/// limits
+///
+/// `None` seeds both from the clock or system entropy.
 pub fn init(seed: Option<u32>) {
     Dice::init(seed);
+    DiceContext::with_global(|ctx| {
+        let fresh = match seed {
+            Some(seed) => DiceContext::with_seed(seed as u64),
+            None => DiceContext::new(),
+        };
+        *ctx = fresh.with_config(*ctx.config());
+    });
 }
 
 pub fn version() -> String {
@@ -193,22 +362,23 @@ pub fn version() -> String {
 }
 
 pub fn roll(sides: i32) -> DiceResult<i32> {
-    Dice::roll(sides)
+    DiceContext::with_global(|ctx| ctx.roll(sides))
 }
 
 pub fn roll_multiple(count: i32, sides: i32) -> DiceResult<i32> {
-    Dice::roll_multiple(count, sides)
+    roll_individual(count, sides).map(|(sum, _)| sum)
 }
 
 pub fn roll_individual(count: i32, sides: i32) -> DiceResult<(i32, Vec<i32>)> {
-    Dice::roll_individual(count, sides)
+    DiceContext::with_global(|ctx| ctx.roll_individual(count, sides))
 }
 
 pub fn roll_notation(notation: &str) -> DiceResult<i32> {
-    Dice::roll_notation(notation)
+    DiceContext::with_global(|ctx| Ok(ctx.roll_notation(notation)?.total))
 }
 
 #[cfg(test)]
+#[allow(clippy::manual_range_contains)]
 mod tests {
     use super::*;
 
@@ -266,7 +436,7 @@ mod tests {
         assert!(sum >= 3 && sum <= 18);
         assert_eq!(individual.len(), 3);
         assert!(individual.iter().all(|&roll| roll >= 1 && roll <= 6));
-        assert_eq!(sum, individual.iter().sum());
+        assert_eq!(sum, individual.iter().sum::<i32>());
         
         // Test single die
         let (sum, individual) = Dice::roll_individual(1, 20).unwrap();
@@ -0,0 +1,148 @@
+//! Margin of success
+//!
+//! Many systems care how far a roll beat its target, not just whether it
+//! did. WFRP counts a degree of success for every full 5 points of margin;
+//! the size of a degree comes from [`RollConfig::degree_threshold`].
+
+use crate::config::RollConfig;
+use crate::context::DiceContext;
+use crate::opposed::SuccessLevel;
+use crate::{Dice, DiceError, DiceResult};
+
+/// Default margin worth one degree of success
+pub const DEGREE_THRESHOLD: u32 = 5;
+
+/// Outcome of a roll against a target number
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MarginResult {
+    pub roll: i32,
+    pub target: i32,
+    /// `roll - target`
+    pub margin: i32,
+    /// The roll met the target
+    pub success: bool,
+    /// Full degree thresholds in the margin; 0 on a failure
+    pub degrees: u32,
+    /// Critical at a margin of two degrees either way
+    pub level: SuccessLevel,
+}
+
+impl MarginResult {
+    fn graded(roll: i32, target: i32, config: &RollConfig) -> DiceResult<MarginResult> {
+        let threshold = config.degree_threshold;
+        if threshold == 0 {
+            return Err(DiceError::InvalidRange {
+                min: 1,
+                max: i32::MAX,
+                actual: 0,
+            });
+        }
+        let margin = roll.checked_sub(target).ok_or(DiceError::Overflow)?;
+        let critical = 2 * threshold as i64;
+        let level = match margin as i64 {
+            m if m >= critical => SuccessLevel::CriticalSuccess,
+            m if m >= 0 => SuccessLevel::Success,
+            m if m <= -critical => SuccessLevel::CriticalFailure,
+            _ => SuccessLevel::Failure,
+        };
+        Ok(MarginResult {
+            roll,
+            target,
+            margin,
+            success: margin >= 0,
+            degrees: margin.max(0) as u32 / threshold,
+            level,
+        })
+    }
+}
+
+impl DiceContext {
+    /// Roll notation against a target number and measure the margin
+    ///
+    /// A degree threshold of 0 fails with [`DiceError::InvalidRange`].
+    ///
+    /// # Arguments
+    ///
+    /// * `attacker_notation` - Dice notation to roll, e.g. "1d100"
+    /// * `target_number` - Total the roll must meet
+    pub fn roll_margin_of_success(
+        &mut self,
+        attacker_notation: &str,
+        target_number: i32,
+    ) -> DiceResult<MarginResult> {
+        let roll = self.roll_notation(attacker_notation)?.total;
+        MarginResult::graded(roll, target_number, self.config())
+    }
+}
+
+impl Dice {
+    /// Roll notation against a target number and measure the margin
+    ///
+    /// # Arguments
+    ///
+    /// * `attacker_notation` - Dice notation to roll, e.g. "1d100"
+    /// * `target_number` - Total the roll must meet
+    pub fn roll_margin_of_success(
+        attacker_notation: &str,
+        target_number: i32,
+    ) -> DiceResult<MarginResult> {
+        DiceContext::new().roll_margin_of_success(attacker_notation, target_number)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn graded(roll: i32, target: i32) -> MarginResult {
+        MarginResult::graded(roll, target, &RollConfig::default()).unwrap()
+    }
+
+    #[test]
+    fn test_levels() {
+        let critical = graded(62, 50);
+        assert_eq!((critical.margin, critical.degrees), (12, 2));
+        assert_eq!(critical.level, SuccessLevel::CriticalSuccess);
+        assert!(critical.success);
+
+        let success = graded(54, 50);
+        assert_eq!((success.margin, success.degrees, success.success), (4, 0, true));
+        assert_eq!(success.level, SuccessLevel::Success);
+        assert_eq!(graded(50, 50).level, SuccessLevel::Success);
+
+        let failure = graded(41, 50);
+        assert_eq!((failure.margin, failure.degrees, failure.success), (-9, 0, false));
+        assert_eq!(failure.level, SuccessLevel::Failure);
+
+        let fumble = graded(40, 50);
+        assert_eq!((fumble.margin, fumble.degrees, fumble.success), (-10, 0, false));
+        assert_eq!(fumble.level, SuccessLevel::CriticalFailure);
+    }
+
+    #[test]
+    fn test_degree_threshold() {
+        let config = RollConfig::default().degree_threshold(10);
+        let result = MarginResult::graded(75, 40, &config).unwrap();
+        assert_eq!((result.degrees, result.level), (3, SuccessLevel::CriticalSuccess));
+        assert_eq!(MarginResult::graded(55, 40, &config).unwrap().level, SuccessLevel::Success);
+        let config = config.degree_threshold(0);
+        assert!(matches!(
+            MarginResult::graded(1, 1, &config),
+            Err(DiceError::InvalidRange { actual: 0, .. })
+        ));
+    }
+
+    #[test]
+    fn test_roll_margin_of_success() {
+        let mut ctx = DiceContext::with_seed(167);
+        for _ in 0..200 {
+            let result = ctx.roll_margin_of_success("1d100", 50).unwrap();
+            assert!((1..=100).contains(&result.roll));
+            assert_eq!(result.margin, result.roll - 50);
+            assert_eq!(result.degrees, result.margin.max(0) as u32 / 5);
+        }
+        assert!(ctx.roll_margin_of_success("1d6", i32::MIN).is_err());
+        assert!(ctx.roll_margin_of_success("bad", 10).is_err());
+    }
+}
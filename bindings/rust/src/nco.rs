@@ -0,0 +1,141 @@
+//! Neon City Overdrive rolls
+//!
+//! Action dice count a success on 4 or more. The GM rolls danger dice
+//! alongside them, and every danger 6 cancels one success.
+
+use crate::context::DiceContext;
+use crate::{Dice, DiceResult};
+
+/// Lowest action die that counts as a success
+const SUCCESS_MIN: i32 = 4;
+
+/// Danger die face that cancels a success
+const CANCEL_FACE: i32 = 6;
+
+/// How a Neon City Overdrive roll resolves
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum NcoOutcome {
+    /// Two or more successes survive, carrying how many
+    StrongHit(u32),
+    /// Exactly one success survives
+    WeakHit,
+    /// Every success was missed or cancelled
+    Miss,
+}
+
+/// Outcome of a Neon City Overdrive roll
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NcoResult {
+    pub action_values: Vec<i32>,
+    pub danger_values: Vec<i32>,
+    /// Action dice showing 4 or more
+    pub raw_successes: u32,
+    /// Successes cancelled by danger 6s
+    pub cancelled: u32,
+    pub net_successes: u32,
+    pub outcome: NcoOutcome,
+}
+
+impl NcoResult {
+    fn from_values(action_values: Vec<i32>, danger_values: Vec<i32>) -> NcoResult {
+        let raw_successes = action_values.iter().filter(|&&v| v >= SUCCESS_MIN).count() as u32;
+        let danger_sixes = danger_values.iter().filter(|&&v| v == CANCEL_FACE).count() as u32;
+        let cancelled = raw_successes.min(danger_sixes);
+        let net_successes = raw_successes - cancelled;
+        let outcome = match net_successes {
+            0 => NcoOutcome::Miss,
+            1 => NcoOutcome::WeakHit,
+            n => NcoOutcome::StrongHit(n),
+        };
+        NcoResult {
+            action_values,
+            danger_values,
+            raw_successes,
+            cancelled,
+            net_successes,
+            outcome,
+        }
+    }
+}
+
+impl DiceContext {
+    /// Roll a Neon City Overdrive action against danger
+    ///
+    /// An empty action pool fails with
+    /// [`DiceError::InvalidCount`](crate::DiceError::InvalidCount).
+    ///
+    /// # Arguments
+    ///
+    /// * `action` - Number of action d6s
+    /// * `danger` - Number of danger d6s; 0 rolls none
+    pub fn roll_neon_city_overdrive(&mut self, action: u32, danger: u32) -> DiceResult<NcoResult> {
+        let action_values = self.roll_pool(action, 6)?;
+        let danger_values = if danger == 0 {
+            Vec::new()
+        } else {
+            self.roll_pool(danger, 6)?
+        };
+        Ok(NcoResult::from_values(action_values, danger_values))
+    }
+}
+
+impl Dice {
+    /// Roll a Neon City Overdrive action against danger
+    ///
+    /// # Arguments
+    ///
+    /// * `action` - Number of action d6s
+    /// * `danger` - Number of danger d6s; 0 rolls none
+    pub fn roll_neon_city_overdrive(action: u32, danger: u32) -> DiceResult<NcoResult> {
+        DiceContext::new().roll_neon_city_overdrive(action, danger)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DiceError;
+
+    #[test]
+    fn test_outcomes() {
+        let result = NcoResult::from_values(vec![6, 4, 5, 2], vec![3]);
+        assert_eq!((result.raw_successes, result.cancelled, result.net_successes), (3, 0, 3));
+        assert_eq!(result.outcome, NcoOutcome::StrongHit(3));
+        let result = NcoResult::from_values(vec![4, 3], vec![]);
+        assert_eq!(result.outcome, NcoOutcome::WeakHit);
+        let result = NcoResult::from_values(vec![1, 2, 3], vec![1]);
+        assert_eq!((result.raw_successes, result.outcome), (0, NcoOutcome::Miss));
+    }
+
+    #[test]
+    fn test_danger_cancels() {
+        let result = NcoResult::from_values(vec![6, 5, 4], vec![6, 1, 6]);
+        assert_eq!((result.raw_successes, result.cancelled, result.net_successes), (3, 2, 1));
+        assert_eq!(result.outcome, NcoOutcome::WeakHit);
+        // More danger sixes than successes cancel only what there is
+        let result = NcoResult::from_values(vec![4, 1], vec![6, 6, 6]);
+        assert_eq!((result.cancelled, result.net_successes), (1, 0));
+        assert_eq!(result.outcome, NcoOutcome::Miss);
+        // A 6 on an action die isn't cancelled by a danger 5
+        let result = NcoResult::from_values(vec![6, 6], vec![5, 5]);
+        assert_eq!(result.outcome, NcoOutcome::StrongHit(2));
+    }
+
+    #[test]
+    fn test_roll_neon_city_overdrive() {
+        let mut ctx = DiceContext::with_seed(193);
+        for _ in 0..200 {
+            let result = ctx.roll_neon_city_overdrive(4, 2).unwrap();
+            assert_eq!((result.action_values.len(), result.danger_values.len()), (4, 2));
+            assert!(result.cancelled <= 2);
+            assert_eq!(result.net_successes, result.raw_successes - result.cancelled);
+        }
+        let result = ctx.roll_neon_city_overdrive(3, 0).unwrap();
+        assert!(result.danger_values.is_empty());
+        assert_eq!(result.cancelled, 0);
+        assert_eq!(ctx.roll_neon_city_overdrive(0, 2), Err(DiceError::InvalidCount(0)));
+        assert!(Dice::roll_neon_city_overdrive(2, 1).is_ok());
+    }
+}
@@ -0,0 +1,442 @@
+//! Canonical notation
+//!
+//! Every expression renders to one canonical string: selections use their
+//! long form (`kh` is written `k`, `d` is written `dl`) and parentheses
+//! appear only where precedence needs them. Parsing the canonical string
+//! gives back the same tree, so equality and hashing are defined on it.
+//! Converting an expression to and from a `String` goes through the same
+//! notation.
+
+use crate::expression::{DiceExpression, DicePredicate};
+use crate::{DiceError, DiceResult};
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+
+impl DiceExpression {
+    /// Canonical notation for this expression
+    ///
+    /// `DiceExpression::parse("4d6kh3")` renders as `4d6k3` and
+    /// `(2*3d6)+4` as `2*3d6+4`.
+    pub fn to_notation(&self) -> String {
+        let mut out = String::new();
+        write_expr(self, &mut out);
+        out
+    }
+
+    /// Canonical notation with drops written as keeps
+    ///
+    /// `4d6l1` renders as `4d6k3`; see
+    /// [`normalize_keep_drop`](Self::normalize_keep_drop), whose errors this
+    /// returns.
+    pub fn to_canonical_notation(&self) -> DiceResult<String> {
+        Ok(self.normalize_keep_drop()?.to_notation())
+    }
+}
+
+/// Binding strength of a node; higher binds tighter
+pub(crate) fn precedence(expr: &DiceExpression) -> u8 {
+    match expr {
+        DiceExpression::Add(..) | DiceExpression::Sub(..) => 1,
+        DiceExpression::Mul(..) | DiceExpression::Div(..) | DiceExpression::FloorDiv(..) => 2,
+        _ => 3,
+    }
+}
+
+fn write_expr(expr: &DiceExpression, out: &mut String) {
+    use DiceExpression::*;
+
+    match expr {
+        Constant(value) => out.push_str(&value.to_string()),
+        Variable(name) => out.push_str(name),
+        Call(name, args) => {
+            out.push_str(name);
+            out.push('(');
+            for (i, arg) in args.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_expr(arg, out);
+            }
+            out.push(')');
+        }
+        Dice(count, sides) => out.push_str(&format!("{}d{}", count, sides)),
+        DynamicDice(count, sides) => {
+            write_operand(count, !matches!(**count, Variable(_)), out);
+            out.push_str(&format!("d{}", sides));
+        }
+        Explode(inner, threshold) => {
+            write_expr(inner, out);
+            match **inner {
+                Dice(_, sides) | DynamicDice(_, sides) if sides == *threshold => out.push('!'),
+                _ => out.push_str(&format!("!>{}", threshold)),
+            }
+        }
+        ExplodeOn(inner, faces) => {
+            write_expr(inner, out);
+            let faces: Vec<String> = faces.iter().map(u32::to_string).collect();
+            out.push_str(&format!("!{{{}}}", faces.join(",")));
+        }
+        KeepHighest(inner, n) => write_selection(inner, "k", *n, out),
+        KeepLowest(inner, n) => write_selection(inner, "kl", *n, out),
+        DropHighest(inner, n) => write_selection(inner, "dh", *n, out),
+        DropLowest(inner, n) => write_selection(inner, "dl", *n, out),
+        CountSuccesses(inner, target) => write_selection(inner, ">", *target, out),
+        CountSuccessesWithBotch(inner, target, botch) => {
+            write_selection(inner, ">", *target, out);
+            out.push_str(&format!("b{}", botch));
+        }
+        // Only a dice term takes a clamp suffix; anything else is min/max of a constant
+        FloorClamp(inner, minimum) if *minimum >= 1 && takes_clamp(inner) => {
+            write_expr(inner, out);
+            out.push_str(&format!("min{}", minimum));
+        }
+        CeilClamp(inner, maximum) if *maximum >= 1 && takes_clamp(inner) => {
+            write_expr(inner, out);
+            out.push_str(&format!("max{}", maximum));
+        }
+        FloorClamp(inner, limit) | CeilClamp(inner, limit) => {
+            out.push_str(if matches!(expr, FloorClamp(..)) { "max(" } else { "min(" });
+            write_expr(inner, out);
+            out.push_str(&format!(",{})", limit));
+        }
+        Conditional {
+            condition,
+            predicate,
+            then_expr,
+            else_expr,
+        } => {
+            out.push('(');
+            // `pool>N` would read back as a success count
+            let ambiguous =
+                condition.is_pool() && matches!(predicate, DicePredicate::GreaterThan(_));
+            write_operand(condition, ambiguous, out);
+            out.push_str(predicate.symbol());
+            out.push_str(&predicate.operand().to_string());
+            out.push_str(")?");
+            write_operand(then_expr, precedence(then_expr) < 3, out);
+            out.push(':');
+            write_operand(else_expr, precedence(else_expr) < 3, out);
+        }
+        Negate(inner) => {
+            out.push('-');
+            write_operand(inner, precedence(inner) < 3, out);
+        }
+        AbsoluteValue(inner) => {
+            out.push('|');
+            write_expr(inner, out);
+            out.push('|');
+        }
+        FloorDiv(inner, divisor) => {
+            write_operand(inner, precedence(inner) < 2, out);
+            out.push('/');
+            out.push_str(&divisor.to_string());
+        }
+        // Rounding up is rounding the negation down: ceil(x/n) = -floor(-x/n)
+        CeilDiv(inner, divisor) => {
+            out.push_str("-(-");
+            write_operand(inner, precedence(inner) < 3, out);
+            out.push('/');
+            out.push_str(&divisor.to_string());
+            out.push(')');
+        }
+        Min(left, right) | Max(left, right) => {
+            out.push_str(if matches!(expr, Min(..)) { "min(" } else { "max(" });
+            write_expr(left, out);
+            out.push(',');
+            write_expr(right, out);
+            out.push(')');
+        }
+        Add(left, right) => write_binary(expr, left, "+", right, out),
+        Sub(left, right) => write_binary(expr, left, "-", right, out),
+        Mul(left, right) => write_binary(expr, left, "*", right, out),
+        // A bare number after `/` would parse back as rounding down
+        Div(left, right) if matches!(**right, Constant(_)) => {
+            write_operand(left, precedence(left) < 2, out);
+            out.push('/');
+            write_operand(right, true, out);
+        }
+        Div(left, right) => write_binary(expr, left, "/", right, out),
+    }
+}
+
+/// Whether `expr` is a dice term that notation can follow with `minN` or `maxN`
+fn takes_clamp(expr: &DiceExpression) -> bool {
+    match expr {
+        DiceExpression::CountSuccesses(inner, _)
+        | DiceExpression::CountSuccessesWithBotch(inner, ..) => inner.is_pool(),
+        DiceExpression::FloorClamp(inner, limit) | DiceExpression::CeilClamp(inner, limit) => {
+            *limit >= 1 && takes_clamp(inner)
+        }
+        expr => expr.is_pool(),
+    }
+}
+
+fn write_selection(inner: &DiceExpression, suffix: &str, n: u32, out: &mut String) {
+    write_operand(inner, !inner.is_pool(), out);
+    out.push_str(suffix);
+    out.push_str(&n.to_string());
+}
+
+/// Binary operators are left associative, so a right operand at the same
+/// level keeps its parentheses
+fn write_binary(
+    expr: &DiceExpression,
+    left: &DiceExpression,
+    op: &str,
+    right: &DiceExpression,
+    out: &mut String,
+) {
+    let level = precedence(expr);
+    write_operand(left, precedence(left) < level, out);
+    out.push_str(op);
+    write_operand(right, precedence(right) <= level, out);
+}
+
+fn write_operand(expr: &DiceExpression, parenthesize: bool, out: &mut String) {
+    if parenthesize {
+        out.push('(');
+        write_expr(expr, out);
+        out.push(')');
+    } else {
+        write_expr(expr, out);
+    }
+}
+
+/// Expressions are equal when their simplified canonical notation matches
+impl PartialEq for DiceExpression {
+    fn eq(&self, other: &Self) -> bool {
+        self.simplify().to_notation() == other.simplify().to_notation()
+    }
+}
+
+impl Eq for DiceExpression {}
+
+impl Hash for DiceExpression {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.simplify().to_notation().hash(state);
+    }
+}
+
+impl FromStr for DiceExpression {
+    type Err = DiceError;
+
+    fn from_str(notation: &str) -> DiceResult<DiceExpression> {
+        DiceExpression::parse(notation)
+    }
+}
+
+impl TryFrom<String> for DiceExpression {
+    type Error = DiceError;
+
+    fn try_from(notation: String) -> DiceResult<DiceExpression> {
+        DiceExpression::parse(&notation)
+    }
+}
+
+impl From<DiceExpression> for String {
+    fn from(expr: DiceExpression) -> String {
+        expr.to_notation()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser;
+    use std::collections::hash_map::DefaultHasher;
+    use std::collections::HashMap;
+
+    fn parse(notation: &str) -> DiceExpression {
+        DiceExpression::parse(notation).unwrap()
+    }
+
+    fn hash_of(expr: &DiceExpression) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        expr.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn test_to_notation() {
+        for (notation, canonical) in [
+            ("3d6", "3d6"),
+            ("4d6kh3", "4d6k3"),
+            ("4d6h3", "4d6k3"),
+            ("4d6d1", "4d6dl1"),
+            ("4d6l1", "4d6dl1"),
+            ("2d20kl1", "2d20kl1"),
+            ("8d6>4", "8d6>4"),
+            ("3d6!", "3d6!"),
+            ("3d6!>5", "3d6!>5"),
+            ("d20 + 5", "1d20+5"),
+            ("(2*3d6)+4", "2*3d6+4"),
+            ("2*(3d6+4)", "2*(3d6+4)"),
+            ("10-(2-3)", "10-(2-3)"),
+            ("(10-2)-3", "10-2-3"),
+            ("-(1d6+2)", "-(1d6+2)"),
+            ("-1d6*2", "-1d6*2"),
+            ("|2d6-7|", "|2d6-7|"),
+            ("3--5", "3--5"),
+            ("Nd6+MOD", "Nd6+MOD"),
+            ("POOLd10!k3", "POOLd10!k3"),
+            ("d%", "1d100"),
+            ("DAMAGE(6, 4) + 1", "DAMAGE(6,4)+1"),
+            ("F()", "F()"),
+            ("(d20>=15)?(2d6+5):(1d4)", "(1d20>=15)?(2d6+5):1d4"),
+            ("(1d6)?1:0", "(1d6!=0)?1:0"),
+            ("((1d20)>15)?1:0", "((1d20)>15)?1:0"),
+            ("(1d6+1 > 3)?-1d4:2*3", "(1d6+1>3)?-1d4:2*3"),
+            ("(1d6>=2)?(2*3):((1d4)?1:2)", "(1d6>=2)?(2*3):(1d4!=0)?1:2"),
+            ("3d%+5", "3d100+5"),
+            ("min(2d6, 1d8)", "min(2d6,1d8)"),
+            ("(2d6)/2", "2d6/2"),
+            ("(1d6+1)/2", "(1d6+1)/2"),
+            ("3d6/-2/3", "3d6/-2/3"),
+            ("12/(3d6/2)", "12/(3d6/2)"),
+            ("12/1d4/2", "12/1d4/2"),
+            ("-max(1d4+1, 3)*2", "-max(1d4+1,3)*2"),
+        ] {
+            assert_eq!(parse(notation).to_notation(), canonical, "{}", notation);
+        }
+    }
+
+    #[test]
+    fn test_canonical_round_trip() {
+        for notation in [
+            "4d6k3+2", "(1d6+2)*3", "1d20-(1d4-1)", "|1d6-4|*-2", "24/(4/2)", "6d6!>5k3>4",
+            "2*((1d20)>15)?1d6:1d4*2", "1d6min2max5", "8d6>4max3",
+        ] {
+            let expr = parser::parse(notation).unwrap();
+            let reparsed = parser::parse(&expr.to_notation()).unwrap();
+            assert_eq!(format!("{:?}", reparsed), format!("{:?}", expr), "{}", notation);
+        }
+    }
+
+    #[test]
+    fn test_canonical_notation_keeps() {
+        assert_eq!(parse("4d6l1+2").to_canonical_notation().unwrap(), "4d6k3+2");
+        assert_eq!(parse("2d20dh1").to_canonical_notation().unwrap(), "2d20kl1");
+        assert_eq!(parse("4d6l1").to_notation(), "4d6dl1");
+        assert_eq!(parse("2d6dl2").to_canonical_notation(), Err(DiceError::InvalidCount(0)));
+    }
+
+    #[test]
+    fn test_string_conversions() {
+        let expr: DiceExpression = "4d6kh3 + 5".parse().unwrap();
+        assert_eq!(String::from(expr.clone()), "4d6k3+5");
+        assert_eq!(DiceExpression::try_from("4d6k3+5".to_string()), Ok(expr));
+        assert!(matches!(
+            DiceExpression::try_from("4d".to_string()),
+            Err(DiceError::ParseError { .. })
+        ));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        let notations = [
+            "3d6", "4d6k3+5", "2d20kl1", "5d6dh2", "4d6dl1", "8d6>4", "3d6!>5", "1d20+5-2",
+            "(1d6+2)*3", "24/(4/2)", "|2d6-7|", "-1d6*2", "Nd6+MOD", "POOLd10!k3", "d%",
+            "DAMAGE(6,4)+1", "(1d20>=15)?(2d6+5):1d4", "(1d6)?1:0", "d66", "6d6!>5k3>4",
+        ];
+        for notation in notations {
+            let expr = parse(notation);
+            let json = serde_json::to_string(&expr).unwrap();
+            assert_eq!(json, format!("\"{}\"", expr.to_notation()));
+            let back: DiceExpression = serde_json::from_str(&json).unwrap();
+            assert_eq!(format!("{:?}", back), format!("{:?}", expr), "{}", notation);
+        }
+
+        let expr: DiceExpression = serde_json::from_str("\"4d6k3+5\"").unwrap();
+        assert_eq!(expr, parse("4d6k3+5"));
+        let error = serde_json::from_str::<DiceExpression>("\"4d\"").unwrap_err();
+        assert!(error.to_string().starts_with("Parse error at position"), "{}", error);
+        assert!(serde_json::from_str::<DiceExpression>("4").is_err());
+    }
+
+    #[test]
+    fn test_clamp_notation() {
+        use DiceExpression::*;
+
+        let dice = Box::new(Dice(2, 6));
+        assert_eq!(FloorClamp(dice.clone(), 4).to_notation(), "2d6min4");
+        assert_eq!(CeilClamp(dice.clone(), -1).to_notation(), "min(2d6,-1)");
+        let sum = Box::new(parse("1d6+1"));
+        assert_eq!(FloorClamp(sum.clone(), 3).to_notation(), "max(1d6+1,3)");
+        assert_eq!(CeilClamp(sum, 5).to_notation(), "min(1d6+1,5)");
+    }
+
+    #[test]
+    fn test_ceil_div_notation() {
+        let up = parse("1d6+1").ceil_div(2);
+        assert_eq!(up.to_notation(), "-(-(1d6+1)/2)");
+        assert_eq!(parse("1d6").ceil_div(-3).to_notation(), "-(-1d6/-3)");
+        // The rendered form rolls the same values
+        let reparsed = parse(&up.to_notation());
+        assert_eq!(reparsed, up);
+        let outcomes = up.all_possible_outcomes().unwrap();
+        assert_eq!(outcomes, vec![1, 2, 3, 4]);
+        assert_eq!(reparsed.all_possible_outcomes().unwrap(), outcomes);
+        for outcome in outcomes {
+            let p = up.pdf_exact(outcome).unwrap();
+            assert!((reparsed.pdf_exact(outcome).unwrap() - p).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_truncating_division_round_trip() {
+        use DiceExpression::*;
+
+        // (0-7)/N with N bound to 2 truncates to -3; rounding down gives -4
+        let expr = parse("(0-7)/N").substitute_constant("N", 2).unwrap();
+        assert!(matches!(expr, Div(_, _)));
+        assert_eq!(expr.to_notation(), "(0-7)/(2)");
+        let mut rng = crate::DiceRng::new(154);
+        let reparsed = parse(&expr.to_notation());
+        assert_eq!(format!("{:?}", reparsed), format!("{:?}", expr));
+        assert_eq!(reparsed.evaluate(&mut rng), Ok(-3));
+        assert_ne!(expr, parse("(0-7)/2"));
+        assert_eq!(parse("(0-7)/2").evaluate(&mut rng), Ok(-4));
+
+        let negative = Div(Box::new(parse("1d6-10")), Box::new(Constant(-2)));
+        assert_eq!(negative.to_notation(), "(1d6-10)/(-2)");
+        let reparsed = parse(&negative.to_notation());
+        assert_eq!(format!("{:?}", reparsed), format!("{:?}", negative));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_truncating_division_serde_round_trip() {
+        let expr = parse("(0-7)/N").substitute_constant("N", 2).unwrap();
+        let json = serde_json::to_string(&expr).unwrap();
+        let back: DiceExpression = serde_json::from_str(&json).unwrap();
+        assert_eq!(format!("{:?}", back), format!("{:?}", expr));
+        assert_eq!(back.evaluate(&mut crate::DiceRng::new(154)), Ok(-3));
+    }
+
+    #[test]
+    fn test_equality() {
+        assert_eq!(parse("4d6k3"), parse("4d6k3"));
+        assert_eq!(parse("4d6k3"), parse("4d6kh3"));
+        assert_eq!(parse("3d6+0"), parse("3d6"));
+        assert_eq!(
+            DiceExpression::Negate(Box::new(DiceExpression::Constant(5))),
+            parse("-5")
+        );
+        assert_ne!(parse("4d6k3"), parse("4d6kl3"));
+        assert_ne!(parse("1d6+1d6"), parse("2d6"));
+        assert_ne!(parse("(1+2)+3"), parse("1+(2+3)"));
+    }
+
+    #[test]
+    fn test_hash_matches_equality() {
+        assert_eq!(hash_of(&parse("4d6k3")), hash_of(&parse("4d6kh3+0")));
+
+        let mut cache: HashMap<DiceExpression, u32> = HashMap::new();
+        for notation in ["4d6k3", "4d6kh3", "4d6h3", "3d6", "3d6*1"] {
+            *cache.entry(parse(notation)).or_insert(0) += 1;
+        }
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache[&parse("4d6k3")], 3);
+    }
+}
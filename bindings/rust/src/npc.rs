@@ -0,0 +1,197 @@
+//! Random NPC stats
+//!
+//! [`NpcGenerator`] rolls a full stat block (six attributes, level and hit
+//! points) from a formula and a hit die, for filling a room with
+//! characters in one call.
+
+use crate::eval::MAX_SIMULATION_ITERATIONS;
+use crate::expression::DiceExpression;
+use crate::{DiceError, DiceResult};
+use rand::Rng;
+
+/// How [`NpcGenerator`] rolls each NPC
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NpcGenConfig<'a> {
+    /// Notation rolled once per attribute, like "4d6k3"
+    pub attribute_formula: &'a str,
+    /// Sides of the die rolled once per level for hit points
+    pub hit_die: u32,
+    /// Lowest and highest level, inclusive
+    pub level_range: (u32, u32),
+}
+
+/// One generated NPC
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NpcStats {
+    pub strength: i32,
+    pub dexterity: i32,
+    pub constitution: i32,
+    pub intelligence: i32,
+    pub wisdom: i32,
+    pub charisma: i32,
+    /// Sum of one hit die per level
+    pub max_hp: i32,
+    pub level: u32,
+}
+
+/// Rolls [`NpcStats`] from a checked [`NpcGenConfig`]
+#[derive(Debug, Clone)]
+pub struct NpcGenerator {
+    attribute: DiceExpression,
+    hit_die: u32,
+    level_range: (u32, u32),
+}
+
+impl NpcGenerator {
+    /// Check the configuration and parse its attribute formula
+    ///
+    /// A zero hit die fails with [`DiceError::InvalidSides`], a level range
+    /// starting at 0 with [`DiceError::InvalidCount`], and a range whose
+    /// highest level is below its lowest with [`DiceError::InvalidRange`].
+    pub fn new(config: NpcGenConfig<'_>) -> DiceResult<NpcGenerator> {
+        let attribute = DiceExpression::parse(config.attribute_formula)?;
+        if config.hit_die == 0 {
+            return Err(DiceError::InvalidSides(0));
+        }
+        let (low, high) = config.level_range;
+        if low == 0 {
+            return Err(DiceError::InvalidCount(0));
+        }
+        if high < low {
+            return Err(DiceError::InvalidRange {
+                min: low as i32,
+                max: i32::MAX,
+                actual: high as i32,
+            });
+        }
+        Ok(NpcGenerator {
+            attribute,
+            hit_die: config.hit_die,
+            level_range: config.level_range,
+        })
+    }
+
+    /// Roll one NPC
+    ///
+    /// The level is rolled uniformly from the level range and hit points
+    /// are that many hit dice. Fails if the formula or hit dice exceed the
+    /// default roll limits.
+    pub fn generate<R: Rng + ?Sized>(&self, rng: &mut R) -> DiceResult<NpcStats> {
+        let mut attribute = || self.attribute.evaluate(rng);
+        let (strength, dexterity, constitution) = (attribute()?, attribute()?, attribute()?);
+        let (intelligence, wisdom, charisma) = (attribute()?, attribute()?, attribute()?);
+        let level = rng.gen_range(self.level_range.0..=self.level_range.1);
+        let max_hp = DiceExpression::Dice(level, self.hit_die).evaluate(rng)?;
+        Ok(NpcStats {
+            strength,
+            dexterity,
+            constitution,
+            intelligence,
+            wisdom,
+            charisma,
+            max_hp,
+            level,
+        })
+    }
+
+    /// Roll `count` NPCs, at most `MAX_SIMULATION_ITERATIONS`
+    pub fn generate_group<R: Rng + ?Sized>(
+        &self,
+        count: u32,
+        rng: &mut R,
+    ) -> DiceResult<Vec<NpcStats>> {
+        if count > MAX_SIMULATION_ITERATIONS {
+            return Err(DiceError::RollCountLimit {
+                requested: count,
+                limit: MAX_SIMULATION_ITERATIONS,
+            });
+        }
+        (0..count).map(|_| self.generate(rng)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::DiceRng;
+
+    fn config(formula: &str) -> NpcGenConfig<'_> {
+        NpcGenConfig {
+            attribute_formula: formula,
+            hit_die: 8,
+            level_range: (1, 5),
+        }
+    }
+
+    #[test]
+    fn test_generate_ranges() {
+        let generator = NpcGenerator::new(config("4d6k3")).unwrap();
+        let mut rng = DiceRng::new(158);
+        for npc in generator.generate_group(200, &mut rng).unwrap() {
+            let attributes = [
+                npc.strength,
+                npc.dexterity,
+                npc.constitution,
+                npc.intelligence,
+                npc.wisdom,
+                npc.charisma,
+            ];
+            assert!(attributes.iter().all(|a| (3..=18).contains(a)), "{:?}", npc);
+            assert!((1..=5).contains(&npc.level));
+            assert!((npc.level as i32..=npc.level as i32 * 8).contains(&npc.max_hp));
+        }
+    }
+
+    #[test]
+    fn test_group_is_reproducible() {
+        let generator = NpcGenerator::new(config("3d6")).unwrap();
+        let group = generator.generate_group(20, &mut DiceRng::new(4)).unwrap();
+        assert_eq!(group.len(), 20);
+        assert_eq!(group, generator.generate_group(20, &mut DiceRng::new(4)).unwrap());
+        assert!(generator.generate_group(0, &mut DiceRng::new(4)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_fixed_level() {
+        let generator = NpcGenerator::new(NpcGenConfig {
+            attribute_formula: "10",
+            hit_die: 1,
+            level_range: (3, 3),
+        })
+        .unwrap();
+        let npc = generator.generate(&mut DiceRng::new(1)).unwrap();
+        assert_eq!((npc.strength, npc.charisma, npc.level, npc.max_hp), (10, 10, 3, 3));
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        assert!(matches!(
+            NpcGenerator::new(config("4d")),
+            Err(DiceError::ParseError { .. })
+        ));
+        let bad = |hit_die, level_range| {
+            NpcGenerator::new(NpcGenConfig {
+                hit_die,
+                level_range,
+                ..config("3d6")
+            })
+            .unwrap_err()
+        };
+        assert_eq!(bad(0, (1, 5)), DiceError::InvalidSides(0));
+        assert_eq!(bad(8, (0, 5)), DiceError::InvalidCount(0));
+        assert_eq!(
+            bad(8, (5, 2)),
+            DiceError::InvalidRange {
+                min: 5,
+                max: i32::MAX,
+                actual: 2
+            }
+        );
+        let generator = NpcGenerator::new(config("3d6")).unwrap();
+        assert!(matches!(
+            generator.generate_group(MAX_SIMULATION_ITERATIONS + 1, &mut DiceRng::new(1)),
+            Err(DiceError::RollCountLimit { .. })
+        ));
+    }
+}
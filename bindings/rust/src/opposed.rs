@@ -0,0 +1,474 @@
+//! Opposed rolls
+//!
+//! Both sides roll and the results are compared. Pool variants count
+//! successes (`8d6>4`) as in Shadowrun or Vampire. Tiered variants grade
+//! the margin into degrees of success. Many pairs can be resolved in one
+//! call with [`DiceContext::roll_opposed_multiple`].
+
+use crate::context::DiceContext;
+use crate::{Dice, DiceError, DiceResult};
+
+/// Which side won an opposed roll
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum OpposedWinner {
+    Attacker,
+    Defender,
+    Tie,
+}
+
+impl OpposedWinner {
+    fn from_margin(margin: i32) -> Self {
+        match margin {
+            m if m > 0 => OpposedWinner::Attacker,
+            m if m < 0 => OpposedWinner::Defender,
+            _ => OpposedWinner::Tie,
+        }
+    }
+}
+
+/// Outcome of two totals rolled against each other
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OpposedResult {
+    pub attacker_roll: i32,
+    pub defender_roll: i32,
+    /// `attacker_roll - defender_roll`
+    pub margin: i32,
+    pub winner: OpposedWinner,
+}
+
+/// Outcome of two success-counting pools rolled against each other
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PoolOpposedResult {
+    pub attacker_successes: u32,
+    pub defender_successes: u32,
+    /// `attacker_successes - defender_successes`
+    pub net_hits: i32,
+    pub outcome: OpposedWinner,
+    /// More than half the attacker's dice showed 1
+    pub attacker_glitch: bool,
+    /// More than half the defender's dice showed 1
+    pub defender_glitch: bool,
+}
+
+/// Degree of success of an opposed roll, from the attacker's side
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SuccessLevel {
+    CriticalFailure,
+    Failure,
+    Success,
+    CriticalSuccess,
+}
+
+/// Margins separating the [`SuccessLevel`]s
+///
+/// Thresholds are checked from critical success down, so a margin meeting
+/// `critical_success_margin` is a critical success even if the other
+/// thresholds overlap it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TieredConfig {
+    /// Smallest margin that succeeds; the default of 1 makes ties fail
+    pub success_margin: i32,
+    /// Smallest margin that succeeds critically
+    pub critical_success_margin: i32,
+    /// Largest margin that fails critically
+    pub critical_failure_margin: i32,
+}
+
+impl TieredConfig {
+    /// Level reached by an attacker winning by `margin`
+    pub fn level(&self, margin: i32) -> SuccessLevel {
+        if margin >= self.critical_success_margin {
+            SuccessLevel::CriticalSuccess
+        } else if margin >= self.success_margin {
+            SuccessLevel::Success
+        } else if margin <= self.critical_failure_margin {
+            SuccessLevel::CriticalFailure
+        } else {
+            SuccessLevel::Failure
+        }
+    }
+}
+
+impl Default for TieredConfig {
+    fn default() -> Self {
+        TieredConfig {
+            success_margin: 1,
+            critical_success_margin: 10,
+            critical_failure_margin: -10,
+        }
+    }
+}
+
+/// Outcome of two rolls compared and graded by a [`TieredConfig`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OpposedTieredResult {
+    pub attacker_roll: i32,
+    pub defender_roll: i32,
+    /// `attacker_roll - defender_roll`
+    pub margin: i32,
+    pub success_level: SuccessLevel,
+    /// Thresholds `success_level` was graded with
+    pub config: TieredConfig,
+}
+
+impl OpposedTieredResult {
+    fn graded(attacker_roll: i32, defender_roll: i32, config: TieredConfig) -> DiceResult<Self> {
+        let margin = attacker_roll.checked_sub(defender_roll).ok_or(DiceError::Overflow)?;
+        Ok(OpposedTieredResult {
+            attacker_roll,
+            defender_roll,
+            margin,
+            success_level: config.level(margin),
+            config,
+        })
+    }
+
+    /// The same rolls with the defender as the attacker
+    ///
+    /// The level is regraded with the same thresholds, so with the default
+    /// config a tie is a failure from both sides.
+    pub fn reversal(&self) -> OpposedTieredResult {
+        OpposedTieredResult {
+            attacker_roll: self.defender_roll,
+            defender_roll: self.attacker_roll,
+            margin: self.margin.saturating_neg(),
+            success_level: self.config.level(self.margin.saturating_neg()),
+            config: self.config,
+        }
+    }
+}
+
+impl DiceContext {
+    /// Roll attacker and defender notation and compare the totals
+    ///
+    /// # Arguments
+    ///
+    /// * `attacker` - Dice notation for the attacking side, e.g. "1d20+5"
+    /// * `defender` - Dice notation for the defending side
+    pub fn roll_opposed(&mut self, attacker: &str, defender: &str) -> DiceResult<OpposedResult> {
+        let attacker_roll = self.roll_notation(attacker)?.total;
+        let defender_roll = self.roll_notation(defender)?.total;
+        let margin = attacker_roll.checked_sub(defender_roll).ok_or(DiceError::Overflow)?;
+        Ok(OpposedResult {
+            attacker_roll,
+            defender_roll,
+            margin,
+            winner: OpposedWinner::from_margin(margin),
+        })
+    }
+
+    /// Roll every pair in order, each with its own draws from this context
+    ///
+    /// A pair that fails to roll doesn't stop the rest; its error takes its
+    /// place in the results. More pairs than
+    /// [`RollConfig::max_simulation_iterations`](crate::RollConfig::max_simulation_iterations)
+    /// fail with [`DiceError::RollCountLimit`] before anything is rolled.
+    ///
+    /// # Arguments
+    ///
+    /// * `pairs` - (attacker, defender) notation for each contest
+    pub fn roll_opposed_multiple(
+        &mut self,
+        pairs: &[(&str, &str)],
+    ) -> DiceResult<Vec<DiceResult<OpposedResult>>> {
+        let limit = self.config().max_simulation_iterations;
+        if pairs.len() > limit as usize {
+            return Err(DiceError::RollCountLimit {
+                requested: pairs.len().min(u32::MAX as usize) as u32,
+                limit,
+            });
+        }
+        Ok(pairs
+            .iter()
+            .map(|(attacker, defender)| self.roll_opposed(attacker, defender))
+            .collect())
+    }
+
+    /// Roll attacker and defender notation and grade the margin
+    ///
+    /// # Arguments
+    ///
+    /// * `attacker` - Dice notation for the attacking side, e.g. "1d20+5"
+    /// * `defender` - Dice notation for the defending side
+    /// * `config` - Margins separating the success levels
+    pub fn roll_opposed_tiered(
+        &mut self,
+        attacker: &str,
+        defender: &str,
+        config: &TieredConfig,
+    ) -> DiceResult<OpposedTieredResult> {
+        let attacker_roll = self.roll_notation(attacker)?.total;
+        let defender_roll = self.roll_notation(defender)?.total;
+        OpposedTieredResult::graded(attacker_roll, defender_roll, *config)
+    }
+
+    /// Roll two success pools against each other
+    ///
+    /// Each pool is counted by
+    /// [`roll_success_notation`](Self::roll_success_notation), under this
+    /// context's limits.
+    ///
+    /// # Arguments
+    ///
+    /// * `attacker_pool` - Pool notation counting successes, e.g. "8d6>4"
+    /// * `defender_pool` - Pool notation for the defending side
+    pub fn roll_opposed_pool(
+        &mut self,
+        attacker_pool: &str,
+        defender_pool: &str,
+    ) -> DiceResult<PoolOpposedResult> {
+        let attacker = self.roll_success_notation(attacker_pool)?;
+        let defender = self.roll_success_notation(defender_pool)?;
+        let attacker_successes = attacker.successes;
+        let defender_successes = defender.successes;
+        let net_hits = attacker_successes as i32 - defender_successes as i32;
+
+        Ok(PoolOpposedResult {
+            attacker_successes,
+            defender_successes,
+            net_hits,
+            outcome: OpposedWinner::from_margin(net_hits),
+            attacker_glitch: attacker.is_glitch,
+            defender_glitch: defender.is_glitch,
+        })
+    }
+}
+
+impl Dice {
+    /// Roll attacker and defender notation and compare the totals
+    ///
+    /// # Arguments
+    ///
+    /// * `attacker` - Dice notation for the attacking side, e.g. "1d20+5"
+    /// * `defender` - Dice notation for the defending side
+    pub fn roll_opposed(attacker: &str, defender: &str) -> DiceResult<OpposedResult> {
+        DiceContext::new().roll_opposed(attacker, defender)
+    }
+
+    /// Roll every pair in order, each with its own draws
+    ///
+    /// # Arguments
+    ///
+    /// * `pairs` - (attacker, defender) notation for each contest
+    pub fn roll_opposed_multiple(
+        pairs: &[(&str, &str)],
+    ) -> DiceResult<Vec<DiceResult<OpposedResult>>> {
+        DiceContext::new().roll_opposed_multiple(pairs)
+    }
+
+    /// Roll attacker and defender notation and grade the margin
+    ///
+    /// # Arguments
+    ///
+    /// * `attacker` - Dice notation for the attacking side, e.g. "1d20+5"
+    /// * `defender` - Dice notation for the defending side
+    /// * `config` - Margins separating the success levels
+    pub fn roll_opposed_tiered(
+        attacker: &str,
+        defender: &str,
+        config: &TieredConfig,
+    ) -> DiceResult<OpposedTieredResult> {
+        DiceContext::new().roll_opposed_tiered(attacker, defender, config)
+    }
+
+    /// Roll two success pools against each other
+    ///
+    /// # Arguments
+    ///
+    /// * `attacker_pool` - Pool notation counting successes, e.g. "8d6>4"
+    /// * `defender_pool` - Pool notation for the defending side
+    pub fn roll_opposed_pool(
+        attacker_pool: &str,
+        defender_pool: &str,
+    ) -> DiceResult<PoolOpposedResult> {
+        DiceContext::new().roll_opposed_pool(attacker_pool, defender_pool)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_attacker_wins() {
+        // Every die meets a target of 1, so success counts are fixed
+        let mut ctx = DiceContext::with_seed(1);
+        let result = ctx.roll_opposed_pool("5d6>1", "2d6>1").unwrap();
+        assert_eq!(result.attacker_successes, 5);
+        assert_eq!(result.defender_successes, 2);
+        assert_eq!(result.net_hits, 3);
+        assert_eq!(result.outcome, OpposedWinner::Attacker);
+    }
+
+    #[test]
+    fn test_defender_wins() {
+        let mut ctx = DiceContext::with_seed(1);
+        let result = ctx.roll_opposed_pool("2d6>1", "6d6>1").unwrap();
+        assert_eq!(result.net_hits, -4);
+        assert_eq!(result.outcome, OpposedWinner::Defender);
+    }
+
+    #[test]
+    fn test_tie() {
+        let mut ctx = DiceContext::with_seed(1);
+        let result = ctx.roll_opposed_pool("3d6>1", "3d8>1").unwrap();
+        assert_eq!(result.net_hits, 0);
+        assert_eq!(result.outcome, OpposedWinner::Tie);
+    }
+
+    #[test]
+    fn test_attacker_glitch() {
+        // d1 pools always show 1
+        let mut ctx = DiceContext::with_seed(1);
+        let result = ctx.roll_opposed_pool("4d1>2", "4d6>7").unwrap();
+        assert!(result.attacker_glitch);
+        assert_eq!(result.attacker_successes, 0);
+        assert_eq!(result.defender_successes, 0);
+        assert_eq!(result.outcome, OpposedWinner::Tie);
+    }
+
+    #[test]
+    fn test_defender_glitch() {
+        let mut ctx = DiceContext::with_seed(1);
+        let result = ctx.roll_opposed_pool("3d6>1", "3d1>1").unwrap();
+        assert!(result.defender_glitch);
+        assert_eq!(result.defender_successes, 3);
+    }
+
+    #[test]
+    fn test_pool_net_hits() {
+        let mut ctx = DiceContext::with_seed(2024);
+        for _ in 0..200 {
+            let result = ctx.roll_opposed_pool("4d6>5", "4d6>5").unwrap();
+            assert!(result.attacker_successes <= 4);
+            assert_eq!(
+                result.net_hits,
+                result.attacker_successes as i32 - result.defender_successes as i32
+            );
+        }
+    }
+
+    #[test]
+    fn test_roll_opposed() {
+        let mut ctx = DiceContext::with_seed(171);
+        let result = ctx.roll_opposed("15", "1d10").unwrap();
+        assert_eq!(result.margin, 15 - result.defender_roll);
+        assert_eq!(result.winner, OpposedWinner::Attacker);
+        assert_eq!(ctx.roll_opposed("7", "7").unwrap().winner, OpposedWinner::Tie);
+        assert_eq!(ctx.roll_opposed("1", "2").unwrap().winner, OpposedWinner::Defender);
+        assert_eq!(ctx.roll_opposed("-2147483647-1", "1"), Err(DiceError::Overflow));
+    }
+
+    #[test]
+    fn test_roll_opposed_multiple() {
+        let fighters = ["1d20", "1d20+1", "1d20+2", "1d20+3", "1d20+4"];
+        let pairs: Vec<(&str, &str)> = fighters
+            .iter()
+            .flat_map(|&a| fighters.iter().filter(move |&&d| d != a).map(move |&d| (a, d)))
+            .take(10)
+            .collect();
+        let mut ctx = DiceContext::with_seed(171);
+        let mut replay = ctx.clone();
+        let results = ctx.roll_opposed_multiple(&pairs).unwrap();
+        assert_eq!(results.len(), 10);
+        // Input order, with the same draws as rolling the pairs one by one
+        for (&(attacker, defender), result) in pairs.iter().zip(&results) {
+            assert_eq!(result, &replay.roll_opposed(attacker, defender));
+        }
+
+        let results = ctx.roll_opposed_multiple(&[("1d6", "1d6"), ("2d", "1d6"), ("3", "1")]);
+        let results = results.unwrap();
+        assert!(results[0].is_ok());
+        assert!(results[1].is_err());
+        assert_eq!(results[2].as_ref().unwrap().margin, 2);
+        assert_eq!(ctx.roll_opposed_multiple(&[]), Ok(vec![]));
+
+        let config = crate::RollConfig {
+            max_simulation_iterations: 2,
+            ..crate::RollConfig::default()
+        };
+        let mut limited = DiceContext::with_seed(171).with_config(config);
+        assert_eq!(
+            limited.roll_opposed_multiple(&pairs[..3]),
+            Err(DiceError::RollCountLimit {
+                requested: 3,
+                limit: 2
+            })
+        );
+    }
+
+    #[test]
+    fn test_tiered_thresholds() {
+        let config = TieredConfig::default();
+        let mut ctx = DiceContext::with_seed(149);
+        let grade = |ctx: &mut DiceContext, attacker: &str, defender: &str| {
+            ctx.roll_opposed_tiered(attacker, defender, &config).unwrap()
+        };
+
+        let tie = grade(&mut ctx, "12", "12");
+        assert_eq!((tie.margin, tie.success_level), (0, SuccessLevel::Failure));
+        let narrow = grade(&mut ctx, "13", "12");
+        assert_eq!((narrow.margin, narrow.success_level), (1, SuccessLevel::Success));
+        let almost = grade(&mut ctx, "21", "12");
+        assert_eq!(almost.success_level, SuccessLevel::Success);
+        let crushing = grade(&mut ctx, "22", "12");
+        assert_eq!((crushing.margin, crushing.success_level), (10, SuccessLevel::CriticalSuccess));
+        assert_eq!(grade(&mut ctx, "3", "12").success_level, SuccessLevel::Failure);
+        assert_eq!(grade(&mut ctx, "2", "12").success_level, SuccessLevel::CriticalFailure);
+
+        let generous = TieredConfig {
+            success_margin: 0,
+            ..TieredConfig::default()
+        };
+        let tie = ctx.roll_opposed_tiered("5", "5", &generous).unwrap();
+        assert_eq!(tie.success_level, SuccessLevel::Success);
+    }
+
+    #[test]
+    fn test_tiered_reversal() {
+        let config = TieredConfig::default();
+        let mut ctx = DiceContext::with_seed(149);
+        let crushing = ctx.roll_opposed_tiered("25", "10", &config).unwrap();
+        let reversed = crushing.reversal();
+        assert_eq!((reversed.attacker_roll, reversed.defender_roll), (10, 25));
+        assert_eq!(reversed.margin, -15);
+        assert_eq!(reversed.success_level, SuccessLevel::CriticalFailure);
+        assert_eq!(reversed.reversal(), crushing);
+
+        let tie = ctx.roll_opposed_tiered("7", "7", &config).unwrap();
+        assert_eq!(tie.reversal().success_level, SuccessLevel::Failure);
+
+        for _ in 0..100 {
+            let result = ctx.roll_opposed_tiered("1d20+5", "1d20", &config).unwrap();
+            assert_eq!(result.margin, result.attacker_roll - result.defender_roll);
+            assert_eq!(result.success_level, config.level(result.margin));
+        }
+        assert!(ctx.roll_opposed_tiered("1d20", "bogus", &config).is_err());
+        assert!(Dice::roll_opposed_tiered("1d20", "1d20", &config).is_ok());
+    }
+
+    #[test]
+    fn test_invalid_pools() {
+        let mut ctx = DiceContext::with_seed(1);
+        assert_eq!(
+            ctx.roll_opposed_pool("8d6", "8d6>4"),
+            Err(DiceError::InvalidNotation("8d6".to_string()))
+        );
+        assert!(ctx.roll_opposed_pool("8d6>4", "bogus").is_err());
+        assert!(Dice::roll_opposed_pool("8d6>4", "6d6>4").is_ok());
+
+        let config = crate::RollConfig {
+            max_dice_count: 6,
+            ..crate::RollConfig::default()
+        };
+        let mut limited = DiceContext::with_seed(103).with_config(config);
+        assert!(limited.roll_opposed_pool("6d6>4", "6d6>4").is_ok());
+        assert_eq!(limited.roll_opposed_pool("8d6>4", "6d6>4"), Err(DiceError::InvalidCount(8)));
+    }
+}
@@ -0,0 +1,1147 @@
+//! Dice notation parser
+//!
+//! Recursive descent with the same precedence levels as the C parser
+//! (see `docs/grammar.md`):
+//!
+//! ```text
+//! expression := term (('+' | '-') term)*
+//! term       := factor (('*' | '/') factor)*
+//! factor     := '-' factor | NUMBER | dice | VARIABLE | call | conditional
+//!             | ('min' | 'max') '(' expression ',' expression ')'
+//!             | '(' expression ')' | '|' expression '|'
+//! call       := (VARIABLE | QUALIFIED) '(' (expression (',' expression)*)? ')'
+//! QUALIFIED  := NAME ('::' NAME)+              imported function (`player::damage`)
+//! NAME       := [A-Za-z_][A-Za-z0-9_]*
+//! conditional := '(' expression (COMPARE '-'? NUMBER)? ')' '?' factor ':' factor
+//! COMPARE    := '>=' | '>' | '<=' | '<' | '==' | '!='
+//! dice       := (NUMBER | VARIABLE 'd')? ('d' | 'D') (NUMBER | '%')
+//!               explode? selection? success? clamp*
+//! explode    := '!' ('>' NUMBER)?            explode dice >= NUMBER
+//!             | '!' '{' NUMBER (',' NUMBER)* '}'  explode on the listed faces
+//! selection  := ('k' | 'kh' | 'h') NUMBER?    keep highest
+//!             | 'kl' NUMBER?                 keep lowest
+//!             | 'dh' NUMBER?                 drop highest
+//!             | ('l' | 'dl' | 'd') NUMBER?   drop lowest
+//! success    := '>' NUMBER botch?            count dice >= NUMBER
+//! botch      := 'b' NUMBER                   minus dice <= NUMBER
+//! clamp      := ('min' | 'max') NUMBER       raise to / cap at NUMBER
+//! ```
+//!
+//! `VARIABLE` is an upper-case name (`[A-Z_][A-Z0-9_]*`), so `Nd6+MOD` reads
+//! as a variable count of d6 plus a variable. `D` followed by a digit is
+//! still a die (`D20`), and a variable count needs a lower-case `d`. A name
+//! followed directly by `(` is a call to a user-defined function. A name
+//! qualified by a module (`player::damage_roll(6)`) may use any case, and
+//! is always a call.
+//!
+//! A conditional without a comparison is true when its condition is
+//! nonzero. A `>` straight after a pool counts successes, so `(1d20>15)` is
+//! a success count; compare a pool with `>=` or parenthesize it
+//! (`((1d20)>15)`).
+//!
+//! Unary minus binds tightest, then `*` and `/`, then `+` and `-`. Binary
+//! operators are left associative and parentheses override precedence.
+//! Dividing by a number rounds down (`2d6/2`); dividing by anything else,
+//! a parenthesized number included (`2d6/(2)`), truncates toward zero.
+//!
+//! Omitted numbers take these defaults:
+//!
+//! * count: 1, so `d20` is `1d20`
+//! * explode threshold: the number of sides, so `3d6!` explodes on 6
+//! * selection: 1, so `2d20k` keeps the highest die and `4d6l` drops the
+//!   lowest
+//!
+//! `%` as the number of sides is percentile dice: `3d%` is `3d100`.
+//!
+//! `d66` and `d666` without a count read d6 as digits, so `d66` is
+//! `1d6*10+1d6`; write `1d66` for a single 66-sided die.
+//!
+//! [`DiceParser`] exposes the parser for notation embedded in other text.
+//!
+//! Notation longer than [`DiceParser::max_length`] bytes is rejected before
+//! any of it is read, and notation nested deeper than
+//! [`DiceParser::MAX_NESTING_DEPTH`] is rejected while parsing, so raising
+//! the length limit can't exhaust the stack.
+
+use crate::expression::{DiceExpression, DicePredicate};
+use crate::{DiceError, DiceResult};
+use rand::Rng;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static MAX_NOTATION_LENGTH: AtomicUsize = AtomicUsize::new(DiceParser::DEFAULT_MAX_LENGTH);
+
+/// Fail if `notation` is longer than `max_length` bytes
+pub(crate) fn check_length(notation: &str, max_length: usize) -> DiceResult<()> {
+    if notation.len() > max_length {
+        return Err(DiceError::InvalidNotation(format!(
+            "notation exceeds maximum length of {} bytes",
+            max_length
+        )));
+    }
+    Ok(())
+}
+
+/// Parse a complete notation string
+pub(crate) fn parse(notation: &str) -> DiceResult<DiceExpression> {
+    check_length(notation, DiceParser::max_length())?;
+    let mut parser = Parser::new(notation);
+    let expr = parser.expression()?;
+    parser.skip_whitespace();
+    if parser.peek().is_some() {
+        return Err(parser.error());
+    }
+    Ok(expr)
+}
+
+/// Parser for dice notation inside larger strings
+pub struct DiceParser;
+
+impl DiceParser {
+    /// Longest notation accepted unless [`set_max_length`](Self::set_max_length)
+    /// changes it
+    pub const DEFAULT_MAX_LENGTH: usize = 1024;
+
+    /// Deepest nesting of parentheses, bars, calls and unary minus accepted
+    pub const MAX_NESTING_DEPTH: usize = 128;
+
+    /// Longest notation, in bytes, that parsing accepts
+    pub fn max_length() -> usize {
+        MAX_NOTATION_LENGTH.load(Ordering::Relaxed)
+    }
+
+    /// Change the longest notation parsing accepts, for every thread
+    ///
+    /// Defaults to [`DEFAULT_MAX_LENGTH`](Self::DEFAULT_MAX_LENGTH). Longer notation fails
+    /// with [`DiceError::InvalidNotation`] without being read; local tools
+    /// that take whole files of notation can raise this to e.g. 65536.
+    pub fn set_max_length(bytes: usize) {
+        MAX_NOTATION_LENGTH.store(bytes, Ordering::Relaxed);
+    }
+
+    /// Parse the notation at the front of `input`, returning the
+    /// [simplified](DiceExpression::simplify) expression and the rest of
+    /// the input
+    ///
+    /// Notation ends at the first character that can't continue it outside
+    /// parentheses, so `"2d6+3 damage"` gives `2d6+3` and `" damage"`.
+    /// Input longer than [`max_length`](Self::max_length), the rest
+    /// included, fails like an overlong notation.
+    pub fn parse_partial(input: &str) -> DiceResult<(DiceExpression, &str)> {
+        check_length(input, DiceParser::max_length())?;
+        let mut parser = Parser::new(input);
+        let expr = parser.expression()?;
+        // Leave whitespace skipped while looking for an operator in the rest
+        let mut end = parser.pos;
+        while end > 0 && input.as_bytes()[end - 1].is_ascii_whitespace() {
+            end -= 1;
+        }
+        Ok((expr.simplify(), &input[end..]))
+    }
+
+    /// Replace every `[notation]` in `text` with its rolled total
+    ///
+    /// Brackets that don't hold valid notation, or whose roll fails, are
+    /// left as they are: `"deal [2d6+3] damage"` becomes `"deal 9 damage"`.
+    pub fn find_and_evaluate_all<R: Rng + ?Sized>(text: &str, rng: &mut R) -> String {
+        let mut out = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(open) = rest.find('[') {
+            out.push_str(&rest[..open]);
+            let inner = &rest[open + 1..];
+            let Some(close) = inner.find(']') else {
+                rest = &rest[open..];
+                break;
+            };
+            let total = DiceExpression::parse(&inner[..close]).and_then(|expr| expr.evaluate(rng));
+            match total {
+                Ok(total) => {
+                    out.push_str(&total.to_string());
+                    rest = &inner[close + 1..];
+                }
+                // Keep the bracket and look for notation after it
+                Err(_) => {
+                    out.push('[');
+                    rest = inner;
+                }
+            }
+        }
+        out.push_str(rest);
+        out
+    }
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    bytes: &'a [u8],
+    pos: usize,
+    /// Factors being parsed, each nested inside the last
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(input: &'a str) -> Self {
+        Parser {
+            input,
+            bytes: input.as_bytes(),
+            pos: 0,
+            depth: 0,
+        }
+    }
+
+    fn error(&self) -> DiceError {
+        DiceError::InvalidNotation(self.input.to_string())
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<u8> {
+        self.bytes.get(self.pos + offset).copied()
+    }
+
+    fn peek_lower(&self) -> Option<u8> {
+        self.peek().map(|c| c.to_ascii_lowercase())
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.pos += 1;
+        }
+    }
+
+    fn expression(&mut self) -> DiceResult<DiceExpression> {
+        let mut left = self.term()?;
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b'+') => {
+                    self.pos += 1;
+                    let right = self.term()?;
+                    left = DiceExpression::Add(Box::new(left), Box::new(right));
+                }
+                Some(b'-') => {
+                    self.pos += 1;
+                    let right = self.term()?;
+                    left = DiceExpression::Sub(Box::new(left), Box::new(right));
+                }
+                _ => return Ok(left),
+            }
+        }
+    }
+
+    fn term(&mut self) -> DiceResult<DiceExpression> {
+        let mut left = self.factor()?;
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b'*') => {
+                    self.pos += 1;
+                    let right = self.factor()?;
+                    left = DiceExpression::Mul(Box::new(left), Box::new(right));
+                }
+                Some(b'/') => {
+                    self.pos += 1;
+                    self.skip_whitespace();
+                    let parenthesized = self.peek() == Some(b'(');
+                    left = match self.factor()? {
+                        DiceExpression::Constant(divisor) if !parenthesized => {
+                            left.floor_div(divisor)
+                        }
+                        right => DiceExpression::Div(Box::new(left), Box::new(right)),
+                    };
+                }
+                _ => return Ok(left),
+            }
+        }
+    }
+
+    /// Every nested expression is parsed through here, so this is where
+    /// nesting is limited
+    fn factor(&mut self) -> DiceResult<DiceExpression> {
+        if self.depth >= DiceParser::MAX_NESTING_DEPTH {
+            return Err(DiceError::InvalidNotation(format!(
+                "notation exceeds maximum nesting depth of {}",
+                DiceParser::MAX_NESTING_DEPTH
+            )));
+        }
+        self.depth += 1;
+        let factor = self.unlimited_factor();
+        self.depth -= 1;
+        factor
+    }
+
+    fn unlimited_factor(&mut self) -> DiceResult<DiceExpression> {
+        self.skip_whitespace();
+        match self.peek() {
+            // Unary minus binds tighter than any binary operator, so
+            // `-1d6+3d8` is `(-1d6)+3d8`
+            Some(b'-') => {
+                self.pos += 1;
+                match self.factor()? {
+                    DiceExpression::Constant(value) => Ok(DiceExpression::Constant(-value)),
+                    inner => Ok(DiceExpression::Negate(Box::new(inner))),
+                }
+            }
+            Some(b'(') => {
+                self.pos += 1;
+                let inner = self.expression()?;
+                let predicate = self.predicate()?;
+                self.expect(b')')?;
+                self.skip_whitespace();
+                match (self.peek(), predicate) {
+                    (Some(b'?'), predicate) => {
+                        let predicate = predicate.unwrap_or(DicePredicate::NotEqual(0));
+                        self.conditional(inner, predicate)
+                    }
+                    (_, Some(_)) => Err(self.error()),
+                    (_, None) => Ok(inner),
+                }
+            }
+            Some(b'|') => {
+                self.pos += 1;
+                let inner = self.expression()?;
+                self.expect(b'|')?;
+                Ok(DiceExpression::AbsoluteValue(Box::new(inner)))
+            }
+            Some(c) if c.is_ascii_digit() => {
+                let value = self.number()?;
+                if self.peek_lower() == Some(b'd') {
+                    self.dice(value)
+                } else {
+                    let value = i32::try_from(value).map_err(|_| self.error())?;
+                    Ok(DiceExpression::Constant(value))
+                }
+            }
+            Some(_) if self.at_qualified_name() => self.qualified_call(),
+            Some(b'm') => self.min_max(),
+            Some(b'D') if matches!(self.peek_at(1), Some(c) if is_identifier_start(c)) => {
+                self.variable()
+            }
+            // Implicit count: `d6` is `1d6`, apart from digit rolls
+            Some(b'd' | b'D') => match self.digit_dice() {
+                Some(digits) => Ok(digits),
+                None => self.dice(1),
+            },
+            Some(c) if is_identifier_start(c) => self.variable(),
+            _ => Err(self.error()),
+        }
+    }
+
+    /// Parse the `dS` part of a dice term whose count has already been read
+    fn dice(&mut self, count: u32) -> DiceResult<DiceExpression> {
+        let sides = self.sides()?;
+        if count == 0 {
+            return Err(DiceError::InvalidCount(0));
+        }
+        if sides == 0 {
+            return Err(DiceError::InvalidSides(0));
+        }
+        self.modifiers(DiceExpression::Dice(count, sides), sides)
+    }
+
+    /// Parse `d66` or `d666` as one d6 per digit, most significant first
+    fn digit_dice(&mut self) -> Option<DiceExpression> {
+        let sides = &self.input.as_bytes()[self.pos + 1..];
+        let digits = sides.iter().take_while(|c| c.is_ascii_digit()).count();
+        if !(2..=3).contains(&digits) || sides[..digits].iter().any(|&c| c != b'6') {
+            return None;
+        }
+        self.pos += 1 + digits;
+        let expr = (0..digits)
+            .map(|place| {
+                let die = DiceExpression::Dice(1, 6);
+                match 10i32.pow((digits - 1 - place) as u32) {
+                    1 => die,
+                    scale => DiceExpression::Mul(
+                        Box::new(die),
+                        Box::new(DiceExpression::Constant(scale)),
+                    ),
+                }
+            })
+            .reduce(|left, right| DiceExpression::Add(Box::new(left), Box::new(right)))?;
+        Some(expr)
+    }
+
+    /// Parse a variable, and the dice it counts if `d` follows (`Nd6`)
+    fn variable(&mut self) -> DiceResult<DiceExpression> {
+        let start = self.pos;
+        while matches!(self.peek(), Some(c) if is_identifier_start(c) || c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        let name = self.input[start..self.pos].to_string();
+        if self.peek() == Some(b'(') {
+            return self.call(name);
+        }
+        let name = DiceExpression::Variable(name);
+        if self.peek() != Some(b'd') {
+            return Ok(name);
+        }
+        let sides = self.sides()?;
+        if sides == 0 {
+            return Err(DiceError::InvalidSides(0));
+        }
+        self.modifiers(DiceExpression::DynamicDice(Box::new(name), sides), sides)
+    }
+
+    /// Parse an optional comparison closing a condition (`>=15`)
+    fn predicate(&mut self) -> DiceResult<Option<DicePredicate>> {
+        type Predicate = fn(i32) -> DicePredicate;
+
+        self.skip_whitespace();
+        let (kind, len): (Predicate, usize) = match (self.peek(), self.peek_at(1)) {
+            (Some(b'>'), Some(b'=')) => (DicePredicate::AtLeast, 2),
+            (Some(b'>'), _) => (DicePredicate::GreaterThan, 1),
+            (Some(b'<'), Some(b'=')) => (DicePredicate::AtMost, 2),
+            (Some(b'<'), _) => (DicePredicate::LessThan, 1),
+            (Some(b'='), Some(b'=')) => (DicePredicate::Equal, 2),
+            (Some(b'!'), Some(b'=')) => (DicePredicate::NotEqual, 2),
+            _ => return Ok(None),
+        };
+        self.pos += len;
+        self.skip_whitespace();
+        let negative = self.peek() == Some(b'-');
+        if negative {
+            self.pos += 1;
+        }
+        if !self.at_digit() {
+            return Err(self.error());
+        }
+        let value = i32::try_from(self.number()?).map_err(|_| self.error())?;
+        Ok(Some(kind(if negative { -value } else { value })))
+    }
+
+    /// Parse `? then : else` after a condition
+    fn conditional(
+        &mut self,
+        condition: DiceExpression,
+        predicate: DicePredicate,
+    ) -> DiceResult<DiceExpression> {
+        self.pos += 1;
+        let then_expr = self.factor()?;
+        self.expect(b':')?;
+        let else_expr = self.factor()?;
+        Ok(DiceExpression::Conditional {
+            condition: Box::new(condition),
+            predicate,
+            then_expr: Box::new(then_expr),
+            else_expr: Box::new(else_expr),
+        })
+    }
+
+    /// Parse `min(a, b)` or `max(a, b)`
+    fn min_max(&mut self) -> DiceResult<DiceExpression> {
+        let node = match self.input.get(self.pos..self.pos + 4) {
+            Some("min(") => DiceExpression::Min,
+            Some("max(") => DiceExpression::Max,
+            _ => return Err(self.error()),
+        };
+        self.pos += 4;
+        let left = self.expression()?;
+        self.expect(b',')?;
+        let right = self.expression()?;
+        self.expect(b')')?;
+        Ok(node(Box::new(left), Box::new(right)))
+    }
+
+    /// Whether a `module::` prefix starts at the cursor
+    fn at_qualified_name(&self) -> bool {
+        let len = self.name_len(self.pos);
+        len > 0 && self.bytes[self.pos + len..].starts_with(b"::")
+    }
+
+    /// Length of the `NAME` starting at `start`, 0 if there isn't one
+    fn name_len(&self, start: usize) -> usize {
+        let rest = &self.bytes[start..];
+        if rest.first().is_none_or(u8::is_ascii_digit) {
+            return 0;
+        }
+        rest.iter().take_while(|&&c| c.is_ascii_alphanumeric() || c == b'_').count()
+    }
+
+    /// Parse a call to a function qualified by its module
+    fn qualified_call(&mut self) -> DiceResult<DiceExpression> {
+        let start = self.pos;
+        loop {
+            let len = self.name_len(self.pos);
+            if len == 0 {
+                return Err(self.error());
+            }
+            self.pos += len;
+            if !self.bytes[self.pos..].starts_with(b"::") {
+                break;
+            }
+            self.pos += 2;
+        }
+        if self.peek() != Some(b'(') {
+            return Err(self.error());
+        }
+        self.call(self.input[start..self.pos].to_string())
+    }
+
+    /// Parse the parenthesized arguments of a function call
+    fn call(&mut self, name: String) -> DiceResult<DiceExpression> {
+        self.pos += 1;
+        let mut args = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(b')') {
+            self.pos += 1;
+            return Ok(DiceExpression::Call(name, args));
+        }
+        loop {
+            args.push(self.expression()?);
+            self.skip_whitespace();
+            if self.peek() != Some(b',') {
+                break;
+            }
+            self.pos += 1;
+        }
+        self.expect(b')')?;
+        Ok(DiceExpression::Call(name, args))
+    }
+
+    /// Parse `d` and the number of sides after it, `%` meaning 100
+    fn sides(&mut self) -> DiceResult<u32> {
+        let d = self.pos;
+        self.pos += 1;
+        if self.peek() == Some(b'%') {
+            self.pos += 1;
+            return Ok(100);
+        }
+        if !self.at_digit() {
+            return Err(DiceError::ParseError {
+                position: d,
+                message: "expected sides after 'd'".to_string(),
+            });
+        }
+        self.number()
+    }
+
+    /// Parse the optional explode, selection and success suffixes of a pool
+    fn modifiers(&mut self, dice: DiceExpression, sides: u32) -> DiceResult<DiceExpression> {
+        let dice = self.explode(dice, sides)?;
+        let pool = self.selection(dice)?;
+        let total = self.success(pool)?;
+        self.clamps(total)
+    }
+
+    /// Parse `!`, `!>N` or `!{A,B}`; a bare `!` explodes on the highest face
+    fn explode(&mut self, pool: DiceExpression, sides: u32) -> DiceResult<DiceExpression> {
+        // `!=` is a comparison
+        if self.peek() != Some(b'!') || self.peek_at(1) == Some(b'=') {
+            return Ok(pool);
+        }
+        self.pos += 1;
+        if self.peek() == Some(b'{') {
+            self.pos += 1;
+            return Ok(DiceExpression::ExplodeOn(Box::new(pool), self.faces()?));
+        }
+        let threshold = if self.peek() == Some(b'>') {
+            self.pos += 1;
+            if !self.at_digit() {
+                return Err(self.error());
+            }
+            self.number()?
+        } else {
+            sides
+        };
+        Ok(DiceExpression::Explode(Box::new(pool), threshold))
+    }
+
+    /// Parse the faces of `!{A,B}` up to the closing brace, sorted and
+    /// without repeats
+    fn faces(&mut self) -> DiceResult<Vec<u32>> {
+        let mut faces = Vec::new();
+        loop {
+            self.skip_whitespace();
+            if !self.at_digit() {
+                return Err(self.error());
+            }
+            faces.push(self.number()?);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b'}') => break,
+                _ => return Err(self.error()),
+            }
+        }
+        self.pos += 1;
+        faces.sort_unstable();
+        faces.dedup();
+        Ok(faces)
+    }
+
+    fn success(&mut self, pool: DiceExpression) -> DiceResult<DiceExpression> {
+        // `>=` is a comparison
+        if self.peek() != Some(b'>') || self.peek_at(1) == Some(b'=') {
+            return Ok(pool);
+        }
+        self.pos += 1;
+        if !self.at_digit() {
+            return Err(self.error());
+        }
+        let target = self.number()?;
+        if self.peek_lower() != Some(b'b') {
+            return Ok(DiceExpression::CountSuccesses(Box::new(pool), target));
+        }
+        self.pos += 1;
+        if !self.at_digit() {
+            return Err(self.error());
+        }
+        let botch = self.number()?;
+        Ok(DiceExpression::CountSuccessesWithBotch(Box::new(pool), target, botch))
+    }
+
+    /// Parse any run of `minN` and `maxN`; a limit below 1 is an invalid die
+    fn clamps(&mut self, mut total: DiceExpression) -> DiceResult<DiceExpression> {
+        type Clamp = fn(Box<DiceExpression>, i32) -> DiceExpression;
+
+        loop {
+            let clamp: Clamp = match self.input.get(self.pos..self.pos + 3) {
+                Some("min") => DiceExpression::FloorClamp,
+                Some("max") => DiceExpression::CeilClamp,
+                _ => return Ok(total),
+            };
+            self.pos += 3;
+            if !self.at_digit() {
+                return Err(self.error());
+            }
+            let limit = i32::try_from(self.number()?).map_err(|_| self.error())?;
+            if limit < 1 {
+                return Err(DiceError::InvalidSides(limit));
+            }
+            total = clamp(Box::new(total), limit);
+        }
+    }
+
+    fn selection(&mut self, pool: DiceExpression) -> DiceResult<DiceExpression> {
+        type Selection = fn(Box<DiceExpression>, u32) -> DiceExpression;
+
+        let kind: Selection = match self.peek_lower() {
+            Some(b'k') => {
+                self.pos += 1;
+                match self.peek_lower() {
+                    Some(b'h') => {
+                        self.pos += 1;
+                        DiceExpression::KeepHighest
+                    }
+                    Some(b'l') => {
+                        self.pos += 1;
+                        DiceExpression::KeepLowest
+                    }
+                    _ => DiceExpression::KeepHighest,
+                }
+            }
+            Some(b'h') => {
+                self.pos += 1;
+                DiceExpression::KeepHighest
+            }
+            Some(b'l') => {
+                self.pos += 1;
+                DiceExpression::DropLowest
+            }
+            Some(b'd') => {
+                self.pos += 1;
+                match self.peek_lower() {
+                    Some(b'h') => {
+                        self.pos += 1;
+                        DiceExpression::DropHighest
+                    }
+                    Some(b'l') => {
+                        self.pos += 1;
+                        DiceExpression::DropLowest
+                    }
+                    _ => DiceExpression::DropLowest,
+                }
+            }
+            _ => return Ok(pool),
+        };
+
+        // A bare selector keeps or drops a single die
+        let count = if self.at_digit() { self.number()? } else { 1 };
+        Ok(kind(Box::new(pool), count))
+    }
+
+    /// Consume a closing delimiter
+    fn expect(&mut self, close: u8) -> DiceResult<()> {
+        self.skip_whitespace();
+        if self.peek() != Some(close) {
+            return Err(self.error());
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn at_digit(&self) -> bool {
+        matches!(self.peek(), Some(c) if c.is_ascii_digit())
+    }
+
+    fn number(&mut self) -> DiceResult<u32> {
+        let start = self.pos;
+        while matches!(self.peek(), Some(c) if c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        self.input[start..self.pos]
+            .parse()
+            .map_err(|_| self.error())
+    }
+}
+
+fn is_identifier_start(c: u8) -> bool {
+    c.is_ascii_uppercase() || c == b'_'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_basic() {
+        assert!(matches!(parse("3d6"), Ok(DiceExpression::Dice(3, 6))));
+        assert!(matches!(parse("1D20"), Ok(DiceExpression::Dice(1, 20))));
+        assert!(matches!(parse("42"), Ok(DiceExpression::Constant(42))));
+        assert!(matches!(parse(" 3d6 + 2 "), Ok(DiceExpression::Add(..))));
+    }
+
+    #[test]
+    fn test_parse_selection() {
+        for notation in ["4d6k3", "4d6kh3", "4d6h3", "4d6K3"] {
+            match parse(notation) {
+                Ok(DiceExpression::KeepHighest(pool, 3)) => {
+                    assert!(matches!(*pool, DiceExpression::Dice(4, 6)))
+                }
+                other => panic!("{}: {:?}", notation, other),
+            }
+        }
+        assert!(matches!(parse("2d20kl1"), Ok(DiceExpression::KeepLowest(_, 1))));
+        assert!(matches!(parse("5d6dh2"), Ok(DiceExpression::DropHighest(_, 2))));
+        assert!(matches!(parse("4d6l1"), Ok(DiceExpression::DropLowest(_, 1))));
+        assert!(matches!(parse("4d6dl1"), Ok(DiceExpression::DropLowest(_, 1))));
+        assert!(matches!(parse("8d4d4"), Ok(DiceExpression::DropLowest(_, 4))));
+    }
+
+    #[test]
+    fn test_parse_successes() {
+        match parse("8d6>4") {
+            Ok(DiceExpression::CountSuccesses(pool, 4)) => {
+                assert!(matches!(*pool, DiceExpression::Dice(8, 6)))
+            }
+            other => panic!("{:?}", other),
+        }
+        assert!(matches!(parse("5d6k3>4"), Ok(DiceExpression::CountSuccesses(..))));
+        assert!(parse("8d6>").is_err());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for notation in [
+            "", "3d", "d", "3d6+", "3>2", "(3d6", "3d6)", "4d6k>", "abc", "3d6 7", "3d6!>",
+        ] {
+            assert!(parse(notation).is_err(), "{} should not parse", notation);
+        }
+        assert_eq!(parse("0d6").unwrap_err(), DiceError::InvalidCount(0));
+        assert_eq!(parse("3d0").unwrap_err(), DiceError::InvalidSides(0));
+    }
+
+    /// Debug form of the tree, for comparing parses
+    fn tree(notation: &str) -> String {
+        format!("{:?}", parse(notation).unwrap())
+    }
+
+    #[test]
+    fn test_precedence() {
+        for (notation, grouped) in [
+            ("3d6+2*4", "3d6+(2*4)"),
+            ("2*3d6+4", "(2*3d6)+4"),
+            ("3d6-1d4*2", "3d6-(1d4*2)"),
+            ("3d6+2*1d4", "3d6+(2*1d4)"),
+            ("1d8/2-1", "(1d8/2)-1"),
+            ("-1d6*2", "(-1d6)*2"),
+            ("10-2-3", "(10-2)-3"),
+            ("24/4/2", "(24/4)/2"),
+        ] {
+            assert_eq!(tree(notation), tree(grouped), "{}", notation);
+        }
+        assert_ne!(tree("3d6+2*4"), tree("(3d6+2)*4"));
+        assert_ne!(tree("2*(3d6+4)"), tree("2*3d6+4"));
+    }
+
+    #[test]
+    fn test_chained_modifiers() {
+        assert_eq!(tree("4d6k3+5-1d4+2"), tree("(((4d6k3)+5)-1d4)+2"));
+        assert_eq!(tree("1d20+5-2+1d4-1"), tree("((((1d20+5)-2)+1d4)-1)"));
+        match parse("4d6k3+5-1d4+2") {
+            Ok(DiceExpression::Add(left, right)) => {
+                assert!(matches!(*right, DiceExpression::Constant(2)));
+                assert!(matches!(*left, DiceExpression::Sub(..)));
+            }
+            other => panic!("{:?}", other),
+        }
+        let long = vec!["1d4"; 50].join("+") + "-3";
+        assert!(parse(&long).is_ok());
+    }
+
+    #[test]
+    fn test_parse_negation() {
+        assert!(matches!(parse("-5"), Ok(DiceExpression::Constant(-5))));
+        assert!(matches!(parse("-2d6"), Ok(DiceExpression::Negate(_))));
+        match parse("-1d6+3d8") {
+            Ok(DiceExpression::Add(left, right)) => {
+                assert!(matches!(*left, DiceExpression::Negate(_)));
+                assert!(matches!(*right, DiceExpression::Dice(3, 8)));
+            }
+            other => panic!("{:?}", other),
+        }
+        match parse("2*-1d4") {
+            Ok(DiceExpression::Mul(_, right)) => {
+                assert!(matches!(*right, DiceExpression::Negate(_)))
+            }
+            other => panic!("{:?}", other),
+        }
+        assert!(matches!(parse("-(1d6+2)"), Ok(DiceExpression::Negate(_))));
+        assert!(parse("-").is_err());
+        assert!(parse("3d6+-").is_err());
+    }
+
+    #[test]
+    fn test_parse_min_max() {
+        match parse("min(2d6, 1d8)") {
+            Ok(DiceExpression::Min(left, right)) => {
+                assert!(matches!(*left, DiceExpression::Dice(2, 6)));
+                assert!(matches!(*right, DiceExpression::Dice(1, 8)));
+            }
+            other => panic!("{:?}", other),
+        }
+        match parse("max( 1d4 + 1 , min(1d6, 3) ) + 2") {
+            Ok(DiceExpression::Add(left, _)) => match *left {
+                DiceExpression::Max(_, right) => {
+                    assert!(matches!(*right, DiceExpression::Min(..)))
+                }
+                other => panic!("{:?}", other),
+            },
+            other => panic!("{:?}", other),
+        }
+        for notation in ["min(1d6)", "min(1, 2, 3)", "mix(1, 2)", "min 1, 2", "max(1,"] {
+            assert!(parse(notation).is_err(), "{} should not parse", notation);
+        }
+        // Upper-case names are user-defined functions
+        assert!(matches!(parse("MIN(1, 2)"), Ok(DiceExpression::Call(..))));
+    }
+
+    #[test]
+    fn test_parse_absolute_value() {
+        match parse("|2d6-7|") {
+            Ok(DiceExpression::AbsoluteValue(inner)) => {
+                assert!(matches!(*inner, DiceExpression::Sub(..)))
+            }
+            other => panic!("{:?}", other),
+        }
+        match parse("||1d6-4|-2|") {
+            Ok(DiceExpression::AbsoluteValue(inner)) => match *inner {
+                DiceExpression::Sub(left, _) => {
+                    assert!(matches!(*left, DiceExpression::AbsoluteValue(_)))
+                }
+                other => panic!("{:?}", other),
+            },
+            other => panic!("{:?}", other),
+        }
+        assert_eq!(
+            parse("|1d20 - 10|"),
+            Ok(DiceExpression::AbsoluteValue(Box::new(DiceExpression::Sub(
+                Box::new(DiceExpression::Dice(1, 20)),
+                Box::new(DiceExpression::Constant(10))
+            ))))
+        );
+        assert!(matches!(parse("|1d6|-|1d4|"), Ok(DiceExpression::Sub(..))));
+        assert!(matches!(parse("2*| 1d4 - 3 |"), Ok(DiceExpression::Mul(..))));
+        for notation in ["|1d6", "1d6|", "||", "|1d6-|"] {
+            assert!(parse(notation).is_err(), "{} should not parse", notation);
+        }
+    }
+
+    #[test]
+    fn test_parse_variables() {
+        assert!(matches!(parse("MOD"), Ok(DiceExpression::Variable(name)) if name == "MOD"));
+        assert!(matches!(parse("_X2"), Ok(DiceExpression::Variable(name)) if name == "_X2"));
+        match parse("Nd6+MOD") {
+            Ok(DiceExpression::Add(left, right)) => {
+                match *left {
+                    DiceExpression::DynamicDice(count, 6) => {
+                        assert!(matches!(*count, DiceExpression::Variable(name) if name == "N"))
+                    }
+                    other => panic!("{:?}", other),
+                }
+                assert!(matches!(*right, DiceExpression::Variable(name) if name == "MOD"));
+            }
+            other => panic!("{:?}", other),
+        }
+        assert!(matches!(parse("POOLd10k3"), Ok(DiceExpression::KeepHighest(..))));
+        assert!(matches!(parse("DEX+D20"), Ok(DiceExpression::Add(..))));
+        assert!(matches!(parse("D20"), Ok(DiceExpression::Dice(1, 20))));
+        assert!(parse("Nd").is_err());
+        assert!(parse("Nd0").is_err());
+        assert!(parse("mod").is_err());
+    }
+
+    #[test]
+    fn test_parse_calls() {
+        assert_eq!(tree("DAMAGE(6, 4)"), "Call(\"DAMAGE\", [Constant(6), Constant(4)])");
+        assert_eq!(tree("F()+1"), "Add(Call(\"F\", []), Constant(1))");
+        assert_eq!(
+            tree("F(1d6+2, G(N))*2"),
+            "Mul(Call(\"F\", [Add(Dice(1, 6), Constant(2)), Call(\"G\", [Variable(\"N\")])]), Constant(2))"
+        );
+        assert!(parse("F(1,").is_err());
+        assert!(parse("F(1 2)").is_err());
+        assert!(parse("F(").is_err());
+    }
+
+    #[test]
+    fn test_parse_qualified_calls() {
+        assert_eq!(
+            tree("player::damage_roll(6)+1d4"),
+            "Add(Call(\"player::damage_roll\", [Constant(6)]), Dice(1, 4))"
+        );
+        assert_eq!(tree("a::b::C2()"), "Call(\"a::b::C2\", [])");
+        // A module name may start like a die or `min`
+        assert_eq!(tree("dm::d6(1)"), "Call(\"dm::d6\", [Constant(1)])");
+        assert_eq!(tree("min::x(1)"), "Call(\"min::x\", [Constant(1)])");
+        for notation in ["player::bonus", "player::(1)", "player::2x(1)", "player:::x(1)"] {
+            assert!(parse(notation).is_err(), "{}", notation);
+        }
+    }
+
+    #[test]
+    fn test_parse_conditional() {
+        assert_eq!(
+            tree("(d20>=15)?(2d6+5):(1d4)"),
+            "Conditional { condition: Dice(1, 20), predicate: AtLeast(15), \
+             then_expr: Add(Dice(2, 6), Constant(5)), else_expr: Dice(1, 4) }"
+        );
+        assert_eq!(
+            tree("(1d6+1 < -2) ? 1 : 2 + 3"),
+            "Add(Conditional { condition: Add(Dice(1, 6), Constant(1)), predicate: LessThan(-2), \
+             then_expr: Constant(1), else_expr: Constant(2) }, Constant(3))"
+        );
+        assert!(tree("(1d6)?1:0").contains("predicate: NotEqual(0)"));
+        assert!(tree("(1d6!=3)?1:0").contains("condition: Dice(1, 6), predicate: NotEqual(3)"));
+        assert!(tree("(1d20>15)?1:0").contains("condition: CountSuccesses(Dice(1, 20), 15)"));
+        assert!(tree("((1d20)>15)?1:0").contains("predicate: GreaterThan(15)"));
+        assert!(tree("(8d6>4==2)?1:0").contains("CountSuccesses(Dice(8, 6), 4), predicate: Equal"));
+        assert!(tree("(1d6<=2)?(1d4>=2)?1:2:3").contains("then_expr: Conditional"));
+        for notation in ["(1d6>=3)", "(1d6>=)?1:0", "(1d6)?1", "(1d6)?1:", "(1d6=3)?1:0"] {
+            assert!(parse(notation).is_err(), "{} should not parse", notation);
+        }
+    }
+
+    #[test]
+    fn test_missing_sides() {
+        let expected = |position| DiceError::ParseError {
+            position,
+            message: "expected sides after 'd'".to_string(),
+        };
+        assert_eq!(parse("3d").unwrap_err(), expected(1));
+        assert_eq!(parse("d").unwrap_err(), expected(0));
+        assert_eq!(parse("2d6+d").unwrap_err(), expected(4));
+    }
+
+    #[test]
+    fn test_implicit_count() {
+        assert!(matches!(parse("d6"), Ok(DiceExpression::Dice(1, 6))));
+        assert!(matches!(parse("D20"), Ok(DiceExpression::Dice(1, 20))));
+        match parse("d20+d4") {
+            Ok(DiceExpression::Add(left, right)) => {
+                assert!(matches!(*left, DiceExpression::Dice(1, 20)));
+                assert!(matches!(*right, DiceExpression::Dice(1, 4)));
+            }
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_percentile() {
+        assert!(matches!(parse("d%"), Ok(DiceExpression::Dice(1, 100))));
+        assert!(matches!(parse("3d%"), Ok(DiceExpression::Dice(3, 100))));
+        assert!(matches!(parse("D%"), Ok(DiceExpression::Dice(1, 100))));
+        assert!(matches!(parse("2d%k1"), Ok(DiceExpression::KeepHighest(_, 1))));
+        match parse("d%+5") {
+            Ok(DiceExpression::Add(left, right)) => {
+                assert!(matches!(*left, DiceExpression::Dice(1, 100)));
+                assert!(matches!(*right, DiceExpression::Constant(5)));
+            }
+            other => panic!("{:?}", other),
+        }
+        assert!(parse("d%%").is_err());
+        assert!(parse("4d%0").is_err());
+    }
+
+    #[test]
+    fn test_default_selection_count() {
+        assert!(matches!(parse("2d20k"), Ok(DiceExpression::KeepHighest(_, 1))));
+        assert!(matches!(parse("2d20kh"), Ok(DiceExpression::KeepHighest(_, 1))));
+        assert!(matches!(parse("2d20h"), Ok(DiceExpression::KeepHighest(_, 1))));
+        assert!(matches!(parse("2d20kl"), Ok(DiceExpression::KeepLowest(_, 1))));
+        assert!(matches!(parse("4d6dh"), Ok(DiceExpression::DropHighest(_, 1))));
+        assert!(matches!(parse("4d6l"), Ok(DiceExpression::DropLowest(_, 1))));
+        assert!(matches!(parse("4d6dl"), Ok(DiceExpression::DropLowest(_, 1))));
+        assert!(matches!(parse("4d6d"), Ok(DiceExpression::DropLowest(_, 1))));
+        assert!(matches!(parse("4d6k>4"), Ok(DiceExpression::CountSuccesses(..))));
+    }
+
+    #[test]
+    fn test_parse_explode() {
+        match parse("3d6!") {
+            Ok(DiceExpression::Explode(pool, 6)) => {
+                assert!(matches!(*pool, DiceExpression::Dice(3, 6)))
+            }
+            other => panic!("{:?}", other),
+        }
+        assert!(matches!(parse("d10!"), Ok(DiceExpression::Explode(_, 10))));
+        assert!(matches!(parse("3d6!>5"), Ok(DiceExpression::Explode(_, 5))));
+        match parse("1d6!{6, 1,6}") {
+            Ok(DiceExpression::ExplodeOn(pool, faces)) => {
+                assert!(matches!(*pool, DiceExpression::Dice(1, 6)));
+                assert_eq!(faces, vec![1, 6]);
+            }
+            other => panic!("{:?}", other),
+        }
+        for notation in ["1d6!{}", "1d6!{1,}", "1d6!{1", "1d6!{x}"] {
+            assert!(parse(notation).is_err(), "{}", notation);
+        }
+        match parse("4d6!k3>5") {
+            Ok(DiceExpression::CountSuccesses(pool, 5)) => {
+                assert!(matches!(*pool, DiceExpression::KeepHighest(_, 3)))
+            }
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_partial() {
+        let (expr, rest) = DiceParser::parse_partial("2d6+3 damage").unwrap();
+        assert_eq!(expr.to_notation(), "2d6+3");
+        assert_eq!(rest, " damage");
+
+        let (expr, rest) = DiceParser::parse_partial("(1d8 + 2) * 2] and more").unwrap();
+        assert_eq!(expr.to_notation(), "(1d8+2)*2");
+        assert_eq!(rest, "] and more");
+
+        let (expr, rest) = DiceParser::parse_partial("4d6k3").unwrap();
+        assert_eq!((expr.to_notation().as_str(), rest), ("4d6k3", ""));
+        assert!(DiceParser::parse_partial("damage").is_err());
+        assert!(DiceParser::parse_partial("(1d6 damage)").is_err());
+    }
+
+    #[test]
+    fn test_digit_dice() {
+        assert_eq!(parse("d66").unwrap().to_notation(), "1d6*10+1d6");
+        assert_eq!(parse("D666").unwrap().to_notation(), "1d6*100+1d6*10+1d6");
+        assert_eq!(parse("d66+1").unwrap().to_notation(), "1d6*10+1d6+1");
+        assert_eq!(parse("1d66").unwrap().to_notation(), "1d66");
+        assert_eq!(parse("d67").unwrap().to_notation(), "1d67");
+        assert_eq!(parse("d6666").unwrap().to_notation(), "1d6666");
+
+        let expr = parse("d66").unwrap();
+        let mut rng = crate::DiceRng::new(146);
+        for _ in 0..200 {
+            let result = expr.roll(&mut rng).unwrap();
+            let dice = result.individual();
+            assert_eq!(result.total, dice[0] * 10 + dice[1]);
+        }
+        assert!(parse("d66k1").is_err());
+    }
+
+    #[test]
+    fn test_parse_botch() {
+        assert!(matches!(
+            parse("5d10>7b1"),
+            Ok(DiceExpression::CountSuccessesWithBotch(_, 7, 1))
+        ));
+        assert!(matches!(parse("5d10k4>8B2"), Ok(DiceExpression::CountSuccessesWithBotch(..))));
+        assert!(matches!(parse("5d10>7b1+1"), Ok(DiceExpression::Add(..))));
+        assert!(parse("5d10>7b").is_err());
+        assert!(parse("5d10b1").is_err());
+    }
+
+    #[test]
+    fn test_parse_clamps() {
+        match parse("1d6min2max5") {
+            Ok(DiceExpression::CeilClamp(inner, 5)) => {
+                assert!(matches!(*inner, DiceExpression::FloorClamp(_, 2)))
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(matches!(parse("1d6min7"), Ok(DiceExpression::FloorClamp(_, 7))));
+        assert!(matches!(parse("8d6>4max3"), Ok(DiceExpression::CeilClamp(..))));
+        assert!(matches!(parse("4d6k3min8+1"), Ok(DiceExpression::Add(..))));
+        assert!(matches!(parse("max(1d6min2, 3)"), Ok(DiceExpression::Max(..))));
+        assert_eq!(parse("1d6min0"), Err(DiceError::InvalidSides(0)));
+        assert!(parse("1d6min").is_err());
+        assert!(parse("1d6max(2)").is_err());
+    }
+
+    #[test]
+    fn test_max_length() {
+        let long = format!("1d20{}", "+1".repeat(998));
+        assert_eq!(long.len(), 2000);
+        assert_eq!(
+            parse(&long),
+            Err(DiceError::InvalidNotation(
+                "notation exceeds maximum length of 1024 bytes".to_string()
+            ))
+        );
+        // Rejected before the invalid tail is reached
+        let invalid = format!("{}@", "1+".repeat(1000));
+        assert!(matches!(
+            parse(&invalid),
+            Err(DiceError::InvalidNotation(message)) if message.contains("1024")
+        ));
+        assert!(parse(&"1+".repeat(300)[..599]).is_ok());
+        assert!(matches!(
+            DiceParser::parse_partial(&format!("{} damage", long)),
+            Err(DiceError::InvalidNotation(message)) if message.contains("1024")
+        ));
+
+        // The global limit is shared with tests running alongside, so
+        // other limits are checked directly
+        assert_eq!(check_length(&long, 65536), Ok(()));
+        assert_eq!(check_length(&long, 2000), Ok(()));
+        assert!(check_length(&long, 1999).is_err());
+    }
+
+    #[test]
+    fn test_max_nesting_depth() {
+        let nested = |depth: usize, value: &str| {
+            format!("{}{}{}", "(".repeat(depth), value, ")".repeat(depth))
+        };
+        let too_deep = Err(DiceError::InvalidNotation(
+            "notation exceeds maximum nesting depth of 128".to_string(),
+        ));
+        // The innermost number is a factor too
+        assert!(parse(&nested(127, "1d6")).is_ok());
+        assert_eq!(parse(&nested(128, "1d6")), too_deep);
+        assert_eq!(parse(&format!("{}1", "-".repeat(200))), too_deep);
+        assert_eq!(parse(&format!("{}1{}", "|".repeat(150), "|".repeat(150))), too_deep);
+        assert!(parse(&format!("1{}", "+(1)".repeat(200))).is_ok());
+
+        // Far past the default length, as a raised limit would allow
+        let deep = nested(100_000, "1");
+        assert_eq!(Parser::new(&deep).expression(), too_deep);
+    }
+
+    #[test]
+    fn test_find_and_evaluate_all() {
+        let mut rng = crate::DiceRng::new(140);
+        assert_eq!(
+            DiceParser::find_and_evaluate_all("deal [2*3] damage, [1d1+1] more", &mut rng),
+            "deal 6 damage, 2 more"
+        );
+        assert_eq!(
+            DiceParser::find_and_evaluate_all("[oops] [1d1] [1d6/0] [unclosed", &mut rng),
+            "[oops] 1 [1d6/0] [unclosed"
+        );
+        let rolled = DiceParser::find_and_evaluate_all("hit for [1d6]", &mut rng);
+        let total: i32 = rolled.trim_start_matches("hit for ").parse().unwrap();
+        assert!((1..=6).contains(&total));
+    }
+}
@@ -0,0 +1,858 @@
+//! Dice pools
+//!
+//! A `DicePool` is a stock of physical dice. Burning rolls dice out of the
+//! pool and removes them until they are replenished, as in Burning Wheel
+//! artha or spent FATE dice.
+//!
+//! A `NamedDicePool` is a labeled set of rolls made together, such as a
+//! character's main hand, off hand and bonus damage. Its totals come back
+//! as a [`LabeledRollSet`] that sorts for display, e.g. by initiative.
+//!
+//! Rolling a whole `DicePool` with [`DicePool::roll_and_sort`] leaves the
+//! dice in place and returns the values sorted for display.
+//! [`DicePool::roll`] also leaves them in place, applying the pool's
+//! [`PoolModifier`]s from first added to last, so
+//! `DicePool::new().with_exploding_dice(None).with_keep_highest(3)` explodes
+//! every die before keeping the best three.
+
+use crate::config::RollConfig;
+use crate::context::DiceContext;
+use crate::die::Die;
+use crate::eval::{DieRoll, RollResult};
+use crate::expression::DiceExpression;
+use crate::rng::DiceRng;
+use crate::{Dice, DiceError, DiceResult};
+use rand::Rng;
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+/// Order of the values in a [`SortedPoolResult`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SortOrder {
+    Ascending,
+    Descending,
+    /// Grouped by die size, fewest sides first, highest value first
+    /// within each group
+    ByGroup,
+}
+
+/// Every die of a pool rolled once, values sorted
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SortedPoolResult {
+    pub sorted_values: Vec<i32>,
+    pub sum: i32,
+}
+
+/// The values separated by commas: `6, 4, 4, 1`
+impl fmt::Display for SortedPoolResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, value) in self.sorted_values.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", value)?;
+        }
+        Ok(())
+    }
+}
+
+/// A change [`DicePool::roll`] makes to the rolled dice
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PoolModifier {
+    /// Roll another die of the same size for every die showing at least
+    /// the threshold, or its highest face when `None`
+    Explode(Option<i32>),
+    /// Reroll every die showing less than the threshold until it doesn't
+    RerollBelow(i32),
+    /// Keep the `n` highest dice and drop the rest
+    KeepHighest(u32),
+}
+
+/// Collection of dice available to roll
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DicePool {
+    dice: Vec<Die>,
+    modifiers: Vec<PoolModifier>,
+}
+
+impl DicePool {
+    pub fn new() -> Self {
+        DicePool::default()
+    }
+
+    /// Explode dice when [rolled](Self::roll)
+    ///
+    /// `None` explodes each die on its highest face, `Some(4)` on 4 or more.
+    pub fn with_exploding_dice(mut self, threshold: Option<i32>) -> DicePool {
+        self.modifiers.push(PoolModifier::Explode(threshold));
+        self
+    }
+
+    /// Reroll dice showing less than `threshold` when [rolled](Self::roll)
+    pub fn with_reroll_below(mut self, threshold: i32) -> DicePool {
+        self.modifiers.push(PoolModifier::RerollBelow(threshold));
+        self
+    }
+
+    /// Keep only the `n` highest dice when [rolled](Self::roll)
+    pub fn with_keep_highest(mut self, n: u32) -> DicePool {
+        self.modifiers.push(PoolModifier::KeepHighest(n));
+        self
+    }
+
+    /// Dice in the pool, in the order they were added
+    pub fn dice(&self) -> &[Die] {
+        &self.dice
+    }
+
+    /// Modifiers [`roll`](Self::roll) applies, in the order it applies them
+    pub fn modifiers(&self) -> &[PoolModifier] {
+        &self.modifiers
+    }
+
+    pub fn len(&self) -> usize {
+        self.dice.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.dice.is_empty()
+    }
+
+    /// Number of `sides`-sided dice available
+    pub fn remaining(&self, sides: u32) -> u32 {
+        self.dice.iter().filter(|die| die.sides() == sides).count() as u32
+    }
+
+    /// Add `count` dice of `sides` sides, e.g. after a rest
+    pub fn replenish(&mut self, sides: u32, count: u32) -> DiceResult<()> {
+        let die = Die::new(sides)?;
+        self.dice.extend(std::iter::repeat_n(die, count as usize));
+        Ok(())
+    }
+
+    /// Add one die to the top of the pool
+    pub fn push_die(&mut self, die: Die) {
+        self.dice.push(die);
+    }
+
+    /// Remove and return the most recently added die
+    pub fn pop_die(&mut self) -> Option<Die> {
+        self.dice.pop()
+    }
+
+    /// Keep only the dice `predicate` accepts, in their current order
+    pub fn retain_dice(&mut self, predicate: impl Fn(&Die) -> bool) {
+        self.dice.retain(predicate);
+    }
+
+    /// Order the dice from fewest to most sides
+    ///
+    /// Dice of the same size keep their relative order, so the last die of
+    /// each size is still the first [`burn`](Self::burn) takes.
+    pub fn sort_by_sides(&mut self) {
+        self.dice.sort_by_key(Die::sides);
+    }
+
+    /// Roll every die in the pool once, without removing any, and sort
+    /// the values
+    ///
+    /// The sort is stable, so equal values keep the order of their dice.
+    /// An empty pool fails with [`DiceError::InvalidCount`].
+    pub fn roll_and_sort<R: Rng + ?Sized>(
+        &self,
+        rng: &mut R,
+        order: SortOrder,
+    ) -> DiceResult<SortedPoolResult> {
+        if self.dice.is_empty() {
+            return Err(DiceError::InvalidCount(0));
+        }
+        let mut rolled: Vec<(u32, i32)> = self
+            .dice
+            .iter()
+            .map(|die| (die.sides(), rng.gen_range(1..=die.sides()) as i32))
+            .collect();
+        let sum = rolled
+            .iter()
+            .try_fold(0i32, |sum, &(_, value)| sum.checked_add(value))
+            .ok_or(DiceError::Overflow)?;
+        match order {
+            SortOrder::Ascending => rolled.sort_by_key(|&(_, value)| value),
+            SortOrder::Descending => rolled.sort_by_key(|&(_, value)| Reverse(value)),
+            SortOrder::ByGroup => rolled.sort_by_key(|&(sides, value)| (sides, Reverse(value))),
+        }
+        Ok(SortedPoolResult {
+            sorted_values: rolled.into_iter().map(|(_, value)| value).collect(),
+            sum,
+        })
+    }
+
+    /// Roll every die in the pool once, without removing any, and apply
+    /// the pool's modifiers in order
+    ///
+    /// Explosions add their dice right after the die that exploded and keep
+    /// is applied to the dice still kept, so a later modifier only touches
+    /// the dice an earlier one kept. An empty pool fails with
+    /// [`DiceError::InvalidCount`].
+    pub fn roll<R: Rng + ?Sized>(&self, rng: &mut R) -> DiceResult<RollResult> {
+        self.roll_with_config(rng, &RollConfig::default())
+    }
+
+    /// [`roll`](Self::roll) under explicit limits
+    ///
+    /// Explosions chain at most `max_explosion_depth` times per die and a
+    /// die is rerolled at most `max_rerolls` times.
+    pub fn roll_with_config<R: Rng + ?Sized>(
+        &self,
+        rng: &mut R,
+        config: &RollConfig,
+    ) -> DiceResult<RollResult> {
+        if self.dice.is_empty() {
+            return Err(DiceError::InvalidCount(0));
+        }
+        let mut dice: Vec<DieRoll> = self
+            .dice
+            .iter()
+            .map(|die| DieRoll {
+                sides: die.sides(),
+                value: rng.gen_range(1..=die.sides()) as i32,
+                kept: true,
+            })
+            .collect();
+        for modifier in &self.modifiers {
+            dice = modifier.apply(dice, rng, config);
+        }
+        let total = dice
+            .iter()
+            .filter(|die| die.kept)
+            .try_fold(0i32, |total, die| total.checked_add(die.value))
+            .ok_or(DiceError::Overflow)?;
+        Ok(RollResult { total, dice })
+    }
+
+    /// Roll `count` dice of `sides` sides and remove them from the pool
+    ///
+    /// If fewer than `count` are available, every remaining die of that
+    /// size is still rolled and removed, and the call fails with
+    /// [`DiceError::InvalidCount`] carrying how many were rolled.
+    pub fn burn<R: Rng + ?Sized>(
+        &mut self,
+        sides: u32,
+        count: u32,
+        rng: &mut R,
+    ) -> DiceResult<RollResult> {
+        Die::new(sides)?;
+        if count == 0 {
+            return Err(DiceError::InvalidCount(0));
+        }
+
+        let mut burned = 0;
+        // Burn the most recently added dice first
+        let mut index = self.dice.len();
+        while burned < count && index > 0 {
+            index -= 1;
+            if self.dice[index].sides() == sides {
+                self.dice.remove(index);
+                burned += 1;
+            }
+        }
+
+        let dice: Vec<DieRoll> = (0..burned)
+            .map(|_| DieRoll {
+                sides,
+                value: rng.gen_range(1..=sides) as i32,
+                kept: true,
+            })
+            .collect();
+        if burned < count {
+            return Err(DiceError::InvalidCount(burned as i32));
+        }
+        let total = dice
+            .iter()
+            .try_fold(0i32, |total, die| total.checked_add(die.value))
+            .ok_or(DiceError::Overflow)?;
+        Ok(RollResult { total, dice })
+    }
+}
+
+impl PoolModifier {
+    fn apply<R: Rng + ?Sized>(
+        &self,
+        dice: Vec<DieRoll>,
+        rng: &mut R,
+        config: &RollConfig,
+    ) -> Vec<DieRoll> {
+        match *self {
+            PoolModifier::Explode(threshold) => {
+                let mut exploded = Vec::with_capacity(dice.len());
+                for die in dice {
+                    exploded.push(die);
+                    if !die.kept {
+                        continue;
+                    }
+                    let threshold = threshold.unwrap_or(die.sides as i32);
+                    let mut value = die.value;
+                    let mut depth = 0;
+                    while value >= threshold && depth < config.max_explosion_depth {
+                        value = rng.gen_range(1..=die.sides) as i32;
+                        exploded.push(DieRoll { value, ..die });
+                        depth += 1;
+                    }
+                }
+                exploded
+            }
+            PoolModifier::RerollBelow(threshold) => dice
+                .into_iter()
+                .map(|mut die| {
+                    let mut rerolls = 0;
+                    while die.kept && die.value < threshold && rerolls < config.max_rerolls {
+                        die.value = rng.gen_range(1..=die.sides) as i32;
+                        rerolls += 1;
+                    }
+                    die
+                })
+                .collect(),
+            PoolModifier::KeepHighest(n) => {
+                let mut kept: Vec<usize> = (0..dice.len()).filter(|&i| dice[i].kept).collect();
+                // Stable sort so ties resolve to the earliest die
+                kept.sort_by_key(|&i| Reverse(dice[i].value));
+                let mut dice = dice;
+                for &i in kept.iter().skip(n as usize) {
+                    dice[i].kept = false;
+                }
+                dice
+            }
+        }
+    }
+}
+
+/// A total with the label it was rolled for
+///
+/// Rolls order by result, with ties broken alphabetically by label.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LabeledRoll {
+    pub label: String,
+    pub result: i32,
+}
+
+impl LabeledRoll {
+    pub fn new(label: &str, result: i32) -> LabeledRoll {
+        LabeledRoll {
+            label: label.to_string(),
+            result,
+        }
+    }
+}
+
+impl Ord for LabeledRoll {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.result.cmp(&other.result).then_with(|| self.label.cmp(&other.label))
+    }
+}
+
+impl PartialOrd for LabeledRoll {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for LabeledRoll {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}={}", self.label, self.result)
+    }
+}
+
+/// Labeled totals, in the order they were added until sorted
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LabeledRollSet {
+    rolls: Vec<LabeledRoll>,
+}
+
+impl LabeledRollSet {
+    pub fn new() -> Self {
+        LabeledRollSet::default()
+    }
+
+    pub fn push(&mut self, roll: LabeledRoll) {
+        self.rolls.push(roll);
+    }
+
+    pub fn rolls(&self) -> &[LabeledRoll] {
+        &self.rolls
+    }
+
+    pub fn len(&self) -> usize {
+        self.rolls.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rolls.is_empty()
+    }
+
+    /// Highest result first, as in an initiative order; ties go to the
+    /// label that sorts first
+    pub fn sort_by_result(&mut self) {
+        self.rolls.sort_by(|a, b| b.result.cmp(&a.result).then_with(|| a.label.cmp(&b.label)));
+    }
+
+    /// Alphabetical by label, ties by result
+    pub fn sort_by_label(&mut self) {
+        self.rolls.sort_by(|a, b| a.label.cmp(&b.label).then(a.result.cmp(&b.result)));
+    }
+}
+
+impl FromIterator<LabeledRoll> for LabeledRollSet {
+    fn from_iter<I: IntoIterator<Item = LabeledRoll>>(iter: I) -> Self {
+        LabeledRollSet {
+            rolls: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl IntoIterator for LabeledRollSet {
+    type Item = LabeledRoll;
+    type IntoIter = std::vec::IntoIter<LabeledRoll>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.rolls.into_iter()
+    }
+}
+
+/// Labeled expressions rolled as one set
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NamedDicePool {
+    // Ordered so seeded rolls are repeatable
+    pools: BTreeMap<String, DiceExpression>,
+}
+
+impl NamedDicePool {
+    pub fn new() -> Self {
+        NamedDicePool::default()
+    }
+
+    /// Add or replace the roll labeled `name`
+    pub fn insert(&mut self, name: &str, notation: &str) -> DiceResult<()> {
+        let expr = DiceExpression::parse(notation)?;
+        self.pools.insert(name.to_string(), expr);
+        Ok(())
+    }
+
+    /// Expression labeled `name`
+    pub fn get(&self, name: &str) -> Option<&DiceExpression> {
+        self.pools.get(name)
+    }
+
+    pub fn len(&self) -> usize {
+        self.pools.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pools.is_empty()
+    }
+
+    /// Roll every labeled expression once, in name order
+    pub fn roll_all<R: Rng + ?Sized>(
+        &self,
+        rng: &mut R,
+    ) -> HashMap<String, DiceResult<RollResult>> {
+        self.pools
+            .iter()
+            .map(|(name, expr)| (name.clone(), expr.roll(rng)))
+            .collect()
+    }
+
+    /// Roll every labeled expression once, in name order, keeping the totals
+    pub fn roll_labeled<R: Rng + ?Sized>(&self, rng: &mut R) -> DiceResult<LabeledRollSet> {
+        self.pools
+            .iter()
+            .map(|(name, expr)| Ok(LabeledRoll::new(name, expr.evaluate(rng)?)))
+            .collect()
+    }
+
+    /// Sum of one roll of every labeled expression
+    pub fn total<R: Rng + ?Sized>(&self, rng: &mut R) -> DiceResult<i32> {
+        self.pools.values().try_fold(0i32, |total, expr| {
+            total.checked_add(expr.evaluate(rng)?).ok_or(DiceError::Overflow)
+        })
+    }
+
+    /// Roll everything and return the label and result that rolled highest
+    ///
+    /// Ties go to the label that sorts first. An empty pool fails with
+    /// [`DiceError::InvalidCount`].
+    pub fn max_pool<R: Rng + ?Sized>(&self, rng: &mut R) -> DiceResult<(String, RollResult)> {
+        let mut best: Option<(String, RollResult)> = None;
+        for (name, expr) in &self.pools {
+            let result = expr.roll(rng)?;
+            if best.as_ref().is_none_or(|(_, highest)| result.total > highest.total) {
+                best = Some((name.clone(), result));
+            }
+        }
+        best.ok_or(DiceError::InvalidCount(0))
+    }
+
+    /// Chance the combined total is at least `target`
+    ///
+    /// Exact when every expression is plain dice and arithmetic; estimated
+    /// from [`DISTRIBUTION_SAMPLES`](crate::distribution::DISTRIBUTION_SAMPLES)
+    /// simulated totals otherwise.
+    pub fn probability_total_at_least(&self, target: i32) -> DiceResult<f64> {
+        let mut rng = DiceRng::new(rand::random());
+        self.probability_total_at_least_with(&mut rng, target)
+    }
+
+    /// [`probability_total_at_least`](Self::probability_total_at_least)
+    /// drawing any simulated rolls from `rng`
+    pub fn probability_total_at_least_with<R: Rng + ?Sized>(
+        &self,
+        rng: &mut R,
+        target: i32,
+    ) -> DiceResult<f64> {
+        let combined = self
+            .pools
+            .values()
+            .cloned()
+            .reduce(|left, right| DiceExpression::Add(Box::new(left), Box::new(right)))
+            .unwrap_or(DiceExpression::Constant(0));
+        Ok(combined
+            .distribution_with(rng)?
+            .range(target..)
+            .map(|(_, p)| p)
+            .sum())
+    }
+}
+
+impl DiceContext {
+    /// Roll `count` dice of `sides` sides out of a pool, removing them
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - Pool the dice are burned from
+    /// * `sides` - Size of the dice to burn
+    /// * `count` - Number of dice to burn
+    pub fn roll_burn(
+        &mut self,
+        pool: &mut DicePool,
+        sides: u32,
+        count: u32,
+    ) -> DiceResult<RollResult> {
+        pool.burn(sides, count, self.rng())
+    }
+}
+
+impl Dice {
+    /// Roll `count` dice of `sides` sides out of a pool, removing them
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - Pool the dice are burned from
+    /// * `sides` - Size of the dice to burn
+    /// * `count` - Number of dice to burn
+    pub fn roll_burn(pool: &mut DicePool, sides: u32, count: u32) -> DiceResult<RollResult> {
+        DiceContext::new().roll_burn(pool, sides, count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eval::MAX_EXPLOSION_DEPTH;
+
+    fn pool() -> DicePool {
+        let mut pool = DicePool::new();
+        pool.replenish(6, 4).unwrap();
+        pool.replenish(8, 2).unwrap();
+        pool
+    }
+
+    #[test]
+    fn test_burn_removes_dice() {
+        let mut ctx = DiceContext::with_seed(121);
+        let mut pool = pool();
+        let result = ctx.roll_burn(&mut pool, 6, 3).unwrap();
+        assert_eq!(result.dice.len(), 3);
+        assert!(result.individual().iter().all(|v| (1..=6).contains(v)));
+        assert_eq!(result.total, result.individual().iter().sum::<i32>());
+        assert_eq!(pool.remaining(6), 1);
+        assert_eq!(pool.remaining(8), 2);
+        assert_eq!(pool.len(), 3);
+    }
+
+    #[test]
+    fn test_burn_more_than_available() {
+        let mut ctx = DiceContext::with_seed(121);
+        let mut pool = pool();
+        assert_eq!(
+            ctx.roll_burn(&mut pool, 8, 5),
+            Err(DiceError::InvalidCount(2))
+        );
+        assert_eq!(pool.remaining(8), 0);
+        assert_eq!(ctx.roll_burn(&mut pool, 8, 1), Err(DiceError::InvalidCount(0)));
+        assert_eq!(pool.remaining(6), 4);
+    }
+
+    #[test]
+    fn test_burn_overflow() {
+        let mut ctx = DiceContext::with_seed(121);
+        let mut pool = DicePool::new();
+        pool.replenish(i32::MAX as u32, 3).unwrap();
+        assert_eq!(ctx.roll_burn(&mut pool, i32::MAX as u32, 3), Err(DiceError::Overflow));
+    }
+
+    #[test]
+    fn test_replenish() {
+        let mut ctx = DiceContext::with_seed(121);
+        let mut pool = pool();
+        ctx.roll_burn(&mut pool, 6, 4).unwrap();
+        assert_eq!(pool.remaining(6), 0);
+        pool.replenish(6, 4).unwrap();
+        assert_eq!(pool.remaining(6), 4);
+        assert_eq!(pool.replenish(0, 1), Err(DiceError::InvalidSides(0)));
+        assert_eq!(pool.replenish(u32::MAX, 3), Err(DiceError::InvalidSides(i32::MAX)));
+        assert!(Dice::roll_burn(&mut pool, 6, 2).is_ok());
+        assert_eq!(pool.remaining(6), 2);
+    }
+
+    #[test]
+    fn test_roll_and_sort() {
+        let mut rng = crate::DiceRng::new(165);
+        let pool = pool();
+        for _ in 0..100 {
+            let down = pool.roll_and_sort(&mut rng, SortOrder::Descending).unwrap();
+            assert_eq!(down.sorted_values.len(), 6);
+            assert!(down.sorted_values.windows(2).all(|pair| pair[0] >= pair[1]));
+            assert_eq!(down.sum, down.sorted_values.iter().sum::<i32>());
+            let up = pool.roll_and_sort(&mut rng, SortOrder::Ascending).unwrap();
+            assert!(up.sorted_values.windows(2).all(|pair| pair[0] <= pair[1]));
+
+            let grouped = pool.roll_and_sort(&mut rng, SortOrder::ByGroup).unwrap();
+            let (d6, d8) = grouped.sorted_values.split_at(4);
+            assert!(d6.iter().all(|v| (1..=6).contains(v)));
+            assert!(d6.windows(2).all(|pair| pair[0] >= pair[1]));
+            assert!(d8.windows(2).all(|pair| pair[0] >= pair[1]));
+        }
+        // Rolling leaves the pool as it was
+        assert_eq!(pool.len(), 6);
+        assert_eq!(
+            DicePool::new().roll_and_sort(&mut rng, SortOrder::Ascending),
+            Err(DiceError::InvalidCount(0))
+        );
+    }
+
+    #[test]
+    fn test_sorted_display() {
+        let result = SortedPoolResult {
+            sorted_values: vec![6, 4, 4, 1],
+            sum: 15,
+        };
+        assert_eq!(result.to_string(), "6, 4, 4, 1");
+    }
+
+    fn character() -> NamedDicePool {
+        let mut pools = NamedDicePool::new();
+        pools.insert("main_hand", "2d6+5").unwrap();
+        pools.insert("off_hand", "1d8+3").unwrap();
+        pools.insert("bonus", "1d4").unwrap();
+        pools
+    }
+
+    #[test]
+    fn test_named_pool_rolls() {
+        let mut rng = crate::DiceRng::new(142);
+        let pools = character();
+        assert_eq!(pools.len(), 3);
+        let results = pools.roll_all(&mut rng);
+        assert_eq!(results.len(), 3);
+        assert!((7..=17).contains(&results["main_hand"].as_ref().unwrap().total));
+        assert!((4..=11).contains(&results["off_hand"].as_ref().unwrap().total));
+        assert!((1..=4).contains(&results["bonus"].as_ref().unwrap().total));
+
+        for _ in 0..100 {
+            assert!((12..=32).contains(&pools.total(&mut rng).unwrap()));
+            let (name, result) = pools.max_pool(&mut rng).unwrap();
+            assert_ne!(name, "bonus");
+            let expr = pools.get(&name).unwrap();
+            assert!(result.total >= expr.min_result().unwrap());
+            assert!(result.total <= expr.max_result().unwrap());
+        }
+
+        assert_eq!(NamedDicePool::new().total(&mut rng), Ok(0));
+        assert_eq!(
+            NamedDicePool::new().max_pool(&mut rng),
+            Err(DiceError::InvalidCount(0))
+        );
+        let mut invalid = NamedDicePool::new();
+        assert!(invalid.insert("broken", "2d").is_err());
+        assert!(invalid.is_empty());
+    }
+
+    #[test]
+    fn test_labeled_roll_order() {
+        let mut set: LabeledRollSet =
+            [("B", 12), ("A", 15), ("C", 8)].iter().map(|&(l, r)| LabeledRoll::new(l, r)).collect();
+        set.sort_by_result();
+        let shown: Vec<_> = set.rolls().iter().map(ToString::to_string).collect();
+        assert_eq!(shown, ["A=15", "B=12", "C=8"]);
+        set.sort_by_label();
+        assert_eq!(set.rolls()[2], LabeledRoll::new("C", 8));
+
+        assert!(LabeledRoll::new("Z", 3) < LabeledRoll::new("A", 4));
+        assert!(LabeledRoll::new("A", 4) < LabeledRoll::new("B", 4));
+        let mut tied: LabeledRollSet =
+            ["Kim", "Ash", "Lee"].iter().map(|l| LabeledRoll::new(l, 10)).collect();
+        tied.sort_by_result();
+        let labels: Vec<_> = tied.into_iter().map(|roll| roll.label).collect();
+        assert_eq!(labels, ["Ash", "Kim", "Lee"]);
+    }
+
+    #[test]
+    fn test_roll_labeled() {
+        let mut rng = crate::DiceRng::new(170);
+        let mut initiative = character().roll_labeled(&mut rng).unwrap();
+        assert_eq!(initiative.rolls()[0].label, "bonus");
+        initiative.sort_by_result();
+        assert!(initiative.rolls().windows(2).all(|w| w[0].result >= w[1].result));
+        assert!(NamedDicePool::new().roll_labeled(&mut rng).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_named_pool_probability() {
+        let pools = character();
+        assert!((pools.probability_total_at_least(12).unwrap() - 1.0).abs() < 1e-9);
+        assert_eq!(pools.probability_total_at_least(33), Ok(0.0));
+        let exact = pools.probability_total_at_least(22).unwrap();
+        assert!(exact > 0.4 && exact < 0.6, "{}", exact);
+
+        let mut rng = crate::DiceRng::new(142);
+        let mut kept = NamedDicePool::new();
+        kept.insert("attack", "2d20k1").unwrap();
+        let p = kept.probability_total_at_least_with(&mut rng, 11).unwrap();
+        // 1 - (10/20)^2
+        assert!((p - 0.75).abs() < 0.01, "{}", p);
+    }
+
+    #[test]
+    fn test_stack_operations() {
+        let mut pool = pool();
+        pool.push_die(Die::D20);
+        assert_eq!(pool.len(), 7);
+        assert_eq!(pool.pop_die(), Some(Die::D20));
+        assert_eq!(pool.pop_die(), Some(Die::D8));
+        assert_eq!(pool.remaining(8), 1);
+
+        pool.push_die(Die::D4);
+        pool.sort_by_sides();
+        let sides: Vec<u32> = pool.dice().iter().map(|die| die.sides()).collect();
+        assert_eq!(sides, vec![4, 6, 6, 6, 6, 8]);
+
+        pool.retain_dice(|die| die.sides() >= 6);
+        assert_eq!(pool.len(), 5);
+        assert_eq!(pool.remaining(4), 0);
+        pool.retain_dice(|_| false);
+        assert_eq!(pool.pop_die(), None);
+    }
+
+    #[test]
+    fn test_burn_validation() {
+        let mut rng = crate::DiceRng::new(1);
+        let mut pool = pool();
+        assert_eq!(pool.burn(0, 1, &mut rng), Err(DiceError::InvalidSides(0)));
+        assert_eq!(pool.burn(6, 0, &mut rng), Err(DiceError::InvalidCount(0)));
+        assert_eq!(pool.len(), 6);
+    }
+
+    #[test]
+    fn test_modifiers_chain_in_order() {
+        let pool = pool()
+            .with_exploding_dice(None)
+            .with_reroll_below(2)
+            .with_keep_highest(3);
+        assert_eq!(
+            pool.modifiers(),
+            [
+                PoolModifier::Explode(None),
+                PoolModifier::RerollBelow(2),
+                PoolModifier::KeepHighest(3)
+            ]
+        );
+        let mut rng = crate::DiceRng::new(192);
+        for _ in 0..200 {
+            let result = pool.roll(&mut rng).unwrap();
+            assert!(result.dice.len() >= 6);
+            assert!(result.dice.iter().all(|die| die.value >= 2));
+            assert_eq!(result.kept().len(), 3);
+            assert_eq!(result.total, result.kept().iter().sum::<i32>());
+            let mut values = result.individual();
+            values.sort_by_key(|&value| Reverse(value));
+            assert_eq!(result.total, values[..3].iter().sum::<i32>());
+        }
+        assert_eq!(pool.len(), 6);
+        assert_eq!(DicePool::new().roll(&mut rng), Err(DiceError::InvalidCount(0)));
+    }
+
+    #[test]
+    fn test_exploding_pool() {
+        let mut rng = crate::DiceRng::new(192);
+        let plain = pool().roll(&mut rng).unwrap();
+        assert_eq!((plain.dice.len(), plain.kept().len()), (6, 6));
+
+        let exploding = pool().with_exploding_dice(Some(4));
+        let mut exploded = 0;
+        for _ in 0..200 {
+            let result = exploding.roll(&mut rng).unwrap();
+            // A die is followed by another of its size for every 4 or more
+            let mut dice = result.dice.iter();
+            let mut rolled = 0;
+            while let Some(die) = dice.next() {
+                rolled += 1;
+                let (mut last, mut depth) = (die, 0);
+                while last.value >= 4 && depth < MAX_EXPLOSION_DEPTH {
+                    last = dice.next().unwrap();
+                    assert_eq!(last.sides, die.sides);
+                    depth += 1;
+                }
+            }
+            assert_eq!(rolled, 6);
+            exploded += result.dice.len() - 6;
+        }
+        assert!(exploded > 0);
+
+        // Every face explodes, so the depth limit stops the chain
+        let config = RollConfig {
+            max_explosion_depth: 2,
+            ..RollConfig::default()
+        };
+        let result = pool().with_exploding_dice(Some(1)).roll_with_config(&mut rng, &config);
+        assert_eq!(result.unwrap().dice.len(), 18);
+    }
+
+    #[test]
+    fn test_modifier_order_matters() {
+        let mut rng = crate::DiceRng::new(192);
+        // Dice dropped before the explosion never explode
+        let late = pool().with_keep_highest(1).with_exploding_dice(Some(1));
+        let result = late.roll(&mut rng).unwrap();
+        assert_eq!(result.dice.len(), 6 + MAX_EXPLOSION_DEPTH as usize);
+        assert_eq!(result.kept().len(), 1 + MAX_EXPLOSION_DEPTH as usize);
+
+        let early = pool().with_exploding_dice(Some(1)).with_keep_highest(1);
+        let result = early.roll(&mut rng).unwrap();
+        assert_eq!(result.dice.len(), 6 * (1 + MAX_EXPLOSION_DEPTH as usize));
+        assert_eq!(result.kept().len(), 1);
+
+        // Rerolls give up at the limit
+        let config = RollConfig {
+            max_rerolls: 3,
+            ..RollConfig::default()
+        };
+        let result = pool().with_reroll_below(100).roll_with_config(&mut rng, &config).unwrap();
+        assert_eq!(result.dice.len(), 6);
+    }
+}
@@ -0,0 +1,146 @@
+//! Recharge dice
+//!
+//! Models abilities such as D&D's "Recharge 5-6": once used, the ability is
+//! unavailable until a roll on the recharge die meets the threshold.
+
+use crate::context::DiceContext;
+use crate::{Dice, DiceError, DiceResult};
+use rand::Rng;
+
+/// Ability gated on a recharge roll; starts uncharged
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RechargeDie {
+    sides: u32,
+    threshold: u32,
+    charged: bool,
+}
+
+impl RechargeDie {
+    /// Recharge die of `sides` sides that recharges on `threshold` or higher
+    pub fn new(sides: u32, threshold: u32) -> DiceResult<RechargeDie> {
+        if sides == 0 {
+            return Err(DiceError::InvalidSides(0));
+        }
+        if threshold == 0 || threshold > sides {
+            return Err(DiceError::InvalidSides(threshold as i32));
+        }
+        Ok(RechargeDie {
+            sides,
+            threshold,
+            charged: false,
+        })
+    }
+
+    pub fn sides(&self) -> u32 {
+        self.sides
+    }
+
+    pub fn threshold(&self) -> u32 {
+        self.threshold
+    }
+
+    pub fn is_charged(&self) -> bool {
+        self.charged
+    }
+
+    /// Roll the recharge die, charging the ability on `threshold` or higher
+    ///
+    /// Returns whether the ability is now charged. A charged ability
+    /// doesn't roll.
+    pub fn attempt_recharge<R: Rng + ?Sized>(&mut self, rng: &mut R) -> DiceResult<bool> {
+        if !self.charged {
+            self.charged = rng.gen_range(1..=self.sides) >= self.threshold;
+        }
+        Ok(self.charged)
+    }
+
+    /// Spend the charge
+    ///
+    /// Fails with [`DiceError::InvalidCount`]`(0)` if the ability isn't
+    /// charged.
+    pub fn use_ability(&mut self) -> DiceResult<()> {
+        if !self.charged {
+            return Err(DiceError::InvalidCount(0));
+        }
+        self.charged = false;
+        Ok(())
+    }
+}
+
+impl DiceContext {
+    /// Attempt to recharge an ability
+    ///
+    /// # Arguments
+    ///
+    /// * `die` - Recharge die to roll
+    /// * `sides` - Sides per die; must match the recharge die
+    pub fn roll_recharge(&mut self, die: &mut RechargeDie, sides: u32) -> DiceResult<bool> {
+        if sides != die.sides {
+            return Err(DiceError::InvalidSides(sides as i32));
+        }
+        die.attempt_recharge(self.rng())
+    }
+}
+
+impl Dice {
+    /// Attempt to recharge an ability
+    ///
+    /// # Arguments
+    ///
+    /// * `die` - Recharge die to roll
+    /// * `sides` - Sides per die; must match the recharge die
+    pub fn roll_recharge(die: &mut RechargeDie, sides: u32) -> DiceResult<bool> {
+        DiceContext::new().roll_recharge(die, sides)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recharge_cycle() {
+        let mut ctx = DiceContext::with_seed(122);
+        let mut die = RechargeDie::new(6, 5).unwrap();
+        assert!(!die.is_charged());
+        assert_eq!(die.use_ability(), Err(DiceError::InvalidCount(0)));
+
+        let mut attempts = 0;
+        while !ctx.roll_recharge(&mut die, 6).unwrap() {
+            attempts += 1;
+            assert!(attempts < 100);
+        }
+        assert!(die.is_charged());
+        assert_eq!(ctx.roll_recharge(&mut die, 6), Ok(true));
+        assert_eq!(die.use_ability(), Ok(()));
+        assert!(!die.is_charged());
+    }
+
+    #[test]
+    fn test_recharge_rate() {
+        let mut ctx = DiceContext::with_seed(5);
+        let trials = 60_000;
+        let mut recharged = 0;
+        for _ in 0..trials {
+            let mut die = RechargeDie::new(6, 5).unwrap();
+            if ctx.roll_recharge(&mut die, 6).unwrap() {
+                recharged += 1;
+            }
+        }
+        let rate = recharged as f64 / trials as f64;
+        assert!((rate - 1.0 / 3.0).abs() < 0.01, "rate {}", rate);
+
+        let mut always = RechargeDie::new(6, 1).unwrap();
+        assert_eq!(ctx.roll_recharge(&mut always, 6), Ok(true));
+    }
+
+    #[test]
+    fn test_recharge_validation() {
+        assert_eq!(RechargeDie::new(0, 1), Err(DiceError::InvalidSides(0)));
+        assert_eq!(RechargeDie::new(6, 7), Err(DiceError::InvalidSides(7)));
+        assert_eq!(RechargeDie::new(6, 0), Err(DiceError::InvalidSides(0)));
+        let mut die = RechargeDie::new(6, 5).unwrap();
+        assert_eq!(Dice::roll_recharge(&mut die, 8), Err(DiceError::InvalidSides(8)));
+    }
+}
@@ -0,0 +1,282 @@
+//! Rerolls
+//!
+//! A die showing less than a threshold is rolled again, up to a caller
+//! limit that is itself capped by `RollConfig::max_rerolls`. A whole roll can
+//! be replaced the same way while its total matches a condition, as in "reroll
+//! 4d6 until it totals at least 12".
+
+use crate::context::DiceContext;
+use crate::expression::{DiceExpression, DicePredicate};
+use crate::{Dice, DiceError, DiceResult};
+
+impl DiceContext {
+    /// Roll a die, rerolling while it shows less than `threshold`
+    ///
+    /// # Arguments
+    ///
+    /// * `sides` - Number of sides on the die
+    /// * `threshold` - Lowest value that doesn't trigger a reroll
+    /// * `max_rerolls` - Rerolls allowed; 0 never rerolls. Capped by
+    ///   `RollConfig::max_rerolls`, so `u32::MAX` is safe.
+    ///
+    /// # Returns
+    ///
+    /// Tuple of (final value, rerolls performed). Fails with
+    /// [`DiceError::InvalidRange`] if the limit was reached with the die
+    /// still below `threshold`.
+    pub fn roll_with_max_rerolls(
+        &mut self,
+        sides: i32,
+        threshold: i32,
+        max_rerolls: u32,
+    ) -> DiceResult<(i32, u32)> {
+        let limit = max_rerolls.min(self.config().max_rerolls);
+        let mut value = self.roll(sides)?;
+        let mut rerolls = 0;
+        while value < threshold && rerolls < limit {
+            value = self.roll(sides)?;
+            rerolls += 1;
+        }
+        if value < threshold {
+            return Err(DiceError::InvalidRange {
+                min: threshold,
+                max: sides,
+                actual: value,
+            });
+        }
+        Ok((value, rerolls))
+    }
+
+    /// Roll `notation`, rolling `fallback` instead while the total matches
+    /// `condition`
+    ///
+    /// # Arguments
+    ///
+    /// * `notation` - Dice notation rolled first
+    /// * `condition` - Totals that call for another roll, like
+    ///   `DicePredicate::LessThan(12)`
+    /// * `fallback` - Dice notation rolled in place of a matching total,
+    ///   often the same as `notation`
+    ///
+    /// # Returns
+    ///
+    /// The first total that doesn't match `condition`, or the last one
+    /// rolled after `RollConfig::max_rerolls` rerolls
+    pub fn roll_sum_conditional(
+        &mut self,
+        notation: &str,
+        condition: DicePredicate,
+        fallback: &str,
+    ) -> DiceResult<i32> {
+        let expr = DiceExpression::parse(notation)?;
+        let fallback = DiceExpression::parse(fallback)?;
+        self.roll_sum_conditional_else(&expr, condition, &fallback)
+    }
+
+    /// [`roll_sum_conditional`](Self::roll_sum_conditional) with parsed
+    /// expressions
+    ///
+    /// # Arguments
+    ///
+    /// * `expr` - Expression rolled first
+    /// * `condition` - Totals that call for another roll
+    /// * `fallback` - Expression rolled in place of a matching total
+    pub fn roll_sum_conditional_else(
+        &mut self,
+        expr: &DiceExpression,
+        condition: DicePredicate,
+        fallback: &DiceExpression,
+    ) -> DiceResult<i32> {
+        let config = *self.config();
+        let mut total = expr.roll_with_config(self.rng(), &config)?.total;
+        let mut rerolls = 0;
+        while condition.matches(total) && rerolls < config.max_rerolls {
+            total = fallback.roll_with_config(self.rng(), &config)?.total;
+            rerolls += 1;
+        }
+        Ok(total)
+    }
+}
+
+impl Dice {
+    /// Roll a die, rerolling while it shows less than `threshold`
+    ///
+    /// # Arguments
+    ///
+    /// * `sides` - Number of sides on the die
+    /// * `threshold` - Lowest value that doesn't trigger a reroll
+    /// * `max_rerolls` - Rerolls allowed; 0 never rerolls. Capped by
+    ///   `RollConfig::max_rerolls`, so `u32::MAX` is safe.
+    ///
+    /// # Returns
+    ///
+    /// Tuple of (final value, rerolls performed)
+    pub fn roll_with_max_rerolls(
+        sides: i32,
+        threshold: i32,
+        max_rerolls: u32,
+    ) -> DiceResult<(i32, u32)> {
+        DiceContext::new().roll_with_max_rerolls(sides, threshold, max_rerolls)
+    }
+
+    /// Roll `notation`, rolling `fallback` instead while the total matches
+    /// `condition`
+    ///
+    /// # Arguments
+    ///
+    /// * `notation` - Dice notation rolled first
+    /// * `condition` - Totals that call for another roll
+    /// * `fallback` - Dice notation rolled in place of a matching total
+    pub fn roll_sum_conditional(
+        notation: &str,
+        condition: DicePredicate,
+        fallback: &str,
+    ) -> DiceResult<i32> {
+        DiceContext::new().roll_sum_conditional(notation, condition, fallback)
+    }
+
+    /// [`roll_sum_conditional`](Self::roll_sum_conditional) with parsed
+    /// expressions
+    ///
+    /// # Arguments
+    ///
+    /// * `expr` - Expression rolled first
+    /// * `condition` - Totals that call for another roll
+    /// * `fallback` - Expression rolled in place of a matching total
+    pub fn roll_sum_conditional_else(
+        expr: &DiceExpression,
+        condition: DicePredicate,
+        fallback: &DiceExpression,
+    ) -> DiceResult<i32> {
+        DiceContext::new().roll_sum_conditional_else(expr, condition, fallback)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RollConfig;
+
+    #[test]
+    fn test_rerolls_until_threshold() {
+        let mut ctx = DiceContext::with_seed(21);
+        for _ in 0..200 {
+            let (value, rerolls) = ctx.roll_with_max_rerolls(6, 3, 50).unwrap();
+            assert!((3..=6).contains(&value));
+            assert!(rerolls <= 50);
+        }
+        let mut saw_reroll = false;
+        for _ in 0..100 {
+            // Running out of rerolls means some were made
+            saw_reroll |= match ctx.roll_with_max_rerolls(20, 20, 5) {
+                Ok((_, rerolls)) => rerolls > 0,
+                Err(err) => matches!(err, DiceError::InvalidRange { .. }),
+            };
+        }
+        assert!(saw_reroll);
+    }
+
+    /// Value of the `n`th plain roll of a die from a fresh context
+    fn nth_roll(seed: u64, sides: i32, n: u32) -> i32 {
+        let mut ctx = DiceContext::with_seed(seed);
+        (0..n).map(|_| ctx.roll(sides).unwrap()).last().unwrap()
+    }
+
+    #[test]
+    fn test_zero_rerolls_is_plain_roll() {
+        let mut a = DiceContext::with_seed(3);
+        let mut b = DiceContext::with_seed(3);
+        for _ in 0..50 {
+            let (value, rerolls) = a.roll_with_max_rerolls(6, 1, 0).unwrap();
+            assert_eq!(rerolls, 0);
+            assert_eq!(value, b.roll(6).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_limit_reached() {
+        // A d6 can never reach 7, so every allowed reroll is used
+        let mut ctx = DiceContext::with_seed(3);
+        assert_eq!(
+            ctx.roll_with_max_rerolls(6, 7, 4),
+            Err(DiceError::InvalidRange {
+                min: 7,
+                max: 6,
+                actual: nth_roll(3, 6, 5),
+            })
+        );
+        assert_eq!(
+            DiceError::InvalidRange {
+                min: 1,
+                max: 6,
+                actual: 7
+            }
+            .to_string(),
+            "result 7 is outside expected range [1, 6]"
+        );
+    }
+
+    #[test]
+    fn test_config_caps_rerolls() {
+        let mut ctx = DiceContext::with_seed(3);
+        let cap = RollConfig::default().max_rerolls;
+        assert!(matches!(
+            ctx.roll_with_max_rerolls(6, 7, u32::MAX),
+            Err(DiceError::InvalidRange { actual, .. }) if actual == nth_roll(3, 6, cap + 1)
+        ));
+
+        let config = RollConfig {
+            max_rerolls: 2,
+            ..RollConfig::default()
+        };
+        let mut ctx = DiceContext::with_seed(3).with_config(config);
+        assert!(matches!(
+            ctx.roll_with_max_rerolls(6, 7, 10),
+            Err(DiceError::InvalidRange { actual, .. }) if actual == nth_roll(3, 6, 3)
+        ));
+        assert_eq!(ctx.roll_with_max_rerolls(0, 1, 1), Err(DiceError::InvalidSides(0)));
+        assert!(Dice::roll_with_max_rerolls(6, 2, 3).is_ok());
+    }
+
+    #[test]
+    fn test_sum_conditional_first_roll_passes() {
+        let mut ctx = DiceContext::with_seed(181);
+        let below_twelve = DicePredicate::LessThan(12);
+        assert_eq!(ctx.roll_sum_conditional("12", below_twelve, "0"), Ok(12));
+        for _ in 0..200 {
+            assert!(ctx.roll_sum_conditional("4d6", below_twelve, "4d6").unwrap() >= 12);
+        }
+    }
+
+    #[test]
+    fn test_sum_conditional_fallback_passes() {
+        let mut ctx = DiceContext::with_seed(181);
+        let below_twelve = DicePredicate::LessThan(12);
+        assert_eq!(ctx.roll_sum_conditional("1", below_twelve, "20"), Ok(20));
+        let expr = DiceExpression::parse("1d6").unwrap();
+        let fallback = DiceExpression::parse("2d6+12").unwrap();
+        let total = ctx.roll_sum_conditional_else(&expr, below_twelve, &fallback).unwrap();
+        assert!((14..=24).contains(&total), "{}", total);
+    }
+
+    #[test]
+    fn test_sum_conditional_limit_reached() {
+        let config = RollConfig {
+            max_rerolls: 3,
+            ..RollConfig::default()
+        };
+        let mut ctx = DiceContext::with_seed(181).with_config(config);
+        // The last fallback is returned even though it still matches
+        assert_eq!(ctx.roll_sum_conditional("1", DicePredicate::LessThan(12), "5"), Ok(5));
+        let config = RollConfig {
+            max_rerolls: 0,
+            ..RollConfig::default()
+        };
+        let mut ctx = DiceContext::with_seed(181).with_config(config);
+        assert_eq!(ctx.roll_sum_conditional("1", DicePredicate::LessThan(12), "5"), Ok(1));
+        assert!(matches!(
+            ctx.roll_sum_conditional("4d6", DicePredicate::LessThan(12), "4d"),
+            Err(DiceError::InvalidNotation(_) | DiceError::ParseError { .. })
+        ));
+    }
+}
@@ -0,0 +1,159 @@
+//! Deterministic random number generation
+//!
+//! `DiceRng` is a pure-Rust xoshiro256++ generator. Unlike the C simple API,
+//! which reseeds from the clock on every call, it can be seeded once and
+//! replayed exactly, which is what the expression engine and `DiceContext`
+//! rely on.
+
+use crate::{DiceError, DiceResult};
+use rand::{Error, Rng, RngCore, SeedableRng};
+
+/// xoshiro256++ pseudo-random number generator
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiceRng {
+    s: [u64; 4],
+}
+
+impl DiceRng {
+    /// Create a generator from a 64-bit seed
+    ///
+    /// The seed is expanded with SplitMix64, so nearby seeds still produce
+    /// unrelated sequences.
+    pub fn new(seed: u64) -> Self {
+        let mut sm = seed;
+        let mut s = [0u64; 4];
+        for word in s.iter_mut() {
+            *word = splitmix64(&mut sm);
+        }
+        DiceRng { s }
+    }
+}
+
+impl DiceRng {
+    /// Internal state words, for saving a generator mid-sequence
+    pub(crate) fn state(&self) -> [u64; 4] {
+        self.s
+    }
+
+    /// Generator resuming from [`state`](Self::state)
+    ///
+    /// An all-zero state is rejected, as it would only ever produce zeros.
+    pub(crate) fn from_state(s: [u64; 4]) -> Option<Self> {
+        (s != [0; 4]).then_some(DiceRng { s })
+    }
+}
+
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+impl RngCore for DiceRng {
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let result = self.s[0]
+            .wrapping_add(self.s[3])
+            .rotate_left(23)
+            .wrapping_add(self.s[0]);
+        let t = self.s[1] << 17;
+
+        self.s[2] ^= self.s[0];
+        self.s[3] ^= self.s[1];
+        self.s[1] ^= self.s[2];
+        self.s[0] ^= self.s[3];
+        self.s[2] ^= t;
+        self.s[3] = self.s[3].rotate_left(45);
+
+        result
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl SeedableRng for DiceRng {
+    type Seed = [u8; 32];
+
+    fn from_seed(seed: Self::Seed) -> Self {
+        let mut s = [0u64; 4];
+        for (word, bytes) in s.iter_mut().zip(seed.chunks(8)) {
+            let mut buf = [0u8; 8];
+            buf.copy_from_slice(bytes);
+            *word = u64::from_le_bytes(buf);
+        }
+        // An all-zero state would only ever produce zeros
+        if s == [0; 4] {
+            return DiceRng::new(0);
+        }
+        DiceRng { s }
+    }
+
+    fn seed_from_u64(seed: u64) -> Self {
+        DiceRng::new(seed)
+    }
+}
+
+/// Generator handed to user-defined functions (see
+/// [`RollContext::define_fn`](crate::RollContext::define_fn))
+///
+/// Every [`RngCore`] is an `RngWrapper`, and `dyn RngWrapper` has all of
+/// [`Rng`]'s methods.
+pub trait RngWrapper: RngCore {
+    /// Roll a single die with `sides` sides
+    fn roll(&mut self, sides: i32) -> DiceResult<i32> {
+        if sides <= 0 {
+            return Err(DiceError::InvalidSides(sides));
+        }
+        Ok(self.gen_range(1..=sides))
+    }
+}
+
+impl<R: RngCore + ?Sized> RngWrapper for R {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    #[test]
+    fn test_same_seed_same_sequence() {
+        let mut a = DiceRng::new(12345);
+        let mut b = DiceRng::new(12345);
+        for _ in 0..100 {
+            assert_eq!(a.next_u64(), b.next_u64());
+        }
+
+        let mut c = DiceRng::new(12346);
+        assert_ne!(DiceRng::new(12345).next_u64(), c.next_u64());
+    }
+
+    #[test]
+    fn test_zero_seed_bytes() {
+        let mut rng = DiceRng::from_seed([0; 32]);
+        assert_ne!(rng.next_u64(), 0);
+    }
+
+    #[test]
+    fn test_range_rolls() {
+        let mut rng = DiceRng::new(42);
+        for _ in 0..1000 {
+            let roll = rng.gen_range(1..=6);
+            assert!((1..=6).contains(&roll));
+        }
+    }
+}
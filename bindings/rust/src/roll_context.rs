@@ -0,0 +1,574 @@
+//! Variable bindings
+//!
+//! `RollContext` pairs a [`DiceContext`] with named values and functions,
+//! so notation like `Nd6+MOD` or `DAMAGE(6, 4)` can be rolled once `N`,
+//! `MOD` and `DAMAGE` are defined. Variables known ahead of time can also
+//! be substituted into the expression itself with
+//! [`DiceExpression::substitute_constants`], and variables that stand for
+//! another roll, like a luck die added to an attack, replaced with that
+//! roll's expression by [`DiceExpression::replace_variables`].
+//!
+//! Functions can also come from named modules: `player::damage_roll(6)`
+//! calls `damage_roll` from the `player` module bound with
+//! [`RollContext::with_module`], and
+//! [`DiceExpression::required_imports`] lists the modules' functions an
+//! expression needs before it can be rolled.
+
+use crate::context::DiceContext;
+use crate::eval::RollResult;
+use crate::expression::DiceExpression;
+use crate::rng::RngWrapper;
+use crate::{DiceError, DiceResult};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::sync::Arc;
+
+/// Function callable from notation, given its evaluated arguments
+pub type UserFunction = dyn Fn(&[i32], &mut dyn RngWrapper) -> DiceResult<i32> + Send + Sync;
+
+/// Shareable [`UserFunction`], as bound by [`RollContext::with_module`]
+pub type DiceFunction = Arc<UserFunction>;
+
+/// Functions by name
+pub(crate) type Functions = BTreeMap<String, Arc<UserFunction>>;
+
+/// Dice context with variable and function bindings
+#[derive(Clone, Default)]
+pub struct RollContext {
+    dice: DiceContext,
+    variables: BTreeMap<String, i32>,
+    functions: Functions,
+}
+
+impl RollContext {
+    /// Create a context seeded from system entropy with no bindings
+    pub fn new() -> Self {
+        RollContext::default()
+    }
+
+    /// Create a context with a fixed seed and no bindings
+    pub fn with_seed(seed: u64) -> Self {
+        RollContext::from(DiceContext::with_seed(seed))
+    }
+
+    /// Bind `name` to `value`, replacing any previous binding
+    pub fn set_variable(&mut self, name: &str, value: i32) {
+        self.variables.insert(name.to_string(), value);
+    }
+
+    /// Value bound to `name`
+    pub fn variable(&self, name: &str) -> Option<i32> {
+        self.variables.get(name).copied()
+    }
+
+    /// Define `name` as a function callable from notation as `NAME(args...)`,
+    /// replacing any previous definition
+    ///
+    /// Arguments are evaluated before the call. Only upper-case names
+    /// (`[A-Z_][A-Z0-9_]*`) and names qualified by a module can be called
+    /// from notation, and calls nest at most
+    /// [`MAX_CALL_DEPTH`](crate::eval::MAX_CALL_DEPTH) deep.
+    pub fn define_fn(
+        &mut self,
+        name: &str,
+        f: impl Fn(&[i32], &mut dyn RngWrapper) -> DiceResult<i32> + Send + Sync + 'static,
+    ) {
+        self.functions.insert(name.to_string(), Arc::new(f));
+    }
+
+    /// Bind every function in `functions` as `module::name`, replacing
+    /// any previous definitions
+    pub fn with_module(mut self, module: &str, functions: HashMap<String, DiceFunction>) -> Self {
+        for (name, f) in functions {
+            self.functions.insert(format!("{}::{}", module, name), f);
+        }
+        self
+    }
+
+    /// Underlying dice context
+    pub fn dice_context(&mut self) -> &mut DiceContext {
+        &mut self.dice
+    }
+
+    /// Parse and roll notation with this context's bindings
+    ///
+    /// # Arguments
+    ///
+    /// * `notation` - Dice notation like "Nd6+MOD"
+    pub fn roll_notation(&mut self, notation: &str) -> DiceResult<RollResult> {
+        DiceExpression::parse(notation)?.roll_with_context(self)
+    }
+}
+
+impl fmt::Debug for RollContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RollContext")
+            .field("dice", &self.dice)
+            .field("variables", &self.variables)
+            .field("functions", &self.functions.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl From<DiceContext> for RollContext {
+    fn from(dice: DiceContext) -> Self {
+        RollContext {
+            dice,
+            ..RollContext::default()
+        }
+    }
+}
+
+impl DiceExpression {
+    /// Roll with the generator, limits, variables and functions of `ctx`
+    ///
+    /// [`roll`](Self::roll) and [`evaluate`](Self::evaluate) are the same
+    /// roll with default limits and nothing bound.
+    pub fn roll_with_context(&self, ctx: &mut RollContext) -> DiceResult<RollResult> {
+        let config = *ctx.dice.config();
+        self.roll_bound(ctx.dice.rng(), &config, &ctx.variables, &ctx.functions)
+    }
+
+    /// Names of every variable the expression reads, sorted and deduplicated
+    pub fn variables_referenced(&self) -> Vec<String> {
+        let mut names = BTreeSet::new();
+        collect_variables(self, &mut names);
+        names.into_iter().map(str::to_string).collect()
+    }
+
+    /// Qualified names of every module function the expression calls,
+    /// sorted and deduplicated
+    ///
+    /// `player::damage_roll(6)+1d4` requires `player::damage_roll`.
+    /// Unqualified calls and variables are local, so they aren't listed.
+    pub fn required_imports(&self) -> Vec<String> {
+        let mut names = BTreeSet::new();
+        collect_imports(self, &mut names);
+        names.into_iter().map(str::to_string).collect()
+    }
+
+    /// Whether every variable the expression reads is bound in `ctx`
+    pub fn is_fully_bound(&self, ctx: &RollContext) -> bool {
+        self.variables_referenced()
+            .iter()
+            .all(|name| ctx.variables.contains_key(name))
+    }
+
+    /// Copy of the expression with the variable `name` replaced by `value`
+    ///
+    /// `Nd6+MOD` with `N` set to 3 becomes `3d6+MOD`. See
+    /// [`substitute_constants`](Self::substitute_constants).
+    pub fn substitute_constant(&self, name: &str, value: i32) -> DiceResult<DiceExpression> {
+        self.substitute_constants(&HashMap::from([(name.to_string(), value)]))
+    }
+
+    /// Copy of the expression with every variable in `bindings` replaced
+    /// by its value
+    ///
+    /// Variables without a binding are left in place. A dice count that
+    /// becomes known turns into a plain `NdS` pool, and fails with
+    /// [`DiceError::InvalidCount`] if it isn't positive.
+    pub fn substitute_constants(
+        &self,
+        bindings: &HashMap<String, i32>,
+    ) -> DiceResult<DiceExpression> {
+        let mut expr = self.clone();
+        substitute(&mut expr, bindings)?;
+        Ok(expr)
+    }
+
+    /// Copy of the expression with the variable `name` replaced by
+    /// `replacement`
+    ///
+    /// `ATK_BONUS+1d20` with `ATK_BONUS` replaced by `1d4` becomes
+    /// `1d4+1d20`. See [`replace_variables`](Self::replace_variables).
+    pub fn replace_variable(
+        &self,
+        name: &str,
+        replacement: DiceExpression,
+    ) -> DiceResult<DiceExpression> {
+        self.replace_variables(&HashMap::from([(name.to_string(), replacement)]))
+    }
+
+    /// Copy of the expression with every variable in `bindings` replaced
+    /// by a copy of its expression
+    ///
+    /// Variables without a binding are left in place, and variables inside
+    /// a replacement aren't replaced again. A dice count replaced by an
+    /// expression that can be 0 or less fails with
+    /// [`DiceError::InvalidCount`] carrying its lowest value; a constant
+    /// count turns into a plain `NdS` pool.
+    pub fn replace_variables(
+        &self,
+        bindings: &HashMap<String, DiceExpression>,
+    ) -> DiceResult<DiceExpression> {
+        let mut expr = self.clone();
+        replace(&mut expr, bindings)?;
+        Ok(expr)
+    }
+}
+
+fn replace(
+    expr: &mut DiceExpression,
+    bindings: &HashMap<String, DiceExpression>,
+) -> DiceResult<()> {
+    if let DiceExpression::Variable(name) = expr {
+        if let Some(replacement) = bindings.get(name.as_str()) {
+            *expr = replacement.clone();
+        }
+        return Ok(());
+    }
+    let replaces_count = matches!(expr, DiceExpression::DynamicDice(count, _)
+        if count.variables_referenced().iter().any(|name| bindings.contains_key(name)));
+    for child in expr.children_mut() {
+        replace(child, bindings)?;
+    }
+    if let (true, DiceExpression::DynamicDice(count, sides)) = (replaces_count, &*expr) {
+        // Counts that can't be bounded yet are checked when rolled
+        if let Ok(min) = count.min_result() {
+            if min <= 0 {
+                return Err(DiceError::InvalidCount(min));
+            }
+        }
+        if let DiceExpression::Constant(count) = **count {
+            *expr = DiceExpression::Dice(count as u32, *sides);
+        }
+    }
+    Ok(())
+}
+
+fn substitute(expr: &mut DiceExpression, bindings: &HashMap<String, i32>) -> DiceResult<()> {
+    for child in expr.children_mut() {
+        substitute(child, bindings)?;
+    }
+    match expr {
+        DiceExpression::Variable(name) => {
+            if let Some(&value) = bindings.get(name.as_str()) {
+                *expr = DiceExpression::Constant(value);
+            }
+        }
+        DiceExpression::DynamicDice(count, sides) => {
+            if let DiceExpression::Constant(count) = **count {
+                if count <= 0 {
+                    return Err(DiceError::InvalidCount(count));
+                }
+                *expr = DiceExpression::Dice(count as u32, *sides);
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn collect_imports<'e>(expr: &'e DiceExpression, names: &mut BTreeSet<&'e str>) {
+    if let DiceExpression::Call(name, _) = expr {
+        if name.contains("::") {
+            names.insert(name);
+        }
+    }
+    for child in expr.children() {
+        collect_imports(child, names);
+    }
+}
+
+fn collect_variables<'e>(expr: &'e DiceExpression, names: &mut BTreeSet<&'e str>) {
+    use DiceExpression::*;
+
+    match expr {
+        Constant(_) | Dice(..) => {}
+        Variable(name) => {
+            names.insert(name);
+        }
+        DynamicDice(inner, _)
+        | Explode(inner, _)
+        | ExplodeOn(inner, _)
+        | KeepHighest(inner, _)
+        | KeepLowest(inner, _)
+        | DropHighest(inner, _)
+        | DropLowest(inner, _)
+        | CountSuccesses(inner, _)
+        | CountSuccessesWithBotch(inner, ..)
+        | FloorClamp(inner, _)
+        | CeilClamp(inner, _)
+        | FloorDiv(inner, _)
+        | CeilDiv(inner, _)
+        | Negate(inner)
+        | AbsoluteValue(inner) => collect_variables(inner, names),
+        Call(_, args) => {
+            for arg in args {
+                collect_variables(arg, names);
+            }
+        }
+        Conditional {
+            condition,
+            then_expr,
+            else_expr,
+            ..
+        } => {
+            collect_variables(condition, names);
+            collect_variables(then_expr, names);
+            collect_variables(else_expr, names);
+        }
+        Add(left, right)
+        | Sub(left, right)
+        | Mul(left, right)
+        | Div(left, right)
+        | Min(left, right)
+        | Max(left, right) => {
+            collect_variables(left, names);
+            collect_variables(right, names);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DiceError, RollConfig};
+
+    fn parse(notation: &str) -> DiceExpression {
+        DiceExpression::parse(notation).unwrap()
+    }
+
+    #[test]
+    fn test_variables_referenced() {
+        assert_eq!(parse("Nd6+MOD").variables_referenced(), vec!["MOD", "N"]);
+        assert_eq!(
+            parse("(STR+STR)*2-Nd4k1+|BONUS|").variables_referenced(),
+            vec!["BONUS", "N", "STR"]
+        );
+        assert!(parse("4d6k3+2").variables_referenced().is_empty());
+    }
+
+    #[test]
+    fn test_is_fully_bound() {
+        let expr = parse("Nd6+MOD");
+        let mut ctx = RollContext::with_seed(1);
+        assert!(!expr.is_fully_bound(&ctx));
+        ctx.set_variable("N", 3);
+        assert!(!expr.is_fully_bound(&ctx));
+        ctx.set_variable("MOD", 2);
+        assert!(expr.is_fully_bound(&ctx));
+        assert!(parse("3d6").is_fully_bound(&RollContext::new()));
+    }
+
+    #[test]
+    fn test_substitute_constant() {
+        let expr = parse("Nd6+MOD");
+        let partial = expr.substitute_constant("N", 3).unwrap();
+        assert_eq!(partial, parse("3d6+MOD"));
+        assert_eq!(partial.variables_referenced(), vec!["MOD"]);
+        assert_eq!(partial.substitute_constant("MOD", -1).unwrap().to_notation(), "3d6+-1");
+        // Unknown names leave the expression as it was
+        assert_eq!(expr.substitute_constant("X", 9).unwrap().to_notation(), "Nd6+MOD");
+
+        let nested = parse("(STR>=3)?Nd4k1:|STR-N|");
+        let bound = nested.substitute_constant("STR", 4).unwrap();
+        assert_eq!(bound.variables_referenced(), vec!["N"]);
+        assert_eq!(bound.to_notation(), "(4>=3)?Nd4k1:|4-N|");
+    }
+
+    #[test]
+    fn test_substitute_constants() {
+        let bindings = HashMap::from([("N".to_string(), 2), ("MOD".to_string(), 5)]);
+        let expr = parse("Nd8+MOD*N").substitute_constants(&bindings).unwrap();
+        assert!(expr.variables_referenced().is_empty());
+        let mut rng = crate::DiceRng::new(173);
+        for _ in 0..100 {
+            assert!((12..=26).contains(&expr.evaluate(&mut rng).unwrap()));
+        }
+        assert_eq!(parse("3d6").substitute_constants(&HashMap::new()), Ok(parse("3d6")));
+    }
+
+    #[test]
+    fn test_substitute_invalid_count() {
+        let expr = parse("Nd6+N");
+        assert_eq!(expr.substitute_constant("N", 0), Err(DiceError::InvalidCount(0)));
+        assert_eq!(expr.substitute_constant("N", -2), Err(DiceError::InvalidCount(-2)));
+        // Outside a count a non-positive value is fine
+        assert!(parse("1d6+N").substitute_constant("N", 0).is_ok());
+    }
+
+    #[test]
+    fn test_roll_with_bindings() {
+        let mut ctx = RollContext::with_seed(120);
+        ctx.set_variable("N", 3);
+        ctx.set_variable("MOD", -1);
+        assert_eq!(ctx.variable("N"), Some(3));
+        for _ in 0..100 {
+            let result = ctx.roll_notation("Nd6+MOD").unwrap();
+            assert_eq!(result.dice.len(), 3);
+            assert_eq!(result.total, result.individual().iter().sum::<i32>() - 1);
+        }
+        assert_eq!(ctx.roll_notation("N*MOD").unwrap().total, -3);
+        assert_eq!(
+            ctx.roll_notation("Xd6"),
+            Err(DiceError::UnboundVariable("X".to_string()))
+        );
+        ctx.set_variable("N", 0);
+        assert_eq!(ctx.roll_notation("Nd6"), Err(DiceError::InvalidCount(0)));
+    }
+
+    #[test]
+    fn test_unbound_without_context() {
+        let mut rng = crate::DiceRng::new(1);
+        assert_eq!(
+            parse("MOD+1").evaluate(&mut rng),
+            Err(DiceError::UnboundVariable("MOD".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_roll_with_context() {
+        let mut ctx = RollContext::from(DiceContext::with_seed(141).with_config(RollConfig {
+            max_dice_count: 4,
+            ..RollConfig::default()
+        }));
+        ctx.set_variable("N", 4);
+        ctx.define_fn("HALF", |args, _| Ok(args[0] / 2));
+        let expr = parse("Nd6+HALF(10)");
+        let result = expr.roll_with_context(&mut ctx).unwrap();
+        assert_eq!(result.total, result.individual().iter().sum::<i32>() + 5);
+        assert_eq!(
+            parse("5d6").roll_with_context(&mut ctx),
+            Err(DiceError::InvalidCount(5))
+        );
+
+        // Same generator stream as rolling notation through the context
+        let mut a = RollContext::with_seed(7);
+        let mut b = RollContext::with_seed(7);
+        assert_eq!(
+            parse("3d6!").roll_with_context(&mut a),
+            b.roll_notation("3d6!")
+        );
+    }
+
+    #[test]
+    fn test_context_is_send() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<RollContext>();
+    }
+
+    #[test]
+    fn test_user_functions() {
+        let mut ctx = RollContext::with_seed(127);
+        ctx.define_fn("DAMAGE", |args, rng| Ok(rng.roll(args[0])? + rng.roll(args[1])?));
+        ctx.define_fn("TWICE", |args, _| Ok(args.iter().sum::<i32>() * 2));
+        ctx.set_variable("STR", 3);
+        for _ in 0..100 {
+            let total = ctx.roll_notation("DAMAGE(6, 4)+STR").unwrap().total;
+            assert!((5..=13).contains(&total));
+        }
+        assert_eq!(ctx.roll_notation("TWICE(STR, 1, TWICE(2))").unwrap().total, 16);
+        let result = ctx.roll_notation("TWICE(1d1+2d1)").unwrap();
+        assert_eq!((result.total, result.dice.len()), (6, 3));
+
+        assert_eq!(
+            ctx.roll_notation("HEAL(4)"),
+            Err(DiceError::InvalidNotation("undefined function: HEAL".to_string()))
+        );
+        assert!(matches!(
+            ctx.roll_notation("DAMAGE(0, 4)"),
+            Err(DiceError::InvalidSides(0))
+        ));
+        assert!(parse("TWICE(1)").evaluate(&mut crate::DiceRng::new(1)).is_err());
+        assert!(matches!(
+            parse("TWICE(1)").max_result(),
+            Err(DiceError::UnsupportedFeature(_))
+        ));
+        assert_eq!(parse("F(Nd6, MOD)").variables_referenced(), vec!["MOD", "N"]);
+    }
+
+    #[test]
+    fn test_recursion_is_limited() {
+        fn recurse(_: &[i32], _: &mut dyn RngWrapper) -> DiceResult<i32> {
+            let mut ctx = RollContext::with_seed(1);
+            ctx.define_fn("RECURSE", recurse);
+            ctx.roll_notation("RECURSE()+1").map(|result| result.total)
+        }
+        let mut ctx = RollContext::with_seed(1);
+        ctx.define_fn("RECURSE", recurse);
+        assert!(matches!(
+            ctx.roll_notation("RECURSE()"),
+            Err(DiceError::InvalidNotation(message)) if message.contains("depth")
+        ));
+        // The depth is released after an error
+        ctx.define_fn("RECURSE", |_, _| Ok(1));
+        assert_eq!(ctx.roll_notation("RECURSE()").unwrap().total, 1);
+    }
+
+    #[test]
+    fn test_required_imports() {
+        let expr = parse("player::damage_roll(6) + 1d4");
+        assert_eq!(expr.required_imports(), vec!["player::damage_roll"]);
+        let expr = parse("enemy::defense(player::level()) + DAMAGE(MOD) - enemy::defense(1)");
+        assert_eq!(expr.required_imports(), vec!["enemy::defense", "player::level"]);
+        assert!(parse("DAMAGE(6)+MOD").required_imports().is_empty());
+    }
+
+    #[test]
+    fn test_with_module() {
+        let damage: DiceFunction = Arc::new(|args, rng| Ok(args[0] * 2 + rng.roll(1)?));
+        let level: DiceFunction = Arc::new(|_, _| Ok(3));
+        let functions = HashMap::from([
+            ("damage_roll".to_string(), damage),
+            ("level".to_string(), level),
+        ]);
+        let mut ctx = RollContext::with_seed(190).with_module("player", functions);
+        assert_eq!(ctx.roll_notation("player::damage_roll(player::level())").unwrap().total, 7);
+        // The module's names aren't bound without their qualifier
+        assert!(matches!(
+            ctx.roll_notation("level()"),
+            Err(DiceError::ParseError { .. } | DiceError::InvalidNotation(_))
+        ));
+        assert_eq!(
+            ctx.roll_notation("enemy::level()"),
+            Err(DiceError::InvalidNotation("undefined function: enemy::level".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_replace_variable() {
+        let expr = parse("ATK_BONUS + 1d20").replace_variable("ATK_BONUS", parse("1d4")).unwrap();
+        assert_eq!(
+            expr,
+            DiceExpression::Add(
+                Box::new(DiceExpression::Dice(1, 4)),
+                Box::new(DiceExpression::Dice(1, 20))
+            )
+        );
+        // Every occurrence gets its own copy
+        let expr = parse("LUCK*2+LUCK").replace_variable("LUCK", parse("1d6!")).unwrap();
+        assert_eq!(expr, parse("1d6!*2+1d6!"));
+        assert_eq!(parse("1d8+MOD").replace_variable("X", parse("1d4")), Ok(parse("1d8+MOD")));
+    }
+
+    #[test]
+    fn test_replace_variables() {
+        let bindings = HashMap::from([
+            ("WILD".to_string(), parse("1d6")),
+            ("N".to_string(), parse("1d4+1")),
+            ("BONUS".to_string(), parse("WILD+2")),
+        ]);
+        let expr = parse("Nd8+WILD+BONUS").replace_variables(&bindings).unwrap();
+        // Replacements aren't searched for further variables
+        assert_eq!(expr.variables_referenced(), vec!["WILD"]);
+        let expr = parse("Nd8+WILD").replace_variables(&bindings).unwrap();
+        let mut rng = crate::DiceRng::new(200);
+        for _ in 0..100 {
+            assert!((3..=46).contains(&expr.evaluate(&mut rng).unwrap()));
+        }
+        let constant = HashMap::from([("N".to_string(), parse("3"))]);
+        assert_eq!(parse("Nd6").replace_variables(&constant), Ok(parse("3d6")));
+    }
+
+    #[test]
+    fn test_replace_invalid_count() {
+        let expr = parse("Nd6+N");
+        assert_eq!(expr.replace_variable("N", parse("1d4-1")), Err(DiceError::InvalidCount(0)));
+        assert_eq!(expr.replace_variable("N", parse("-2")), Err(DiceError::InvalidCount(-2)));
+        // Outside a count anything goes, and an unbounded count waits for the roll
+        assert!(parse("1d6+N").replace_variable("N", parse("1d4-3")).is_ok());
+        assert!(expr.replace_variable("N", parse("M-1")).is_ok());
+    }
+}
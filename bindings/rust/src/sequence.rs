@@ -0,0 +1,81 @@
+//! Replayable roll sequences
+//!
+//! A seed and a list of notations fully determine the rolls made from them,
+//! so storing `(seed, notations)` is enough to replay and verify a session.
+
+use crate::context::DiceContext;
+use crate::{Dice, DiceResult};
+
+impl DiceContext {
+    /// Lazily roll each notation in turn, yielding its total
+    ///
+    /// # Arguments
+    ///
+    /// * `notations` - Dice notation to roll, in order
+    pub fn roll_sequence<'a>(
+        &'a mut self,
+        notations: &'a [&str],
+    ) -> impl Iterator<Item = DiceResult<i32>> + 'a {
+        notations
+            .iter()
+            .map(move |notation| self.roll_notation(notation).map(|result| result.total))
+    }
+}
+
+impl Dice {
+    /// Lazily roll each notation in turn from a fixed seed
+    ///
+    /// The same seed and notations always yield the same totals.
+    ///
+    /// # Arguments
+    ///
+    /// * `notations` - Dice notation to roll, in order
+    /// * `seed` - Seed for the deterministic generator
+    pub fn roll_sequence<'a>(
+        notations: &'a [&str],
+        seed: u32,
+    ) -> impl Iterator<Item = DiceResult<i32>> + 'a {
+        let mut ctx = DiceContext::with_seed(seed as u64);
+        notations
+            .iter()
+            .map(move |notation| ctx.roll_notation(notation).map(|result| result.total))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DiceError;
+
+    const NOTATIONS: [&str; 5] = ["1d20+5", "4d6k3", "2d6!", "8d10>7", "1d100"];
+
+    #[test]
+    fn test_sequence_replays() {
+        let mut count = 0;
+        let replay = Dice::roll_sequence(&NOTATIONS, 129);
+        for (a, b) in Dice::roll_sequence(&NOTATIONS, 129).zip(replay) {
+            assert_eq!(a, b);
+            assert!(a.is_ok());
+            count += 1;
+        }
+        assert_eq!(count, NOTATIONS.len());
+
+        let other: Vec<_> = Dice::roll_sequence(&NOTATIONS, 130).collect();
+        assert_ne!(other, Dice::roll_sequence(&NOTATIONS, 129).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_sequence_matches_context() {
+        let mut ctx = DiceContext::with_seed(129);
+        let from_context: Vec<_> = ctx.roll_sequence(&NOTATIONS).collect();
+        assert_eq!(from_context, Dice::roll_sequence(&NOTATIONS, 129).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_sequence_errors_in_place() {
+        let totals: Vec<_> = Dice::roll_sequence(&["1d6", "0d6", "1d6"], 1).collect();
+        assert_eq!(totals.len(), 3);
+        assert!(totals[0].is_ok() && totals[2].is_ok());
+        assert_eq!(totals[1], Err(DiceError::InvalidCount(0)));
+    }
+}
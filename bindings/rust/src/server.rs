@@ -0,0 +1,236 @@
+//! JSON-over-HTTP dice service (`server` feature)
+//!
+//! Routes:
+//!
+//! * `POST /roll` with `{ "notation": "3d6+2" }`
+//! * `GET /roll/:sides` for a single die
+//!
+//! Both return `{ "result": 14, "individual": [3, 5, 6], "notation": "3d6+2" }`.
+//! Failures return `400 Bad Request` with the serialized `DiceError`.
+//! Notation longer than [`MAX_NOTATION_LENGTH`] bytes is refused, as is an
+//! expression deeper than [`MAX_EXPRESSION_DEPTH`] or with more than
+//! [`MAX_NODE_COUNT`] nodes.
+
+use crate::context::DiceContext;
+use crate::expression::DiceExpression;
+use crate::parser;
+use crate::DiceError;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+
+/// Longest notation `POST /roll` accepts, in bytes
+pub const MAX_NOTATION_LENGTH: usize = 256;
+
+/// Deepest expression `POST /roll` accepts; see [`DiceExpression::depth`]
+pub const MAX_EXPRESSION_DEPTH: usize = 10;
+
+/// Most nodes `POST /roll` accepts; see [`DiceExpression::node_count`]
+pub const MAX_NODE_COUNT: usize = 50;
+
+/// Context shared by all requests
+pub type SharedContext = Arc<Mutex<DiceContext>>;
+
+/// Body of `POST /roll`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RollRequest {
+    pub notation: String,
+}
+
+/// Successful roll response
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RollResponse {
+    pub result: i32,
+    pub individual: Vec<i32>,
+    pub notation: String,
+}
+
+/// Error response body
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ErrorResponse {
+    pub error: DiceError,
+    pub message: String,
+}
+
+struct ApiError(DiceError);
+
+impl From<DiceError> for ApiError {
+    fn from(error: DiceError) -> Self {
+        ApiError(error)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = ErrorResponse {
+            message: self.0.to_string(),
+            error: self.0,
+        };
+        (StatusCode::BAD_REQUEST, Json(body)).into_response()
+    }
+}
+
+/// Build the service router around a shared context
+pub fn router(ctx: DiceContext) -> Router {
+    let state: SharedContext = Arc::new(Mutex::new(ctx));
+    Router::new()
+        .route("/roll", post(roll_notation))
+        .route("/roll/:sides", get(roll_sides))
+        .with_state(state)
+}
+
+async fn roll_notation(
+    State(ctx): State<SharedContext>,
+    Json(request): Json<RollRequest>,
+) -> Result<Json<RollResponse>, ApiError> {
+    parser::check_length(&request.notation, MAX_NOTATION_LENGTH)?;
+    let expr = DiceExpression::parse(&request.notation)?;
+    check_complexity(&expr)?;
+    let mut ctx = ctx.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let config = *ctx.config();
+    let result = expr.roll_with_config(ctx.rng(), &config)?;
+    Ok(Json(RollResponse {
+        result: result.total,
+        individual: result.individual(),
+        notation: request.notation,
+    }))
+}
+
+/// Refuse expressions past [`MAX_EXPRESSION_DEPTH`] or [`MAX_NODE_COUNT`]
+fn check_complexity(expr: &DiceExpression) -> Result<(), DiceError> {
+    if expr.depth() > MAX_EXPRESSION_DEPTH {
+        return Err(DiceError::InvalidNotation(format!(
+            "expression exceeds maximum depth of {}",
+            MAX_EXPRESSION_DEPTH
+        )));
+    }
+    if expr.node_count() > MAX_NODE_COUNT {
+        return Err(DiceError::InvalidNotation(format!(
+            "expression exceeds maximum of {} nodes",
+            MAX_NODE_COUNT
+        )));
+    }
+    Ok(())
+}
+
+async fn roll_sides(
+    State(ctx): State<SharedContext>,
+    Path(sides): Path<i32>,
+) -> Result<Json<RollResponse>, ApiError> {
+    let result = ctx
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .roll(sides)?;
+    Ok(Json(RollResponse {
+        result,
+        individual: vec![result],
+        notation: format!("1d{}", sides),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::Request;
+    use http_body_util::BodyExt;
+    use tower::ServiceExt;
+
+    async fn send(request: Request<Body>) -> (StatusCode, serde_json::Value) {
+        let response = router(DiceContext::with_seed(12345))
+            .oneshot(request)
+            .await
+            .unwrap();
+        let status = response.status();
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        (status, serde_json::from_slice(&bytes).unwrap())
+    }
+
+    fn post_roll(body: &str) -> Request<Body> {
+        Request::post("/roll")
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_post_roll() {
+        let (status, body) = send(post_roll(r#"{"notation":"3d6+2"}"#)).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let response: RollResponse = serde_json::from_value(body).unwrap();
+        assert_eq!(response.notation, "3d6+2");
+        assert_eq!(response.individual.len(), 3);
+        assert_eq!(response.result, response.individual.iter().sum::<i32>() + 2);
+    }
+
+    #[tokio::test]
+    async fn test_get_roll_sides() {
+        let request = Request::get("/roll/20").body(Body::empty()).unwrap();
+        let (status, body) = send(request).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let response: RollResponse = serde_json::from_value(body).unwrap();
+        assert!((1..=20).contains(&response.result));
+        assert_eq!(response.individual, vec![response.result]);
+        assert_eq!(response.notation, "1d20");
+    }
+
+    #[tokio::test]
+    async fn test_errors_are_serialized() {
+        let (status, body) = send(post_roll(r#"{"notation":"bogus"}"#)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let response: ErrorResponse = serde_json::from_value(body).unwrap();
+        assert_eq!(response.error, DiceError::InvalidNotation("bogus".to_string()));
+
+        let long = format!(r#"{{"notation":"1d6{}"}}"#, "+1".repeat(200));
+        let (status, body) = send(post_roll(&long)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(
+            body["message"],
+            "Invalid dice notation: notation exceeds maximum length of 256 bytes"
+        );
+
+        let request = Request::get("/roll/0").body(Body::empty()).unwrap();
+        let (status, body) = send(request).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"], serde_json::json!({ "InvalidSides": 0 }));
+    }
+
+    #[tokio::test]
+    async fn test_depth_limit() {
+        // A chain of n terms is n deep
+        let chain = |terms: usize| vec!["1d6"; terms].join("+");
+        let (status, _) = send(post_roll(&format!(r#"{{"notation":"{}"}}"#, chain(10)))).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, body) = send(post_roll(&format!(r#"{{"notation":"{}"}}"#, chain(11)))).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(
+            body["message"],
+            "Invalid dice notation: expression exceeds maximum depth of 10"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_node_count_limit() {
+        // 8 terms are 15 nodes, 8 deep: two pairs of them are 63 nodes, 10 deep
+        let group = format!("({})", ["1d6"; 8].join("+"));
+        let notation = format!("({g}+{g})*({g}+{g})", g = group);
+        let expr = DiceExpression::parse(&notation).unwrap();
+        assert_eq!((expr.depth(), expr.node_count()), (10, 63));
+        let (status, body) = send(post_roll(&format!(r#"{{"notation":"{}"}}"#, notation))).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(
+            body["message"],
+            "Invalid dice notation: expression exceeds maximum of 50 nodes"
+        );
+
+        let small = format!("{g}*{g}", g = group);
+        let (status, _) = send(post_roll(&format!(r#"{{"notation":"{}"}}"#, small))).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+}
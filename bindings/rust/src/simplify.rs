@@ -0,0 +1,371 @@
+//! Identity simplification
+//!
+//! Removes nodes that can't change the outcome: `+ 0`, `- 0`, `* 1`, `/ 1`,
+//! double negation, nested absolute values, keeping at least every die of a pool and dropping none,
+//! and clamps the result can never reach. The dice rolled
+//! are unchanged, so a simplified expression rolls exactly like the
+//! original for the same generator.
+//!
+//! [`DiceExpression::symbolically_equivalent`] builds on this to compare
+//! expressions regardless of the order of commutative operands, and
+//! [`DiceExpression::normalize_keep_drop`] rewrites drops as keeps.
+
+use crate::bounds::pool_shape;
+use crate::expression::DiceExpression;
+use crate::{DiceError, DiceResult};
+
+impl DiceExpression {
+    /// Copy of the expression with identity nodes removed
+    ///
+    /// Rules are applied bottom-up, and every rule returns an already
+    /// simplified child, so one pass reaches the fixpoint.
+    /// [`parse`](Self::parse) calls this automatically.
+    pub fn simplify(&self) -> DiceExpression {
+        use DiceExpression::*;
+
+        let binary = |left: &DiceExpression, right: &DiceExpression| {
+            (Box::new(left.simplify()), Box::new(right.simplify()))
+        };
+
+        match self {
+            Constant(_) | Variable(_) | Dice(..) => self.clone(),
+            DynamicDice(count, sides) => DynamicDice(Box::new(count.simplify()), *sides),
+            KeepHighest(inner, n) | KeepLowest(inner, n) => {
+                let inner = inner.simplify();
+                if keeps_all(&inner, *n) {
+                    return inner;
+                }
+                match self {
+                    KeepHighest(..) => KeepHighest(Box::new(inner), *n),
+                    _ => KeepLowest(Box::new(inner), *n),
+                }
+            }
+            DropHighest(inner, n) | DropLowest(inner, n) => {
+                let inner = inner.simplify();
+                if *n == 0 && inner.is_pool() {
+                    return inner;
+                }
+                match self {
+                    DropHighest(..) => DropHighest(Box::new(inner), *n),
+                    _ => DropLowest(Box::new(inner), *n),
+                }
+            }
+            Call(name, args) => Call(name.clone(), args.iter().map(Self::simplify).collect()),
+            Explode(inner, threshold) => Explode(Box::new(inner.simplify()), *threshold),
+            ExplodeOn(inner, faces) => ExplodeOn(Box::new(inner.simplify()), faces.clone()),
+            CountSuccesses(inner, target) => CountSuccesses(Box::new(inner.simplify()), *target),
+            CountSuccessesWithBotch(inner, target, botch) => {
+                CountSuccessesWithBotch(Box::new(inner.simplify()), *target, *botch)
+            }
+            FloorClamp(inner, minimum) => {
+                let inner = inner.simplify();
+                if matches!(inner.min_result(), Ok(min) if min >= *minimum) {
+                    return inner;
+                }
+                FloorClamp(Box::new(inner), *minimum)
+            }
+            CeilClamp(inner, maximum) => {
+                let inner = inner.simplify();
+                if matches!(inner.max_result(), Ok(max) if max <= *maximum) {
+                    return inner;
+                }
+                CeilClamp(Box::new(inner), *maximum)
+            }
+            Conditional {
+                condition,
+                predicate,
+                then_expr,
+                else_expr,
+            } => Conditional {
+                condition: Box::new(condition.simplify()),
+                predicate: *predicate,
+                then_expr: Box::new(then_expr.simplify()),
+                else_expr: Box::new(else_expr.simplify()),
+            },
+            Negate(inner) => match inner.simplify() {
+                Negate(inner) => *inner,
+                inner => Negate(Box::new(inner)),
+            },
+            // |-x| and ||x|| are |x|
+            AbsoluteValue(inner) => match inner.simplify() {
+                Negate(inner) | AbsoluteValue(inner) => AbsoluteValue(inner),
+                inner => AbsoluteValue(Box::new(inner)),
+            },
+            Add(left, right) => match binary(left, right) {
+                (left, right) if matches!(*right, Constant(0)) => *left,
+                (left, right) if matches!(*left, Constant(0)) => *right,
+                (left, right) => Add(left, right),
+            },
+            Sub(left, right) => match binary(left, right) {
+                (left, right) if matches!(*right, Constant(0)) => *left,
+                (left, right) => Sub(left, right),
+            },
+            Mul(left, right) => match binary(left, right) {
+                (left, right) if matches!(*right, Constant(1)) => *left,
+                (left, right) if matches!(*left, Constant(1)) => *right,
+                (left, right) => Mul(left, right),
+            },
+            Div(left, right) => match binary(left, right) {
+                (left, right) if matches!(*right, Constant(1)) => *left,
+                (left, right) => Div(left, right),
+            },
+            FloorDiv(inner, 1) | CeilDiv(inner, 1) => inner.simplify(),
+            FloorDiv(inner, divisor) => FloorDiv(Box::new(inner.simplify()), *divisor),
+            CeilDiv(inner, divisor) => CeilDiv(Box::new(inner.simplify()), *divisor),
+            Min(left, right) => {
+                let (left, right) = binary(left, right);
+                Min(left, right)
+            }
+            Max(left, right) => {
+                let (left, right) = binary(left, right);
+                Max(left, right)
+            }
+        }
+    }
+}
+
+impl DiceExpression {
+    /// Whether both expressions are the same up to identities and the
+    /// order of `+`, `*`, `min` and `max` operands
+    ///
+    /// Both sides are [simplified](Self::simplify), chains of each of those
+    /// operators are flattened with their constant terms folded, and the
+    /// operands are sorted before the trees are compared. `1d6+2` and
+    /// `2+1d6` are equivalent; `2d6` and `1d6+1d6` are not, even though
+    /// [`semantic_equivalent`](Self::semantic_equivalent) may accept them.
+    pub fn symbolically_equivalent(&self, other: &DiceExpression) -> bool {
+        canonical(&self.simplify()) == canonical(&other.simplify())
+    }
+}
+
+impl DiceExpression {
+    /// Copy of the expression with every drop written as the matching keep
+    ///
+    /// Dropping the lowest `n` of `c` dice keeps the highest `c - n`, so
+    /// `4d6l1` becomes `4d6k3` and `5d6dh2` becomes `5d6kl3`; the dice kept
+    /// are the same for the same generator. Pools whose size isn't fixed,
+    /// like `Nd6dl1` or `4d6!dl1`, are left as they are. Dropping every
+    /// die fails with [`DiceError::InvalidCount`], since no keep keeps
+    /// none.
+    pub fn normalize_keep_drop(&self) -> DiceResult<DiceExpression> {
+        use DiceExpression::*;
+
+        let mut expr = self.clone();
+        for child in expr.children_mut() {
+            *child = child.normalize_keep_drop()?;
+        }
+        let (inner, n) = match &expr {
+            DropLowest(inner, n) | DropHighest(inner, n) => (inner, *n),
+            _ => return Ok(expr),
+        };
+        let count = match pool_shape(inner) {
+            Ok(shape) if shape.min_dice == shape.max_dice => shape.max_dice,
+            _ => return Ok(expr),
+        };
+        if n >= count {
+            return Err(DiceError::InvalidCount(0));
+        }
+        Ok(match expr {
+            DropLowest(inner, _) => KeepHighest(inner, count - n),
+            DropHighest(inner, _) => KeepLowest(inner, count - n),
+            _ => unreachable!(),
+        })
+    }
+}
+
+/// Copy of `expr` with commutative operands flattened, folded and sorted
+fn canonical(expr: &DiceExpression) -> DiceExpression {
+    use DiceExpression::*;
+
+    let mut expr = expr.clone();
+    for child in expr.children_mut() {
+        *child = canonical(child);
+    }
+    match expr {
+        Add(..) => commutative(expr, |e| matches!(e, Add(..)), 0, i32::checked_add, Add),
+        Mul(..) => commutative(expr, |e| matches!(e, Mul(..)), 1, i32::checked_mul, Mul),
+        Min(..) => {
+            commutative(expr, |e| matches!(e, Min(..)), i32::MAX, |a, b| Some(a.min(b)), Min)
+        }
+        Max(..) => {
+            commutative(expr, |e| matches!(e, Max(..)), i32::MIN, |a, b| Some(a.max(b)), Max)
+        }
+        expr => expr,
+    }
+}
+
+/// Rebuild a chain of one commutative operator in canonical order
+fn commutative(
+    expr: DiceExpression,
+    same_node: fn(&DiceExpression) -> bool,
+    identity: i32,
+    fold: fn(i32, i32) -> Option<i32>,
+    node: fn(Box<DiceExpression>, Box<DiceExpression>) -> DiceExpression,
+) -> DiceExpression {
+    let mut pending = vec![expr];
+    let mut operands = Vec::new();
+    let mut constant = identity;
+    while let Some(expr) = pending.pop() {
+        if same_node(&expr) {
+            pending.extend(expr.children().into_iter().cloned());
+            continue;
+        }
+        match expr {
+            // Leave a constant that would overflow as an operand
+            DiceExpression::Constant(value) => match fold(constant, value) {
+                Some(folded) => constant = folded,
+                None => operands.push(expr),
+            },
+            expr => operands.push(expr),
+        }
+    }
+    if constant != identity || operands.is_empty() {
+        operands.push(DiceExpression::Constant(constant));
+    }
+    operands.sort_by_cached_key(DiceExpression::to_notation);
+    operands
+        .into_iter()
+        .reduce(|left, right| node(Box::new(left), Box::new(right)))
+        .unwrap_or(DiceExpression::Constant(identity))
+}
+
+/// Whether keeping `n` dice of `pool` keeps every die it rolls
+fn keeps_all(pool: &DiceExpression, n: u32) -> bool {
+    matches!(pool_shape(pool), Ok(shape) if n >= shape.max_dice)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser;
+    use crate::rng::DiceRng;
+
+    fn simplified(notation: &str) -> String {
+        format!("{:?}", parser::parse(notation).unwrap().simplify())
+    }
+
+    fn debug(notation: &str) -> String {
+        format!("{:?}", parser::parse(notation).unwrap())
+    }
+
+    #[test]
+    fn test_additive_identity() {
+        assert_eq!(simplified("3d6+0"), debug("3d6"));
+        assert_eq!(simplified("0+3d6"), debug("3d6"));
+        assert_eq!(simplified("3d6-0"), debug("3d6"));
+        assert_eq!(simplified("0-3d6"), debug("0-3d6"));
+        assert_eq!(simplified("--3d6"), debug("3d6"));
+        assert_eq!(simplified("---3d6"), debug("-3d6"));
+        assert_eq!(simplified("||1d6-3||"), debug("|1d6-3|"));
+        assert_eq!(simplified("|-(1d6-3)|"), debug("|1d6-3|"));
+    }
+
+    #[test]
+    fn test_multiplicative_identity() {
+        assert_eq!(simplified("2d8*1"), debug("2d8"));
+        assert_eq!(simplified("1*2d8"), debug("2d8"));
+        assert_eq!(simplified("2d8/1"), debug("2d8"));
+        assert_eq!(simplified("1/2d8"), debug("1/2d8"));
+        assert_eq!(simplified("(1d4+0)*1+0"), debug("1d4"));
+    }
+
+    #[test]
+    fn test_selection_identity() {
+        assert_eq!(simplified("4d6k4"), debug("4d6"));
+        assert_eq!(simplified("4d6kl9"), debug("4d6"));
+        assert_eq!(simplified("4d6k3"), debug("4d6k3"));
+        assert_eq!(simplified("4d6dl0"), debug("4d6"));
+        assert_eq!(simplified("4d6dh0>3"), debug("4d6>3"));
+        assert_eq!(simplified("4d6dl1"), debug("4d6dl1"));
+        assert_eq!(simplified("4d6!k4"), debug("4d6!k4"));
+        assert_eq!(simplified("1d6min1"), debug("1d6"));
+        assert_eq!(simplified("1d6max6min1"), debug("1d6"));
+        assert_eq!(simplified("1d6min2"), debug("1d6min2"));
+        assert_eq!(simplified("1d6max5"), debug("1d6max5"));
+    }
+
+    #[test]
+    fn test_normalize_keep_drop() {
+        let normalized = |notation: &str| {
+            parser::parse(notation).unwrap().normalize_keep_drop().map(|e| e.to_notation())
+        };
+        assert_eq!(normalized("4d6l1").unwrap(), "4d6k3");
+        assert_eq!(normalized("5d6dh2").unwrap(), "5d6kl3");
+        assert_eq!(normalized("4d6k3").unwrap(), "4d6k3");
+        assert_eq!(normalized("4d6").unwrap(), "4d6");
+        assert_eq!(normalized("2*(4d6dl1>4)+1d20").unwrap(), "2*4d6k3>4+1d20");
+        // A keep always leaves a fixed count
+        let nested = DiceExpression::DropLowest(Box::new(parser::parse("4d6k3").unwrap()), 1);
+        assert_eq!(
+            nested.normalize_keep_drop(),
+            Ok(DiceExpression::KeepHighest(Box::new(nested.children()[0].clone()), 2))
+        );
+        // Pool sizes that vary are left alone
+        assert_eq!(normalized("Nd6dl1").unwrap(), "Nd6dl1");
+        assert_eq!(normalized("4d6!dl1").unwrap(), "4d6!dl1");
+        assert_eq!(normalized("4d6dl4"), Err(DiceError::InvalidCount(0)));
+        assert_eq!(normalized("1d20+2d20dh3"), Err(DiceError::InvalidCount(0)));
+    }
+
+    #[test]
+    fn test_normalized_rolls_match() {
+        for notation in ["4d6dl1", "6d10dh2+3", "5d6dl2>4"] {
+            let expr = parser::parse(notation).unwrap();
+            let normalized = expr.normalize_keep_drop().unwrap();
+            for seed in 0..20 {
+                assert_eq!(
+                    expr.evaluate(&mut DiceRng::new(seed)),
+                    normalized.evaluate(&mut DiceRng::new(seed)),
+                    "{}",
+                    notation
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_simplifies() {
+        let expr = DiceExpression::parse("4d6k4+0").unwrap();
+        assert!(matches!(expr, DiceExpression::Dice(4, 6)));
+    }
+
+    #[test]
+    fn test_symbolically_equivalent() {
+        use DiceExpression::*;
+
+        let dice_first = Add(Box::new(Dice(1, 6)), Box::new(Constant(2)));
+        let constant_first = Add(Box::new(Constant(2)), Box::new(Dice(1, 6)));
+        assert!(dice_first.symbolically_equivalent(&constant_first));
+
+        let equivalent = |a: &str, b: &str| {
+            parser::parse(a).unwrap().symbolically_equivalent(&parser::parse(b).unwrap())
+        };
+        assert!(equivalent("1d6+2+1d4", "1d4+(1d6+2)"));
+        assert!(equivalent("1d6+2+3", "5+1d6"));
+        assert!(equivalent("2*3d6*3", "3d6*6"));
+        assert!(equivalent("(1d8+1)*2", "2*(1+1d8)+0"));
+        assert!(equivalent("4d6k3+1d4", "1d4+4d6kh3"));
+        assert!(equivalent("|1d6+1d4|", "|1d4+1d6|"));
+        assert!(equivalent("min(1d6, 1d8)", "min(1d8, 1d6)"));
+        assert!(equivalent("max(1d4, max(3, 1d6))", "max(max(1d6, 1d4), 3)"));
+        assert!(!equivalent("min(1d6, 1d8)", "max(1d6, 1d8)"));
+        assert!(!equivalent("1d6-2", "2-1d6"));
+        assert!(!equivalent("1d6/2", "2/1d6"));
+        assert!(!equivalent("2d6", "1d6+1d6"));
+        assert!(!equivalent("1d6+2", "1d6+3"));
+        assert!(!equivalent("(1d6+2)*3", "1d6+2*3"));
+    }
+
+    #[test]
+    fn test_simplified_rolls_match() {
+        for notation in ["4d6k4+0", "(2d6-0)*1", "1*3d8dl0/1", "0+10d4kh10>2"] {
+            let original = parser::parse(notation).unwrap();
+            let simplified = original.simplify();
+            let mut a = DiceRng::new(404);
+            let mut b = DiceRng::new(404);
+            for _ in 0..10_000 {
+                assert_eq!(original.evaluate(&mut a), simplified.evaluate(&mut b));
+            }
+        }
+    }
+}
@@ -0,0 +1,269 @@
+//! Batch rolling of simple dice specs
+//!
+//! A [`DiceSpec`] is the `NdS`, keep/drop and modifier shape most rolls in a
+//! game round take. Specs parse from notation like `4d6k3+2` and roll as the
+//! equivalent [`DiceExpression`].
+
+use crate::context::DiceContext;
+use crate::eval::RollResult;
+use crate::expression::DiceExpression;
+use crate::parser;
+use crate::{Dice, DiceError, DiceResult};
+use std::str::FromStr;
+
+/// `count`d`sides`, keeping the highest `keep` and dropping the lowest
+/// `drop` dice, plus `modifier`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DiceSpec {
+    pub count: u32,
+    pub sides: u32,
+    pub modifier: i32,
+    pub keep: Option<u32>,
+    pub drop: Option<u32>,
+}
+
+impl DiceSpec {
+    /// Plain `count`d`sides` with no selection or modifier
+    pub fn new(count: u32, sides: u32) -> Self {
+        DiceSpec {
+            count,
+            sides,
+            modifier: 0,
+            keep: None,
+            drop: None,
+        }
+    }
+
+    /// Check the spec describes dice that exist and selections that fit
+    ///
+    /// `keep` must be between 1 and `count`, and `drop` between 0 and
+    /// `count - 1`; either outside that fails with
+    /// [`DiceError::InvalidRange`].
+    pub fn validate(&self) -> DiceResult<()> {
+        if self.count == 0 {
+            return Err(DiceError::InvalidCount(0));
+        }
+        if self.sides == 0 {
+            return Err(DiceError::InvalidSides(0));
+        }
+        let out_of_range = |n: u32, min: u32, max: u32| DiceError::InvalidRange {
+            min: saturate(min),
+            max: saturate(max),
+            actual: saturate(n),
+        };
+        match (self.keep, self.drop) {
+            (Some(n), _) if n == 0 || n > self.count => Err(out_of_range(n, 1, self.count)),
+            (_, Some(n)) if n >= self.count => Err(out_of_range(n, 0, self.count - 1)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Expression the spec rolls as
+    ///
+    /// The drop applies first, then the keep, then the modifier.
+    pub fn to_expression(&self) -> DiceExpression {
+        let mut expr = DiceExpression::Dice(self.count, self.sides);
+        if let Some(n) = self.drop {
+            expr = DiceExpression::DropLowest(Box::new(expr), n);
+        }
+        if let Some(n) = self.keep {
+            expr = DiceExpression::KeepHighest(Box::new(expr), n);
+        }
+        if self.modifier != 0 {
+            expr = DiceExpression::Add(
+                Box::new(expr),
+                Box::new(DiceExpression::Constant(self.modifier)),
+            );
+        }
+        expr
+    }
+}
+
+fn saturate(value: u32) -> i32 {
+    i32::try_from(value).unwrap_or(i32::MAX)
+}
+
+impl FromStr for DiceSpec {
+    type Err = DiceError;
+
+    /// Parse `NdS`, optionally followed by one keep-highest or drop-lowest
+    /// selection and a `+K` or `-K` modifier
+    fn from_str(notation: &str) -> DiceResult<DiceSpec> {
+        let not_a_spec = || DiceError::InvalidNotation(format!("not a dice spec: {}", notation));
+
+        let (pool, modifier) = match parser::parse(notation)? {
+            DiceExpression::Add(pool, right) => match *right {
+                DiceExpression::Constant(value) => (*pool, value),
+                _ => return Err(not_a_spec()),
+            },
+            DiceExpression::Sub(pool, right) => match *right {
+                DiceExpression::Constant(value) => {
+                    (*pool, value.checked_neg().ok_or(DiceError::Overflow)?)
+                }
+                _ => return Err(not_a_spec()),
+            },
+            pool => (pool, 0),
+        };
+        let (dice, keep, drop) = match pool {
+            DiceExpression::KeepHighest(dice, n) => (*dice, Some(n), None),
+            DiceExpression::DropLowest(dice, n) => (*dice, None, Some(n)),
+            dice => (dice, None, None),
+        };
+        let spec = match dice {
+            DiceExpression::Dice(count, sides) => DiceSpec {
+                count,
+                sides,
+                modifier,
+                keep,
+                drop,
+            },
+            _ => return Err(not_a_spec()),
+        };
+        spec.validate()?;
+        Ok(spec)
+    }
+}
+
+impl DiceContext {
+    /// Roll every spec in order, collecting each outcome
+    ///
+    /// A failing spec doesn't stop the batch; its slot holds the error.
+    ///
+    /// # Arguments
+    ///
+    /// * `specs` - Rolls to make, in order
+    pub fn roll_multiple_returning_all(
+        &mut self,
+        specs: &[DiceSpec],
+    ) -> Vec<DiceResult<RollResult>> {
+        let config = *self.config();
+        specs
+            .iter()
+            .map(|spec| {
+                spec.validate()?;
+                spec.to_expression().roll_with_config(self.rng(), &config)
+            })
+            .collect()
+    }
+
+    /// Roll every spec in order, failing with the first error
+    ///
+    /// # Arguments
+    ///
+    /// * `specs` - Rolls to make, in order
+    pub fn roll_multiple_all_or_err(&mut self, specs: &[DiceSpec]) -> DiceResult<Vec<RollResult>> {
+        self.roll_multiple_returning_all(specs).into_iter().collect()
+    }
+}
+
+impl Dice {
+    /// Roll every spec in order, collecting each outcome
+    ///
+    /// # Arguments
+    ///
+    /// * `specs` - Rolls to make, in order
+    pub fn roll_multiple_returning_all(specs: &[DiceSpec]) -> Vec<DiceResult<RollResult>> {
+        DiceContext::new().roll_multiple_returning_all(specs)
+    }
+
+    /// Roll every spec in order, failing with the first error
+    ///
+    /// # Arguments
+    ///
+    /// * `specs` - Rolls to make, in order
+    pub fn roll_multiple_all_or_err(specs: &[DiceSpec]) -> DiceResult<Vec<RollResult>> {
+        DiceContext::new().roll_multiple_all_or_err(specs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_spec() {
+        assert_eq!("3d8".parse(), Ok(DiceSpec::new(3, 8)));
+        assert_eq!(
+            "4d6k3+2".parse(),
+            Ok(DiceSpec {
+                modifier: 2,
+                keep: Some(3),
+                ..DiceSpec::new(4, 6)
+            })
+        );
+        assert_eq!(
+            "2d20dl1-1".parse(),
+            Ok(DiceSpec {
+                modifier: -1,
+                drop: Some(1),
+                ..DiceSpec::new(2, 20)
+            })
+        );
+        for notation in ["4d6kl3", "1d6+1d4", "2*1d6", "3d6!", "5", "Nd6"] {
+            assert!(
+                matches!(notation.parse::<DiceSpec>(), Err(DiceError::InvalidNotation(_))),
+                "{}",
+                notation
+            );
+        }
+        assert!(matches!("4d".parse::<DiceSpec>(), Err(DiceError::ParseError { .. })));
+        assert_eq!(
+            "4d6k5".parse::<DiceSpec>(),
+            Err(DiceError::InvalidRange {
+                min: 1,
+                max: 4,
+                actual: 5
+            })
+        );
+        assert_eq!(
+            "2d6dl2".parse::<DiceSpec>(),
+            Err(DiceError::InvalidRange {
+                min: 0,
+                max: 1,
+                actual: 2
+            })
+        );
+    }
+
+    #[test]
+    fn test_spec_expression() {
+        let spec: DiceSpec = "4d6k3+2".parse().unwrap();
+        assert_eq!(spec.to_expression().to_notation(), "4d6k3+2");
+        let both = DiceSpec {
+            keep: Some(2),
+            drop: Some(1),
+            ..DiceSpec::new(5, 6)
+        };
+        assert_eq!(both.to_expression().to_notation(), "5d6dl1k2");
+    }
+
+    #[test]
+    fn test_roll_multiple_returning_all() {
+        let specs = [
+            DiceSpec::new(4, 6),
+            DiceSpec::new(0, 6),
+            "1d20+5".parse().unwrap(),
+            "4d6k3".parse().unwrap(),
+        ];
+        let mut ctx = DiceContext::with_seed(126);
+        let results = ctx.roll_multiple_returning_all(&specs);
+        assert_eq!(results.len(), 4);
+        assert_eq!(results[0].as_ref().unwrap().dice.len(), 4);
+        assert_eq!(results[1], Err(DiceError::InvalidCount(0)));
+        assert!((6..=25).contains(&results[2].as_ref().unwrap().total));
+        assert_eq!(results[3].as_ref().unwrap().kept().len(), 3);
+        let overkeep = DiceSpec {
+            keep: Some(3),
+            ..DiceSpec::new(2, 6)
+        };
+        assert!(matches!(
+            ctx.roll_multiple_returning_all(&[overkeep])[0],
+            Err(DiceError::InvalidRange { .. })
+        ));
+
+        assert_eq!(ctx.roll_multiple_all_or_err(&specs), Err(DiceError::InvalidCount(0)));
+        let rolled = Dice::roll_multiple_all_or_err(&[specs[0], specs[2]]).unwrap();
+        assert_eq!(rolled.len(), 2);
+    }
+}
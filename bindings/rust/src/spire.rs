@@ -0,0 +1,199 @@
+//! Spire action rolls
+//!
+//! Spire rolls a pool of d10s and reads the highest die, like Forged in
+//! the Dark: 1-2 is a critical consequence, 3-5 a standard consequence,
+//! 6-7 success at a cost, 8-9 a success and 10 a critical success. A
+//! pool of zero dice rolls 2d10 and takes the lower. The bands come from a
+//! [`SpireConfig`].
+
+use crate::context::DiceContext;
+use crate::{Dice, DiceResult};
+
+/// Result band of a Spire roll, worst first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SpireOutcome {
+    CriticalConsequence,
+    StandardConsequence,
+    SuccessAtCost,
+    Success,
+    CriticalSuccess,
+}
+
+/// Lowest die reaching each [`SpireOutcome`]
+///
+/// Thresholds are checked from critical success down, so a die meeting
+/// `critical_success` is a critical success even if the other thresholds
+/// overlap it. Anything below `standard_consequence` is a critical
+/// consequence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SpireConfig {
+    pub standard_consequence: i32,
+    pub success_at_cost: i32,
+    pub success: i32,
+    pub critical_success: i32,
+}
+
+impl SpireConfig {
+    /// Band reached by a die showing `value`
+    pub fn outcome(&self, value: i32) -> SpireOutcome {
+        if value >= self.critical_success {
+            SpireOutcome::CriticalSuccess
+        } else if value >= self.success {
+            SpireOutcome::Success
+        } else if value >= self.success_at_cost {
+            SpireOutcome::SuccessAtCost
+        } else if value >= self.standard_consequence {
+            SpireOutcome::StandardConsequence
+        } else {
+            SpireOutcome::CriticalConsequence
+        }
+    }
+}
+
+impl Default for SpireConfig {
+    fn default() -> Self {
+        SpireConfig {
+            standard_consequence: 3,
+            success_at_cost: 6,
+            success: 8,
+            critical_success: 10,
+        }
+    }
+}
+
+/// Outcome of a Spire roll
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SpireResult {
+    /// Every die rolled; two for a zero-dice pool
+    pub all_values: Vec<i32>,
+    /// The die that counts: the highest, or the lowest of a zero-dice pool
+    pub effective: i32,
+    pub outcome: SpireOutcome,
+}
+
+impl DiceContext {
+    /// Roll a Spire action pool with the standard bands
+    ///
+    /// # Arguments
+    ///
+    /// * `dice` - Number of d10s; 0 rolls 2d10 and keeps the lowest
+    pub fn roll_spire(&mut self, dice: u32) -> DiceResult<SpireResult> {
+        self.roll_spire_with_config(dice, &SpireConfig::default())
+    }
+
+    /// Roll a Spire action pool graded by `config`
+    ///
+    /// # Arguments
+    ///
+    /// * `dice` - Number of d10s; 0 rolls 2d10 and keeps the lowest
+    /// * `config` - Lowest die reaching each band
+    pub fn roll_spire_with_config(
+        &mut self,
+        dice: u32,
+        config: &SpireConfig,
+    ) -> DiceResult<SpireResult> {
+        let (all_values, effective) = self.roll_action_pool(dice, 10)?;
+        Ok(SpireResult {
+            all_values,
+            effective,
+            outcome: config.outcome(effective),
+        })
+    }
+}
+
+impl Dice {
+    /// Roll a Spire action pool with the standard bands
+    ///
+    /// # Arguments
+    ///
+    /// * `dice` - Number of d10s; 0 rolls 2d10 and keeps the lowest
+    pub fn roll_spire(dice: u32) -> DiceResult<SpireResult> {
+        DiceContext::new().roll_spire(dice)
+    }
+
+    /// Roll a Spire action pool graded by `config`
+    ///
+    /// # Arguments
+    ///
+    /// * `dice` - Number of d10s; 0 rolls 2d10 and keeps the lowest
+    /// * `config` - Lowest die reaching each band
+    pub fn roll_spire_with_config(dice: u32, config: &SpireConfig) -> DiceResult<SpireResult> {
+        DiceContext::new().roll_spire_with_config(dice, config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_standard_bands() {
+        use SpireOutcome::*;
+
+        let config = SpireConfig::default();
+        let bands: Vec<SpireOutcome> = (1..=10).map(|value| config.outcome(value)).collect();
+        assert_eq!(
+            bands,
+            [
+                CriticalConsequence,
+                CriticalConsequence,
+                StandardConsequence,
+                StandardConsequence,
+                StandardConsequence,
+                SuccessAtCost,
+                SuccessAtCost,
+                Success,
+                Success,
+                CriticalSuccess
+            ]
+        );
+    }
+
+    #[test]
+    fn test_roll_spire() {
+        let mut ctx = DiceContext::with_seed(194);
+        let mut seen = Vec::new();
+        for _ in 0..500 {
+            let result = ctx.roll_spire(3).unwrap();
+            assert_eq!(result.all_values.len(), 3);
+            assert_eq!(Some(&result.effective), result.all_values.iter().max());
+            assert_eq!(result.outcome, SpireConfig::default().outcome(result.effective));
+            if !seen.contains(&result.outcome) {
+                seen.push(result.outcome);
+            }
+        }
+        assert_eq!(seen.len(), 5);
+        assert!(Dice::roll_spire(1).is_ok());
+    }
+
+    #[test]
+    fn test_zero_dice_takes_lowest() {
+        let mut ctx = DiceContext::with_seed(194);
+        for _ in 0..200 {
+            let result = ctx.roll_spire(0).unwrap();
+            assert_eq!(result.all_values.len(), 2);
+            assert_eq!(Some(&result.effective), result.all_values.iter().min());
+        }
+    }
+
+    #[test]
+    fn test_configured_bands() {
+        // Overlapping thresholds resolve to the best band reached
+        let config = SpireConfig {
+            success: 1,
+            critical_success: 9,
+            ..SpireConfig::default()
+        };
+        assert_eq!(config.outcome(1), SpireOutcome::Success);
+        assert_eq!(config.outcome(9), SpireOutcome::CriticalSuccess);
+        let mut ctx = DiceContext::with_seed(194);
+        for _ in 0..100 {
+            let result = ctx.roll_spire_with_config(2, &config).unwrap();
+            assert!(result.outcome >= SpireOutcome::Success);
+        }
+        assert!(Dice::roll_spire_with_config(0, &config).is_ok());
+    }
+}
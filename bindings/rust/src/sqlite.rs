@@ -0,0 +1,185 @@
+//! Persistent roll log backed by SQLite (`sqlite` feature)
+//!
+//! Unlike [`RollHistory`](crate::history::RollHistory), rolls written here
+//! survive the process. Each roll is one row of the `rolls` table.
+
+use crate::history::{join_individual, split_individual, RollRecord};
+use crate::{DiceError, DiceResult};
+use rusqlite::{params, Connection};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const CREATE_TABLE: &str = "CREATE TABLE IF NOT EXISTS rolls (
+    id INTEGER PRIMARY KEY,
+    timestamp INTEGER,
+    notation TEXT,
+    result INTEGER,
+    individual_results TEXT,
+    seed INTEGER
+)";
+
+fn db_error(err: rusqlite::Error) -> DiceError {
+    DiceError::Io(err.to_string())
+}
+
+/// SQLite-backed roll log
+pub struct SqliteRollLog {
+    conn: Connection,
+    seed: Option<u64>,
+}
+
+impl SqliteRollLog {
+    /// Open (or create) a log database at `db_path`
+    pub fn new(db_path: &Path) -> DiceResult<SqliteRollLog> {
+        SqliteRollLog::from_connection(Connection::open(db_path).map_err(db_error)?)
+    }
+
+    /// Open a log that lives only as long as this value
+    pub fn open_in_memory() -> DiceResult<SqliteRollLog> {
+        SqliteRollLog::from_connection(Connection::open_in_memory().map_err(db_error)?)
+    }
+
+    fn from_connection(conn: Connection) -> DiceResult<SqliteRollLog> {
+        conn.execute(CREATE_TABLE, []).map_err(db_error)?;
+        Ok(SqliteRollLog { conn, seed: None })
+    }
+
+    /// Seed stored alongside subsequent rolls (e.g. `DiceContext::seed()`)
+    pub fn set_seed(&mut self, seed: Option<u64>) {
+        self.seed = seed;
+    }
+
+    /// Append a roll, timestamped now
+    pub fn record(&self, notation: &str, result: i32, individual: &[i32]) -> DiceResult<()> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_millis() as i64)
+            .unwrap_or(0);
+        // SQLite integers are signed; store the seed's bit pattern
+        let seed = self.seed.map(|seed| seed as i64);
+        self.conn
+            .execute(
+                "INSERT INTO rolls (timestamp, notation, result, individual_results, seed)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![timestamp, notation, result, join_individual(individual), seed],
+            )
+            .map_err(db_error)?;
+        Ok(())
+    }
+
+    /// Rolls whose result lies in `[min, max]`, oldest first
+    ///
+    /// The row id becomes `sequence_number`; `context_name` is empty since
+    /// the table doesn't store one.
+    pub fn query_range(&self, min: i32, max: i32) -> DiceResult<Vec<RollRecord>> {
+        let mut statement = self
+            .conn
+            .prepare(
+                "SELECT id, timestamp, notation, result, individual_results FROM rolls
+                 WHERE result BETWEEN ?1 AND ?2 ORDER BY id",
+            )
+            .map_err(db_error)?;
+        let rows = statement
+            .query_map(params![min, max], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, i64>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, i32>(3)?,
+                    row.get::<_, String>(4)?,
+                ))
+            })
+            .map_err(db_error)?;
+
+        let mut records = Vec::new();
+        for row in rows {
+            let (id, timestamp, notation, result, individual) = row.map_err(db_error)?;
+            let individual_results = split_individual(&individual).ok_or_else(|| {
+                DiceError::InvalidFormat(format!("row {}: invalid individual_results", id))
+            })?;
+            records.push(RollRecord {
+                sequence_number: id as u64,
+                timestamp_unix_ms: timestamp as u64,
+                notation,
+                result,
+                individual_results,
+                context_name: String::new(),
+            });
+        }
+        Ok(records)
+    }
+
+    /// Mean result of every logged roll of `notation`, or None if never rolled
+    pub fn average(&self, notation: &str) -> DiceResult<Option<f64>> {
+        self.conn
+            .query_row(
+                "SELECT AVG(result) FROM rolls WHERE notation = ?1",
+                params![notation],
+                |row| row.get::<_, Option<f64>>(0),
+            )
+            .map_err(db_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::DiceContext;
+
+    #[test]
+    fn test_record_and_query() {
+        let log = SqliteRollLog::open_in_memory().unwrap();
+        log.record("3d6", 10, &[3, 3, 4]).unwrap();
+        log.record("3d6", 15, &[5, 5, 5]).unwrap();
+        log.record("1d20", 20, &[20]).unwrap();
+
+        let records = log.query_range(10, 15).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].sequence_number, 1);
+        assert_eq!(records[0].individual_results, vec![3, 3, 4]);
+        assert_eq!(records[1].result, 15);
+        assert!(log.query_range(16, 19).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_average() {
+        let log = SqliteRollLog::open_in_memory().unwrap();
+        assert_eq!(log.average("3d6"), Ok(None));
+        log.record("3d6", 10, &[3, 3, 4]).unwrap();
+        log.record("3d6", 15, &[5, 5, 5]).unwrap();
+        log.record("1d20", 20, &[20]).unwrap();
+        assert_eq!(log.average("3d6"), Ok(Some(12.5)));
+        assert_eq!(log.average("1d20"), Ok(Some(20.0)));
+
+        log.conn.execute("DROP TABLE rolls", []).unwrap();
+        assert!(matches!(log.average("3d6"), Err(DiceError::Io(_))));
+    }
+
+    #[test]
+    fn test_persists_to_file() {
+        let path = std::env::temp_dir().join(format!("roll-dice-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let mut ctx = DiceContext::with_seed(77);
+        {
+            let mut log = SqliteRollLog::new(&path).unwrap();
+            log.set_seed(Some(ctx.seed()));
+            let result = ctx.roll_notation("4d6k3").unwrap();
+            log.record("4d6k3", result.total, &result.individual()).unwrap();
+        }
+
+        let log = SqliteRollLog::new(&path).unwrap();
+        let records = log.query_range(i32::MIN, i32::MAX).unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].notation, "4d6k3");
+        assert_eq!(records[0].individual_results.len(), 4);
+        let seed: i64 = log
+            .conn
+            .query_row("SELECT seed FROM rolls", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(seed as u64, 77);
+
+        drop(log);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
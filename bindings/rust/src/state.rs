@@ -0,0 +1,167 @@
+//! Saved context state
+//!
+//! A [`DiceContext`] can be written to a file and loaded later to carry on
+//! the same sequence of rolls, e.g. across the sessions of a saved game.
+//! The file is plain text with a version header:
+//!
+//! ```text
+//! roll-dice-state 1
+//! seed 42
+//! rng 1f0c3a5b7d9e2f41 8a6b4c2d0e1f3a5b 7c9d1e3f5a7b9c0d 2e4f6a8b0c1d3e5f
+//! ```
+//!
+//! Only the seed and generator are saved; a loaded context has the default
+//! [`RollConfig`](crate::RollConfig).
+
+use crate::context::DiceContext;
+use crate::rng::DiceRng;
+use crate::{Dice, DiceError, DiceResult};
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+/// First line of a state file
+const STATE_HEADER: &str = "roll-dice-state 1";
+
+impl DiceContext {
+    /// Write the seed and generator state to `path`
+    ///
+    /// The state goes to a temporary file beside `path` that is then
+    /// renamed over it, so a crash never leaves a half-written file.
+    pub fn save_state(&self, path: &Path) -> io::Result<()> {
+        let mut name = path.file_name().unwrap_or_default().to_os_string();
+        name.push(".tmp");
+        let temp = path.with_file_name(name);
+
+        let mut file = fs::File::create(&temp)?;
+        let [a, b, c, d] = self.rng_state();
+        writeln!(file, "{}", STATE_HEADER)?;
+        writeln!(file, "seed {}", self.seed())?;
+        writeln!(file, "rng {:016x} {:016x} {:016x} {:016x}", a, b, c, d)?;
+        file.sync_all()?;
+        fs::rename(&temp, path)
+    }
+
+    /// Load a context saved with [`save_state`](Self::save_state)
+    ///
+    /// A file with another header or a damaged body fails with
+    /// [`DiceError::InvalidFormat`].
+    pub fn load_state(path: &Path) -> DiceResult<DiceContext> {
+        let text = fs::read_to_string(path)?;
+        let invalid = |what: &str| DiceError::InvalidFormat(format!("state file: {}", what));
+
+        let mut lines = text.lines();
+        if lines.next() != Some(STATE_HEADER) {
+            return Err(invalid("missing or unsupported header"));
+        }
+        let seed = lines
+            .next()
+            .and_then(|line| line.strip_prefix("seed "))
+            .and_then(|seed| seed.parse().ok())
+            .ok_or_else(|| invalid("invalid seed"))?;
+        let words: Vec<u64> = lines
+            .next()
+            .and_then(|line| line.strip_prefix("rng "))
+            .and_then(|words| {
+                words
+                    .split(' ')
+                    .map(|word| u64::from_str_radix(word, 16).ok())
+                    .collect()
+            })
+            .ok_or_else(|| invalid("invalid generator state"))?;
+        let state: [u64; 4] = words
+            .try_into()
+            .map_err(|_| invalid("invalid generator state"))?;
+        let rng = DiceRng::from_state(state).ok_or_else(|| invalid("invalid generator state"))?;
+        Ok(DiceContext::with_seed(seed).with_rng(rng))
+    }
+}
+
+impl Dice {
+    /// Roll a single die from the generator saved in `seed_file`, then
+    /// save the advanced generator back
+    ///
+    /// The file must already exist; create it with
+    /// [`DiceContext::save_state`]. Consecutive calls continue one
+    /// deterministic sequence, even across program runs.
+    ///
+    /// # Arguments
+    ///
+    /// * `sides` - Number of sides on the die
+    /// * `seed_file` - State file read and rewritten
+    pub fn roll_with_persistent_seed(sides: i32, seed_file: &Path) -> DiceResult<i32> {
+        let mut ctx = DiceContext::load_state(seed_file)?;
+        let value = ctx.roll(sides)?;
+        ctx.save_state(seed_file)?;
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("roll-dice-{}-{}.state", name, std::process::id()))
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let path = state_path("save");
+        let mut ctx = DiceContext::with_seed(152);
+        ctx.roll_notation("4d6").unwrap();
+        ctx.save_state(&path).unwrap();
+
+        let mut loaded = DiceContext::load_state(&path).unwrap();
+        assert_eq!(loaded.seed(), 152);
+        for _ in 0..20 {
+            assert_eq!(loaded.roll_notation("3d6!"), ctx.roll_notation("3d6!"));
+        }
+        let text = fs::read_to_string(&path).unwrap();
+        assert!(text.starts_with("roll-dice-state 1\nseed 152\nrng "));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_persistent_seed_resumes() {
+        let path = state_path("persistent");
+        DiceContext::with_seed(7).save_state(&path).unwrap();
+        let persisted: Vec<i32> = (0..10)
+            .map(|_| Dice::roll_with_persistent_seed(20, &path).unwrap())
+            .collect();
+
+        let mut ctx = DiceContext::with_seed(7);
+        let expected: Vec<i32> = (0..10).map(|_| ctx.roll(20).unwrap()).collect();
+        assert_eq!(persisted, expected);
+        assert_eq!(
+            Dice::roll_with_persistent_seed(0, &path),
+            Err(DiceError::InvalidSides(0))
+        );
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(
+            Dice::roll_with_persistent_seed(20, &path),
+            Err(DiceError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn test_invalid_state_files() {
+        let path = state_path("invalid");
+        for text in [
+            "",
+            "roll-dice-state 2\nseed 1\nrng 1 2 3 4\n",
+            "roll-dice-state 1\nseed x\nrng 1 2 3 4\n",
+            "roll-dice-state 1\nseed 1\nrng 1 2 3\n",
+            "roll-dice-state 1\nseed 1\nrng 1 2 3 zz\n",
+            "roll-dice-state 1\nseed 1\nrng 0 0 0 0\n",
+        ] {
+            fs::write(&path, text).unwrap();
+            assert!(
+                matches!(DiceContext::load_state(&path), Err(DiceError::InvalidFormat(_))),
+                "{:?}",
+                text
+            );
+        }
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,537 @@
+//! Statistical helpers
+//!
+//! Chi-square machinery shared by the simulation-based checks, plus
+//! distribution comparisons on [`DiceExpression`], RNG fairness reports and
+//! quick range checks.
+
+use crate::context::DiceContext;
+use crate::expression::DiceExpression;
+use crate::rng::DiceRng;
+use crate::{Dice, DiceError, DiceResult};
+use rand::Rng;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+/// Samples drawn from each expression by [`DiceExpression::semantic_equivalent`]
+pub const EQUIVALENCE_ITERATIONS: u32 = 100_000;
+
+/// Default significance level for chi-square tests
+pub const DEFAULT_SIGNIFICANCE: f64 = 0.05;
+
+/// Minimum combined observations per bin before adjacent outcomes are merged
+const MIN_BIN_COUNT: u64 = 10;
+
+/// Upper-tail probability of the chi-square distribution
+///
+/// Returns `P(X >= statistic)` for `X ~ χ²(degrees_of_freedom)`, computed via
+/// the regularized upper incomplete gamma function.
+pub fn chi_square_p_value(statistic: f64, degrees_of_freedom: u32) -> f64 {
+    if degrees_of_freedom == 0 {
+        return 1.0;
+    }
+    if statistic <= 0.0 {
+        return 1.0;
+    }
+    regularized_gamma_q(degrees_of_freedom as f64 / 2.0, statistic / 2.0)
+}
+
+/// Lanczos approximation of `ln Γ(x)` for `x > 0`
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 6] = [
+        76.180_091_729_471_46,
+        -86.505_320_329_416_77,
+        24.014_098_240_830_91,
+        -1.231_739_572_450_155,
+        0.120_865_097_386_617_9e-2,
+        -0.539_523_938_495_3e-5,
+    ];
+    let tmp = x + 5.5;
+    let tmp = tmp - (x + 0.5) * tmp.ln();
+    let mut series = 1.000_000_000_190_015;
+    let mut y = x;
+    for coefficient in COEFFICIENTS {
+        y += 1.0;
+        series += coefficient / y;
+    }
+    -tmp + (2.506_628_274_631_000_5 * series / x).ln()
+}
+
+/// Regularized upper incomplete gamma function `Q(a, x)`
+fn regularized_gamma_q(a: f64, x: f64) -> f64 {
+    const EPSILON: f64 = 1e-14;
+    const MAX_ITERATIONS: usize = 10_000;
+
+    if x < a + 1.0 {
+        // Series expansion of P(a, x)
+        let mut term = 1.0 / a;
+        let mut sum = term;
+        let mut n = a;
+        for _ in 0..MAX_ITERATIONS {
+            n += 1.0;
+            term *= x / n;
+            sum += term;
+            if term.abs() < sum.abs() * EPSILON {
+                break;
+            }
+        }
+        let p = sum * (-x + a * x.ln() - ln_gamma(a)).exp();
+        (1.0 - p).clamp(0.0, 1.0)
+    } else {
+        // Lentz continued fraction for Q(a, x)
+        let tiny = f64::MIN_POSITIVE / EPSILON;
+        let mut b = x + 1.0 - a;
+        let mut c = 1.0 / tiny;
+        let mut d = 1.0 / b;
+        let mut h = d;
+        for i in 1..MAX_ITERATIONS {
+            let an = -(i as f64) * (i as f64 - a);
+            b += 2.0;
+            d = an * d + b;
+            if d.abs() < tiny {
+                d = tiny;
+            }
+            c = b + an / c;
+            if c.abs() < tiny {
+                c = tiny;
+            }
+            d = 1.0 / d;
+            let delta = d * c;
+            h *= delta;
+            if (delta - 1.0).abs() < EPSILON {
+                break;
+            }
+        }
+        ((-x + a * x.ln() - ln_gamma(a)).exp() * h).clamp(0.0, 1.0)
+    }
+}
+
+/// Chi-square test of homogeneity for two equally sized samples
+///
+/// Each map holds outcome counts. Adjacent outcomes are merged until every
+/// bin has at least [`MIN_BIN_COUNT`] combined observations so sparse tails
+/// don't dominate the statistic. Returns the p-value.
+fn two_sample_p_value(a: &BTreeMap<i32, u64>, b: &BTreeMap<i32, u64>) -> f64 {
+    let mut outcomes: Vec<i32> = a.keys().chain(b.keys()).copied().collect();
+    outcomes.sort_unstable();
+    outcomes.dedup();
+
+    let mut bins: Vec<(u64, u64)> = Vec::new();
+    let mut current = (0u64, 0u64);
+    for outcome in outcomes {
+        current.0 += a.get(&outcome).copied().unwrap_or(0);
+        current.1 += b.get(&outcome).copied().unwrap_or(0);
+        if current.0 + current.1 >= MIN_BIN_COUNT {
+            bins.push(current);
+            current = (0, 0);
+        }
+    }
+    if current.0 + current.1 > 0 {
+        match bins.last_mut() {
+            Some(last) => {
+                last.0 += current.0;
+                last.1 += current.1;
+            }
+            None => bins.push(current),
+        }
+    }
+
+    let statistic: f64 = bins
+        .iter()
+        .map(|&(x, y)| {
+            let diff = x as f64 - y as f64;
+            diff * diff / (x + y) as f64
+        })
+        .sum();
+    chi_square_p_value(statistic, bins.len().saturating_sub(1) as u32)
+}
+
+/// Chi-square goodness-of-fit of a single die against the uniform distribution
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FairnessReport {
+    pub chi_square_statistic: f64,
+    pub p_value: f64,
+    /// `p_value > DEFAULT_SIGNIFICANCE`
+    pub is_fair: bool,
+    /// (face, times rolled) for every face, in ascending order
+    pub face_frequencies: Vec<(i32, u32)>,
+    /// Rolls expected per face: `sample_size / sides`
+    pub expected_frequency: f64,
+}
+
+/// How often repeated rolls landed above, on or below a target
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CompareResult {
+    pub above: u32,
+    pub equal: u32,
+    pub below: u32,
+    /// Rolls made: `above + equal + below`
+    pub total: u32,
+    /// Mean of all totals rolled
+    pub mean: f64,
+}
+
+impl DiceContext {
+    /// Roll a die repeatedly and count how often each face came up
+    ///
+    /// Index `i - 1` holds the count for face `i`. Nothing but the counts is
+    /// stored, so memory grows with `sides`, not `sample_count`. No count
+    /// can exceed `sample_count`, so the `u32` counters never overflow.
+    /// More sides than `RollConfig::max_sides` fail with
+    /// [`DiceError::InvalidSides`].
+    ///
+    /// # Arguments
+    ///
+    /// * `sides` - Number of sides on the die
+    /// * `sample_count` - Number of rolls; 0 leaves every count at 0
+    pub fn roll_histogram(&mut self, sides: i32, sample_count: u32) -> DiceResult<Vec<u32>> {
+        if sides <= 0 || sides as u32 > self.config().max_sides {
+            return Err(DiceError::InvalidSides(sides));
+        }
+        let mut counts = vec![0u32; sides as usize];
+        let rng = self.rng();
+        for _ in 0..sample_count {
+            counts[rng.gen_range(0..sides as usize)] += 1;
+        }
+        Ok(counts)
+    }
+
+    /// Roll a die repeatedly and test the faces for uniformity
+    ///
+    /// # Arguments
+    ///
+    /// * `sides` - Number of sides on the die
+    /// * `sample_size` - Number of rolls
+    pub fn fairness_report(&mut self, sides: i32, sample_size: u32) -> DiceResult<FairnessReport> {
+        if sides <= 0 {
+            return Err(DiceError::InvalidSides(sides));
+        }
+        if sample_size == 0 {
+            return Err(DiceError::InvalidCount(0));
+        }
+
+        let counts = self.roll_histogram(sides, sample_size)?;
+
+        let expected_frequency = sample_size as f64 / sides as f64;
+        let chi_square_statistic: f64 = counts
+            .iter()
+            .map(|&observed| {
+                let diff = observed as f64 - expected_frequency;
+                diff * diff / expected_frequency
+            })
+            .sum();
+        let p_value = chi_square_p_value(chi_square_statistic, sides as u32 - 1);
+
+        Ok(FairnessReport {
+            chi_square_statistic,
+            p_value,
+            is_fair: p_value > DEFAULT_SIGNIFICANCE,
+            face_frequencies: (1..=sides).zip(counts).collect(),
+            expected_frequency,
+        })
+    }
+
+    /// Roll notation repeatedly and report `(min, max, mean)` of the totals
+    ///
+    /// Totals are streamed, not stored; the mean is kept with Welford's
+    /// online update. `count` is capped by
+    /// `RollConfig::max_simulation_iterations`.
+    ///
+    /// # Arguments
+    ///
+    /// * `notation` - Dice notation like "3d6", "1d20+5", "4d6k3"
+    /// * `count` - Number of rolls
+    pub fn roll_sum_range(&mut self, notation: &str, count: u32) -> DiceResult<(i32, i32, f64)> {
+        let config = *self.config();
+        if count == 0 {
+            return Err(DiceError::InvalidCount(0));
+        }
+        if count > config.max_simulation_iterations {
+            return Err(DiceError::RollCountLimit {
+                requested: count,
+                limit: config.max_simulation_iterations,
+            });
+        }
+        let expr = DiceExpression::parse(notation)?;
+
+        let (mut min, mut max, mut mean) = (i32::MAX, i32::MIN, 0.0);
+        for n in 1..=count {
+            let total = expr.roll_with_config(self.rng(), &config)?.total;
+            min = min.min(total);
+            max = max.max(total);
+            mean += (total as f64 - mean) / n as f64;
+        }
+        Ok((min, max, mean))
+    }
+
+    /// Roll notation repeatedly and count totals above, on and below `target`
+    ///
+    /// Totals are streamed, not stored. `count` is capped by
+    /// `RollConfig::max_simulation_iterations`.
+    ///
+    /// # Arguments
+    ///
+    /// * `notation` - Dice notation like "1d20+5"
+    /// * `count` - Number of rolls
+    /// * `target` - Value each total is compared with
+    pub fn roll_n_compare(
+        &mut self,
+        notation: &str,
+        count: u32,
+        target: i32,
+    ) -> DiceResult<CompareResult> {
+        let config = *self.config();
+        if count == 0 {
+            return Err(DiceError::InvalidCount(0));
+        }
+        if count > config.max_simulation_iterations {
+            return Err(DiceError::RollCountLimit {
+                requested: count,
+                limit: config.max_simulation_iterations,
+            });
+        }
+        let expr = DiceExpression::parse(notation)?;
+
+        let mut result = CompareResult {
+            above: 0,
+            equal: 0,
+            below: 0,
+            total: count,
+            mean: 0.0,
+        };
+        let mut sum = 0i64;
+        for _ in 0..count {
+            let total = expr.roll_with_config(self.rng(), &config)?.total;
+            match total.cmp(&target) {
+                Ordering::Greater => result.above += 1,
+                Ordering::Equal => result.equal += 1,
+                Ordering::Less => result.below += 1,
+            }
+            sum += total as i64;
+        }
+        result.mean = sum as f64 / count as f64;
+        Ok(result)
+    }
+}
+
+impl Dice {
+    /// Roll a die repeatedly and count how often each face came up
+    ///
+    /// # Arguments
+    ///
+    /// * `sides` - Number of sides on the die
+    /// * `sample_count` - Number of rolls; 0 leaves every count at 0
+    pub fn roll_histogram(sides: i32, sample_count: u32) -> DiceResult<Vec<u32>> {
+        DiceContext::new().roll_histogram(sides, sample_count)
+    }
+
+    /// Roll a die repeatedly and test the faces for uniformity
+    ///
+    /// # Arguments
+    ///
+    /// * `sides` - Number of sides on the die
+    /// * `sample_size` - Number of rolls
+    pub fn fairness_report(sides: i32, sample_size: u32) -> DiceResult<FairnessReport> {
+        DiceContext::new().fairness_report(sides, sample_size)
+    }
+
+    /// Roll notation repeatedly and report `(min, max, mean)` of the totals
+    ///
+    /// # Arguments
+    ///
+    /// * `notation` - Dice notation like "3d6", "1d20+5", "4d6k3"
+    /// * `count` - Number of rolls
+    pub fn roll_sum_range(notation: &str, count: u32) -> DiceResult<(i32, i32, f64)> {
+        DiceContext::new().roll_sum_range(notation, count)
+    }
+
+    /// Roll notation repeatedly and count totals above, on and below `target`
+    ///
+    /// # Arguments
+    ///
+    /// * `notation` - Dice notation like "1d20+5"
+    /// * `count` - Number of rolls
+    /// * `target` - Value each total is compared with
+    pub fn roll_n_compare(notation: &str, count: u32, target: i32) -> DiceResult<CompareResult> {
+        DiceContext::new().roll_n_compare(notation, count, target)
+    }
+}
+
+impl DiceExpression {
+    /// Check whether two expressions appear to produce the same distribution
+    ///
+    /// This is a **probabilistic** test, not a proof: both expressions are
+    /// rolled [`EQUIVALENCE_ITERATIONS`] times and compared with a chi-square
+    /// test at [`DEFAULT_SIGNIFICANCE`]. Equivalent expressions are reported
+    /// as different about 5% of the time, and distributions that differ only
+    /// very slightly may be reported as equal. `1d6+1d6` and `2d6` are
+    /// equivalent; `2d6` and `1d12` are not.
+    pub fn semantic_equivalent(&self, other: &DiceExpression) -> bool {
+        let mut rng = DiceRng::new(rand::random());
+        self.semantic_equivalent_with(other, DEFAULT_SIGNIFICANCE, &mut rng)
+    }
+
+    /// [`semantic_equivalent`](Self::semantic_equivalent) with an explicit
+    /// significance level and generator
+    ///
+    /// # Arguments
+    ///
+    /// * `significance` - Probability of rejecting truly equivalent
+    ///   expressions (e.g. 0.05 or 0.01)
+    /// * `rng` - Generator used for both simulations
+    ///
+    /// Returns false if either expression fails to evaluate.
+    pub fn semantic_equivalent_with<R: Rng + ?Sized>(
+        &self,
+        other: &DiceExpression,
+        significance: f64,
+        rng: &mut R,
+    ) -> bool {
+        let mut sample = |expr: &DiceExpression| -> Option<BTreeMap<i32, u64>> {
+            let mut counts = BTreeMap::new();
+            for _ in 0..EQUIVALENCE_ITERATIONS {
+                *counts.entry(expr.evaluate(rng).ok()?).or_insert(0) += 1;
+            }
+            Some(counts)
+        };
+
+        match (sample(self), sample(other)) {
+            (Some(a), Some(b)) => two_sample_p_value(&a, &b) > significance,
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(notation: &str) -> DiceExpression {
+        DiceExpression::parse(notation).unwrap()
+    }
+
+    #[test]
+    fn test_chi_square_p_value() {
+        // Critical values at the 5% level
+        assert!((chi_square_p_value(3.841, 1) - 0.05).abs() < 1e-3);
+        assert!((chi_square_p_value(11.070, 5) - 0.05).abs() < 1e-3);
+        assert!((chi_square_p_value(124.342, 100) - 0.05).abs() < 1e-3);
+        // Critical value at the 1% level
+        assert!((chi_square_p_value(6.635, 1) - 0.01).abs() < 1e-3);
+        assert_eq!(chi_square_p_value(0.0, 3), 1.0);
+    }
+
+    #[test]
+    fn test_equivalent_distributions() {
+        let mut rng = DiceRng::new(12345);
+        assert!(parse("1d6+1d6").semantic_equivalent_with(&parse("2d6"), 0.01, &mut rng));
+        assert!(parse("2+1d4").semantic_equivalent_with(&parse("1d4+2"), 0.01, &mut rng));
+    }
+
+    #[test]
+    fn test_different_distributions() {
+        let mut rng = DiceRng::new(12345);
+        assert!(!parse("2d6").semantic_equivalent_with(&parse("1d12"), 0.01, &mut rng));
+        assert!(!parse("3d6").semantic_equivalent_with(&parse("3d6+1"), 0.01, &mut rng));
+        assert!(!parse("4d6k3").semantic_equivalent_with(&parse("3d6"), 0.01, &mut rng));
+    }
+
+    #[test]
+    fn test_failed_evaluation_is_not_equivalent() {
+        let mut rng = DiceRng::new(1);
+        let broken = parse("1d6/0");
+        assert!(!broken.semantic_equivalent_with(&parse("1d6"), 0.05, &mut rng));
+    }
+
+    #[test]
+    fn test_fairness_report() {
+        let mut ctx = DiceContext::with_seed(42);
+        let report = ctx.fairness_report(6, 60_000).unwrap();
+        assert!(report.is_fair, "{:?}", report);
+        assert_eq!(report.expected_frequency, 10_000.0);
+        assert_eq!(report.face_frequencies.len(), 6);
+        assert_eq!(report.face_frequencies[0].0, 1);
+        let total: u32 = report.face_frequencies.iter().map(|&(_, n)| n).sum();
+        assert_eq!(total, 60_000);
+    }
+
+    #[test]
+    fn test_fairness_report_errors() {
+        let mut ctx = DiceContext::with_seed(1);
+        assert_eq!(ctx.fairness_report(0, 100), Err(DiceError::InvalidSides(0)));
+        assert_eq!(ctx.fairness_report(6, 0), Err(DiceError::InvalidCount(0)));
+        // A d1 can only be fair
+        let report = ctx.fairness_report(1, 10).unwrap();
+        assert_eq!(report.chi_square_statistic, 0.0);
+        assert!(report.is_fair);
+    }
+
+    #[test]
+    fn test_roll_sum_range() {
+        let mut ctx = DiceContext::with_seed(125);
+        let (min, max, mean) = ctx.roll_sum_range("3d6", 20_000).unwrap();
+        assert_eq!((min, max), (3, 18));
+        assert!((mean - 10.5).abs() < 0.1, "mean {}", mean);
+
+        let (min, max, mean) = ctx.roll_sum_range("7", 3).unwrap();
+        assert_eq!((min, max, mean), (7, 7, 7.0));
+
+        assert_eq!(ctx.roll_sum_range("3d6", 0), Err(DiceError::InvalidCount(0)));
+        assert!(ctx.roll_sum_range("1d6/0", 5).is_err());
+        assert!(Dice::roll_sum_range("bad", 5).is_err());
+        assert_eq!(
+            ctx.roll_sum_range("3d6", u32::MAX),
+            Err(DiceError::RollCountLimit {
+                requested: u32::MAX,
+                limit: crate::RollConfig::default().max_simulation_iterations
+            })
+        );
+    }
+
+    #[test]
+    fn test_roll_n_compare() {
+        let mut ctx = DiceContext::with_seed(134);
+        let result = ctx.roll_n_compare("1d6", 6000, 3).unwrap();
+        assert_eq!(result.above + result.equal + result.below, result.total);
+        assert_eq!(result.total, 6000);
+        assert!((result.above as i32 - 3000).abs() < 150, "{:?}", result);
+        assert!((result.equal as i32 - 1000).abs() < 100, "{:?}", result);
+        assert!((result.below as i32 - 2000).abs() < 130, "{:?}", result);
+        assert!((result.mean - 3.5).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_roll_n_compare_limits() {
+        let config = crate::RollConfig {
+            max_simulation_iterations: 100,
+            ..crate::RollConfig::default()
+        };
+        let mut ctx = DiceContext::with_seed(134).with_config(config);
+        assert!(ctx.roll_n_compare("1d20", 100, 10).is_ok());
+        assert_eq!(
+            ctx.roll_n_compare("1d20", 101, 10),
+            Err(DiceError::RollCountLimit {
+                requested: 101,
+                limit: 100
+            })
+        );
+        assert_eq!(ctx.roll_n_compare("1d20", 0, 10), Err(DiceError::InvalidCount(0)));
+        let always = ctx.roll_n_compare("5", 10, 5).unwrap();
+        assert_eq!((always.above, always.equal, always.below, always.mean), (0, 10, 0, 5.0));
+    }
+
+    #[test]
+    fn test_roll_histogram() {
+        let mut ctx = DiceContext::with_seed(197);
+        let counts = ctx.roll_histogram(6, 6000).unwrap();
+        assert_eq!(counts.len(), 6);
+        assert_eq!(counts.iter().sum::<u32>(), 6000);
+        assert!(counts.iter().all(|count| (850..1150).contains(count)), "{:?}", counts);
+        assert_eq!(ctx.roll_histogram(1, 10).unwrap(), vec![10]);
+        assert_eq!(ctx.roll_histogram(4, 0).unwrap(), vec![0; 4]);
+        assert_eq!(ctx.roll_histogram(0, 10), Err(DiceError::InvalidSides(0)));
+        assert_eq!(ctx.roll_histogram(i32::MAX, 1), Err(DiceError::InvalidSides(i32::MAX)));
+        assert_eq!(Dice::roll_histogram(20, 100).unwrap().len(), 20);
+    }
+}
@@ -0,0 +1,116 @@
+//! Stress dice
+//!
+//! A pool where the best die decides the outcome and the last few dice are
+//! stress dice: any of them showing 1-3 brings a consequence, as in Blades
+//! in the Dark.
+
+use crate::context::DiceContext;
+use crate::{Dice, DiceError, DiceResult};
+
+/// Highest face on a stress die that triggers a consequence
+const CONSEQUENCE_MAX: i32 = 3;
+
+/// Outcome of a stress pool
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StressResult {
+    /// Every die in the pool, stress dice last
+    pub values: Vec<i32>,
+    /// Best die in the whole pool
+    pub highest: i32,
+    /// The stress dice, also the tail of `values`
+    pub stress_values: Vec<i32>,
+    /// At least one stress die showed 1-3
+    pub consequence_triggered: bool,
+}
+
+impl StressResult {
+    /// Dice that aren't stress dice
+    pub fn regular_values(&self) -> &[i32] {
+        &self.values[..self.values.len() - self.stress_values.len()]
+    }
+}
+
+impl DiceContext {
+    /// Roll a pool whose last `stress_dice` dice are stress dice
+    ///
+    /// # Arguments
+    ///
+    /// * `pool_size` - Number of dice, stress dice included
+    /// * `stress_dice` - How many of the dice are stress dice
+    /// * `sides` - Sides per die
+    pub fn roll_stress(
+        &mut self,
+        pool_size: u32,
+        stress_dice: u32,
+        sides: u32,
+    ) -> DiceResult<StressResult> {
+        if stress_dice > pool_size {
+            return Err(DiceError::InvalidCount(stress_dice as i32));
+        }
+        let values = self.roll_pool(pool_size, sides)?;
+        let stress_values = values[(pool_size - stress_dice) as usize..].to_vec();
+
+        Ok(StressResult {
+            // roll_pool rejects empty pools
+            highest: values.iter().copied().max().unwrap_or(0),
+            consequence_triggered: stress_values.iter().any(|&value| value <= CONSEQUENCE_MAX),
+            stress_values,
+            values,
+        })
+    }
+}
+
+impl Dice {
+    /// Roll a pool whose last `stress_dice` dice are stress dice
+    ///
+    /// # Arguments
+    ///
+    /// * `pool_size` - Number of dice, stress dice included
+    /// * `stress_dice` - How many of the dice are stress dice
+    /// * `sides` - Sides per die
+    pub fn roll_stress(pool_size: u32, stress_dice: u32, sides: u32) -> DiceResult<StressResult> {
+        DiceContext::new().roll_stress(pool_size, stress_dice, sides)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_standard_roll() {
+        let mut ctx = DiceContext::with_seed(150);
+        let mut triggered = 0;
+        for _ in 0..1000 {
+            let result = ctx.roll_stress(6, 1, 6).unwrap();
+            assert_eq!(result.values.len(), 6);
+            assert_eq!(result.highest, *result.values.iter().max().unwrap());
+            assert_eq!(result.stress_values, vec![result.values[5]]);
+            assert_eq!(result.regular_values(), &result.values[..5]);
+            assert_eq!(result.consequence_triggered, result.values[5] <= 3);
+            triggered += result.consequence_triggered as u32;
+        }
+        // One stress die triggers half the time
+        assert!((400..600).contains(&triggered), "{}", triggered);
+    }
+
+    #[test]
+    fn test_stress_counts() {
+        let mut ctx = DiceContext::with_seed(150);
+        let calm = ctx.roll_stress(4, 0, 6).unwrap();
+        assert!(calm.stress_values.is_empty());
+        assert!(!calm.consequence_triggered);
+        assert_eq!(calm.regular_values().len(), 4);
+
+        // d3 stress dice always trigger
+        let all = ctx.roll_stress(3, 3, 3).unwrap();
+        assert!(all.regular_values().is_empty());
+        assert!(all.consequence_triggered);
+
+        assert_eq!(ctx.roll_stress(2, 3, 6), Err(DiceError::InvalidCount(3)));
+        assert_eq!(ctx.roll_stress(0, 0, 6), Err(DiceError::InvalidCount(0)));
+        assert_eq!(ctx.roll_stress(2, 1, 0), Err(DiceError::InvalidSides(0)));
+        assert!(Dice::roll_stress(6, 1, 6).is_ok());
+    }
+}
@@ -0,0 +1,319 @@
+//! Success counting
+//!
+//! Count the dice of a pool that beat a target, as in Shadowrun or World of
+//! Darkness, and spot glitches: more than half the pool showing 1. World of
+//! Darkness pools can also botch, with each die at or below the botch
+//! value cancelling a success.
+
+use crate::context::DiceContext;
+use crate::expression::DiceExpression;
+use crate::{Dice, DiceError, DiceResult};
+
+/// How a die is compared with the success target
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Comparison {
+    GreaterThan,
+    GreaterOrEqual,
+    Equal,
+    LessOrEqual,
+    LessThan,
+}
+
+impl Comparison {
+    /// Whether `value` compared with `target` is a success
+    pub fn matches(&self, value: i32, target: i32) -> bool {
+        match self {
+            Comparison::GreaterThan => value > target,
+            Comparison::GreaterOrEqual => value >= target,
+            Comparison::Equal => value == target,
+            Comparison::LessOrEqual => value <= target,
+            Comparison::LessThan => value < target,
+        }
+    }
+}
+
+/// Outcome of a success-counting pool
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SuccessCount {
+    pub total_dice: u32,
+    pub successes: u32,
+    /// Dice showing 1
+    pub ones: u32,
+    /// More than half the dice showed 1
+    pub is_glitch: bool,
+    /// A glitch with no successes
+    pub is_critical_glitch: bool,
+}
+
+impl SuccessCount {
+    fn count(values: &[i32], matches: impl Fn(i32) -> bool) -> SuccessCount {
+        let total_dice = values.len() as u32;
+        let successes = values.iter().filter(|&&value| matches(value)).count() as u32;
+        let ones = values.iter().filter(|&&value| value == 1).count() as u32;
+        let is_glitch = ones > total_dice / 2;
+        SuccessCount {
+            total_dice,
+            successes,
+            ones,
+            is_glitch,
+            is_critical_glitch: is_glitch && successes == 0,
+        }
+    }
+}
+
+/// Net successes at which a World of Darkness roll becomes critical
+pub const EXCEPTIONAL_SUCCESSES: u32 = 5;
+
+/// Grade of a World of Darkness roll
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum WoDOutcome {
+    /// At least [`EXCEPTIONAL_SUCCESSES`] net successes
+    CriticalSuccess(u32),
+    /// Fewer net successes, but at least one
+    Success(u32),
+    /// Botches cancelled every success exactly, or nothing was rolled either way
+    Failure,
+    /// More botches than successes
+    Botch,
+}
+
+/// Outcome of a success pool with botches
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SuccessWithBotch {
+    pub dice_values: Vec<i32>,
+    /// Dice showing at least the success target
+    pub successes: u32,
+    /// Dice showing at most the botch value
+    pub botches: u32,
+    /// `successes - botches`
+    pub net_successes: i32,
+    pub outcome: WoDOutcome,
+}
+
+impl SuccessWithBotch {
+    fn grade(dice_values: Vec<i32>, success_target: u32, botch_value: u32) -> SuccessWithBotch {
+        let count = |matches: &dyn Fn(i32) -> bool| {
+            dice_values.iter().filter(|&&value| matches(value)).count() as u32
+        };
+        let successes = count(&|value| value >= success_target as i32);
+        let botches = count(&|value| value <= botch_value as i32);
+        let net_successes = successes as i32 - botches as i32;
+        let outcome = match net_successes {
+            net if net < 0 => WoDOutcome::Botch,
+            0 => WoDOutcome::Failure,
+            net if net as u32 >= EXCEPTIONAL_SUCCESSES => WoDOutcome::CriticalSuccess(net as u32),
+            net => WoDOutcome::Success(net as u32),
+        };
+        SuccessWithBotch {
+            dice_values,
+            successes,
+            botches,
+            net_successes,
+            outcome,
+        }
+    }
+}
+
+impl DiceContext {
+    /// Roll a pool and count the dice that compare successfully with `target`
+    ///
+    /// # Arguments
+    ///
+    /// * `count` - Number of dice
+    /// * `sides` - Sides per die
+    /// * `target` - Value each die is compared with
+    /// * `comparison` - Comparison a die must satisfy to succeed
+    pub fn roll_count_successes(
+        &mut self,
+        count: u32,
+        sides: u32,
+        target: u32,
+        comparison: Comparison,
+    ) -> DiceResult<SuccessCount> {
+        let values = self.roll_pool(count, sides)?;
+        let target = target as i32;
+        Ok(SuccessCount::count(&values, |value| comparison.matches(value, target)))
+    }
+
+    /// Roll success notation like "8d6>4" and count its kept dice
+    ///
+    /// `>` in notation succeeds on at least the target, so "8d6>4" counts
+    /// like [`roll_count_successes`](Self::roll_count_successes) with
+    /// [`Comparison::GreaterOrEqual`]. Notation that doesn't count
+    /// successes fails with [`DiceError::InvalidNotation`].
+    ///
+    /// # Arguments
+    ///
+    /// * `notation` - Success pool notation like "8d6>4" or "10d10>8"
+    pub fn roll_success_notation(&mut self, notation: &str) -> DiceResult<SuccessCount> {
+        let expr = DiceExpression::parse(notation)?;
+        let DiceExpression::CountSuccesses(_, target) = expr else {
+            return Err(DiceError::InvalidNotation(notation.to_string()));
+        };
+        let config = *self.config();
+        let result = expr.roll_with_config(self.rng(), &config)?;
+        let target = target as i32;
+        Ok(SuccessCount::count(&result.kept(), |value| value >= target))
+    }
+
+    /// Roll a World of Darkness pool where botches cancel successes
+    ///
+    /// Notation writes the same roll as `5d10>7b1`.
+    ///
+    /// # Arguments
+    ///
+    /// * `count` - Number of dice
+    /// * `sides` - Sides per die
+    /// * `success_target` - Lowest value counted as a success
+    /// * `botch_value` - Highest value counted as a botch; 0 never botches
+    pub fn roll_success_with_botch(
+        &mut self,
+        count: u32,
+        sides: u32,
+        success_target: u32,
+        botch_value: u32,
+    ) -> DiceResult<SuccessWithBotch> {
+        let values = self.roll_pool(count, sides)?;
+        Ok(SuccessWithBotch::grade(values, success_target, botch_value))
+    }
+}
+
+impl Dice {
+    /// Roll a pool and count the dice that compare successfully with `target`
+    ///
+    /// # Arguments
+    ///
+    /// * `count` - Number of dice
+    /// * `sides` - Sides per die
+    /// * `target` - Value each die is compared with
+    /// * `comparison` - Comparison a die must satisfy to succeed
+    pub fn roll_count_successes(
+        count: u32,
+        sides: u32,
+        target: u32,
+        comparison: Comparison,
+    ) -> DiceResult<SuccessCount> {
+        DiceContext::new().roll_count_successes(count, sides, target, comparison)
+    }
+
+    /// Roll success notation like "8d6>4" and count its kept dice
+    ///
+    /// # Arguments
+    ///
+    /// * `notation` - Success pool notation like "8d6>4" or "10d10>8"
+    pub fn roll_success_notation(notation: &str) -> DiceResult<SuccessCount> {
+        DiceContext::new().roll_success_notation(notation)
+    }
+
+    /// Roll a World of Darkness pool where botches cancel successes
+    ///
+    /// # Arguments
+    ///
+    /// * `count` - Number of dice
+    /// * `sides` - Sides per die
+    /// * `success_target` - Lowest value counted as a success
+    /// * `botch_value` - Highest value counted as a botch; 0 never botches
+    pub fn roll_success_with_botch(
+        count: u32,
+        sides: u32,
+        success_target: u32,
+        botch_value: u32,
+    ) -> DiceResult<SuccessWithBotch> {
+        DiceContext::new().roll_success_with_botch(count, sides, success_target, botch_value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_comparisons() {
+        let mut ctx = DiceContext::with_seed(159);
+        let always = |ctx: &mut DiceContext, target, comparison| {
+            ctx.roll_count_successes(6, 6, target, comparison).unwrap().successes
+        };
+        assert_eq!(always(&mut ctx, 1, Comparison::GreaterOrEqual), 6);
+        assert_eq!(always(&mut ctx, 6, Comparison::GreaterThan), 0);
+        assert_eq!(always(&mut ctx, 6, Comparison::LessOrEqual), 6);
+        assert_eq!(always(&mut ctx, 1, Comparison::LessThan), 0);
+        assert_eq!(always(&mut ctx, 7, Comparison::Equal), 0);
+        assert_eq!(
+            ctx.roll_count_successes(0, 6, 4, Comparison::Equal),
+            Err(DiceError::InvalidCount(0))
+        );
+    }
+
+    #[test]
+    fn test_counts_match_values() {
+        let values = [1, 1, 5, 6, 3];
+        let count = SuccessCount::count(&values, |value| Comparison::GreaterThan.matches(value, 4));
+        assert_eq!((count.total_dice, count.successes, count.ones), (5, 2, 2));
+        assert!(!count.is_glitch);
+
+        let glitch = SuccessCount::count(&[1, 1, 1, 5], |value| value >= 5);
+        assert!(glitch.is_glitch && !glitch.is_critical_glitch);
+        let critical = SuccessCount::count(&[1, 1, 4], |value| value >= 5);
+        assert!(critical.is_glitch && critical.is_critical_glitch);
+        // Exactly half isn't a glitch
+        assert!(!SuccessCount::count(&[1, 1, 4, 4], |value| value >= 5).is_glitch);
+    }
+
+    #[test]
+    fn test_notation_matches_pool() {
+        let mut a = DiceContext::with_seed(8);
+        let mut b = DiceContext::with_seed(8);
+        for _ in 0..200 {
+            let from_notation = a.roll_success_notation("8d6>4").unwrap();
+            let from_pool = b
+                .roll_count_successes(8, 6, 4, Comparison::GreaterOrEqual)
+                .unwrap();
+            assert_eq!(from_notation, from_pool);
+        }
+        assert_eq!(
+            a.roll_success_notation("8d6"),
+            Err(DiceError::InvalidNotation("8d6".to_string()))
+        );
+        let exploded = a.roll_success_notation("6d6!>6>5").unwrap();
+        assert!(exploded.total_dice >= 6);
+    }
+
+    #[test]
+    fn test_botch_outcomes() {
+        let grade = |values: &[i32]| SuccessWithBotch::grade(values.to_vec(), 7, 1);
+        let critical = grade(&[7, 8, 9, 10, 10, 3]);
+        assert_eq!(critical.outcome, WoDOutcome::CriticalSuccess(5));
+        let success = grade(&[8, 1, 10, 4, 2]);
+        assert_eq!((success.successes, success.botches, success.net_successes), (2, 1, 1));
+        assert_eq!(success.outcome, WoDOutcome::Success(1));
+        assert_eq!(grade(&[9, 1, 5]).outcome, WoDOutcome::Failure);
+        assert_eq!(grade(&[2, 3, 6]).outcome, WoDOutcome::Failure);
+        let botch = grade(&[1, 1, 9, 4]);
+        assert_eq!((botch.net_successes, botch.outcome), (-1, WoDOutcome::Botch));
+    }
+
+    #[test]
+    fn test_roll_with_botch_matches_notation() {
+        let expr = DiceExpression::parse("5d10>7b1").unwrap();
+        assert_eq!(expr.to_notation(), "5d10>7b1");
+        assert_eq!((expr.min_result(), expr.max_result()), (Ok(-5), Ok(5)));
+        let mut a = DiceContext::with_seed(161);
+        let mut b = DiceContext::with_seed(161);
+        let mut seen = std::collections::HashSet::new();
+        for _ in 0..2000 {
+            let result = a.roll_success_with_botch(5, 10, 7, 1).unwrap();
+            assert_eq!(result.dice_values.len(), 5);
+            assert_eq!(Ok(result.net_successes), expr.evaluate(b.rng()));
+            seen.insert(std::mem::discriminant(&result.outcome));
+        }
+        assert_eq!(seen.len(), 4);
+        assert_eq!(
+            a.roll_success_with_botch(0, 10, 7, 1),
+            Err(DiceError::InvalidCount(0))
+        );
+    }
+}
@@ -0,0 +1,615 @@
+//! Result tables
+//!
+//! Look up a rolled total in a table of ranges, as in wound, loot or random
+//! encounter tables:
+//!
+//! ```text
+//! 1-5 Scratch, 6-10 Light, 11-15 Serious, 16-20 Critical, 21+ Mortal
+//! ```
+//!
+//! d66 tables are keyed by two d6 read as tens and units (11-16, 21-26, ...,
+//! 61-66) instead of a sum. Modifier tables map every face of a die to a
+//! bonus added to the roll.
+
+use crate::context::DiceContext;
+use crate::{Dice, DiceError, DiceResult};
+use std::collections::BTreeMap;
+use std::ops::{Bound, RangeBounds};
+
+/// Ranges of results mapped to values; the first matching entry wins
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WoundTable<T> {
+    /// (lowest, highest, value), both ends inclusive
+    entries: Vec<(i32, i32, T)>,
+}
+
+impl<T: Clone> WoundTable<T> {
+    pub fn new() -> Self {
+        WoundTable {
+            entries: Vec::new(),
+        }
+    }
+
+    /// Map `range` (`1..=5`, `21..`) to `value`
+    pub fn entry(mut self, range: impl RangeBounds<i32>, value: T) -> Self {
+        let low = match range.start_bound() {
+            Bound::Included(&n) => Some(n),
+            Bound::Excluded(&n) => n.checked_add(1),
+            Bound::Unbounded => Some(i32::MIN),
+        };
+        let high = match range.end_bound() {
+            Bound::Included(&n) => Some(n),
+            Bound::Excluded(&n) => n.checked_sub(1),
+            Bound::Unbounded => Some(i32::MAX),
+        };
+        // A range holding no integers can never match
+        if let (Some(low), Some(high)) = (low, high) {
+            self.entries.push((low, high, value));
+        }
+        self
+    }
+
+    /// Value for `roll`, or None if no entry covers it
+    pub fn lookup(&self, roll: i32) -> Option<T> {
+        self.entries
+            .iter()
+            .find(|(low, high, _)| (*low..=*high).contains(&roll))
+            .map(|(_, _, value)| value.clone())
+    }
+
+    /// Lowest and highest results any entry covers
+    fn span(&self) -> Option<(i32, i32)> {
+        let low = self.entries.iter().map(|(low, _, _)| *low).min()?;
+        let high = self.entries.iter().map(|(_, high, _)| *high).max()?;
+        Some((low, high))
+    }
+}
+
+impl<T: Clone> Default for WoundTable<T> {
+    fn default() -> Self {
+        WoundTable::new()
+    }
+}
+
+/// Modifier table being filled in; see [`ModifierTable::new`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModifierTableBuilder {
+    sides: u32,
+    table: WoundTable<i32>,
+}
+
+impl ModifierTableBuilder {
+    /// Add `modifier` to rolls in `range` (`1..=5`)
+    pub fn entry(mut self, range: impl RangeBounds<i32>, modifier: i32) -> Self {
+        self.table = self.table.entry(range, modifier);
+        self
+    }
+
+    /// Check every face from 1 to `sides` has exactly one entry
+    ///
+    /// A die with no sides fails with [`DiceError::InvalidSides`]. A face
+    /// with no entry, a face with two, or an entry past the die fails with
+    /// [`DiceError::InvalidRange`] carrying the offending face.
+    pub fn build(self) -> DiceResult<ModifierTable> {
+        let sides = i32::try_from(self.sides)
+            .ok()
+            .filter(|&sides| sides > 0)
+            .ok_or(DiceError::InvalidSides(i32::try_from(self.sides).unwrap_or(i32::MAX)))?;
+        let out_of_range = |actual: i32| DiceError::InvalidRange {
+            min: 1,
+            max: sides,
+            actual,
+        };
+
+        let mut entries = self.table.entries;
+        entries.sort_by_key(|(low, _, _)| *low);
+        let past_end = sides.saturating_add(1);
+        // First face still to cover, or `None` once the last face is
+        let mut next = Some(1);
+        for &(low, high, _) in &entries {
+            // A gap before this entry, or an overlap with the last one
+            match next {
+                Some(next) if low == next => {}
+                Some(next) => return Err(out_of_range(low.min(next))),
+                None => return Err(out_of_range(low.min(past_end))),
+            }
+            if high > sides {
+                return Err(out_of_range(low.max(past_end)));
+            }
+            next = (high < sides).then(|| high + 1);
+        }
+        if let Some(next) = next {
+            return Err(out_of_range(next));
+        }
+        Ok(ModifierTable {
+            sides: self.sides,
+            entries,
+        })
+    }
+}
+
+/// Modifier for every face of a die, built by [`ModifierTable::new`]
+///
+/// With the `serde` feature a table deserializes through
+/// [`build`](ModifierTableBuilder::build), so its entries must cover every
+/// face once.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "ModifierTableEntries")
+)]
+pub struct ModifierTable {
+    sides: u32,
+    /// (lowest, highest, modifier), ascending and covering every face once
+    entries: Vec<(i32, i32, i32)>,
+}
+
+/// Unchecked [`ModifierTable`] as read by serde
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct ModifierTableEntries {
+    sides: u32,
+    entries: Vec<(i32, i32, i32)>,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<ModifierTableEntries> for ModifierTable {
+    type Error = DiceError;
+
+    fn try_from(raw: ModifierTableEntries) -> DiceResult<ModifierTable> {
+        raw.entries
+            .into_iter()
+            .fold(ModifierTable::new(raw.sides), |builder, (low, high, modifier)| {
+                builder.entry(low..=high, modifier)
+            })
+            .build()
+    }
+}
+
+impl ModifierTable {
+    /// Start a table for a die with `sides` sides, adding entries with
+    /// [`entry`](ModifierTableBuilder::entry) and finishing with
+    /// [`build`](ModifierTableBuilder::build)
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(sides: u32) -> ModifierTableBuilder {
+        ModifierTableBuilder {
+            sides,
+            table: WoundTable::new(),
+        }
+    }
+
+    pub fn sides(&self) -> u32 {
+        self.sides
+    }
+
+    /// Modifier for a raw roll, or None if `roll` isn't a face of the die
+    pub fn modifier(&self, roll: i32) -> Option<i32> {
+        self.entries
+            .iter()
+            .find(|(low, high, _)| (*low..=*high).contains(&roll))
+            .map(|(_, _, modifier)| *modifier)
+    }
+
+    /// Mean modifier over a fair roll of the die
+    pub fn expected_modifier(&self) -> f64 {
+        let total: f64 = self
+            .entries
+            .iter()
+            .map(|&(low, high, modifier)| (high - low + 1) as f64 * modifier as f64)
+            .sum();
+        total / self.sides as f64
+    }
+}
+
+/// Values keyed by the `(tens, units)` dice of a d66 roll
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct D66Table<T> {
+    entries: BTreeMap<(i32, i32), T>,
+}
+
+impl<T: Clone> D66Table<T> {
+    pub fn new() -> Self {
+        D66Table {
+            entries: BTreeMap::new(),
+        }
+    }
+
+    /// Map the roll `tens`, `units` (`3, 4` for 34) to `value`
+    ///
+    /// A digit outside 1-6 can never be rolled, so its entry never matches.
+    pub fn entry(mut self, tens: i32, units: i32, value: T) -> Self {
+        self.entries.insert((tens, units), value);
+        self
+    }
+
+    /// Value for the roll `tens`, `units`, or None if there is no entry
+    pub fn lookup(&self, tens: i32, units: i32) -> Option<T> {
+        self.entries.get(&(tens, units)).cloned()
+    }
+}
+
+impl<T: Clone> Default for D66Table<T> {
+    fn default() -> Self {
+        D66Table::new()
+    }
+}
+
+impl DiceContext {
+    /// Roll two d6 as tens and units
+    ///
+    /// # Returns
+    ///
+    /// Tuple of (tens die, units die, combined), where combined is one of
+    /// 11-16, 21-26, ..., 61-66
+    pub fn roll_d66(&mut self) -> DiceResult<(i32, i32, i32)> {
+        let tens = self.roll(6)?;
+        let units = self.roll(6)?;
+        Ok((tens, units, tens * 10 + units))
+    }
+
+    /// Roll three d6 as hundreds, tens and units
+    ///
+    /// # Returns
+    ///
+    /// Tuple of (hundreds die, tens die, units die, combined), where
+    /// combined is one of 111-116, ..., 666
+    pub fn roll_d666(&mut self) -> DiceResult<(i32, i32, i32, i32)> {
+        let hundreds = self.roll(6)?;
+        let (tens, units, combined) = self.roll_d66()?;
+        Ok((hundreds, tens, units, hundreds * 100 + combined))
+    }
+
+    /// Roll a d66 and look it up in `table`
+    ///
+    /// A roll with no entry fails with [`DiceError::InvalidRange`] spanning
+    /// 11-66.
+    ///
+    /// # Arguments
+    ///
+    /// * `table` - Table to look the roll up in
+    pub fn roll_d66_and_lookup<T: Clone>(&mut self, table: &D66Table<T>) -> DiceResult<T> {
+        let (tens, units, combined) = self.roll_d66()?;
+        table.lookup(tens, units).ok_or(DiceError::InvalidRange {
+            min: 11,
+            max: 66,
+            actual: combined,
+        })
+    }
+
+    /// Roll a die and add the modifier its face maps to in `modifiers`
+    ///
+    /// A face the table has no entry for fails with
+    /// [`DiceError::InvalidRange`] carrying the face.
+    ///
+    /// # Arguments
+    ///
+    /// * `sides` - Number of sides on the die; must match the table
+    /// * `modifiers` - Modifier for each face
+    ///
+    /// # Returns
+    ///
+    /// Tuple of (raw roll, roll plus modifier)
+    pub fn roll_and_apply_modifier_table(
+        &mut self,
+        sides: i32,
+        modifiers: &ModifierTable,
+    ) -> DiceResult<(i32, i32)> {
+        if i32::try_from(modifiers.sides()) != Ok(sides) {
+            return Err(DiceError::InvalidSides(sides));
+        }
+        let raw = self.roll(sides)?;
+        let modifier = modifiers.modifier(raw).ok_or(DiceError::InvalidRange {
+            min: 1,
+            max: sides,
+            actual: raw,
+        })?;
+        Ok((raw, raw.checked_add(modifier).ok_or(DiceError::Overflow)?))
+    }
+
+    /// Roll notation and look the total up in `table`
+    ///
+    /// A total no entry covers fails with [`DiceError::InvalidRange`]
+    /// spanning the results the table does cover.
+    ///
+    /// # Arguments
+    ///
+    /// * `notation` - Dice notation like "2d10"
+    /// * `table` - Table to look the total up in
+    pub fn roll_and_lookup<T: Clone>(
+        &mut self,
+        notation: &str,
+        table: &WoundTable<T>,
+    ) -> DiceResult<T> {
+        let total = self.roll_notation(notation)?.total;
+        self.roll_wound_table(total, table)
+    }
+
+    /// Look an already rolled result up in `table`
+    ///
+    /// A roll no entry covers fails with [`DiceError::InvalidRange`]
+    /// spanning the results the table does cover.
+    ///
+    /// # Arguments
+    ///
+    /// * `roll` - Result to look up, e.g. a damage total
+    /// * `table` - Table to look the roll up in
+    pub fn roll_wound_table<T: Clone>(&self, roll: i32, table: &WoundTable<T>) -> DiceResult<T> {
+        table.lookup(roll).ok_or_else(|| {
+            // An empty table covers the empty range
+            let (min, max) = table.span().unwrap_or((i32::MAX, i32::MIN));
+            DiceError::InvalidRange {
+                min,
+                max,
+                actual: roll,
+            }
+        })
+    }
+}
+
+impl Dice {
+    /// Roll notation and look the total up in `table`
+    ///
+    /// # Arguments
+    ///
+    /// * `notation` - Dice notation like "2d10"
+    /// * `table` - Table to look the total up in
+    pub fn roll_and_lookup<T: Clone>(notation: &str, table: &WoundTable<T>) -> DiceResult<T> {
+        DiceContext::new().roll_and_lookup(notation, table)
+    }
+
+    /// Look an already rolled result up in `table`
+    ///
+    /// # Arguments
+    ///
+    /// * `roll` - Result to look up, e.g. a damage total
+    /// * `table` - Table to look the roll up in
+    pub fn roll_wound_table<T: Clone>(roll: i32, table: &WoundTable<T>) -> DiceResult<T> {
+        DiceContext::new().roll_wound_table(roll, table)
+    }
+
+    /// Roll a die and add the modifier its face maps to in `modifiers`
+    ///
+    /// # Arguments
+    ///
+    /// * `sides` - Number of sides on the die; must match the table
+    /// * `modifiers` - Modifier for each face
+    pub fn roll_and_apply_modifier_table(
+        sides: i32,
+        modifiers: &ModifierTable,
+    ) -> DiceResult<(i32, i32)> {
+        DiceContext::new().roll_and_apply_modifier_table(sides, modifiers)
+    }
+
+    /// Roll two d6 as tens and units
+    ///
+    /// # Returns
+    ///
+    /// Tuple of (tens die, units die, combined)
+    pub fn roll_d66() -> DiceResult<(i32, i32, i32)> {
+        DiceContext::new().roll_d66()
+    }
+
+    /// Roll three d6 as hundreds, tens and units
+    ///
+    /// # Returns
+    ///
+    /// Tuple of (hundreds die, tens die, units die, combined)
+    pub fn roll_d666() -> DiceResult<(i32, i32, i32, i32)> {
+        DiceContext::new().roll_d666()
+    }
+
+    /// Roll a d66 and look it up in `table`
+    ///
+    /// # Arguments
+    ///
+    /// * `table` - Table to look the roll up in
+    pub fn roll_d66_and_lookup<T: Clone>(table: &D66Table<T>) -> DiceResult<T> {
+        DiceContext::new().roll_d66_and_lookup(table)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum WoundSeverity {
+        Scratch,
+        Light,
+        Serious,
+        Critical,
+        Mortal,
+    }
+
+    fn wounds() -> WoundTable<WoundSeverity> {
+        WoundTable::new()
+            .entry(1..=5, WoundSeverity::Scratch)
+            .entry(6..=10, WoundSeverity::Light)
+            .entry(11..=15, WoundSeverity::Serious)
+            .entry(16..=20, WoundSeverity::Critical)
+            .entry(21.., WoundSeverity::Mortal)
+    }
+
+    #[test]
+    fn test_lookup() {
+        let table = wounds();
+        assert_eq!(table.lookup(1), Some(WoundSeverity::Scratch));
+        assert_eq!(table.lookup(10), Some(WoundSeverity::Light));
+        assert_eq!(table.lookup(11), Some(WoundSeverity::Serious));
+        assert_eq!(table.lookup(20), Some(WoundSeverity::Critical));
+        assert_eq!(table.lookup(500), Some(WoundSeverity::Mortal));
+        assert_eq!(table.lookup(0), None);
+
+        let overlapping = WoundTable::new().entry(1..10, "first").entry(5..=12, "second");
+        assert_eq!(overlapping.lookup(9), Some("first"));
+        assert_eq!(overlapping.lookup(10), Some("second"));
+    }
+
+    #[test]
+    fn test_roll_and_lookup() {
+        let mut ctx = DiceContext::with_seed(135);
+        for _ in 0..100 {
+            let severity = ctx.roll_and_lookup("1d10", &wounds()).unwrap();
+            assert!(matches!(severity, WoundSeverity::Scratch | WoundSeverity::Light));
+        }
+        assert_eq!(ctx.roll_and_lookup("25", &wounds()), Ok(WoundSeverity::Mortal));
+    }
+
+    #[test]
+    fn test_roll_d66() {
+        let mut ctx = DiceContext::with_seed(146);
+        let mut seen = std::collections::BTreeSet::new();
+        for _ in 0..1000 {
+            let (tens, units, combined) = ctx.roll_d66().unwrap();
+            assert!((1..=6).contains(&tens) && (1..=6).contains(&units));
+            assert_eq!(combined, tens * 10 + units);
+            seen.insert(combined);
+
+            let (hundreds, tens, units, combined) = ctx.roll_d666().unwrap();
+            assert!([hundreds, tens, units].iter().all(|d| (1..=6).contains(d)));
+            assert_eq!(combined, hundreds * 100 + tens * 10 + units);
+        }
+        let expected: Vec<i32> = (1..=6).flat_map(|t| (1..=6).map(move |u| t * 10 + u)).collect();
+        assert_eq!(seen.into_iter().collect::<Vec<_>>(), expected);
+        assert!(Dice::roll_d66().is_ok());
+    }
+
+    #[test]
+    fn test_d66_table() {
+        let mut full = D66Table::new();
+        for tens in 1..=6 {
+            for units in 1..=6 {
+                full = full.entry(tens, units, tens * 10 + units);
+            }
+        }
+        let mut ctx = DiceContext::with_seed(146);
+        for _ in 0..100 {
+            let value = ctx.roll_d66_and_lookup(&full).unwrap();
+            assert!((11..=66).contains(&value) && (1..=6).contains(&(value % 10)));
+        }
+
+        let sparse = D66Table::new().entry(1, 1, "snake eyes").entry(7, 1, "never");
+        assert_eq!(sparse.lookup(1, 1), Some("snake eyes"));
+        assert_eq!(sparse.lookup(1, 2), None);
+        let empty: D66Table<&str> = D66Table::default();
+        assert!(matches!(
+            Dice::roll_d66_and_lookup(&empty),
+            Err(DiceError::InvalidRange { min: 11, max: 66, .. })
+        ));
+    }
+
+    fn d20_modifiers() -> ModifierTableBuilder {
+        ModifierTable::new(20)
+            .entry(1..=5, -2)
+            .entry(6..=10, 0)
+            .entry(11..=15, 1)
+            .entry(16..=20, 3)
+    }
+
+    #[test]
+    fn test_modifier_table() {
+        let table = d20_modifiers().build().unwrap();
+        assert_eq!(table.modifier(1), Some(-2));
+        assert_eq!(table.modifier(10), Some(0));
+        assert_eq!(table.modifier(20), Some(3));
+        assert_eq!(table.modifier(21), None);
+        // (5 * -2 + 5 * 0 + 5 * 1 + 5 * 3) / 20
+        assert!((table.expected_modifier() - 0.5).abs() < 1e-12);
+
+        let mut ctx = DiceContext::with_seed(155);
+        for _ in 0..200 {
+            let (raw, value) = ctx.roll_and_apply_modifier_table(20, &table).unwrap();
+            assert!((1..=20).contains(&raw));
+            assert_eq!(value, raw + table.modifier(raw).unwrap());
+        }
+        assert_eq!(
+            ctx.roll_and_apply_modifier_table(12, &table),
+            Err(DiceError::InvalidSides(12))
+        );
+        assert!(Dice::roll_and_apply_modifier_table(20, &table).is_ok());
+    }
+
+    #[test]
+    fn test_modifier_table_coverage() {
+        let gap = |actual| {
+            Err(DiceError::InvalidRange {
+                min: 1,
+                max: 20,
+                actual,
+            })
+        };
+        let missing_middle = ModifierTable::new(20).entry(1..=5, -2).entry(7..=20, 1);
+        assert_eq!(missing_middle.build(), gap(6));
+        assert_eq!(ModifierTable::new(20).entry(2..=20, 0).build(), gap(1));
+        assert_eq!(ModifierTable::new(20).entry(1..=19, 0).build(), gap(20));
+        assert_eq!(d20_modifiers().entry(15..=15, 9).build(), gap(15));
+        assert_eq!(d20_modifiers().entry(21.., 9).build(), gap(21));
+        assert_eq!(ModifierTable::new(20).entry(1.., 0).build(), gap(21));
+        assert_eq!(ModifierTable::new(0).build(), Err(DiceError::InvalidSides(0)));
+        let too_big = ModifierTable::new(u32::MAX).entry(1.., 0).build();
+        assert_eq!(too_big, Err(DiceError::InvalidSides(i32::MAX)));
+
+        // An open-ended entry may run up to the largest die
+        let widest = ModifierTable::new(i32::MAX as u32).entry(1.., 2).build().unwrap();
+        assert_eq!(widest.modifier(i32::MAX), Some(2));
+        let overlap = ModifierTable::new(i32::MAX as u32).entry(1.., 2).entry(5..=9, 0);
+        assert!(matches!(overlap.build(), Err(DiceError::InvalidRange { actual: 5, .. })));
+
+        // Entries may be given in any order
+        let shuffled = ModifierTable::new(4).entry(3..=4, 1).entry(1..3, -1).build().unwrap();
+        assert_eq!(shuffled.expected_modifier(), 0.0);
+    }
+
+    #[test]
+    fn test_lookup_gaps() {
+        let gappy = WoundTable::new().entry(1..=3, 'a').entry(7..=9, 'b');
+        let mut ctx = DiceContext::with_seed(135);
+        assert_eq!(
+            ctx.roll_and_lookup("5", &gappy),
+            Err(DiceError::InvalidRange {
+                min: 1,
+                max: 9,
+                actual: 5
+            })
+        );
+        let empty: WoundTable<char> = WoundTable::default();
+        assert!(matches!(
+            Dice::roll_and_lookup("1d6", &empty),
+            Err(DiceError::InvalidRange { .. })
+        ));
+        assert!(Dice::roll_and_lookup("bad", &gappy).is_err());
+    }
+
+    #[test]
+    fn test_roll_wound_table() {
+        let table = wounds();
+        let ctx = DiceContext::with_seed(135);
+        assert_eq!(ctx.roll_wound_table(3, &table), Ok(WoundSeverity::Scratch));
+        assert_eq!(ctx.roll_wound_table(16, &table), Ok(WoundSeverity::Critical));
+        assert_eq!(Dice::roll_wound_table(250, &table), Ok(WoundSeverity::Mortal));
+        assert_eq!(
+            Dice::roll_wound_table(0, &table),
+            Err(DiceError::InvalidRange {
+                min: 1,
+                max: i32::MAX,
+                actual: 0
+            })
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_modifier_table_serde() {
+        let table = d20_modifiers().build().unwrap();
+        let json = serde_json::to_string(&table).unwrap();
+        assert_eq!(serde_json::from_str::<ModifierTable>(&json).unwrap(), table);
+
+        let gappy = r#"{"sides":20,"entries":[[1,5,-2],[7,20,1]]}"#;
+        let error = serde_json::from_str::<ModifierTable>(gappy).unwrap_err();
+        assert!(error.to_string().contains("result 6 is outside"), "{}", error);
+        assert!(serde_json::from_str::<ModifierTable>(r#"{"sides":0,"entries":[]}"#).is_err());
+    }
+}
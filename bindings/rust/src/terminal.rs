@@ -0,0 +1,218 @@
+//! ANSI colored roll output (`terminal` feature)
+//!
+//! Dice showing their maximum face are printed as criticals, dice showing 1
+//! as fumbles. Color is only emitted when stdout is a terminal and the
+//! `NO_COLOR` environment variable is unset or empty.
+
+use crate::eval::{DieRoll, RollResult};
+use std::fmt;
+
+/// Terminal color used by [`ColorConfig`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Color {
+    /// The terminal's own foreground color; no escape code is written
+    Default,
+    Black,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+}
+
+impl Color {
+    /// SGR foreground code, or None for [`Color::Default`]
+    fn code(self) -> Option<u8> {
+        match self {
+            Color::Default => None,
+            Color::Black => Some(30),
+            Color::Red => Some(31),
+            Color::Green => Some(32),
+            Color::Yellow => Some(33),
+            Color::Blue => Some(34),
+            Color::Magenta => Some(35),
+            Color::Cyan => Some(36),
+            Color::White => Some(37),
+        }
+    }
+}
+
+/// Colors used for critical, fumbled and ordinary results
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ColorConfig {
+    pub critical_color: Color,
+    pub fumble_color: Color,
+    pub normal_color: Color,
+}
+
+impl Default for ColorConfig {
+    fn default() -> Self {
+        ColorConfig {
+            critical_color: Color::Green,
+            fumble_color: Color::Red,
+            normal_color: Color::Default,
+        }
+    }
+}
+
+/// Whether colored output should be written to stdout
+pub fn color_enabled() -> bool {
+    let no_color = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+    !no_color && atty::is(atty::Stream::Stdout)
+}
+
+/// [`RollResult`] formatter returned by [`RollResult::display_colored`]
+///
+/// Displays as `total [die, die, ...]`. Each die is colored on its own; the
+/// total is colored when every kept die is a critical or every kept die is a
+/// fumble.
+#[derive(Debug, Clone, Copy)]
+pub struct ColoredRoll<'a> {
+    result: &'a RollResult,
+    config: ColorConfig,
+    enabled: bool,
+}
+
+impl RollResult {
+    /// Format the result with default colors, if stdout supports them
+    pub fn display_colored(&self) -> ColoredRoll<'_> {
+        self.display_colored_with(ColorConfig::default())
+    }
+
+    /// Format the result with custom colors, if stdout supports them
+    pub fn display_colored_with(&self, config: ColorConfig) -> ColoredRoll<'_> {
+        ColoredRoll {
+            result: self,
+            config,
+            enabled: color_enabled(),
+        }
+    }
+}
+
+impl ColoredRoll<'_> {
+    /// Override terminal detection
+    pub fn with_color(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+
+    fn color_for(&self, dice: &[&DieRoll]) -> Color {
+        if dice.is_empty() {
+            self.config.normal_color
+        } else if dice.iter().all(|die| die.value == die.sides as i32) {
+            self.config.critical_color
+        } else if dice.iter().all(|die| die.value == 1) {
+            self.config.fumble_color
+        } else {
+            self.config.normal_color
+        }
+    }
+
+    fn paint(&self, f: &mut fmt::Formatter<'_>, color: Color, text: &dyn fmt::Display) -> fmt::Result {
+        match color.code().filter(|_| self.enabled) {
+            Some(code) => write!(f, "\x1b[{}m{}\x1b[0m", code, text),
+            None => write!(f, "{}", text),
+        }
+    }
+}
+
+impl fmt::Display for ColoredRoll<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kept: Vec<&DieRoll> = self.result.dice.iter().filter(|die| die.kept).collect();
+        self.paint(f, self.color_for(&kept), &self.result.total)?;
+
+        if self.result.dice.is_empty() {
+            return Ok(());
+        }
+        write!(f, " [")?;
+        for (i, die) in self.result.dice.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            let color = if die.kept {
+                self.color_for(&[die])
+            } else {
+                self.config.normal_color
+            };
+            self.paint(f, color, &die.value)?;
+        }
+        write!(f, "]")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn die(sides: u32, value: i32) -> DieRoll {
+        DieRoll {
+            sides,
+            value,
+            kept: true,
+        }
+    }
+
+    #[test]
+    fn test_colored_in_tty_mode() {
+        let result = RollResult {
+            total: 7,
+            dice: vec![die(6, 6), die(6, 1)],
+        };
+        let mut out = Vec::new();
+        write!(out, "{}", result.display_colored().with_color(true)).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "7 [\x1b[32m6\x1b[0m, \x1b[31m1\x1b[0m]"
+        );
+
+        let critical = RollResult {
+            total: 20,
+            dice: vec![die(20, 20)],
+        };
+        let mut out = Vec::new();
+        write!(out, "{}", critical.display_colored().with_color(true)).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\x1b[32m20\x1b[0m [\x1b[32m20\x1b[0m]"
+        );
+    }
+
+    #[test]
+    fn test_plain_when_disabled() {
+        let result = RollResult {
+            total: 7,
+            dice: vec![die(6, 6), die(6, 1)],
+        };
+        let mut out = Vec::new();
+        write!(out, "{}", result.display_colored().with_color(false)).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert_eq!(text, "7 [6, 1]");
+        assert!(!text.contains('\x1b'));
+    }
+
+    #[test]
+    fn test_custom_colors() {
+        let config = ColorConfig {
+            critical_color: Color::Cyan,
+            fumble_color: Color::Magenta,
+            normal_color: Color::Yellow,
+        };
+        let mut dropped = die(6, 1);
+        dropped.kept = false;
+        let result = RollResult {
+            total: 3,
+            dice: vec![die(6, 1), die(6, 3), dropped],
+        };
+        let mut out = Vec::new();
+        write!(out, "{}", result.display_colored_with(config).with_color(true)).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\x1b[33m3\x1b[0m [\x1b[35m1\x1b[0m, \x1b[33m3\x1b[0m, \x1b[33m1\x1b[0m]"
+        );
+    }
+}
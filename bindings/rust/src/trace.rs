@@ -0,0 +1,231 @@
+//! Evaluation traces
+//!
+//! [`DiceExpression::evaluate_with_trace`] rolls an expression and records
+//! what every node saw and produced, so a surprising total can be explained
+//! step by step.
+
+use crate::config::RollConfig;
+use crate::expression::DiceExpression;
+use crate::DiceResult;
+use rand::Rng;
+
+/// What one node of an expression did during a roll
+///
+/// The tree mirrors the expression. `inputs` are the values the node
+/// combined: die values for a dice pool (only the kept ones for a keep or
+/// drop), the counted dice for a success count, and the outputs of the
+/// children for everything else.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EvalTrace {
+    /// Name of the expression variant, like "Add" or "KeepHighest"
+    pub node_type: String,
+    pub inputs: Vec<i32>,
+    pub output: i32,
+    /// Traces of the child nodes that were evaluated, in evaluation order
+    pub children: Vec<EvalTrace>,
+}
+
+/// One node of an [`EvalTrace`] without its children
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TraceEntry {
+    /// Distance from the root, which is 0
+    pub depth: usize,
+    pub node_type: String,
+    pub inputs: Vec<i32>,
+    pub output: i32,
+}
+
+impl EvalTrace {
+    /// Every node of the trace, depth-first with parents before children
+    pub fn flatten(&self) -> Vec<TraceEntry> {
+        let mut entries = Vec::new();
+        let mut pending = vec![(0, self)];
+        while let Some((depth, node)) = pending.pop() {
+            entries.push(TraceEntry {
+                depth,
+                node_type: node.node_type.clone(),
+                inputs: node.inputs.clone(),
+                output: node.output,
+            });
+            pending.extend(node.children.iter().rev().map(|child| (depth + 1, child)));
+        }
+        entries
+    }
+}
+
+impl DiceExpression {
+    /// Roll the expression, returning the total and a trace of every node
+    ///
+    /// Rolls exactly like [`evaluate`](Self::evaluate) for the same
+    /// generator. Branches of a conditional that weren't taken don't appear.
+    pub fn evaluate_with_trace<R: Rng + ?Sized>(
+        &self,
+        rng: &mut R,
+    ) -> DiceResult<(i32, EvalTrace)> {
+        let (result, trace) = self.roll_traced(rng, &RollConfig::default())?;
+        Ok((result.total, trace))
+    }
+}
+
+/// Trace under construction, one open frame per node being evaluated
+#[derive(Default)]
+pub(crate) struct Tracer {
+    open: Vec<EvalTrace>,
+}
+
+impl Tracer {
+    /// Start tracing a node; its children are traced until the matching
+    /// [`exit`](Self::exit)
+    pub(crate) fn enter(&mut self) {
+        self.open.push(EvalTrace::default());
+    }
+
+    /// Finish the innermost node, using the outputs of its children as
+    /// inputs unless `inputs` is given
+    pub(crate) fn exit(&mut self, expr: &DiceExpression, output: i32, inputs: Option<Vec<i32>>) {
+        let mut node = self.open.pop().expect("exit without enter");
+        node.node_type = node_type(expr).to_string();
+        node.inputs = match (inputs, expr) {
+            (Some(inputs), _) => inputs,
+            (
+                None,
+                DiceExpression::CountSuccesses(..) | DiceExpression::CountSuccessesWithBotch(..),
+            ) => node.children[0].inputs.clone(),
+            (None, _) => node.children.iter().map(|child| child.output).collect(),
+        };
+        node.output = output;
+        match self.open.last_mut() {
+            Some(parent) => parent.children.push(node),
+            None => self.open.push(node),
+        }
+    }
+
+    /// The finished root node
+    pub(crate) fn finish(mut self) -> EvalTrace {
+        self.open.pop().unwrap_or_default()
+    }
+}
+
+fn node_type(expr: &DiceExpression) -> &'static str {
+    use DiceExpression::*;
+
+    match expr {
+        Constant(_) => "Constant",
+        Variable(_) => "Variable",
+        Dice(..) => "Dice",
+        DynamicDice(..) => "DynamicDice",
+        Call(..) => "Call",
+        Explode(..) => "Explode",
+        ExplodeOn(..) => "ExplodeOn",
+        KeepHighest(..) => "KeepHighest",
+        KeepLowest(..) => "KeepLowest",
+        DropHighest(..) => "DropHighest",
+        DropLowest(..) => "DropLowest",
+        CountSuccesses(..) => "CountSuccesses",
+        CountSuccessesWithBotch(..) => "CountSuccessesWithBotch",
+        FloorClamp(..) => "FloorClamp",
+        CeilClamp(..) => "CeilClamp",
+        Conditional { .. } => "Conditional",
+        Negate(_) => "Negate",
+        AbsoluteValue(_) => "AbsoluteValue",
+        Add(..) => "Add",
+        Sub(..) => "Sub",
+        Mul(..) => "Mul",
+        Div(..) => "Div",
+        FloorDiv(..) => "FloorDiv",
+        CeilDiv(..) => "CeilDiv",
+        Min(..) => "Min",
+        Max(..) => "Max",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::DiceRng;
+
+    fn trace(notation: &str, seed: u64) -> (i32, EvalTrace) {
+        let expr = DiceExpression::parse(notation).unwrap();
+        expr.evaluate_with_trace(&mut DiceRng::new(seed)).unwrap()
+    }
+
+    #[test]
+    fn test_keep_highest_trace() {
+        let (total, trace) = trace("4d6k3", 156);
+        assert_eq!(trace.node_type, "KeepHighest");
+        assert_eq!(trace.output, total);
+        let dice = &trace.children[0];
+        assert_eq!(dice.node_type, "Dice");
+        assert_eq!(dice.inputs.len(), 4);
+        assert_eq!(dice.output, dice.inputs.iter().sum::<i32>());
+
+        let mut sorted = dice.inputs.clone();
+        sorted.sort_unstable();
+        assert_eq!(trace.inputs.len(), 3);
+        assert_eq!(trace.inputs.iter().sum::<i32>(), total);
+        assert_eq!(total, sorted[1..].iter().sum::<i32>());
+    }
+
+    #[test]
+    fn test_trace_matches_evaluate() {
+        for notation in ["4d6k3+2", "2d6!*3", "8d6>4-1", "min(1d20, 1d20)/2", "4d6dl1>3"] {
+            let expr = DiceExpression::parse(notation).unwrap();
+            let mut a = DiceRng::new(56);
+            let mut b = DiceRng::new(56);
+            for _ in 0..200 {
+                let (total, trace) = expr.evaluate_with_trace(&mut a).unwrap();
+                assert_eq!(Ok(total), expr.evaluate(&mut b));
+                assert_eq!(trace.output, total);
+            }
+        }
+    }
+
+    #[test]
+    fn test_arithmetic_inputs() {
+        let (total, trace) = trace("1d6+2*3", 9);
+        assert_eq!(trace.node_type, "Add");
+        let die = trace.children[0].output;
+        assert_eq!(trace.inputs, vec![die, 6]);
+        assert_eq!(total, die + 6);
+        assert_eq!(trace.children[1].inputs, vec![2, 3]);
+    }
+
+    #[test]
+    fn test_count_inputs_are_dice() {
+        let (successes, trace) = trace("5d6>4", 3);
+        assert_eq!(trace.inputs.len(), 5);
+        assert_eq!(successes, trace.inputs.iter().filter(|&&v| v >= 4).count() as i32);
+    }
+
+    #[test]
+    fn test_flatten_depth_first() {
+        let (_, trace) = trace("(4d6k3+1)*2", 1);
+        let entries = trace.flatten();
+        let shape: Vec<_> = entries
+            .iter()
+            .map(|entry| (entry.depth, entry.node_type.as_str()))
+            .collect();
+        assert_eq!(
+            shape,
+            vec![
+                (0, "Mul"),
+                (1, "Add"),
+                (2, "KeepHighest"),
+                (3, "Dice"),
+                (2, "Constant"),
+                (1, "Constant"),
+            ]
+        );
+        assert_eq!(entries[0].output, trace.output);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_trace_round_trip() {
+        let (_, trace) = trace("2d20kl1+5", 4);
+        let json = serde_json::to_string(&trace).unwrap();
+        assert_eq!(serde_json::from_str::<EvalTrace>(&json).unwrap(), trace);
+    }
+}
@@ -0,0 +1,221 @@
+//! Troika! initiative
+//!
+//! Instead of rolling, Troika! draws tokens from a bag: one per player,
+//! one per foe and an End of Round token. Whoever's token comes out acts,
+//! and the round ends as soon as the End of Round token is drawn, so a
+//! round can end before everyone has acted.
+
+use crate::context::DiceContext;
+use crate::eval::MAX_SIMULATION_ITERATIONS;
+use crate::{Dice, DiceError, DiceResult};
+use rand::Rng;
+use std::fmt;
+
+/// A token in the Troika! initiative bag
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TroikaToken {
+    /// Numbered from 1
+    Player(u32),
+    /// Numbered from 1
+    Foe(u32),
+    EndOfRound,
+}
+
+impl fmt::Display for TroikaToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TroikaToken::Player(n) => write!(f, "Player {}", n),
+            TroikaToken::Foe(n) => write!(f, "Foe {}", n),
+            TroikaToken::EndOfRound => write!(f, "End of Round"),
+        }
+    }
+}
+
+/// A bag of initiative tokens
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TroikaInitiative {
+    player_count: u32,
+    foe_count: u32,
+    bag: Vec<TroikaToken>,
+}
+
+impl TroikaInitiative {
+    /// Full bag for `player_count` players and `foe_count` foes
+    ///
+    /// A bag holding more than [`MAX_SIMULATION_ITERATIONS`] tokens, End of
+    /// Round included, fails with [`DiceError::RollCountLimit`].
+    pub fn new(player_count: u32, foe_count: u32) -> DiceResult<TroikaInitiative> {
+        TroikaInitiative::with_limit(player_count, foe_count, MAX_SIMULATION_ITERATIONS)
+    }
+
+    /// Full bag, failing if it would hold more than `limit` tokens
+    fn with_limit(player_count: u32, foe_count: u32, limit: u32) -> DiceResult<TroikaInitiative> {
+        let tokens = player_count.saturating_add(foe_count).saturating_add(1);
+        if tokens > limit {
+            return Err(DiceError::RollCountLimit {
+                requested: tokens,
+                limit,
+            });
+        }
+        let mut initiative = TroikaInitiative {
+            player_count,
+            foe_count,
+            bag: Vec::new(),
+        };
+        initiative.reset();
+        Ok(initiative)
+    }
+
+    /// Tokens left in the bag
+    pub fn remaining(&self) -> usize {
+        self.bag.len()
+    }
+
+    /// Put every token back in the bag
+    pub fn reset(&mut self) {
+        self.bag.clear();
+        self.bag.extend((1..=self.player_count).map(TroikaToken::Player));
+        self.bag.extend((1..=self.foe_count).map(TroikaToken::Foe));
+        self.bag.push(TroikaToken::EndOfRound);
+    }
+
+    /// Draw a random token; `None` once the bag is empty
+    pub fn draw<R: Rng + ?Sized>(&mut self, rng: &mut R) -> Option<TroikaToken> {
+        if self.bag.is_empty() {
+            return None;
+        }
+        let index = rng.gen_range(0..self.bag.len());
+        Some(self.bag.swap_remove(index))
+    }
+
+    /// Draw tokens up to and including the End of Round token
+    ///
+    /// Stops early if the bag runs out first. The bag isn't refilled, so
+    /// call [`reset`](Self::reset) before the next round.
+    pub fn draw_until_end<R: Rng + ?Sized>(&mut self, rng: &mut R) -> Vec<TroikaToken> {
+        let mut round = Vec::new();
+        while let Some(token) = self.draw(rng) {
+            round.push(token);
+            if token == TroikaToken::EndOfRound {
+                break;
+            }
+        }
+        round
+    }
+}
+
+impl DiceContext {
+    /// Fill a Troika! initiative bag for `foes` foes
+    ///
+    /// The bag holds a token per foe and the End of Round token; build one
+    /// with [`TroikaInitiative::new`] to add player tokens too. Draw from it
+    /// with this context's generator. A bag holding more tokens than
+    /// `RollConfig::max_simulation_iterations` fails with
+    /// [`DiceError::RollCountLimit`].
+    ///
+    /// # Arguments
+    ///
+    /// * `foes` - Foe tokens in the bag
+    pub fn roll_troika_initiative(&mut self, foes: u32) -> DiceResult<TroikaInitiative> {
+        TroikaInitiative::with_limit(0, foes, self.config().max_simulation_iterations)
+    }
+}
+
+impl Dice {
+    /// Fill a Troika! initiative bag for `foes` foes
+    ///
+    /// # Arguments
+    ///
+    /// * `foes` - Foe tokens in the bag
+    pub fn roll_troika_initiative(foes: u32) -> DiceResult<TroikaInitiative> {
+        DiceContext::new().roll_troika_initiative(foes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::DiceRng;
+    use crate::RollConfig;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_bag_contents() {
+        let mut rng = DiceRng::new(184);
+        let mut initiative = TroikaInitiative::new(3, 2).unwrap();
+        assert_eq!(initiative.remaining(), 6);
+        let drawn: HashSet<_> = (0..6).map(|_| initiative.draw(&mut rng).unwrap()).collect();
+        let expected = [
+            TroikaToken::Player(1),
+            TroikaToken::Player(2),
+            TroikaToken::Player(3),
+            TroikaToken::Foe(1),
+            TroikaToken::Foe(2),
+            TroikaToken::EndOfRound,
+        ];
+        assert_eq!(drawn, expected.into_iter().collect());
+        assert_eq!(initiative.draw(&mut rng), None);
+        initiative.reset();
+        assert_eq!(initiative.remaining(), 6);
+
+        assert!(TroikaInitiative::new(999_998, 1).is_ok());
+        assert_eq!(
+            TroikaInitiative::new(u32::MAX, u32::MAX),
+            Err(DiceError::RollCountLimit {
+                requested: u32::MAX,
+                limit: MAX_SIMULATION_ITERATIONS
+            })
+        );
+    }
+
+    #[test]
+    fn test_draw_until_end() {
+        let mut rng = DiceRng::new(184);
+        let mut initiative = TroikaInitiative::new(4, 4).unwrap();
+        let mut lengths = HashSet::new();
+        for _ in 0..200 {
+            initiative.reset();
+            let round = initiative.draw_until_end(&mut rng);
+            assert_eq!(round.last(), Some(&TroikaToken::EndOfRound));
+            assert_eq!(round.iter().collect::<HashSet<_>>().len(), round.len());
+            assert_eq!(initiative.remaining(), 9 - round.len());
+            lengths.insert(round.len());
+        }
+        // The End of Round token can come out anywhere
+        assert_eq!(lengths.len(), 9);
+        // Without a reset the next round is what's left
+        let rest = initiative.draw_until_end(&mut rng);
+        assert!(!rest.contains(&TroikaToken::EndOfRound));
+        assert!(initiative.draw_until_end(&mut rng).is_empty());
+    }
+
+    #[test]
+    fn test_roll_troika_initiative() {
+        let mut ctx = DiceContext::with_seed(184);
+        let mut empty = ctx.roll_troika_initiative(0).unwrap();
+        assert_eq!(empty.draw_until_end(ctx.rng()), vec![TroikaToken::EndOfRound]);
+        let mut bag = ctx.roll_troika_initiative(3).unwrap();
+        assert_eq!(bag, TroikaInitiative::new(0, 3).unwrap());
+        let round = bag.draw_until_end(ctx.rng());
+        assert_eq!(round.last(), Some(&TroikaToken::EndOfRound));
+        assert!(round.iter().all(|token| !matches!(token, TroikaToken::Player(_))));
+        assert_eq!(TroikaToken::Foe(2).to_string(), "Foe 2");
+
+        let config = RollConfig {
+            max_simulation_iterations: 5,
+            ..RollConfig::default()
+        };
+        let mut ctx = DiceContext::with_seed(184).with_config(config);
+        assert!(ctx.roll_troika_initiative(4).is_ok());
+        assert_eq!(
+            ctx.roll_troika_initiative(5),
+            Err(DiceError::RollCountLimit {
+                requested: 6,
+                limit: 5
+            })
+        );
+        assert_eq!(Dice::roll_troika_initiative(2).unwrap().remaining(), 3);
+    }
+}
@@ -0,0 +1,116 @@
+//! Unary roll variants
+//!
+//! Single-die helpers matching the unary operators of the notation:
+//! negation (`-1d6`) and absolute value (`|1d6-4|`).
+
+use crate::context::DiceContext;
+use crate::{Dice, DiceError, DiceResult};
+
+impl DiceContext {
+    /// Roll a die and negate it; a d6 gives `-6..=-1`
+    ///
+    /// # Arguments
+    ///
+    /// * `sides` - Number of sides on the die
+    pub fn roll_negated(&mut self, sides: i32) -> DiceResult<i32> {
+        Ok(-self.roll(sides)?)
+    }
+
+    /// Roll a die, add a modifier and take the absolute value (`|1dS+K|`)
+    ///
+    /// # Arguments
+    ///
+    /// * `sides` - Number of sides on the die
+    /// * `modifier` - Added to the roll before taking the absolute value
+    pub fn roll_absolute(&mut self, sides: i32, modifier: i32) -> DiceResult<i32> {
+        self.roll(sides)?
+            .checked_add(modifier)
+            .and_then(i32::checked_abs)
+            .ok_or(DiceError::Overflow)
+    }
+}
+
+impl Dice {
+    /// Roll a die and negate it; a d6 gives `-6..=-1`
+    ///
+    /// # Arguments
+    ///
+    /// * `sides` - Number of sides on the die
+    pub fn roll_negated(sides: i32) -> DiceResult<i32> {
+        DiceContext::new().roll_negated(sides)
+    }
+
+    /// Roll a die, add a modifier and take the absolute value (`|1dS+K|`)
+    ///
+    /// # Arguments
+    ///
+    /// * `sides` - Number of sides on the die
+    /// * `modifier` - Added to the roll before taking the absolute value
+    pub fn roll_absolute(sides: i32, modifier: i32) -> DiceResult<i32> {
+        DiceContext::new().roll_absolute(sides, modifier)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expression::DiceExpression;
+
+    #[test]
+    fn test_roll_negated() {
+        let mut ctx = DiceContext::with_seed(6);
+        for _ in 0..100 {
+            assert!((-6..=-1).contains(&ctx.roll_negated(6).unwrap()));
+        }
+        assert_eq!(ctx.roll_negated(1), Ok(-1));
+        assert_eq!(ctx.roll_negated(0), Err(DiceError::InvalidSides(0)));
+        assert!((-20..=-1).contains(&Dice::roll_negated(20).unwrap()));
+    }
+
+    #[test]
+    fn test_negated_notation() {
+        let mut ctx = DiceContext::with_seed(6);
+        assert_eq!(ctx.roll_notation("-1d1").unwrap().total, -1);
+        for _ in 0..200 {
+            let result = ctx.roll_notation("-2d6+5").unwrap();
+            assert!((-7..=3).contains(&result.total));
+            assert_eq!(result.total, 5 - result.individual().iter().sum::<i32>());
+
+            let mixed = ctx.roll_notation("-1d6+3d8").unwrap();
+            let values = mixed.individual();
+            assert_eq!(mixed.total, -values[0] + values[1..].iter().sum::<i32>());
+        }
+        let overflow = DiceExpression::Negate(Box::new(DiceExpression::Constant(i32::MIN)));
+        assert_eq!(overflow.evaluate(ctx.rng()), Err(DiceError::Overflow));
+    }
+
+    #[test]
+    fn test_roll_absolute() {
+        let mut ctx = DiceContext::with_seed(13);
+        for _ in 0..200 {
+            assert!((0..=3).contains(&ctx.roll_absolute(6, -4).unwrap()));
+            assert!((1..=6).contains(&ctx.roll_absolute(6, 0).unwrap()));
+        }
+        assert_eq!(ctx.roll_absolute(1, -10), Ok(9));
+        assert_eq!(ctx.roll_absolute(0, 1), Err(DiceError::InvalidSides(0)));
+        assert_eq!(ctx.roll_absolute(1, i32::MAX), Err(DiceError::Overflow));
+        assert!(Dice::roll_absolute(6, -4).is_ok());
+    }
+
+    #[test]
+    fn test_absolute_notation_distribution() {
+        let dist = DiceExpression::parse("|1d6-4|").unwrap().distribution().unwrap();
+        assert_eq!(dist.keys().copied().collect::<Vec<_>>(), vec![0, 1, 2, 3]);
+        for (outcome, expected) in [(0, 1.0 / 6.0), (1, 2.0 / 6.0), (2, 2.0 / 6.0), (3, 1.0 / 6.0)] {
+            assert!((dist[&outcome] - expected).abs() < 1e-12);
+        }
+
+        let mut ctx = DiceContext::with_seed(13);
+        let mut seen = [0u32; 4];
+        for _ in 0..6000 {
+            seen[ctx.roll_notation("|1d6-4|").unwrap().total as usize] += 1;
+        }
+        assert!(seen.iter().all(|&count| count > 700));
+        assert!(seen[1] > seen[0] && seen[2] > seen[3]);
+    }
+}
@@ -0,0 +1,86 @@
+//! Wide rolls
+//!
+//! Pools whose totals can exceed `i32`, such as `100000d1000`, rolled in
+//! pure Rust with `i64` arithmetic throughout.
+
+use crate::context::DiceContext;
+use crate::{Dice, DiceError, DiceResult};
+use rand::Rng;
+
+impl DiceContext {
+    /// Roll `count` dice of `sides` sides and sum them as an `i64`
+    ///
+    /// The count is capped by `RollConfig::max_simulation_iterations`
+    /// rather than `max_dice_count`, and fails with [`DiceError::Overflow`]
+    /// if `count * sides` doesn't fit in an `i64`.
+    ///
+    /// # Arguments
+    ///
+    /// * `count` - Number of dice to roll
+    /// * `sides` - Number of sides on each die
+    pub fn roll_multiple_i64(&mut self, count: i64, sides: i64) -> DiceResult<i64> {
+        let saturate = |value: i64| value.clamp(i32::MIN as i64, i32::MAX as i64) as i32;
+        if count <= 0 {
+            return Err(DiceError::InvalidCount(saturate(count)));
+        }
+        if sides <= 0 {
+            return Err(DiceError::InvalidSides(saturate(sides)));
+        }
+        count.checked_mul(sides).ok_or(DiceError::Overflow)?;
+        let limit = self.config().max_simulation_iterations;
+        if count > limit as i64 {
+            return Err(DiceError::RollCountLimit {
+                requested: u32::try_from(count).unwrap_or(u32::MAX),
+                limit,
+            });
+        }
+
+        let rng = self.rng();
+        Ok((0..count).map(|_| rng.gen_range(1..=sides)).sum())
+    }
+}
+
+impl Dice {
+    /// Roll `count` dice of `sides` sides and sum them as an `i64`
+    ///
+    /// # Arguments
+    ///
+    /// * `count` - Number of dice to roll
+    /// * `sides` - Number of sides on each die
+    pub fn roll_multiple_i64(count: i64, sides: i64) -> DiceResult<i64> {
+        DiceContext::new().roll_multiple_i64(count, sides)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_large_pool() {
+        let mut ctx = DiceContext::with_seed(138);
+        let total = ctx.roll_multiple_i64(100_000, 6).unwrap();
+        // Standard deviation is about 540
+        assert!((total - 350_000).abs() < 5_000, "{}", total);
+
+        let total = ctx.roll_multiple_i64(1_000_000, 1_000_000).unwrap();
+        assert!(total > i32::MAX as i64);
+    }
+
+    #[test]
+    fn test_wide_errors() {
+        let mut ctx = DiceContext::with_seed(138);
+        assert_eq!(ctx.roll_multiple_i64(0, 6), Err(DiceError::InvalidCount(0)));
+        assert_eq!(
+            ctx.roll_multiple_i64(-1 << 40, 6),
+            Err(DiceError::InvalidCount(i32::MIN))
+        );
+        assert_eq!(ctx.roll_multiple_i64(3, 0), Err(DiceError::InvalidSides(0)));
+        assert_eq!(ctx.roll_multiple_i64(4, i64::MAX / 2), Err(DiceError::Overflow));
+        assert!(matches!(
+            Dice::roll_multiple_i64(2_000_000, 6),
+            Err(DiceError::RollCountLimit { .. })
+        ));
+        assert_eq!(ctx.roll_multiple_i64(1, 1), Ok(1));
+    }
+}
@@ -0,0 +1,131 @@
+//! Wrath & Glory pools
+//!
+//! A d6 pool from Warhammer 40,000: Wrath & Glory, where 4+ is an icon and
+//! a 6 is an exalted icon worth two. One die may be the Wrath Die, and a 1
+//! on it brings a complication.
+
+use crate::context::DiceContext;
+use crate::{Dice, DiceResult};
+
+/// Lowest face that counts as an icon
+const ICON_MIN: i32 = 4;
+
+/// Face that counts as an exalted icon
+const EXALTED: i32 = 6;
+
+/// Outcome of a Wrath & Glory pool
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WnGResult {
+    /// Every die in the pool, the Wrath Die first when there is one
+    pub dice_values: Vec<i32>,
+    /// Dice showing 4 or more, the Wrath Die included
+    pub successes: u32,
+    /// Dice showing 6
+    pub exalted_icons: u32,
+    /// The Wrath Die, or 0 when the pool has none
+    pub wrath_value: i32,
+    /// The Wrath Die showed 1
+    pub has_complication: bool,
+}
+
+impl WnGResult {
+    /// Icons the pool is worth, with each exalted icon counting twice
+    pub fn total_icons(&self) -> u32 {
+        self.successes + self.exalted_icons
+    }
+
+    fn from_values(dice_values: Vec<i32>, wrath_die: bool) -> WnGResult {
+        let count = |matches: &dyn Fn(i32) -> bool| {
+            dice_values.iter().filter(|&&value| matches(value)).count() as u32
+        };
+        let successes = count(&|value| value >= ICON_MIN);
+        let exalted_icons = count(&|value| value == EXALTED);
+        let wrath_value = if wrath_die { dice_values[0] } else { 0 };
+        WnGResult {
+            successes,
+            exalted_icons,
+            wrath_value,
+            has_complication: wrath_value == 1,
+            dice_values,
+        }
+    }
+}
+
+impl DiceContext {
+    /// Roll a Wrath & Glory pool of d6s
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - Number of dice, the Wrath Die included
+    /// * `wrath_die` - Whether the first die is the Wrath Die
+    pub fn roll_wrath_and_glory(&mut self, pool: u32, wrath_die: bool) -> DiceResult<WnGResult> {
+        let values = self.roll_pool(pool, 6)?;
+        Ok(WnGResult::from_values(values, wrath_die))
+    }
+}
+
+impl Dice {
+    /// Roll a Wrath & Glory pool of d6s
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - Number of dice, the Wrath Die included
+    /// * `wrath_die` - Whether the first die is the Wrath Die
+    pub fn roll_wrath_and_glory(pool: u32, wrath_die: bool) -> DiceResult<WnGResult> {
+        DiceContext::new().roll_wrath_and_glory(pool, wrath_die)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DiceError;
+
+    #[test]
+    fn test_all_ones() {
+        let result = WnGResult::from_values(vec![1, 1, 1, 1], true);
+        assert_eq!((result.successes, result.exalted_icons, result.total_icons()), (0, 0, 0));
+        assert_eq!(result.wrath_value, 1);
+        assert!(result.has_complication);
+        // Without a Wrath Die a 1 is just a miss
+        let result = WnGResult::from_values(vec![1, 1, 1, 1], false);
+        assert_eq!(result.wrath_value, 0);
+        assert!(!result.has_complication);
+    }
+
+    #[test]
+    fn test_all_sixes() {
+        let result = WnGResult::from_values(vec![6, 6, 6], true);
+        assert_eq!((result.successes, result.exalted_icons, result.total_icons()), (3, 3, 6));
+        assert_eq!(result.wrath_value, 6);
+        assert!(!result.has_complication);
+    }
+
+    #[test]
+    fn test_complication_with_successes() {
+        let result = WnGResult::from_values(vec![1, 4, 5, 6, 2], true);
+        assert_eq!((result.successes, result.exalted_icons, result.total_icons()), (3, 1, 4));
+        assert!(result.has_complication);
+        // A Wrath Die showing an icon still counts
+        let result = WnGResult::from_values(vec![5, 3], true);
+        assert_eq!((result.successes, result.wrath_value), (1, 5));
+    }
+
+    #[test]
+    fn test_roll_pool() {
+        let mut ctx = DiceContext::with_seed(162);
+        let mut complications = 0;
+        for _ in 0..600 {
+            let result = ctx.roll_wrath_and_glory(5, true).unwrap();
+            assert_eq!(result.dice_values.len(), 5);
+            assert_eq!(result.wrath_value, result.dice_values[0]);
+            assert!(result.exalted_icons <= result.successes);
+            complications += u32::from(result.has_complication);
+        }
+        // About a sixth of the rolls
+        assert!((50..150).contains(&complications), "{}", complications);
+        assert!(!ctx.roll_wrath_and_glory(1, false).unwrap().has_complication);
+        assert_eq!(ctx.roll_wrath_and_glory(0, true), Err(DiceError::InvalidCount(0)));
+    }
+}
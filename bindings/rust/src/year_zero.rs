@@ -0,0 +1,155 @@
+//! Year Zero Engine pushes
+//!
+//! Mutant: Year Zero and Forbidden Lands count every 6 as a success. A
+//! player may push a roll once, rerolling every die that shows neither a
+//! success nor a 1; the 1s are banes, and any rolled on the push add to
+//! those kept from the first roll.
+
+use crate::context::DiceContext;
+use crate::{Dice, DiceError, DiceResult};
+
+/// Lowest face that counts as a success
+const SUCCESS_MIN: i32 = 6;
+
+/// Face that counts as a bane
+const BANE: i32 = 1;
+
+/// Outcome of a Year Zero roll, pushed or not
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RumbleResult {
+    /// The pool before any push
+    pub first_roll: Vec<i32>,
+    /// New values of the dice the push rerolled, in pool order; `None` when
+    /// the roll wasn't pushed
+    pub pushed_roll: Option<Vec<i32>>,
+    /// Dice showing 6 or more in `final_values`
+    pub successes: u32,
+    /// Dice showing 1 in `final_values`, from the first roll and the push
+    pub banes: u32,
+    /// The pool after the push, or the first roll when there was none
+    pub final_values: Vec<i32>,
+}
+
+impl DiceContext {
+    /// Roll a Year Zero pool, optionally pushing it
+    ///
+    /// Dice with fewer than 6 sides can't succeed and fail with
+    /// [`DiceError::InvalidSides`].
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - Number of dice
+    /// * `sides` - Sides per die, 6 for base dice
+    /// * `pushed` - Reroll every die that isn't a success or a bane
+    pub fn roll_rumble_strip(
+        &mut self,
+        pool: u32,
+        sides: u32,
+        pushed: bool,
+    ) -> DiceResult<RumbleResult> {
+        if sides < SUCCESS_MIN as u32 {
+            return Err(DiceError::InvalidSides(sides as i32));
+        }
+        let first_roll = self.roll_pool(pool, sides)?;
+        let mut final_values = first_roll.clone();
+        let mut pushed_roll = None;
+        if pushed {
+            let rerolled: Vec<usize> = (0..final_values.len())
+                .filter(|&i| final_values[i] != BANE && final_values[i] < SUCCESS_MIN)
+                .collect();
+            let new_values = match rerolled.len() {
+                0 => Vec::new(),
+                count => self.roll_pool(count as u32, sides)?,
+            };
+            for (&i, &value) in rerolled.iter().zip(&new_values) {
+                final_values[i] = value;
+            }
+            pushed_roll = Some(new_values);
+        }
+        let count = |matches: &dyn Fn(i32) -> bool| {
+            final_values.iter().filter(|&&value| matches(value)).count() as u32
+        };
+        Ok(RumbleResult {
+            successes: count(&|value| value >= SUCCESS_MIN),
+            banes: count(&|value| value == BANE),
+            first_roll,
+            pushed_roll,
+            final_values,
+        })
+    }
+}
+
+impl Dice {
+    /// Roll a Year Zero pool, optionally pushing it
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - Number of dice
+    /// * `sides` - Sides per die, 6 for base dice
+    /// * `pushed` - Reroll every die that isn't a success or a bane
+    pub fn roll_rumble_strip(pool: u32, sides: u32, pushed: bool) -> DiceResult<RumbleResult> {
+        DiceContext::new().roll_rumble_strip(pool, sides, pushed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unpushed_roll() {
+        let mut ctx = DiceContext::with_seed(189);
+        for _ in 0..200 {
+            let result = ctx.roll_rumble_strip(5, 6, false).unwrap();
+            assert_eq!(result.pushed_roll, None);
+            assert_eq!(result.final_values, result.first_roll);
+            let sixes = result.first_roll.iter().filter(|&&v| v == 6).count() as u32;
+            let ones = result.first_roll.iter().filter(|&&v| v == 1).count() as u32;
+            assert_eq!((result.successes, result.banes), (sixes, ones));
+        }
+    }
+
+    #[test]
+    fn test_push_keeps_successes_and_banes() {
+        let mut ctx = DiceContext::with_seed(189);
+        let mut new_banes = 0;
+        for _ in 0..200 {
+            let result = ctx.roll_rumble_strip(6, 6, true).unwrap();
+            let pushed = result.pushed_roll.as_ref().unwrap();
+            let mut pushed_values = pushed.iter();
+            for (&first, &last) in result.first_roll.iter().zip(&result.final_values) {
+                if first == 1 || first == 6 {
+                    assert_eq!(first, last);
+                } else {
+                    assert_eq!(Some(&last), pushed_values.next());
+                }
+            }
+            assert_eq!(pushed_values.next(), None);
+            let first_banes = result.first_roll.iter().filter(|&&v| v == 1).count() as u32;
+            let push_banes = pushed.iter().filter(|&&v| v == 1).count() as u32;
+            assert_eq!(result.banes, first_banes + push_banes);
+            new_banes += push_banes;
+        }
+        // Pushing risks fresh banes
+        assert!(new_banes > 0);
+    }
+
+    #[test]
+    fn test_push_with_nothing_to_reroll() {
+        // A lone 1 or 6 isn't rerolled, so the push rolls nothing
+        let mut ctx = DiceContext::with_seed(189);
+        let mut saw_empty_push = false;
+        for _ in 0..500 {
+            let result = ctx.roll_rumble_strip(1, 6, true).unwrap();
+            if matches!(result.first_roll[..], [1] | [6]) {
+                assert_eq!(result.pushed_roll, Some(Vec::new()));
+                saw_empty_push = true;
+            }
+        }
+        assert!(saw_empty_push);
+        assert_eq!(ctx.roll_rumble_strip(3, 4, true), Err(DiceError::InvalidSides(4)));
+        assert_eq!(ctx.roll_rumble_strip(0, 6, false), Err(DiceError::InvalidCount(0)));
+        assert!(Dice::roll_rumble_strip(4, 8, true).is_ok());
+    }
+}